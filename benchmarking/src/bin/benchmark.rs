@@ -1,16 +1,32 @@
 // Copyright 2022-2023 VMware, Inc.
 // SPDX-License-Identifier: BSD-2-Clause
 
-//! Run all of the examples as benchmarks and report the results in a table.
+//! Run the examples as benchmarks, report the results in a table, and
+//! optionally save them and compare them against a baseline.
 
-use std::time::Duration;
+use std::{collections::BTreeMap, path::PathBuf, process, time::Duration};
 
 use benchmarking::{
+    compare::compare,
+    config::{qalpha_benchmarks, sort_bounds, BenchmarkConfig},
     run::{get_examples, BenchmarkMeasurement},
     time_bench::{compile_flyvy_bin, compile_time_bin, REPO_ROOT_PATH},
 };
 use clap::Parser;
 
+#[derive(clap::Args, Clone, Debug, PartialEq, Eq)]
+struct BoundedBenchArgs {
+    /// Time limit for checking each file.
+    #[arg(long, default_value = "60s")]
+    time_limit: humantime::Duration,
+    /// Bound to use for every sort.
+    #[arg(long, default_value_t = 2)]
+    size: usize,
+    /// Maximum number of transitions to explore.
+    #[arg(long, default_value_t = 4)]
+    depth: usize,
+}
+
 #[derive(clap::Subcommand, Clone, Debug, PartialEq, Eq)]
 enum Command {
     Verify {
@@ -21,6 +37,23 @@ enum Command {
         #[arg(long, default_value = "z3")]
         solver: String,
     },
+    /// Run `infer qalpha` as given by the `# TEST` lines in the examples and
+    /// by a TOML config.
+    Qalpha {
+        /// Time limit for each inference run.
+        #[arg(long, default_value = "300s")]
+        time_limit: humantime::Duration,
+        /// Config with additional qalpha benchmarks (defaults to
+        /// benchmarking/qalpha.toml)
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+    /// Run the set-based bounded model checker on every example.
+    SetCheck(BoundedBenchArgs),
+    /// Run the SAT-based bounded model checker on every example.
+    SatCheck(BoundedBenchArgs),
+    /// Run the BDD-based bounded model checker on every example.
+    BddCheck(BoundedBenchArgs),
 }
 
 #[derive(clap::Parser, Debug)]
@@ -28,36 +61,158 @@ struct App {
     /// Command to run
     #[command(subcommand)]
     command: Command,
+    /// Number of times to run each benchmark (defaults to 1, or 3 with
+    /// --baseline). Reporting slowdowns against a baseline requires at least 2.
+    #[arg(long, global = true)]
+    repeat: Option<usize>,
+    /// Save the results as JSON to this file.
+    #[arg(long, global = true)]
+    output: Option<PathBuf>,
+    /// Compare the results against a baseline saved with --output.
+    #[arg(long, global = true)]
+    baseline: Option<PathBuf>,
+    /// Smallest slowdown to report, as a percentage of the baseline time.
+    #[arg(long, global = true, default_value_t = 10.0)]
+    threshold: f64,
 }
 
-fn benchmark_verify(time_limit: Duration, solver: &str) -> Vec<BenchmarkMeasurement> {
-    get_examples()
-        .into_iter()
-        .map(|file| {
+fn run_benchmarks(
+    benchmarks: Vec<BenchmarkConfig>,
+    time_limit: Duration,
+    repeat: usize,
+) -> Vec<BenchmarkMeasurement> {
+    let mut results = vec![];
+    for _ in 0..repeat {
+        for b in &benchmarks {
             eprintln!(
-                "verify {}",
-                file.strip_prefix(REPO_ROOT_PATH()).unwrap().display()
+                "{} {}",
+                b.command.join(" "),
+                b.file.strip_prefix(REPO_ROOT_PATH()).unwrap().display()
             );
-            BenchmarkMeasurement::run(
-                vec![String::from("verify")],
-                vec![format!("--solver={solver}")],
-                file,
+            results.push(BenchmarkMeasurement::run(
+                b.command.clone(),
+                b.args.clone(),
+                b.file.clone(),
                 time_limit,
-            )
+            ));
+        }
+    }
+    results
+}
+
+fn verify_benchmarks(solver: &str) -> Vec<BenchmarkConfig> {
+    get_examples()
+        .into_iter()
+        .map(|file| BenchmarkConfig {
+            command: vec![String::from("verify")],
+            args: vec![format!("--solver={solver}")],
+            file,
+        })
+        .collect()
+}
+
+fn bounded_benchmarks(command: &str, args: &BoundedBenchArgs) -> Vec<BenchmarkConfig> {
+    get_examples()
+        .into_iter()
+        .filter_map(|file| match sort_bounds(&file, args.size) {
+            Ok(mut bounds) => {
+                bounds.push(format!("--depth={}", args.depth));
+                bounds.push(String::from("--print-timing=false"));
+                Some(BenchmarkConfig {
+                    command: vec![String::from(command)],
+                    args: bounds,
+                    file,
+                })
+            }
+            Err(err) => {
+                eprintln!("skipping {}: {err}", file.display());
+                None
+            }
         })
         .collect()
 }
 
 impl App {
+    fn benchmarks(&self) -> (Vec<BenchmarkConfig>, Duration) {
+        match &self.command {
+            Command::Verify { time_limit, solver } => {
+                (verify_benchmarks(solver), (*time_limit).into())
+            }
+            Command::Qalpha { time_limit, config } => {
+                let config = config
+                    .clone()
+                    .unwrap_or_else(|| REPO_ROOT_PATH().join("benchmarking/qalpha.toml"));
+                let benchmarks =
+                    qalpha_benchmarks(&get_examples(), Some(&config)).unwrap_or_else(|err| {
+                        eprintln!("could not load qalpha benchmarks: {err}");
+                        process::exit(1)
+                    });
+                (benchmarks, (*time_limit).into())
+            }
+            Command::SetCheck(args) => (
+                bounded_benchmarks("set-check", args),
+                args.time_limit.into(),
+            ),
+            Command::SatCheck(args) => (
+                bounded_benchmarks("sat-check", args),
+                args.time_limit.into(),
+            ),
+            Command::BddCheck(args) => (
+                bounded_benchmarks("bdd-check", args),
+                args.time_limit.into(),
+            ),
+        }
+    }
+
     fn exec(&self) {
+        // load the baseline first to report errors before running anything
+        let baseline = self.baseline.as_ref().map(|path| {
+            BenchmarkMeasurement::load(path).unwrap_or_else(|err| {
+                eprintln!("could not load baseline {}: {err}", path.display());
+                process::exit(1)
+            })
+        });
+        let repeat = self
+            .repeat
+            .unwrap_or(if baseline.is_some() { 3 } else { 1 });
+        if let Some(baseline) = &baseline {
+            if repeat < 2 {
+                eprintln!("warning: slowdowns are only reported with --repeat 2 or more");
+            }
+            let mut counts = BTreeMap::<_, usize>::new();
+            for r in baseline {
+                *counts.entry(r.key()).or_default() += 1;
+            }
+            if counts.values().any(|&n| n < 2) {
+                eprintln!(
+                    "warning: the baseline has benchmarks with a single run, so slowdowns will not be reported for them"
+                );
+            }
+        }
         // make sure `time` is available
         compile_time_bin();
         // make sure `temporal-verifier` is available
         compile_flyvy_bin();
-        match &self.command {
-            Command::Verify { time_limit, solver } => {
-                let results = benchmark_verify((*time_limit).into(), solver);
-                BenchmarkMeasurement::print_table(results);
+        let (benchmarks, time_limit) = self.benchmarks();
+        let results = run_benchmarks(benchmarks, time_limit, repeat);
+        BenchmarkMeasurement::print_table(&results);
+        if let Some(path) = &self.output {
+            if let Err(err) = BenchmarkMeasurement::save(&results, path) {
+                eprintln!("could not save results to {}: {err}", path.display());
+                process::exit(1);
+            }
+        }
+        if let Some(baseline) = baseline {
+            let changes = compare(&baseline, &results, self.threshold / 100.0);
+            println!();
+            if changes.is_empty() {
+                println!("no changes from baseline");
+            }
+            for change in &changes {
+                println!("{change}");
+            }
+            if changes.iter().any(|c| c.is_regression()) {
+                process::exit(1);
             }
         }
     }
@@ -0,0 +1,277 @@
+// Copyright 2022-2023 VMware, Inc.
+// SPDX-License-Identifier: BSD-2-Clause
+
+//! Compare benchmark results against a stored baseline.
+//!
+//! Results are grouped by benchmark (command, parameters and file), so that
+//! repeated runs of the same benchmark form a sample. A benchmark is reported
+//! as slower if its mean running time grew by more than a threshold and a
+//! one-sided Welch's t-test finds the difference significant at the 95% level,
+//! which requires at least two runs on each side.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use crate::run::{BenchmarkMeasurement, Outcome};
+
+/// Identifies a benchmark across runs.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BenchmarkKey {
+    /// The temporal-verifier subcommand.
+    pub command: String,
+    /// Its other arguments.
+    pub params: String,
+    /// The file, relative to the repository root.
+    pub file: String,
+}
+
+impl fmt::Display for BenchmarkKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.command, self.file)?;
+        if self.params != "" {
+            write!(f, " ({})", self.params)?;
+        }
+        Ok(())
+    }
+}
+
+/// A difference between the baseline and new results.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// The benchmark got significantly slower.
+    Slowdown {
+        /// The benchmark
+        key: BenchmarkKey,
+        /// Mean running time in the baseline, in seconds
+        baseline_mean: f64,
+        /// Mean running time in the new results, in seconds
+        mean: f64,
+    },
+    /// The outcomes of the benchmark changed.
+    Outcome {
+        /// The benchmark
+        key: BenchmarkKey,
+        /// Outcomes in the baseline
+        baseline: BTreeSet<Outcome>,
+        /// Outcomes in the new results
+        new: BTreeSet<Outcome>,
+    },
+    /// The benchmark is only in the new results.
+    Added(BenchmarkKey),
+    /// The benchmark is only in the baseline.
+    Missing(BenchmarkKey),
+}
+
+impl Change {
+    /// Whether this change should be treated as a regression.
+    pub fn is_regression(&self) -> bool {
+        matches!(self, Change::Slowdown { .. } | Change::Outcome { .. })
+    }
+}
+
+fn outcome_list(outcomes: &BTreeSet<Outcome>) -> String {
+    outcomes
+        .iter()
+        .map(|o| o.to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Slowdown {
+                key,
+                baseline_mean,
+                mean,
+            } => write!(
+                f,
+                "slower:  {key}: {baseline_mean:0.2}s -> {mean:0.2}s ({:+0.0}%)",
+                (mean / baseline_mean - 1.0) * 100.0
+            ),
+            Change::Outcome { key, baseline, new } => write!(
+                f,
+                "outcome: {key}: {} -> {}",
+                outcome_list(baseline),
+                outcome_list(new)
+            ),
+            Change::Added(key) => write!(f, "new:     {key}"),
+            Change::Missing(key) => write!(f, "missing: {key}"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Sample {
+    times: Vec<f64>,
+    outcomes: BTreeSet<Outcome>,
+}
+
+fn group(results: &[BenchmarkMeasurement]) -> BTreeMap<BenchmarkKey, Sample> {
+    let mut groups = BTreeMap::<BenchmarkKey, Sample>::new();
+    for r in results {
+        let sample = groups.entry(r.key()).or_default();
+        sample.times.push(r.measurement.real_time.as_secs_f64());
+        sample.outcomes.insert(r.outcome);
+    }
+    groups
+}
+
+fn mean_var(xs: &[f64]) -> (f64, f64) {
+    let n = xs.len() as f64;
+    let mean = xs.iter().sum::<f64>() / n;
+    let var = xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, var)
+}
+
+/// Critical values of the t distribution for a one-sided test at the 95% level,
+/// indexed by degrees of freedom (starting at 1).
+const T_CRITICAL_95: [f64; 30] = [
+    6.314, 2.920, 2.353, 2.132, 2.015, 1.943, 1.895, 1.860, 1.833, 1.812, 1.796, 1.782, 1.771,
+    1.761, 1.753, 1.746, 1.740, 1.734, 1.729, 1.725, 1.721, 1.717, 1.714, 1.711, 1.708, 1.706,
+    1.703, 1.701, 1.699, 1.697,
+];
+
+fn t_critical(df: f64) -> f64 {
+    // rounding down is conservative
+    let df = (df.floor() as usize).max(1);
+    T_CRITICAL_95.get(df - 1).copied().unwrap_or(1.645)
+}
+
+/// Whether `new` is significantly larger than `baseline` according to a
+/// one-sided Welch's t-test. Both samples need at least two elements.
+fn significantly_larger(baseline: &[f64], new: &[f64]) -> bool {
+    assert!(baseline.len() >= 2 && new.len() >= 2);
+    let (m1, v1) = mean_var(baseline);
+    let (m2, v2) = mean_var(new);
+    let (s1, s2) = (v1 / baseline.len() as f64, v2 / new.len() as f64);
+    let se2 = s1 + s2;
+    if se2 == 0.0 {
+        return m2 > m1;
+    }
+    let t = (m2 - m1) / se2.sqrt();
+    let df = se2.powi(2)
+        / (s1.powi(2) / (baseline.len() - 1) as f64 + s2.powi(2) / (new.len() - 1) as f64);
+    t > t_critical(df)
+}
+
+/// Compare new results against a baseline. A slowdown is reported if the mean
+/// running time grew by more than `threshold` (as a fraction, so 0.1 is 10%)
+/// and the difference is significant.
+pub fn compare(
+    baseline: &[BenchmarkMeasurement],
+    results: &[BenchmarkMeasurement],
+    threshold: f64,
+) -> Vec<Change> {
+    let baseline = group(baseline);
+    let results = group(results);
+    let mut changes = vec![];
+    for (key, new) in &results {
+        let Some(old) = baseline.get(key) else {
+            changes.push(Change::Added(key.clone()));
+            continue;
+        };
+        if old.outcomes != new.outcomes {
+            changes.push(Change::Outcome {
+                key: key.clone(),
+                baseline: old.outcomes.clone(),
+                new: new.outcomes.clone(),
+            });
+            continue;
+        }
+        if old.times.len() < 2 || new.times.len() < 2 {
+            continue;
+        }
+        let (baseline_mean, _) = mean_var(&old.times);
+        let (mean, _) = mean_var(&new.times);
+        if mean > baseline_mean * (1.0 + threshold) && significantly_larger(&old.times, &new.times)
+        {
+            changes.push(Change::Slowdown {
+                key: key.clone(),
+                baseline_mean,
+                mean,
+            });
+        }
+    }
+    for key in baseline.keys() {
+        if !results.contains_key(key) {
+            changes.push(Change::Missing(key.clone()));
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use super::*;
+    use crate::measurement::RunMeasurement;
+
+    fn result(file: &str, secs: f64, outcome: Outcome) -> BenchmarkMeasurement {
+        let real_time = Duration::from_secs_f64(secs);
+        BenchmarkMeasurement {
+            command: vec!["verify".to_string()],
+            params: "--solver=z3".to_string(),
+            file: PathBuf::from(file),
+            outcome,
+            measurement: RunMeasurement {
+                real_time,
+                user_time: real_time,
+                sys_time: Duration::ZERO,
+                self_user_time: real_time,
+                max_mem_bytes: 0,
+                timed_out: outcome == Outcome::Timeout,
+                success: outcome == Outcome::Success,
+            },
+        }
+    }
+
+    fn results(file: &str, times: &[f64]) -> Vec<BenchmarkMeasurement> {
+        times
+            .iter()
+            .map(|&t| result(file, t, Outcome::Success))
+            .collect()
+    }
+
+    #[test]
+    fn test_welch() {
+        assert!(significantly_larger(&[1.0, 1.1, 0.9], &[2.0, 2.1, 1.9]));
+        assert!(!significantly_larger(&[1.0, 1.1, 0.9], &[1.0, 1.2, 0.95]));
+        // noisy samples are not significant even with a large mean difference
+        assert!(!significantly_larger(&[1.0, 3.0], &[1.5, 4.0]));
+        assert!(significantly_larger(&[1.0, 1.0], &[1.5, 1.5]));
+    }
+
+    #[test]
+    fn test_compare() {
+        let mut baseline = results("a.fly", &[1.0, 1.1, 0.9]);
+        baseline.extend(results("b.fly", &[1.0, 1.1, 0.9]));
+        baseline.extend(results("c.fly", &[1.0, 1.1, 0.9]));
+        baseline.push(result("d.fly", 1.0, Outcome::Success));
+
+        let mut new = results("a.fly", &[2.0, 2.1, 1.9]);
+        // significant, but below the threshold
+        new.extend(results("b.fly", &[1.05, 1.05, 1.04]));
+        new.push(result("c.fly", 60.0, Outcome::Timeout));
+        new.push(result("e.fly", 1.0, Outcome::Success));
+
+        let changes = compare(&baseline, &new, 0.1);
+        let key = |file: &str| result(file, 0.0, Outcome::Success).key();
+        assert_eq!(changes.len(), 4, "{changes:?}");
+        assert!(matches!(&changes[0], Change::Slowdown { key: k, .. } if *k == key("a.fly")));
+        assert_eq!(
+            changes[1],
+            Change::Outcome {
+                key: key("c.fly"),
+                baseline: BTreeSet::from([Outcome::Success]),
+                new: BTreeSet::from([Outcome::Timeout]),
+            }
+        );
+        assert_eq!(changes[2], Change::Added(key("e.fly")));
+        assert_eq!(changes[3], Change::Missing(key("d.fly")));
+        assert_eq!(changes.iter().filter(|c| c.is_regression()).count(), 2);
+    }
+}
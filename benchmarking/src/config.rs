@@ -0,0 +1,161 @@
+// Copyright 2022-2023 VMware, Inc.
+// SPDX-License-Identifier: BSD-2-Clause
+
+//! Benchmark configurations: which arguments to run each example with.
+
+use std::{error::Error, fs, path::Path, path::PathBuf};
+
+use serde::Deserialize;
+
+use crate::time_bench::REPO_ROOT_PATH;
+
+/// A single invocation of temporal-verifier to benchmark.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchmarkConfig {
+    /// The subcommand, like `infer qalpha` (including any options that must
+    /// come before the nested subcommand).
+    pub command: Vec<String>,
+    /// The remaining arguments.
+    pub args: Vec<String>,
+    /// The .fly file to run on.
+    pub file: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConfigFile {
+    benchmarks: Vec<ConfigEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConfigEntry {
+    file: PathBuf,
+    args: Vec<String>,
+}
+
+/// The directory with the benchmarked examples.
+pub fn examples_dir() -> PathBuf {
+    REPO_ROOT_PATH().join("temporal-verifier/examples")
+}
+
+/// Split temporal-verifier arguments at `subcommand`, so that the command is
+/// everything up to and including it. Returns `None` if `subcommand` does not
+/// appear.
+fn split_at_subcommand(args: Vec<String>, subcommand: &str) -> Option<BenchmarkConfig> {
+    let i = args.iter().position(|arg| arg == subcommand)?;
+    let mut command = args;
+    let args = command.split_off(i + 1);
+    Some(BenchmarkConfig {
+        command,
+        args,
+        file: PathBuf::new(),
+    })
+}
+
+/// Get the `infer qalpha` invocations from the `# TEST` lines in a file's
+/// contents.
+fn qalpha_test_lines(contents: &str) -> Vec<Vec<String>> {
+    contents
+        .lines()
+        .filter_map(|l| l.strip_prefix("# TEST "))
+        .filter_map(|test_line| {
+            let split_line = shell_words::split(test_line).ok()?;
+            let sep = split_line.iter().position(|arg| arg == "--")?;
+            let args = split_line[sep + 1..].to_vec();
+            if args.first().map(|s| s.as_str()) == Some("infer")
+                && args.iter().any(|arg| arg == "qalpha")
+            {
+                Some(args)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Get the `infer qalpha` benchmarks, from the `# TEST` lines in the examples
+/// and from a TOML config file (if given).
+///
+/// The config file has a list of `[[benchmarks]]`, each with a `file` relative
+/// to `temporal-verifier/examples` and the `args` to run it with, as in
+/// `["infer", "qalpha", "--max-exist", "0"]`.
+pub fn qalpha_benchmarks(
+    examples: &[PathBuf],
+    config: Option<&Path>,
+) -> Result<Vec<BenchmarkConfig>, Box<dyn Error>> {
+    let mut benchmarks = vec![];
+    for file in examples {
+        let contents = fs::read_to_string(file)?;
+        for args in qalpha_test_lines(&contents) {
+            let config = split_at_subcommand(args, "qalpha").unwrap();
+            benchmarks.push(BenchmarkConfig {
+                file: file.clone(),
+                ..config
+            });
+        }
+    }
+    if let Some(config) = config {
+        let contents = fs::read_to_string(config)?;
+        let config: ConfigFile = toml::from_str(&contents)?;
+        for entry in config.benchmarks {
+            let file = examples_dir().join(&entry.file);
+            let Some(config) = split_at_subcommand(entry.args, "qalpha") else {
+                return Err(
+                    format!("benchmark for {} is not a qalpha run", entry.file.display()).into(),
+                );
+            };
+            benchmarks.push(BenchmarkConfig { file, ..config });
+        }
+    }
+    Ok(benchmarks)
+}
+
+/// Get arguments that bound every sort in a file to `size`, as in `--bound
+/// node=2`.
+pub fn sort_bounds(file: &Path, size: usize) -> Result<Vec<String>, Box<dyn Error>> {
+    let contents = fs::read_to_string(file)?;
+    let m = fly::parser::parse(&contents).map_err(|err| format!("could not parse: {err}"))?;
+    Ok(m.signature
+        .sorts
+        .iter()
+        .map(|sort| format!("--bound={sort}={size}"))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qalpha_test_lines() {
+        let contents = "
+# TEST -- verify
+# TEST --name infer-z3 -- infer --no-print-invariant qalpha --until-safe --max-exist 0
+# TEST --expect-fail -- infer qalpha --quantifier 'F node 2'
+";
+        let tests = qalpha_test_lines(contents);
+        assert_eq!(tests.len(), 2);
+        let config = split_at_subcommand(tests[0].clone(), "qalpha").unwrap();
+        assert_eq!(
+            config.command,
+            vec!["infer", "--no-print-invariant", "qalpha"]
+        );
+        assert_eq!(config.args, vec!["--until-safe", "--max-exist", "0"]);
+        assert_eq!(
+            tests[1],
+            vec!["infer", "qalpha", "--quantifier", "F node 2"]
+        );
+    }
+
+    #[test]
+    fn test_qalpha_config() {
+        let config = REPO_ROOT_PATH().join("benchmarking/qalpha.toml");
+        let benchmarks = qalpha_benchmarks(&crate::run::get_examples(), Some(&config)).unwrap();
+        assert!(benchmarks
+            .iter()
+            .any(|b| b.file.ends_with("lockserver.fly") && b.args.contains(&"--ice".to_string())));
+        for b in &benchmarks {
+            assert!(b.file.exists(), "{} does not exist", b.file.display());
+            assert_eq!(b.command.last().unwrap(), "qalpha");
+        }
+    }
+}
@@ -11,6 +11,8 @@
 #![allow(rustdoc::private_intra_doc_links)]
 #![deny(rustdoc::broken_intra_doc_links)]
 
+pub mod compare;
+pub mod config;
 pub mod measurement;
 pub mod run;
 pub mod time_bench;
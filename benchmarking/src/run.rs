@@ -3,13 +3,22 @@
 
 //! Library for running and reporting benchmark measurements.
 
-use std::{collections::HashMap, ffi::OsStr, path::PathBuf, time::Duration};
+use std::{
+    collections::HashMap,
+    error::Error,
+    ffi::OsStr,
+    fmt, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use crate::{
+    compare::BenchmarkKey,
     measurement::RunMeasurement,
     time_bench::{Time, REPO_ROOT_PATH},
 };
 
+use serde::{Deserialize, Serialize};
 use tabled::settings::{
     object::{Columns, Object, Rows},
     width::MinWidth,
@@ -17,13 +26,54 @@ use tabled::settings::{
 };
 use walkdir::WalkDir;
 
+/// The outcome of a single benchmark run.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    /// temporal-verifier exited successfully.
+    Success,
+    /// temporal-verifier exited with an error.
+    Fail,
+    /// The run was killed for exceeding the time limit.
+    Timeout,
+}
+
+impl Outcome {
+    fn from_measurement(measurement: &RunMeasurement) -> Self {
+        if measurement.timed_out {
+            Outcome::Timeout
+        } else if measurement.success {
+            Outcome::Success
+        } else {
+            Outcome::Fail
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Outcome::Success => "ok",
+            Outcome::Fail => "fail",
+            Outcome::Timeout => "timeout",
+        };
+        write!(f, "{s}")
+    }
+}
+
 /// A benchmark configuration and its resulting measurement.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BenchmarkMeasurement {
-    command: Vec<String>,
-    params: String,
-    file: PathBuf,
-    measurement: RunMeasurement,
+    /// The temporal-verifier subcommand.
+    pub command: Vec<String>,
+    /// The other arguments to temporal-verifier.
+    pub params: String,
+    /// The file benchmarked, relative to the repository root.
+    pub file: PathBuf,
+    /// The outcome of the run.
+    pub outcome: Outcome,
+    /// Statistics for the run.
+    pub measurement: RunMeasurement,
 }
 
 impl BenchmarkMeasurement {
@@ -51,14 +101,42 @@ impl BenchmarkMeasurement {
         timer.args(&args);
         timer.arg(&file);
         let measurement = timer.run().expect("error getting timing");
+        let file = file
+            .strip_prefix(REPO_ROOT_PATH())
+            .map(|f| f.to_path_buf())
+            .unwrap_or(file);
         BenchmarkMeasurement {
             command,
             params: args.join(" "),
             file,
+            outcome: Outcome::from_measurement(&measurement),
             measurement,
         }
     }
 
+    /// The key used to match up runs of the same benchmark.
+    pub fn key(&self) -> BenchmarkKey {
+        BenchmarkKey {
+            command: self.command.join(" "),
+            params: self.params.clone(),
+            file: self.file.display().to_string(),
+        }
+    }
+
+    /// Save a list of results as JSON.
+    pub fn save(results: &[Self], path: &Path) -> Result<(), Box<dyn Error>> {
+        let json = serde_json::to_string_pretty(results)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Load a list of results saved with [`Self::save`].
+    pub fn load(path: &Path) -> Result<Vec<Self>, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)?;
+        let results = serde_json::from_str(&contents)?;
+        Ok(results)
+    }
+
     /// Header used for printing table. Make sure this stays in sync with [`Self::row`].
     fn header() -> Vec<String> {
         [
@@ -70,20 +148,18 @@ impl BenchmarkMeasurement {
     }
 
     fn success(&self) -> &'static str {
-        if self.measurement.timed_out {
-            "timeout"
-        } else if self.measurement.success {
-            ""
-        } else {
-            "fail"
+        match self.outcome {
+            Outcome::Success => "",
+            Outcome::Fail => "fail",
+            Outcome::Timeout => "timeout",
         }
     }
 
     fn row(&self) -> Vec<String> {
         let file_name = self
             .file
-            .strip_prefix(REPO_ROOT_PATH().join("temporal-verifier/examples"))
-            .unwrap_or(self.file.strip_prefix(REPO_ROOT_PATH()).unwrap());
+            .strip_prefix("temporal-verifier/examples")
+            .unwrap_or(&self.file);
         let measure = &self.measurement;
         let real_time = measure.real_time.as_secs_f64();
         vec![
@@ -99,9 +175,9 @@ impl BenchmarkMeasurement {
     }
 
     /// Print a nicely-formatting table from a list of results.
-    pub fn print_table(results: Vec<Self>) {
+    pub fn print_table(results: &[Self]) {
         let mut success_counts = HashMap::<&str, usize>::new();
-        for r in &results {
+        for r in results {
             let mut key = r.success();
             if key == "" {
                 key = "ok";
@@ -27,9 +27,8 @@ use nix::{
         time::TimeVal,
         wait::{waitpid, WaitStatus},
     },
-    unistd::{dup2, getpgid, setsid, Pid},
+    unistd::{close, dup2, getpgid, pipe, read, setsid, Pid},
 };
-use process_sync::{SharedCondvar, SharedMutex};
 
 use crate::measurement::RunMeasurement;
 
@@ -212,10 +211,15 @@ impl Time {
     /// directly and is thus only intended for running from the `time` binary,
     /// not from user code.
     pub fn exec(&self) -> Result<ExitCode, io::Error> {
-        let (mut lock, mut cvar) = (SharedMutex::new().unwrap(), SharedCondvar::new().unwrap());
+        // the child closes its end of this pipe once it has its own process
+        // group, which the parent waits for before looking up the group
+        let (ready_r, ready_w) = pipe()?;
         match fork::fork() {
             Ok(Fork::Parent(child)) => {
-                cvar.wait(&mut lock).unwrap();
+                close(ready_w)?;
+                // returns at EOF, when the child closes its end
+                read(ready_r, &mut [0u8; 1])?;
+                close(ready_r)?;
                 let measurements = self.get_child_measurements(child)?;
                 let parsed = measurements.clone().into_measurement();
                 if self.json {
@@ -235,8 +239,9 @@ impl Time {
                 Ok(ExitCode::SUCCESS)
             }
             Ok(Fork::Child) => {
+                _ = close(ready_r);
                 _ = setsid();
-                cvar.notify_one().unwrap();
+                _ = close(ready_w);
                 let null = fcntl::open("/dev/null", OFlag::O_WRONLY, Mode::empty())
                     .expect("could not get /dev/null");
                 dup2(null, io::stdout().as_raw_fd()).expect("could not replace stdout with null");
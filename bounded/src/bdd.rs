@@ -20,7 +20,9 @@ pub fn check<'a>(
     depth: Option<usize>,
     print_timing: bool,
 ) -> Result<CheckerAnswer<(Bdd, Indices<'a>)>, CheckerError> {
-    check_internal(module, universe, depth, print_timing, false)
+    check_in_order(module, |run| {
+        check_internal(run, &module.signature, universe, depth, print_timing, false)
+    })
 }
 
 /// The same as `check`, but instead of starting at `init` and going until it gets to `not_safe`,
@@ -32,11 +34,17 @@ pub fn check_reversed<'a>(
     depth: Option<usize>,
     print_timing: bool,
 ) -> Result<CheckerAnswer<(Bdd, Indices<'a>)>, CheckerError> {
-    check_internal(module, universe, depth, print_timing, true)
+    check_in_order(module, |run| {
+        check_internal(run, &module.signature, universe, depth, print_timing, true)
+    })
 }
 
+/// Check a run of assertions (see [`check_in_order`]). `signature` is the
+/// signature of `module`, but borrowed from the original module so that the
+/// returned `Indices` can outlive the run.
 fn check_internal<'a>(
-    module: &'a Module,
+    module: &Module,
+    signature: &'a Signature,
     universe: &'a UniverseBounds,
     depth: Option<usize>,
     print_timing: bool,
@@ -58,7 +66,13 @@ fn check_internal<'a>(
         panic!("definitions in checker (use Module::inline_defs)")
     }
 
-    let d = extract(module).map_err(CheckerError::ExtractionError)?;
+    let (d, liveness) = extract_with_liveness(module).map_err(CheckerError::ExtractionError)?;
+    if !liveness.is_empty() && !d.fairness.is_empty() {
+        return Err(CheckerError::FairnessUnsupported);
+    }
+    if reversed && !liveness.is_empty() {
+        return Err(CheckerError::ReversedLiveness);
+    }
     let inits = d.inits.iter().chain(&d.axioms).cloned();
     let transitions = d
         .transitions
@@ -67,7 +81,7 @@ fn check_internal<'a>(
         .cloned();
     let safeties = d.proofs.iter().map(|proof| proof.safety.x.clone());
 
-    let indices = Indices::new(&module.signature, universe, 2);
+    let indices = Indices::new(signature, universe, 2);
 
     let translate = |term| {
         let term = enumerate_quantifiers(&term, &module.signature, universe)
@@ -81,6 +95,10 @@ fn check_internal<'a>(
     let init = translate(Term::and(inits))?;
     let tr = translate(Term::and(transitions))?;
     let not_safe = translate(Term::and(safeties))?.not();
+    let liveness = liveness
+        .into_iter()
+        .map(|property| Ok((translate(property.x.trigger)?, translate(property.x.goal)?)))
+        .collect::<Result<Vec<_>, CheckerError>>()?;
 
     if print_timing {
         println!(
@@ -105,21 +123,7 @@ fn check_internal<'a>(
             init,
             not_safe,
             Box::new(|current: &mut Bdd, indices: &Indices| {
-                let unprimed = 0..indices.num_mutables;
-                *current = Bdd::binary_op_with_exists(
-                    current,
-                    &tr,
-                    op_function::and,
-                    &indices.bdd_variables[unprimed.clone()],
-                );
-                for i in unprimed {
-                    unsafe {
-                        current.rename_variable(
-                            indices.bdd_variables[i + indices.num_mutables],
-                            indices.bdd_variables[i],
-                        );
-                    }
-                }
+                *current = image(current, &tr, indices);
             }),
         ),
         true => (
@@ -128,21 +132,7 @@ fn check_internal<'a>(
             not_safe,
             init,
             Box::new(|current: &mut Bdd, indices: &Indices| {
-                let primed = indices.num_mutables..indices.num_mutables * 2;
-                for i in primed.clone().rev() {
-                    unsafe {
-                        current.rename_variable(
-                            indices.bdd_variables[i - indices.num_mutables],
-                            indices.bdd_variables[i],
-                        );
-                    }
-                }
-                *current = Bdd::binary_op_with_exists(
-                    current,
-                    &tr,
-                    op_function::and,
-                    &indices.bdd_variables[primed],
-                );
+                *current = preimage(current, &tr, indices);
             }),
         ),
     };
@@ -150,7 +140,7 @@ fn check_internal<'a>(
     // Do the search
     if let Some(valuation) = current.and(&not_safe).sat_witness() {
         let models = trace_to_models(&indices, &valuation, &trace, &tr, reversed);
-        return Ok(CheckerAnswer::Counterexample(models));
+        return Ok(CheckerAnswer::Counterexample(Trace::finite(models)));
     }
     let mut i = 0;
     while depth.map(|d| i < d).unwrap_or(true) {
@@ -162,6 +152,9 @@ fn check_internal<'a>(
         }
 
         if reachable == new_reachable {
+            if let Some(lasso) = find_lasso(&indices, &trace, &reachable, &tr, &liveness) {
+                return Ok(CheckerAnswer::Counterexample(lasso));
+            }
             return Ok(CheckerAnswer::Convergence((reachable, indices)));
         } else {
             reachable = new_reachable;
@@ -170,15 +163,126 @@ fn check_internal<'a>(
         trace.push(current.clone());
         if let Some(valuation) = current.and(&not_safe).sat_witness() {
             let models = trace_to_models(&indices, &valuation, &trace, &tr, reversed);
-            return Ok(CheckerAnswer::Counterexample(models));
+            return Ok(CheckerAnswer::Counterexample(Trace::finite(models)));
         }
 
         i += 1;
     }
 
+    if let Some(lasso) = find_lasso(&indices, &trace, &reachable, &tr, &liveness) {
+        return Ok(CheckerAnswer::Counterexample(lasso));
+    }
     Ok(CheckerAnswer::Unknown)
 }
 
+/// The states reachable from `states` in one transition.
+fn image(states: &Bdd, tr: &Bdd, indices: &Indices) -> Bdd {
+    let unprimed = 0..indices.num_mutables;
+    let mut out = Bdd::binary_op_with_exists(
+        states,
+        tr,
+        op_function::and,
+        &indices.bdd_variables[unprimed.clone()],
+    );
+    for i in unprimed {
+        unsafe {
+            out.rename_variable(
+                indices.bdd_variables[i + indices.num_mutables],
+                indices.bdd_variables[i],
+            );
+        }
+    }
+    out
+}
+
+/// The states which reach `states` in one transition.
+fn preimage(states: &Bdd, tr: &Bdd, indices: &Indices) -> Bdd {
+    let primed = indices.num_mutables..indices.num_mutables * 2;
+    let mut states = states.clone();
+    for i in primed.clone().rev() {
+        unsafe {
+            states.rename_variable(
+                indices.bdd_variables[i - indices.num_mutables],
+                indices.bdd_variables[i],
+            );
+        }
+    }
+    Bdd::binary_op_with_exists(
+        &states,
+        tr,
+        op_function::and,
+        &indices.bdd_variables[primed],
+    )
+}
+
+/// The single state given by the unprimed variables of `valuation`.
+fn valuation_to_bdd(indices: &Indices, valuation: &BddValuation) -> Bdd {
+    indices.bdd_and(indices.iter().flat_map(|(relation, map)| {
+        map.iter().map(|(elements, (v, _))| {
+            let var = indices.bdd_var(relation, 0, elements);
+            if valuation.value(indices.bdd_variables[*v]) {
+                var
+            } else {
+                var.not()
+            }
+        })
+    }))
+}
+
+/// Find a lasso among the `reachable` states which violates one of the liveness properties, given
+/// as pairs of BDDs `(trigger, goal)`. The states from which the goal can be avoided forever are
+/// the greatest fixpoint `EG !goal = nu Z. !goal & EX Z`; a violation is a path (through the BFS
+/// layers in `trace`) to a triggered state in that set, followed by a walk inside the set until it
+/// revisits a state.
+fn find_lasso(
+    indices: &Indices,
+    trace: &[Bdd],
+    reachable: &Bdd,
+    tr: &Bdd,
+    liveness: &[(Bdd, Bdd)],
+) -> Option<Trace> {
+    let model = |valuation: &BddValuation| {
+        indices.model(0, |i| valuation.value(indices.bdd_variables[i]) as usize)
+    };
+
+    for (trigger, goal) in liveness {
+        let bad = reachable.and(&goal.not());
+        let mut eg = bad.clone();
+        loop {
+            let next = bad.and(&preimage(&eg, tr, indices));
+            if next == eg {
+                break;
+            }
+            eg = next;
+        }
+
+        let start = eg.and(trigger);
+        let Some((layer, valuation)) = trace
+            .iter()
+            .enumerate()
+            .find_map(|(i, layer)| Some((i, layer.and(&start).sat_witness()?)))
+        else {
+            continue;
+        };
+        let mut states = trace_to_models(indices, &valuation, &trace[..=layer], tr, false);
+        let triggered = states.len() - 1;
+        let mut valuation = valuation;
+        loop {
+            let next = image(&valuation_to_bdd(indices, &valuation), tr, indices).and(&eg);
+            valuation = next
+                .sat_witness()
+                .expect("every state in EG has a successor in EG");
+            let state = model(&valuation);
+            if let Some(loop_back) = states[triggered..].iter().position(|s| *s == state) {
+                return Some(Trace::lasso(states, triggered + loop_back));
+            }
+            states.push(state);
+        }
+    }
+
+    None
+}
+
 fn trace_to_models(
     indices: &Indices,
     valuation: &BddValuation,
@@ -453,6 +557,84 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn checker_bdd_liveness() -> Result<(), CheckerError> {
+        let source = include_str!("../../temporal-verifier/tests/examples/liveness_buggy.fly");
+
+        let mut module = fly::parser::parse(source).unwrap();
+        sort_check_module(&mut module).unwrap();
+        let universe = HashMap::from([("node".to_string(), 2)]);
+        let property = extract_with_liveness(&module).unwrap().1[0].x.to_term();
+
+        assert!(matches!(
+            check(&module, &universe, Some(0), false)?,
+            CheckerAnswer::Unknown,
+        ));
+        let CheckerAnswer::Counterexample(lasso) = check(&module, &universe, None, false)? else {
+            panic!("expected a lasso violating liveness")
+        };
+        assert!(lasso.loop_back.is_some());
+        assert_eq!(lasso.eval(&property), 0);
+
+        let source =
+            "mutable p: bool\nassume !p\nassume always ((p')<->!p)\nassert always eventually p\n";
+        let mut module = fly::parser::parse(source).unwrap();
+        sort_check_module(&mut module).unwrap();
+        assert!(matches!(
+            check(&module, &HashMap::new(), None, false)?,
+            CheckerAnswer::Convergence(_),
+        ));
+        assert!(matches!(
+            check_reversed(&module, &HashMap::new(), None, false),
+            Err(CheckerError::ReversedLiveness),
+        ));
+
+        let source = "sort node\nmutable p(node): bool\nassume always forall n:node. (p(n))' = p(n)
+assume weak fair forall n. p(n) -> !p(n)'\nassert always eventually forall n:node. !p(n)\n";
+        let mut module = fly::parser::parse(source).unwrap();
+        sort_check_module(&mut module).unwrap();
+        let (d, _) = extract_with_liveness(&module).unwrap();
+        assert_eq!(d.fairness.len(), 1);
+        assert_eq!(
+            d.fairness[0].binders[0].sort,
+            Sort::Uninterpreted("node".to_string())
+        );
+        assert!(matches!(
+            check(&module, &universe, None, false),
+            Err(CheckerError::FairnessUnsupported),
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn checker_bdd_in_order() -> Result<(), CheckerError> {
+        let source = "mutable x: bool
+assume !x
+assume always (x' <-> !x)
+assert always !x
+assume always (x' <-> x)
+assert always !x
+";
+        let mut module = fly::parser::parse(source).unwrap();
+        sort_check_module(&mut module).unwrap();
+
+        // the later assumption doesn't apply to the first assertion
+        let CheckerAnswer::Counterexample(trace) = check(&module, &HashMap::new(), None, false)?
+        else {
+            panic!("expected a counterexample to the first assertion")
+        };
+        assert_eq!(trace.states.len(), 2);
+
+        module.statements.remove(2);
+        assert!(matches!(
+            check(&module, &HashMap::new(), None, false)?,
+            CheckerAnswer::Convergence(..),
+        ));
+
+        Ok(())
+    }
+
     #[test]
     fn checker_bdd_basic_reversed() -> Result<(), CheckerError> {
         let source = include_str!("../../temporal-verifier/tests/examples/basic2.fly");
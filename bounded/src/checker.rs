@@ -4,20 +4,50 @@
 //! The interface that all bounded model checkers use.
 
 use crate::quant_enum::*;
-use fly::{semantics::*, transitions::*};
+use fly::{semantics::*, syntax::Module, transitions::*};
 use thiserror::Error;
 
 /// The result of a successful run of a bounded model checker
 #[derive(Debug, PartialEq)]
 pub enum CheckerAnswer<C> {
-    /// The checker found a counterexample
-    Counterexample(Vec<Model>),
+    /// The checker found a counterexample: a finite trace ending in a state
+    /// which violates safety, or a lasso which violates liveness
+    Counterexample(Trace),
     /// The checker did not find a counterexample
     Unknown,
     /// The checker found that the set of states stopped changing
     Convergence(C),
 }
 
+/// Check each run of consecutive assertions of `module` in order (see
+/// [`split_assertions`]), so that later assumptions aren't used for earlier
+/// assertions, and stop at the first counterexample. The answer is a
+/// convergence (that of the last run) only if every run converges.
+pub(crate) fn check_in_order<C>(
+    module: &Module,
+    mut check: impl FnMut(&Module) -> Result<CheckerAnswer<C>, CheckerError>,
+) -> Result<CheckerAnswer<C>, CheckerError> {
+    let mut unknown = false;
+    let mut answer = CheckerAnswer::Unknown;
+    for run in split_assertions(module) {
+        answer = match check(&run)? {
+            CheckerAnswer::Counterexample(trace) => {
+                return Ok(CheckerAnswer::Counterexample(trace))
+            }
+            CheckerAnswer::Unknown => {
+                unknown = true;
+                CheckerAnswer::Unknown
+            }
+            convergence => convergence,
+        };
+    }
+    Ok(if unknown {
+        CheckerAnswer::Unknown
+    } else {
+        answer
+    })
+}
+
 /// The result of an unsuccessful attempt to run a bounded model checker.
 #[derive(Debug, PartialEq, Error)]
 pub enum CheckerError {
@@ -30,11 +60,17 @@ pub enum CheckerError {
     /// See [`EnumerationError`]
     #[error("{0}")]
     EnumerationError(EnumerationError),
+    /// Lassos would also have to be fair, which isn't implemented
+    #[error("the bounded checkers do not support fairness constraints with liveness properties")]
+    FairnessUnsupported,
 
     // sat.rs specific
     /// The SAT solver failed
     #[error("solver failed, likely a timeout")]
     SatSolverFailed,
+    /// The lasso found by the SAT solver violates no liveness property
+    #[error("the lasso found by the solver does not violate liveness")]
+    InvalidLasso,
 
     // set.rs specific
     /// The translated formula was not a conjunction
@@ -49,6 +85,17 @@ pub enum CheckerError {
     /// We can't support unproven mutable axioms without post-guards
     #[error("an axiom that mentioned mutable relations couldn't be proven")]
     UnprovenMutableAxiom,
+    /// The states are too large for the set checker's fixed-size representation
+    #[error("states need {0} bits, but the set checker supports at most {1}")]
+    StateTooLarge(usize, usize),
+    /// The deadline passed before all reachable states were enumerated
+    #[error("the deadline passed before all reachable states were enumerated")]
+    DeadlinePassed,
+
+    // bdd.rs specific
+    /// Liveness needs forward reachability
+    #[error("the reversed BDD checker does not support liveness properties")]
+    ReversedLiveness,
 
     // smt.rs
     /// See solver::SolveError
@@ -6,6 +6,7 @@
 // configure clippy
 #![allow(clippy::needless_return)]
 #![allow(clippy::large_enum_variant)]
+#![allow(clippy::result_large_err)]
 #![allow(clippy::upper_case_acronyms)]
 #![allow(clippy::type_complexity)]
 #![deny(clippy::uninlined_format_args)]
@@ -3,7 +3,12 @@
 
 //! A translation from terms with quantifiers to simplified terms without them.
 
-use fly::{ouritertools::OurItertools, semantics::*, syntax::*};
+use fly::{
+    ouritertools::OurItertools,
+    semantics::*,
+    syntax::*,
+    term::hashcons::{HTerm, Node, TermStore},
+};
 use itertools::Itertools;
 use std::collections::HashMap;
 use thiserror::Error;
@@ -62,7 +67,15 @@ pub fn enumerate_quantifiers(
 ) -> Result<Enumerated, EnumerationError> {
     let term = nullary_id_to_app(term, &signature.relations);
     let term = fly::term::prime::Next::new(signature).normalize(&term);
-    term_to_enumerated(&term, universe, &HashMap::default())
+    let mut store = TermStore::new();
+    let term = store.intern(&term);
+    Enumerator {
+        store: &store,
+        universe,
+        free_vars: HashMap::default(),
+        cache: HashMap::default(),
+    }
+    .enumerate(term, &HashMap::default())
 }
 
 fn nullary_id_to_app(term: &Term, rs: &[RelationDecl]) -> Term {
@@ -188,91 +201,179 @@ impl Enumerated {
     }
 }
 
-fn term_to_enumerated(
-    term: &Term,
-    universe: &UniverseBounds,
-    assignments: &HashMap<String, Element>,
-) -> Result<Enumerated, EnumerationError> {
-    let go = |term| term_to_enumerated(term, universe, assignments);
-    let element = |term: &Term| match term {
-        Term::Id(id) => match assignments.get(id) {
-            Some(x) => Ok(*x),
-            None => Err(EnumerationError::UnknownId(term.clone())),
-        },
-        term => match term_to_enumerated(term, universe, assignments) {
-            Ok(formula) if formula == Enumerated::always_true() => Ok(1),
-            Ok(formula) if formula == Enumerated::always_false() => Ok(0),
-            _ => Err(EnumerationError::NotAnElement(term.clone())),
-        },
-    };
+/// Converts hash-consed terms to `Enumerated` terms. Subterms are shared, so the
+/// conversion of a subterm is cached for each assignment to its free variables.
+struct Enumerator<'a> {
+    store: &'a TermStore,
+    universe: &'a UniverseBounds,
+    free_vars: HashMap<HTerm, Vec<String>>,
+    cache: HashMap<(HTerm, Vec<Option<Element>>), Enumerated>,
+}
 
-    let enumerated = match term {
-        Term::Literal(true) => Enumerated::always_true(),
-        Term::Literal(false) => Enumerated::always_false(),
-        Term::Id(_) => match element(term)? {
-            1 => Enumerated::always_true(),
-            0 => Enumerated::always_false(),
-            _ => unreachable!(),
-        },
-        Term::App(name, primes, args) => {
-            if *primes > 1 {
-                return Err(EnumerationError::TooManyPrimes(term.clone()));
-            }
-            let args = args.iter().map(element).collect::<Result<Vec<_>, _>>()?;
-            Enumerated::App(name.clone(), *primes, args)
-        }
-        Term::UnaryOp(UOp::Not, term) => go(term)?.not(),
-        Term::BinOp(BinOp::Equals | BinOp::Iff, a, b) => match (element(a), element(b)) {
-            (Ok(a), Ok(b)) if a == b => Enumerated::always_true(),
-            (Ok(a), Ok(b)) if a != b => Enumerated::always_false(),
-            _ => Enumerated::Eq(Box::new(go(a)?), Box::new(go(b)?)),
-        },
-        Term::BinOp(BinOp::NotEquals, a, b) => {
-            go(&Term::BinOp(BinOp::Equals, a.clone(), b.clone()))?.not()
-        }
-        Term::BinOp(BinOp::Implies, a, b) => match element(a) {
-            Ok(1) => go(b)?,
-            Ok(0) => Enumerated::always_true(),
-            _ => Enumerated::or(vec![go(a)?.not(), go(b)?]),
-        },
-        Term::NAryOp(NOp::And, terms) => {
-            Enumerated::and(terms.iter().map(go).collect::<Result<Vec<_>, _>>()?)
-        }
-        Term::NAryOp(NOp::Or, terms) => {
-            Enumerated::or(terms.iter().map(go).collect::<Result<Vec<_>, _>>()?)
+impl Enumerator<'_> {
+    /// The free variables of `term`, sorted by name.
+    fn free_vars(&mut self, term: HTerm) -> Vec<String> {
+        if let Some(vars) = self.free_vars.get(&term) {
+            return vars.clone();
         }
-        Term::Ite { cond, then, else_ } => Enumerated::or([
-            Enumerated::and([go(cond)?, go(then)?]),
-            Enumerated::and([go(cond)?.not(), go(else_)?]),
-        ]),
-        Term::Quantified {
-            quantifier,
-            binders,
-            body,
-        } => {
-            let terms = binders
+        let mut vars = match self.store.node(term) {
+            Node::Literal(_) => vec![],
+            Node::Id(id) => vec![id.clone()],
+            Node::App(_, _, args) | Node::NAryOp(_, args) => {
+                args.iter().flat_map(|&t| self.free_vars(t)).collect()
+            }
+            Node::UnaryOp(_, t) => self.free_vars(*t),
+            Node::BinOp(_, a, b) => [*a, *b].iter().flat_map(|&t| self.free_vars(t)).collect(),
+            Node::Ite { cond, then, else_ } => [*cond, *then, *else_]
                 .iter()
-                .map(|b| cardinality(universe, &b.sort))
-                .map(|card| (0..card).collect::<Vec<Element>>())
-                .multi_cartesian_product_fixed()
-                .map(|elements| {
-                    let mut new_assignments = assignments.clone();
-                    for (binder, element) in binders.iter().zip_eq(elements) {
-                        new_assignments.insert(binder.name.clone(), element);
-                    }
-                    term_to_enumerated(body, universe, &new_assignments)
-                })
-                .collect::<Result<Vec<_>, _>>()?;
-            match quantifier {
-                Quantifier::Forall => Enumerated::and(terms),
-                Quantifier::Exists => Enumerated::or(terms),
+                .flat_map(|&t| self.free_vars(t))
+                .collect(),
+            Node::Quantified { binders, body, .. } => {
+                let binders = binders.iter().map(|b| &b.name).collect_vec();
+                let mut vars = self.free_vars(*body);
+                vars.retain(|v| !binders.contains(&v));
+                vars
             }
+        };
+        vars.sort();
+        vars.dedup();
+        self.free_vars.insert(term, vars.clone());
+        vars
+    }
+
+    fn element(
+        &mut self,
+        term: HTerm,
+        assignments: &HashMap<String, Element>,
+    ) -> Result<Element, EnumerationError> {
+        match self.store.node(term) {
+            Node::Id(id) => match assignments.get(id) {
+                Some(x) => Ok(*x),
+                None => Err(EnumerationError::UnknownId(self.store.to_term(term))),
+            },
+            _ => match self.enumerate(term, assignments) {
+                Ok(formula) if formula == Enumerated::always_true() => Ok(1),
+                Ok(formula) if formula == Enumerated::always_false() => Ok(0),
+                _ => Err(EnumerationError::NotAnElement(self.store.to_term(term))),
+            },
         }
-        Term::UnaryOp(UOp::Prime | UOp::Always | UOp::Eventually, _)
-        | Term::UnaryOp(UOp::Next | UOp::Previous, _)
-        | Term::BinOp(BinOp::Until | BinOp::Since, ..) => {
-            return Err(EnumerationError::TemporalOperator(term.clone()))
+    }
+
+    fn enumerate(
+        &mut self,
+        term: HTerm,
+        assignments: &HashMap<String, Element>,
+    ) -> Result<Enumerated, EnumerationError> {
+        let key = (
+            term,
+            self.free_vars(term)
+                .iter()
+                .map(|v| assignments.get(v).copied())
+                .collect_vec(),
+        );
+        if let Some(enumerated) = self.cache.get(&key) {
+            return Ok(enumerated.clone());
         }
-    };
-    Ok(enumerated)
+        let enumerated = self.enumerate_uncached(term, assignments)?;
+        self.cache.insert(key, enumerated.clone());
+        Ok(enumerated)
+    }
+
+    fn enumerate_uncached(
+        &mut self,
+        term: HTerm,
+        assignments: &HashMap<String, Element>,
+    ) -> Result<Enumerated, EnumerationError> {
+        let store = self.store;
+        let mut go = |term: HTerm| self.enumerate(term, assignments);
+
+        let enumerated = match store.node(term) {
+            Node::Literal(true) => Enumerated::always_true(),
+            Node::Literal(false) => Enumerated::always_false(),
+            Node::Id(_) => match self.element(term, assignments)? {
+                1 => Enumerated::always_true(),
+                0 => Enumerated::always_false(),
+                _ => unreachable!(),
+            },
+            Node::App(name, primes, args) => {
+                if *primes > 1 {
+                    return Err(EnumerationError::TooManyPrimes(store.to_term(term)));
+                }
+                let args = args
+                    .iter()
+                    .map(|&arg| self.element(arg, assignments))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Enumerated::App(name.clone(), *primes, args)
+            }
+            Node::UnaryOp(UOp::Not, term) => go(*term)?.not(),
+            Node::BinOp(op @ (BinOp::Equals | BinOp::Iff | BinOp::NotEquals), a, b) => {
+                let equals = match (self.element(*a, assignments), self.element(*b, assignments)) {
+                    (Ok(a), Ok(b)) if a == b => Enumerated::always_true(),
+                    (Ok(a), Ok(b)) if a != b => Enumerated::always_false(),
+                    _ => Enumerated::Eq(
+                        Box::new(self.enumerate(*a, assignments)?),
+                        Box::new(self.enumerate(*b, assignments)?),
+                    ),
+                };
+                if *op == BinOp::NotEquals {
+                    equals.not()
+                } else {
+                    equals
+                }
+            }
+            Node::BinOp(BinOp::Implies, a, b) => match self.element(*a, assignments) {
+                Ok(1) => self.enumerate(*b, assignments)?,
+                Ok(0) => Enumerated::always_true(),
+                _ => Enumerated::or(vec![
+                    self.enumerate(*a, assignments)?.not(),
+                    self.enumerate(*b, assignments)?,
+                ]),
+            },
+            Node::NAryOp(NOp::And, terms) => Enumerated::and(
+                terms
+                    .iter()
+                    .map(|&t| go(t))
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            Node::NAryOp(NOp::Or, terms) => Enumerated::or(
+                terms
+                    .iter()
+                    .map(|&t| go(t))
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            Node::Ite { cond, then, else_ } => Enumerated::or([
+                Enumerated::and([go(*cond)?, go(*then)?]),
+                Enumerated::and([go(*cond)?.not(), go(*else_)?]),
+            ]),
+            Node::Quantified {
+                quantifier,
+                binders,
+                body,
+            } => {
+                let terms = binders
+                    .iter()
+                    .map(|b| cardinality(self.universe, &b.sort))
+                    .map(|card| (0..card).collect::<Vec<Element>>())
+                    .multi_cartesian_product_fixed()
+                    .map(|elements| {
+                        let mut new_assignments = assignments.clone();
+                        for (binder, element) in binders.iter().zip_eq(elements) {
+                            new_assignments.insert(binder.name.clone(), element);
+                        }
+                        self.enumerate(*body, &new_assignments)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                match quantifier {
+                    Quantifier::Forall => Enumerated::and(terms),
+                    Quantifier::Exists => Enumerated::or(terms),
+                }
+            }
+            Node::UnaryOp(UOp::Prime | UOp::Always | UOp::Eventually, _)
+            | Node::UnaryOp(UOp::Next | UOp::Previous, _)
+            | Node::BinOp(BinOp::Until | BinOp::Since, ..) => {
+                return Err(EnumerationError::TemporalOperator(store.to_term(term)))
+            }
+        };
+        Ok(enumerated)
+    }
 }
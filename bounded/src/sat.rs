@@ -17,6 +17,17 @@ pub fn check(
     universe: &UniverseBounds,
     depth: usize,
     print_timing: bool,
+) -> Result<CheckerAnswer<()>, CheckerError> {
+    check_in_order(module, |module| {
+        check_run(module, universe, depth, print_timing)
+    })
+}
+
+fn check_run(
+    module: &Module,
+    universe: &UniverseBounds,
+    depth: usize,
+    print_timing: bool,
 ) -> Result<CheckerAnswer<()>, CheckerError> {
     for sort in &module.signature.sorts {
         if !universe.contains_key(sort) {
@@ -34,7 +45,10 @@ pub fn check(
         panic!("definitions in checker (use Module::inline_defs)")
     }
 
-    let d = extract(module).map_err(CheckerError::ExtractionError)?;
+    let (d, liveness) = extract_with_liveness(module).map_err(CheckerError::ExtractionError)?;
+    if !liveness.is_empty() && !d.fairness.is_empty() {
+        return Err(CheckerError::FairnessUnsupported);
+    }
     let inits = d.inits.iter().chain(&d.axioms).cloned();
     let transitions = d
         .transitions
@@ -57,7 +71,7 @@ pub fn check(
     let tr = translate(Term::and(transitions))?;
     let not_safe = Enumerated::Not(Box::new(translate(Term::and(safeties))?));
 
-    let mut program = vec![init];
+    let mut program = vec![init.clone()];
     for i in 0..depth {
         program.push(tr.clone().prime(i));
     }
@@ -75,18 +89,104 @@ pub fn check(
     println!("starting search...");
     let search = std::time::Instant::now();
 
+    let mut answer = solve(&cnf, &indices)?.map(Trace::finite);
+
+    if answer.is_none() && !liveness.is_empty() {
+        // Look for a lasso of depth + 1 states whose last state steps to the
+        // state at some loop-back index l (the extra copy depth + 1 equals copy l).
+        let mut indices = Indices::new(&module.signature, universe, depth + 2);
+        let mut program = vec![init];
+        for i in 0..=depth {
+            program.push(tr.clone().prime(i));
+        }
+        let mut violations = vec![];
+        for property in &liveness {
+            let trigger = translate(property.x.trigger.clone())?;
+            let not_goal = Enumerated::Not(Box::new(translate(property.x.goal.clone())?));
+            let not_goal_between = |from: usize, to: usize| {
+                Enumerated::And((from..to).map(|j| not_goal.clone().prime(j)).collect())
+            };
+            // Some trigger at i is followed by states violating the goal up to
+            // the end of the loop. A trigger inside the loop (i > l) recurs
+            // forever, so the goal must be violated along the whole loop.
+            violations.extend((0..=depth).map(|l| {
+                Enumerated::And(vec![
+                    loops_to(&indices, depth + 1, l),
+                    not_goal_between(l, depth + 1),
+                    Enumerated::Or(
+                        (0..=depth)
+                            .map(|i| {
+                                Enumerated::And(vec![
+                                    trigger.clone().prime(i),
+                                    not_goal_between(i.min(l), l),
+                                ])
+                            })
+                            .collect(),
+                    ),
+                ])
+            }));
+        }
+        program.push(Enumerated::Or(violations));
+
+        let cnf = tseytin(&Enumerated::And(program), &mut indices);
+        if let Some(mut states) = solve(&cnf, &indices)? {
+            let last = states.pop().unwrap();
+            let loop_back = (0..states.len())
+                .find(|&l| {
+                    states[l] == last
+                        && liveness.iter().any(|property| {
+                            Trace::lasso(states.clone(), l).eval(&property.x.to_term()) == 0
+                        })
+                })
+                .ok_or(CheckerError::InvalidLasso)?;
+            answer = Some(Trace::lasso(states, loop_back));
+        }
+    }
+
+    if print_timing {
+        println!("search finished in {:0.1}s", search.elapsed().as_secs_f64());
+    }
+
+    Ok(match answer {
+        Some(trace) => CheckerAnswer::Counterexample(trace),
+        None => CheckerAnswer::Unknown,
+    })
+}
+
+/// The constraint that the mutable copy `from` equals the mutable copy `to`.
+fn loops_to(indices: &Indices, from: usize, to: usize) -> Enumerated {
+    Enumerated::And(
+        indices
+            .iter()
+            .flat_map(|(relation, map)| {
+                map.iter()
+                    .filter(|(_, (_, mutable))| *mutable)
+                    .map(|(elements, _)| {
+                        let app = |primes| {
+                            Enumerated::App(relation.to_string(), primes, elements.clone())
+                        };
+                        Enumerated::Eq(Box::new(app(from)), Box::new(app(to)))
+                    })
+            })
+            .collect(),
+    )
+}
+
+/// Solve the CNF and return the mutable copies of a satisfying assignment as
+/// models, or `None` if it is unsatisfiable.
+fn solve(cnf: &Cnf, indices: &Indices) -> Result<Option<Vec<Model>>, CheckerError> {
     let mut solver: Solver = Default::default();
-    for clause in &cnf {
+    for clause in cnf {
         let cadical_clause = clause
             .iter()
             .map(|l| (l.var as i32 + 1) * if l.pos { 1 } else { -1 });
         solver.add_clause(cadical_clause);
     }
 
-    let answer = match solver.solve() {
+    match solver.solve() {
         None => Err(CheckerError::SatSolverFailed),
-        Some(false) => Ok(CheckerAnswer::Unknown),
-        Some(true) => Ok(CheckerAnswer::Counterexample(
+        Some(false) => Ok(None),
+        Some(true) => Ok(Some(
             (0..indices.num_mutable_copies)
                 .map(|primes| {
                     indices.model(primes, |i| {
@@ -95,13 +195,7 @@ pub fn check(
                 })
                 .collect(),
         )),
-    };
-
-    if print_timing {
-        println!("search finished in {:0.1}s", search.elapsed().as_secs_f64());
     }
-
-    answer
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -250,6 +344,44 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn checker_sat_liveness() -> Result<(), CheckerError> {
+        let source = include_str!("../../temporal-verifier/tests/examples/liveness_buggy.fly");
+
+        let mut module = fly::parser::parse(source).unwrap();
+        sort_check_module(&mut module).unwrap();
+        let universe = HashMap::from([("node".to_string(), 2)]);
+        let property = extract_with_liveness(&module).unwrap().1[0].x.to_term();
+
+        assert_eq!(CheckerAnswer::Unknown, check(&module, &universe, 0, false)?);
+        let CheckerAnswer::Counterexample(lasso) = check(&module, &universe, 1, false)? else {
+            panic!("expected a lasso violating liveness")
+        };
+        assert!(lasso.loop_back.is_some());
+        assert_eq!(lasso.eval(&property), 0);
+
+        // The trigger only holds inside the loop.
+        let source = "mutable p: bool\nmutable q: bool\nassume !p & !q\nassume always ((p')<->!p) & ((q')<->q)\nassert always (p -> eventually q)\n";
+        let mut module = fly::parser::parse(source).unwrap();
+        sort_check_module(&mut module).unwrap();
+        let CheckerAnswer::Counterexample(lasso) = check(&module, &HashMap::new(), 1, false)?
+        else {
+            panic!("expected a lasso violating liveness")
+        };
+        assert_eq!(lasso.loop_back, Some(0));
+
+        let source =
+            "mutable p: bool\nassume !p\nassume always ((p')<->!p)\nassert always eventually p\n";
+        let mut module = fly::parser::parse(source).unwrap();
+        sort_check_module(&mut module).unwrap();
+        assert!(matches!(
+            check(&module, &HashMap::new(), 5, false)?,
+            CheckerAnswer::Unknown,
+        ));
+
+        Ok(())
+    }
+
     #[test]
     fn checker_sat_immutability() -> Result<(), CheckerError> {
         let source =
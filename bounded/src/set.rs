@@ -22,9 +22,41 @@ pub fn check(
     depth: Option<usize>,
     compress_traces: TraceCompression,
     print_timing: bool,
+) -> Result<CheckerAnswer<()>, CheckerError> {
+    check_in_order(module, |module| {
+        check_run(module, universe, depth, compress_traces, print_timing)
+    })
+}
+
+fn check_run(
+    module: &Module,
+    universe: &UniverseBounds,
+    depth: Option<usize>,
+    compress_traces: TraceCompression,
+    print_timing: bool,
 ) -> Result<CheckerAnswer<()>, CheckerError> {
     let (program, indices) = translate(module, universe, print_timing)?;
-    match interpret(&program, depth, compress_traces, print_timing, &indices) {
+    let result = interpret(&program, depth, compress_traces, print_timing, &indices);
+    if !matches!(result, InterpreterResult::Counterexample(_)) && !program.liveness.is_empty() {
+        let timer = std::time::Instant::now();
+        let lasso = find_lasso(&program, depth);
+        if print_timing {
+            println!(
+                "liveness search finished in {:0.1}s",
+                timer.elapsed().as_secs_f64()
+            );
+        }
+        if let Some((states, loop_back)) = lasso {
+            let models = states
+                .iter()
+                .map(|state| indices.model(0, |i| state.get(i) as Element))
+                .collect();
+            return Ok(CheckerAnswer::Counterexample(fly::semantics::Trace::lasso(
+                models, loop_back,
+            )));
+        }
+    }
+    match result {
         InterpreterResult::Unknown => Ok(CheckerAnswer::Unknown),
         InterpreterResult::Convergence => Ok(CheckerAnswer::Convergence(())),
         InterpreterResult::Counterexample(trace) => {
@@ -45,11 +77,60 @@ pub fn check(
                     Trace::CompressedTrace(..) => unreachable!(),
                 },
             };
-            Ok(CheckerAnswer::Counterexample(models))
+            Ok(CheckerAnswer::Counterexample(
+                fly::semantics::Trace::finite(models),
+            ))
         }
     }
 }
 
+/// Enumerate the states reachable within `depth` transitions (or all reachable states, if `depth`
+/// is `None`), including ones which violate safety. Only one state is returned from each class of
+/// isomorphic states. Fails if `deadline` passes before the enumeration is done.
+pub fn reachable(
+    module: &Module,
+    universe: &UniverseBounds,
+    depth: Option<usize>,
+    deadline: Option<std::time::Instant>,
+    print_timing: bool,
+) -> Result<Vec<Model>, CheckerError> {
+    let (program, indices) = translate(module, universe, print_timing)?;
+
+    let mut seen = IsoStateSet::new(&indices);
+    let mut states = vec![];
+    let mut queue: VecDeque<(BoundedState, usize)> = VecDeque::new();
+    for init in &program.inits {
+        if seen.insert(init) {
+            queue.push_back((*init, 0));
+        }
+    }
+
+    let mut transitions = Transitions::new();
+    for tr in &program.trs {
+        transitions.insert(tr);
+    }
+
+    while let Some((state, d)) = queue.pop_front() {
+        if deadline.is_some_and(|deadline| std::time::Instant::now() >= deadline) {
+            return Err(CheckerError::DeadlinePassed);
+        }
+        states.push(indices.model(0, |i| state.get(i) as Element));
+        if depth.map(|md| d < md).unwrap_or(true) {
+            for tr in transitions.get_subsets(&state) {
+                let mut next = state;
+                tr.updates
+                    .iter()
+                    .for_each(|update| next.set(update.index, update.formula.evaluate(&state)));
+                if seen.insert(&next) {
+                    queue.push_back((next, d + 1));
+                }
+            }
+        }
+    }
+
+    Ok(states)
+}
+
 /// Compile-time upper bound on the bounded universe size.
 const STATE_LEN: usize = 128;
 
@@ -115,6 +196,9 @@ struct BoundedProgram {
     trs: Vec<Transition>,
     /// Safety property to check in each reachable state.
     safe: Formula,
+    /// Liveness properties `always (trigger -> eventually goal)`, as pairs of
+    /// `(trigger, goal)`.
+    liveness: Vec<(Formula, Formula)>,
 }
 
 /// A Transition is a deterministic partial function on states expressed as a guarded update.
@@ -217,6 +301,9 @@ fn translate<'a>(
     }
 
     let indices = Indices::new(&module.signature, universe, 1);
+    if indices.num_vars > STATE_LEN {
+        return Err(CheckerError::StateTooLarge(indices.num_vars, STATE_LEN));
+    }
 
     for sort in &module.signature.sorts {
         if !universe.contains_key(sort) {
@@ -231,7 +318,10 @@ fn translate<'a>(
     println!("starting translation...");
     let timer = std::time::Instant::now();
 
-    let d = extract(module).map_err(CheckerError::ExtractionError)?;
+    let (d, liveness) = extract_with_liveness(module).map_err(CheckerError::ExtractionError)?;
+    if !liveness.is_empty() && !d.fairness.is_empty() {
+        return Err(CheckerError::FairnessUnsupported);
+    }
 
     let formula = |term| {
         let term = enumerate_quantifiers(&term, &module.signature, universe)
@@ -401,6 +491,10 @@ fn translate<'a>(
     // compute safety property
     let safes = d.proofs.iter().map(|proof| proof.safety.x.clone());
     let safe = formula(Term::and(safes))?;
+    let liveness = liveness
+        .into_iter()
+        .map(|property| Ok((formula(property.x.trigger)?, formula(property.x.goal)?)))
+        .collect::<Result<Vec<_>, CheckerError>>()?;
 
     if print_timing {
         println!(
@@ -467,7 +561,15 @@ fn translate<'a>(
         );
     }
 
-    Ok((BoundedProgram { inits, trs, safe }, indices))
+    Ok((
+        BoundedProgram {
+            inits,
+            trs,
+            safe,
+            liveness,
+        },
+        indices,
+    ))
 }
 
 /// A propositional formula over `Guard`s.
@@ -868,6 +970,129 @@ fn interpret(
     }
 }
 
+/// Search the states reachable within `max_depth` transitions for a lasso which violates one of the
+/// program's liveness properties: a path to a state satisfying the trigger, followed by states
+/// violating the goal which end in a cycle. This is a search for a fair SCC, i.e., a nontrivial
+/// strongly connected component of goal-violating states which is reachable from a triggered state
+/// through goal-violating states; a depth-first search finds one exactly when it finds a back edge.
+///
+/// Unlike `interpret`, this does not merge isomorphic states, so that the cycle is concrete.
+/// Returns the states of the lasso and the index of the state that follows the last one.
+fn find_lasso(
+    program: &BoundedProgram,
+    max_depth: Option<usize>,
+) -> Option<(Vec<BoundedState>, usize)> {
+    let mut transitions = Transitions::new();
+    for tr in &program.trs {
+        transitions.insert(tr);
+    }
+
+    // Explore the reachable states, keeping a BFS tree and the successors of each state.
+    let mut ids: HashMap<BoundedState, usize> = HashMap::default();
+    let mut states: Vec<BoundedState> = vec![];
+    let mut parents: Vec<Option<usize>> = vec![];
+    let mut successors: Vec<Vec<usize>> = vec![];
+    let mut queue: VecDeque<(usize, usize)> = VecDeque::new();
+    for init in &program.inits {
+        if !ids.contains_key(init) {
+            ids.insert(*init, states.len());
+            states.push(*init);
+            parents.push(None);
+            successors.push(vec![]);
+            queue.push_back((states.len() - 1, 0));
+        }
+    }
+    while let Some((id, depth)) = queue.pop_front() {
+        if max_depth.map(|md| depth >= md).unwrap_or(false) {
+            continue;
+        }
+        let state = states[id];
+        for tr in transitions.get_subsets(&state) {
+            let mut next = state;
+            tr.updates
+                .iter()
+                .for_each(|update| next.set(update.index, update.formula.evaluate(&state)));
+            let next = *ids.entry(next).or_insert_with(|| {
+                states.push(next);
+                parents.push(Some(id));
+                successors.push(vec![]);
+                queue.push_back((states.len() - 1, depth + 1));
+                states.len() - 1
+            });
+            successors[id].push(next);
+        }
+    }
+    let n = states.len();
+
+    for (trigger, goal) in &program.liveness {
+        let bad: Vec<bool> = states.iter().map(|state| !goal.evaluate(state)).collect();
+
+        // The states reachable from a triggered state through goal-violating states, with the
+        // state they were reached from (`Some(None)` for the triggered states themselves).
+        let mut via: Vec<Option<Option<usize>>> = vec![None; n];
+        let mut queue: VecDeque<usize> = (0..n)
+            .filter(|&id| bad[id] && trigger.evaluate(&states[id]))
+            .collect();
+        for &id in &queue {
+            via[id] = Some(None);
+        }
+        while let Some(id) = queue.pop_front() {
+            for &next in &successors[id] {
+                if bad[next] && via[next].is_none() {
+                    via[next] = Some(Some(id));
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        // Find a cycle among those states.
+        let mut on_stack = vec![false; n];
+        let mut done = vec![false; n];
+        for root in (0..n).filter(|&id| via[id].is_some()) {
+            if done[root] {
+                continue;
+            }
+            let mut stack: Vec<(usize, usize)> = vec![(root, 0)];
+            on_stack[root] = true;
+            while let Some((id, edge)) = stack.last_mut() {
+                let id = *id;
+                if *edge == successors[id].len() {
+                    on_stack[id] = false;
+                    done[id] = true;
+                    stack.pop();
+                    continue;
+                }
+                let next = successors[id][*edge];
+                *edge += 1;
+                if via[next].is_none() || done[next] {
+                    continue;
+                }
+                if !on_stack[next] {
+                    on_stack[next] = true;
+                    stack.push((next, 0));
+                    continue;
+                }
+
+                // `next` starts a cycle; build the path to it and then go around the cycle.
+                let cycle = &stack[stack.iter().position(|(id, _)| *id == next).unwrap()..];
+                let mut path = vec![next];
+                while let Some(Some(prev)) = via[*path.last().unwrap()] {
+                    path.push(prev);
+                }
+                while let Some(prev) = parents[*path.last().unwrap()] {
+                    path.push(prev);
+                }
+                path.reverse();
+                let loop_back = path.len() - 1;
+                path.extend(cycle[1..].iter().map(|(id, _)| *id));
+                return Some((path.into_iter().map(|id| states[id]).collect(), loop_back));
+            }
+        }
+    }
+
+    None
+}
+
 /// A set of transitions indexed by their guards, i.e., a map from guards to transitions. We use a
 /// set trie data structure that allows efficiently answering the question "give me all the
 /// transitions whose guard sets are *subsets* of the given set". During model checking, this allows
@@ -1105,6 +1330,7 @@ mod tests {
                 index: 0,
                 value: false,
             }),
+            liveness: vec![],
         };
         let result0 = interpret(&program, Some(0), TraceCompression::No, false, &indices);
         let result1 = interpret(&program, Some(1), TraceCompression::No, false, &indices);
@@ -1220,6 +1446,7 @@ mod tests {
                 index: 3,
                 value: false,
             }),
+            liveness: vec![],
         };
         let result1 = interpret(&program, Some(0), TraceCompression::No, false, &indices);
         let result2 = interpret(&program, Some(1), TraceCompression::No, false, &indices);
@@ -1326,6 +1553,7 @@ mod tests {
                 Formula::Guard(excludes("holds_lock", vec![0], &indices)),
                 Formula::Guard(excludes("holds_lock", vec![1], &indices)),
             ]),
+            liveness: vec![],
         };
 
         let (target, _) = translate(&m, &universe, false)?;
@@ -1364,6 +1592,71 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn checker_set_lockserver_reachable() -> Result<(), CheckerError> {
+        let source = include_str!("../../temporal-verifier/examples/lockserver.fly");
+
+        let mut m = fly::parser::parse(source).unwrap();
+        sort_check_module(&mut m).unwrap();
+        let universe = std::collections::HashMap::from([("node".to_string(), 2)]);
+
+        let states = reachable(&m, &universe, None, None, false)?;
+        assert!(!states.is_empty());
+        let inv = fly::parser::term(
+            "forall n1:node, n2:node. holds_lock(n1) & holds_lock(n2) -> n1 = n2",
+        );
+        for state in &states {
+            assert_eq!(state.eval(&inv), 1);
+        }
+        assert!(reachable(&m, &universe, Some(0), None, false)?.len() < states.len());
+        let passed = std::time::Instant::now();
+        assert!(matches!(
+            reachable(&m, &universe, None, Some(passed), false),
+            Err(CheckerError::DeadlinePassed)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn checker_set_liveness() -> Result<(), CheckerError> {
+        let source = include_str!("../../temporal-verifier/tests/examples/liveness_buggy.fly");
+
+        let mut module = fly::parser::parse(source).unwrap();
+        sort_check_module(&mut module).unwrap();
+        let universe = std::collections::HashMap::from([("node".to_string(), 2)]);
+        let property = extract_with_liveness(&module).unwrap().1[0].x.to_term();
+
+        assert_eq!(
+            CheckerAnswer::Unknown,
+            check(&module, &universe, Some(0), TraceCompression::No, false)?
+        );
+        let CheckerAnswer::Counterexample(lasso) =
+            check(&module, &universe, None, TraceCompression::Yes, false)?
+        else {
+            panic!("expected a lasso violating liveness")
+        };
+        assert!(lasso.loop_back.is_some());
+        assert_eq!(lasso.eval(&property), 0);
+
+        let source =
+            "mutable p: bool\nassume !p\nassume always ((p')<->!p)\nassert always eventually p\n";
+        let mut module = fly::parser::parse(source).unwrap();
+        sort_check_module(&mut module).unwrap();
+        assert!(matches!(
+            check(
+                &module,
+                &UniverseBounds::new(),
+                None,
+                TraceCompression::No,
+                false
+            )?,
+            CheckerAnswer::Convergence(()),
+        ));
+
+        Ok(())
+    }
+
     #[test]
     fn checker_set_consensus() -> Result<(), CheckerError> {
         let source = include_str!("../../temporal-verifier/examples/consensus.fly");
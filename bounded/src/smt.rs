@@ -5,7 +5,7 @@
 //! This is useful, even though it's slow, because it doesn't require sort bounds.
 
 use crate::checker::*;
-use fly::{syntax::*, term::prime::Next, transitions::*};
+use fly::{semantics::Trace, syntax::*, term::prime::Next, transitions::*};
 use solver::{conf::SolverConf, SatResp};
 use std::collections::HashMap;
 
@@ -16,6 +16,17 @@ pub fn check(
     conf: &SolverConf,
     depth: usize,
     print_timing: bool,
+) -> Result<CheckerAnswer<()>, CheckerError> {
+    check_in_order(module, |module| {
+        check_run(module, conf, depth, print_timing)
+    })
+}
+
+fn check_run(
+    module: &Module,
+    conf: &SolverConf,
+    depth: usize,
+    print_timing: bool,
 ) -> Result<CheckerAnswer<()>, CheckerError> {
     if !module.defs.is_empty() {
         panic!("definitions are not supported yet");
@@ -50,11 +61,11 @@ pub fn check(
     let mut solver = conf.solver(&module.signature, depth + 1);
     solver.assert(&Term::and(program));
     let answer = match solver.check_sat(HashMap::new()).expect("error in solver") {
-        SatResp::Sat { .. } => {
+        SatResp::Sat => {
             let states = solver
                 .get_minimal_model()
                 .expect("solver error while minimizing");
-            CheckerAnswer::Counterexample(states)
+            CheckerAnswer::Counterexample(Trace::finite(states))
         }
         SatResp::Unsat => CheckerAnswer::Unknown,
         SatResp::Unknown(m) => return Err(CheckerError::SolverError(m)),
@@ -78,6 +89,7 @@ mod tests {
         SolverConf {
             backend: GenericBackend::new(SolverType::Z3, &solver_path("z3")),
             tee: None,
+            transcript: false,
         }
     }
 
@@ -3,48 +3,10 @@
 
 //! Utility to inline the definitions of a module.
 
-use std::{collections::HashMap, iter::zip};
+use std::iter::zip;
 
-use super::syntax::{Binder, Definition, Module, Proof, Term, ThmStmt};
-
-fn subst(t: &mut Term, repl: &HashMap<String, &Term>) {
-    let go = |t: &mut Term| subst(t, repl);
-    match t {
-        Term::Literal(_) => {}
-        Term::Id(ref s) => {
-            if let Some(&y) = repl.get(s) {
-                *t = y.clone();
-            }
-        }
-        Term::App(_f, _p, xs) => {
-            for t in xs {
-                go(t);
-            }
-        }
-        Term::UnaryOp(_, x) => go(x),
-        Term::BinOp(_, lhs, rhs) => {
-            go(lhs);
-            go(rhs);
-        }
-        Term::NAryOp(_, args) => {
-            for a in args {
-                go(a);
-            }
-        }
-        Term::Ite { cond, then, else_ } => {
-            go(cond);
-            go(then);
-            go(else_);
-        }
-        // TODO: didn't worry about shadowing here
-        Term::Quantified { body, .. } => go(body),
-    }
-}
-
-fn subst_binders(t: &mut Term, xs: &[Binder], ts: &[Term]) {
-    let repl = zip(xs, ts).map(|(x, y)| (x.name.clone(), y)).collect();
-    subst(t, &repl);
-}
+use super::syntax::{Definition, Module, Proof, Term, ThmStmt};
+use super::term::subst::{substitute, Substitution};
 
 fn inline_def_term(def: &Definition, t: &mut Term) {
     let body = &def.body;
@@ -60,9 +22,10 @@ fn inline_def_term(def: &Definition, t: &mut Term) {
         Term::App(f, _p, ts) => {
             if f == &def.name {
                 // substitute ts for def.binders in body before doing the replacement
-                let mut body = body.clone();
-                subst_binders(&mut body, &def.binders, ts);
-                *t = body;
+                let substitution: Substitution = zip(&def.binders, ts.iter())
+                    .map(|(x, t)| (x.name.clone(), t.clone()))
+                    .collect();
+                *t = substitute(body, &substitution);
             }
         }
         Term::UnaryOp(_, x) => go(x),
@@ -97,13 +60,21 @@ impl Module {
         }
         for step in &mut self.statements {
             match step {
-                ThmStmt::Assume(e) => inline_def_term(def, e),
-                ThmStmt::Assert(Proof { assert, invariants }) => {
+                ThmStmt::Assume(e) => inline_def_term(def, &mut e.x),
+                ThmStmt::Assert(Proof {
+                    assert,
+                    invariants,
+                    candidates,
+                }) => {
                     inline_def_term(def, &mut assert.x);
-                    for inv in invariants {
+                    for inv in invariants.iter_mut().chain(candidates) {
                         inline_def_term(def, &mut inv.x);
                     }
                 }
+                ThmStmt::Fair(fairness) => {
+                    inline_def_term(def, &mut fairness.enabled);
+                    inline_def_term(def, &mut fairness.taken);
+                }
             }
         }
     }
@@ -116,3 +87,31 @@ impl Module {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse, term};
+    use crate::syntax::ThmStmt;
+
+    #[test]
+    fn test_inline_avoids_capture() {
+        let mut m = parse(
+            "
+            sort t
+            immutable r(t, t): bool
+
+            def related(y: t) -> bool {
+              exists x:t. r(x, y)
+            }
+
+            assume forall x:t. related(x)
+            ",
+        )
+        .expect("test module should parse");
+        m.inline_defs();
+        let ThmStmt::Assume(assume) = &m.statements[0] else {
+            panic!("expected an assume");
+        };
+        assert_eq!(assume.x, term("forall x:t. exists x_1:t. r(x_1, x)"));
+    }
+}
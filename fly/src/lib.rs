@@ -6,6 +6,7 @@
 // configure clippy
 #![allow(clippy::needless_return)]
 #![allow(clippy::large_enum_variant)]
+#![allow(clippy::result_large_err)]
 #![allow(clippy::upper_case_acronyms)]
 #![allow(clippy::type_complexity)]
 #![allow(clippy::useless_format)]
@@ -18,6 +19,7 @@
 pub mod defs;
 pub mod ouritertools;
 pub mod parser;
+pub mod past;
 pub mod printer;
 pub mod rets;
 pub mod semantics;
@@ -147,29 +147,63 @@ grammar parser() for str {
      = newline_separated(<def()>)
 
      rule assume_stmt() -> ThmStmt
-     = "assume" __ t:term() { ThmStmt::Assume(t) }
+     = t:spanned(<"assume" __ t:term() {t}>) { ThmStmt::Assume(t) }
 
-     rule invariants() -> Vec<Spanned<Term>>
-     = newline_separated(<spanned(<"invariant" __ t:term() { t }>)>)
+     rule fairness_kind() -> FairnessKind
+     = "weak" word_boundary() { FairnessKind::Weak } /
+       "strong" word_boundary() { FairnessKind::Strong }
+
+     // `assume weak fair forall n:node. enabled(n) -> taken(n)`
+     rule fair_stmt() -> ThmStmt
+     = "assume" __ kind:fairness_kind() _ "fair" __ t:term()
+       { ThmStmt::Fair(Fairness::from_constraint(kind, t)) }
+
+     // an invariant (false) or candidate invariant (true)
+     rule proof_item() -> (bool, Spanned<Term>)
+     = t:spanned(<"invariant" __ t:term() { t }>) { (false, t) } /
+       t:spanned(<"candidate" __ t:term() { t }>) { (true, t) }
+
+     rule proof_items() -> Vec<(bool, Spanned<Term>)>
+     = newline_separated(<proof_item()>)
 
      rule assert_stmt() -> ThmStmt
      = t:spanned(<"assert" __ t:term() {t}>)
-       invariants:(newline_separator()
-        "proof" __ "{" _ invs:invariants() _ "}" {invs})?
-       { ThmStmt::Assert(Proof{
-          assert: t, invariants: invariants.unwrap_or_default(),
-         }) }
+       items:(newline_separator()
+        "proof" __ "{" _ items:proof_items() _ "}" {items})?
+       {
+         let (candidates, invariants): (Vec<_>, Vec<_>) =
+           items.unwrap_or_default().into_iter().partition(|(c, _)| *c);
+         ThmStmt::Assert(Proof{
+          assert: t,
+          invariants: invariants.into_iter().map(|(_, t)| t).collect(),
+          candidates: candidates.into_iter().map(|(_, t)| t).collect(),
+         })
+       }
+
+     pub rule candidates() -> Vec<Term>
+     = _ items:proof_items() _ { items.into_iter().map(|(_, t)| t.x).collect() }
 
       pub(super) rule stmt() -> ThmStmt
-      = assume_stmt() / assert_stmt()
+      = fair_stmt() / assume_stmt() / assert_stmt()
 
      rule stmts() -> Vec<ThmStmt>
      = newline_separated(<stmt()>)
 
+     rule pragma_value() -> String
+     = "\"" s:$([^'"' | '\n' | '\r']*) "\"" { s.to_string() } /
+       s:$([^' ' | '\t' | '\n' | '\r' | '#' | '"']+) { s.to_string() }
+
+     rule pragma() -> Pragma
+     = "pragma" __ name:ident() [' ' | '\t']+ value:pragma_value()
+       { Pragma { name, value } }
+
+     rule pragmas() -> Vec<Pragma>
+     = newline_separated(<pragma()>)
+
      rule module0() -> Module
-     = _ sig:signature() _ defs:defs() _ thm:stmts() _
+     = _ pragmas:pragmas() _ sig:signature() _ defs:defs() _ thm:stmts() _
        { Module{
-          signature: sig, defs, statements: thm,
+          pragmas, signature: sig, defs, statements: thm,
          } }
 
       pub rule module() -> Module = traced(<module0()>)
@@ -192,6 +226,46 @@ grammar parser() for str {
   }
 }
 
+/// Parse a single term.
+pub fn term(s: &str) -> Term {
+    parser::term(s).expect("test term should parse")
+}
+
+/// Parse a single term, reporting a human-readable error on failure.
+pub fn parse_term(s: &str) -> Result<Term, ParseError<LineCol>> {
+    parser::term(s)
+}
+
+/// Parse a signature.
+pub fn parse_signature(s: &str) -> Signature {
+    parser::signature(s.trim()).expect("invalid signature in test")
+}
+
+/// Parse a fly module, reporting a human-readable error on failure.
+pub fn parse(s: &str) -> Result<Module, ParseError<LineCol>> {
+    parser::module(s)
+}
+
+/// Parse a list of candidate invariants, each written as `candidate t` or
+/// `invariant t` on its own line (as in a proof block).
+pub fn parse_candidates(s: &str) -> Result<Vec<Term>, ParseError<LineCol>> {
+    parser::candidates(s)
+}
+
+/// Convert an opaque FileId and error to a readable `Diagnostic`
+pub fn parse_error_diagnostic<FileId>(
+    file_id: FileId,
+    e: &ParseError<LineCol>,
+) -> Diagnostic<FileId> {
+    Diagnostic::error()
+        .with_message("could not parse file")
+        .with_labels(vec![Label::primary(
+            file_id,
+            e.location.offset..e.location.offset + 1,
+        )
+        .with_message(format!("expected {}", e.expected))])
+}
+
 #[cfg(test)]
 mod tests {
     use super::parser;
@@ -288,6 +362,7 @@ proof {
     invariant !p
     invariant !q
     # invariant $l2s_w. p
+    candidate !p | !q
 }
 
 # we don't allow this: forall x:t1. exists x:t2. p(x:t1, x:t2)
@@ -298,12 +373,100 @@ proof {
         assert_eq!(m.statements.len(), 2);
         match &m.statements[1] {
             ThmStmt::Assert(pf) => {
-                assert_eq!(pf.invariants.len(), 2, "wrong number of invariants parsed")
+                assert_eq!(pf.invariants.len(), 2, "wrong number of invariants parsed");
+                assert_eq!(pf.candidates.len(), 1, "wrong number of candidates parsed");
             }
             _ => panic!("incorrect 2nd statement"),
         }
     }
 
+    #[test]
+    fn test_fairness() {
+        let m = parser::module(
+            r"sort node
+mutable p(node): bool
+mutable q: bool
+
+assume weak fair forall n:node. p(n) -> !p(n)'
+assume strong fair q -> !q'
+assume weak fair q
+assume forall n:node. (always eventually p(n)) -> (always eventually !p(n)')
+",
+        )
+        .expect("test module should parse");
+        let fairness = m
+            .statements
+            .iter()
+            .map(|s| match s {
+                ThmStmt::Fair(f) => f.clone(),
+                ThmStmt::Assume(t) => Fairness::from_term(&t.x).expect("should be fairness"),
+                _ => panic!("expected only fairness constraints"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(fairness[0].kind, FairnessKind::Weak);
+        assert_eq!(fairness[0].binders.len(), 1);
+        assert_eq!(fairness[0].enabled, term("p(n)"));
+        assert_eq!(fairness[0].taken, term("!p(n)'"));
+        assert_eq!(fairness[1].kind, FairnessKind::Strong);
+        assert_eq!(fairness[1].binders, vec![]);
+        assert_eq!(fairness[2].enabled, Term::true_());
+        assert_eq!(fairness[2].taken, term("q"));
+        assert_eq!(fairness[3].kind, FairnessKind::Strong);
+        assert_eq!(fairness[3].taken, term("!p(n)'"));
+
+        for f in &fairness {
+            assert_eq!(&Fairness::from_term(&f.to_term()).unwrap(), f);
+            assert_eq!(&Fairness::from_constraint(f.kind, f.constraint()), f);
+        }
+        let mut fair = m.clone();
+        fair.statements = fairness.into_iter().map(ThmStmt::Fair).collect();
+        let reparsed =
+            parser::module(&crate::printer::fmt(&fair)).expect("printed module should parse");
+        assert_eq!(reparsed.statements, fair.statements);
+
+        // a constraint whose action is quantified keeps its own binders
+        let f = Fairness::from_constraint(FairnessKind::Weak, term("true -> forall n:node. p(n)"));
+        assert_eq!(f.binders, vec![]);
+        assert_eq!(Fairness::from_constraint(f.kind, f.constraint()), f);
+    }
+
+    #[test]
+    fn test_candidates() {
+        let candidates = parser::candidates(
+            r"
+candidate !p
+# comments are allowed
+invariant p -> q
+",
+        )
+        .expect("candidates should parse");
+        assert_eq!(candidates, vec![term("!p"), term("p -> q")]);
+    }
+
+    #[test]
+    fn test_module_pragmas() {
+        let m = parser::module(
+            r#"pragma smt_logic UF
+pragma solver_opt "mbqi=true"
+# comments are allowed between pragmas
+pragma solver_opt seed=3
+
+mutable p: bool
+
+assert p
+"#,
+        )
+        .expect("test module should parse");
+        assert_eq!(m.pragma_values("smt_logic").collect::<Vec<_>>(), ["UF"]);
+        assert_eq!(
+            m.pragma_values("solver_opt").collect::<Vec<_>>(),
+            ["mbqi=true", "seed=3"]
+        );
+        assert_eq!(m.signature.relations.len(), 1);
+
+        assert!(parser::module("pragma smt_logic\nmutable p: bool").is_err());
+    }
+
     #[test]
     fn test_quantifiers() {
         term("forall x:t. x = y");
@@ -341,32 +504,3 @@ proof {
         );
     }
 }
-
-/// Parse a single term.
-pub fn term(s: &str) -> Term {
-    parser::term(s).expect("test term should parse")
-}
-
-/// Parse a signature.
-pub fn parse_signature(s: &str) -> Signature {
-    parser::signature(s.trim()).expect("invalid signature in test")
-}
-
-/// Parse a fly module, reporting a human-readable error on failure.
-pub fn parse(s: &str) -> Result<Module, ParseError<LineCol>> {
-    parser::module(s)
-}
-
-/// Convert an opaque FileId and error to a readable `Diagnostic`
-pub fn parse_error_diagnostic<FileId>(
-    file_id: FileId,
-    e: &ParseError<LineCol>,
-) -> Diagnostic<FileId> {
-    Diagnostic::error()
-        .with_message("could not parse file")
-        .with_labels(vec![Label::primary(
-            file_id,
-            e.location.offset..e.location.offset + 1,
-        )
-        .with_message(format!("expected {}", e.expected))])
-}
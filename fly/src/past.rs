@@ -0,0 +1,408 @@
+// Copyright 2022-2023 VMware, Inc.
+// SPDX-License-Identifier: BSD-2-Clause
+
+//! Utility to replace the past-time operators in a Module with history relations.
+//!
+//! Each past subformula `X^-1 t` or `a since b` gets a fresh mutable relation
+//! `h`, with one argument for every variable of the subformula which is bound
+//! outside of it. The relation holds the value of `X^-1 t` or `X^-1 (a since
+//! b)` in the current state, so it is initially false and is updated by `h' <->
+//! t` or `h' <-> (b | a & h)`, which only refer to the current state. Then `X^-1
+//! t` is replaced by `h` and `a since b` by `b | a & h`.
+
+use crate::{semantics::*, syntax::*, term::fo::FirstOrder};
+use thiserror::Error;
+
+/// The result of an unsuccessful attempt to run `monitor_past`.
+#[derive(Debug, Error, PartialEq)]
+pub enum PastError {
+    /// A history relation needs an argument for this variable, but its sort is unknown.
+    #[error("variable {0} is used under a past operator, but has no sort annotation")]
+    MissingSort(String),
+}
+
+impl Module {
+    /// Replaces all past-time operators (`X^-1` and `since`) with fresh
+    /// mutable relations that record the history of the trace. Their initial
+    /// conditions are added as assumptions, and their updates are added to the
+    /// first transition relation (or as a new one, if there is none), inside
+    /// each of its top-level disjuncts. Returns a function which removes the
+    /// history relations from models.
+    ///
+    /// This should be called after sort checking and inlining definitions.
+    pub fn monitor_past(&mut self) -> Result<Box<dyn Fn(&Model) -> Model>, PastError> {
+        let old_signature = self.signature.clone();
+        let mut monitor = Monitor {
+            signature: &mut self.signature,
+            histories: vec![],
+            inits: vec![],
+            updates: vec![],
+        };
+
+        for statement in &mut self.statements {
+            match statement {
+                ThmStmt::Assume(term) => term.x = monitor.rewrite(&term.x, &[])?,
+                ThmStmt::Assert(Proof {
+                    assert,
+                    invariants,
+                    candidates,
+                }) => {
+                    assert.x = monitor.rewrite(&assert.x, &[])?;
+                    for invariant in invariants.iter_mut().chain(candidates) {
+                        invariant.x = monitor.rewrite(&invariant.x, &[])?;
+                    }
+                }
+                ThmStmt::Fair(fairness) => {
+                    fairness.enabled = monitor.rewrite(&fairness.enabled, &fairness.binders)?;
+                    fairness.taken = monitor.rewrite(&fairness.taken, &fairness.binders)?;
+                }
+            }
+        }
+
+        let Monitor { inits, updates, .. } = monitor;
+        if !updates.is_empty() {
+            let transition = self
+                .statements
+                .iter_mut()
+                .find_map(|statement| match statement {
+                    ThmStmt::Assume(Spanned {
+                        x: Term::UnaryOp(UOp::Always, term),
+                        ..
+                    }) if FirstOrder::unrolling(term) == Some(1) => Some(term),
+                    _ => None,
+                });
+            let assume = |x| ThmStmt::Assume(Spanned { x, span: None });
+            let mut assumes = inits.into_iter().map(assume).collect::<Vec<_>>();
+            match transition {
+                Some(term) => **term = add_to_transition(term, &updates),
+                None => assumes.push(assume(Term::always(Term::and(updates)))),
+            }
+            self.statements.splice(0..0, assumes);
+        }
+
+        Ok(Box::new(move |model| {
+            Model::new(
+                &old_signature,
+                &model.universe,
+                model.interp[..old_signature.relations.len()].to_vec(),
+            )
+        }))
+    }
+
+    /// Returns whether some statement of the module uses a past-time operator.
+    /// Commands which don't call `monitor_past` should reject such modules.
+    pub fn uses_past(&self) -> bool {
+        self.statements.iter().any(|statement| match statement {
+            ThmStmt::Assume(term) => contains_past(&term.x),
+            ThmStmt::Assert(Proof {
+                assert,
+                invariants,
+                candidates,
+            }) => {
+                contains_past(&assert.x)
+                    || invariants
+                        .iter()
+                        .chain(candidates)
+                        .any(|inv| contains_past(&inv.x))
+            }
+            ThmStmt::Fair(fairness) => {
+                contains_past(&fairness.enabled) || contains_past(&fairness.taken)
+            }
+        })
+    }
+}
+
+/// The state of the rewriting: the history relations added so far.
+struct Monitor<'a> {
+    signature: &'a mut Signature,
+    /// Each past subformula (with its rewritten arguments) and the name of
+    /// its history relation
+    histories: Vec<(Term, String)>,
+    inits: Vec<Term>,
+    updates: Vec<Term>,
+}
+
+impl Monitor<'_> {
+    /// Rewrite the past operators in `term`, where `scope` is the list of
+    /// variables bound around it (from outermost to innermost).
+    fn rewrite(&mut self, term: &Term, scope: &[Binder]) -> Result<Term, PastError> {
+        let mut go = |t: &Term| self.rewrite(t, scope);
+        Ok(match term {
+            Term::UnaryOp(UOp::Previous, t) => {
+                let t = go(t)?;
+                self.history(Term::previous(t.clone()), scope, |_| t.clone())?
+            }
+            Term::BinOp(BinOp::Since, lhs, rhs) => {
+                let (lhs, rhs) = (go(lhs)?, go(rhs)?);
+                let now = |h: &Term| Term::or([rhs.clone(), Term::and([lhs.clone(), h.clone()])]);
+                let h = self.history(Term::since(lhs.clone(), rhs.clone()), scope, now)?;
+                now(&h)
+            }
+            Term::Literal(_) | Term::Id(_) => term.clone(),
+            Term::App(f, p, xs) => {
+                Term::App(f.clone(), *p, xs.iter().map(go).collect::<Result<_, _>>()?)
+            }
+            Term::UnaryOp(op, t) => Term::UnaryOp(*op, Box::new(go(t)?)),
+            Term::BinOp(op, lhs, rhs) => Term::BinOp(*op, Box::new(go(lhs)?), Box::new(go(rhs)?)),
+            Term::NAryOp(op, ts) => Term::NAryOp(*op, ts.iter().map(go).collect::<Result<_, _>>()?),
+            Term::Ite { cond, then, else_ } => Term::ite(go(cond)?, go(then)?, go(else_)?),
+            Term::Quantified {
+                quantifier,
+                binders,
+                body,
+            } => {
+                let mut scope = scope.to_vec();
+                scope.extend(binders.iter().cloned());
+                Term::Quantified {
+                    quantifier: *quantifier,
+                    binders: binders.clone(),
+                    body: Box::new(self.rewrite(body, &scope)?),
+                }
+            }
+        })
+    }
+
+    /// Return the history relation of the past subformula `key` applied to its
+    /// free variables, adding the relation if it's new. `now` gives the value
+    /// of the history relation in the next state, given its value in this one.
+    fn history(
+        &mut self,
+        key: Term,
+        scope: &[Binder],
+        now: impl Fn(&Term) -> Term,
+    ) -> Result<Term, PastError> {
+        // the innermost binding of each variable that is free in `key`
+        let mut vars: Vec<Binder> = vec![];
+        for binder in scope.iter().rev() {
+            if occurs_free(&binder.name, &key) && vars.iter().all(|v| v.name != binder.name) {
+                if binder.sort == Sort::unknown() {
+                    return Err(PastError::MissingSort(binder.name.clone()));
+                }
+                vars.push(binder.clone());
+            }
+        }
+        vars.reverse();
+
+        let key = Term::forall(vars.clone(), key);
+        let name = match self.histories.iter().find(|(k, _)| *k == key) {
+            Some((_, name)) => name.clone(),
+            None => {
+                let name = (0..)
+                    .map(|i| format!("__past_{i}"))
+                    .find(|name| !self.signature.contains_relation(name))
+                    .unwrap();
+                self.signature.relations.push(RelationDecl {
+                    mutable: true,
+                    name: name.clone(),
+                    args: vars.iter().map(|v| v.sort.clone()).collect(),
+                    sort: Sort::Bool,
+                });
+                let h = apply(&name, &vars);
+                self.inits
+                    .push(Term::forall(vars.clone(), Term::not(h.clone())));
+                self.updates.push(Term::forall(
+                    vars.clone(),
+                    Term::iff(Term::prime(h.clone()), now(&h)),
+                ));
+                self.histories.push((key, name.clone()));
+                name
+            }
+        };
+        Ok(apply(&name, &vars))
+    }
+}
+
+fn apply(name: &str, vars: &[Binder]) -> Term {
+    if vars.is_empty() {
+        Term::id(name)
+    } else {
+        Term::app(name, 0, vars.iter().map(|v| Term::id(&v.name)))
+    }
+}
+
+fn occurs_free(name: &str, term: &Term) -> bool {
+    let go = |t| occurs_free(name, t);
+    match term {
+        Term::Literal(_) => false,
+        Term::Id(id) => id == name,
+        Term::App(_, _, xs) | Term::NAryOp(_, xs) => xs.iter().any(go),
+        Term::UnaryOp(_, x) => go(x),
+        Term::BinOp(_, x, y) => go(x) || go(y),
+        Term::Ite { cond, then, else_ } => go(cond) || go(then) || go(else_),
+        Term::Quantified { binders, body, .. } => {
+            binders.iter().all(|b| b.name != name) && go(body)
+        }
+    }
+}
+
+fn contains_past(term: &Term) -> bool {
+    match term {
+        Term::UnaryOp(UOp::Previous, _) | Term::BinOp(BinOp::Since, _, _) => true,
+        Term::Literal(_) | Term::Id(_) => false,
+        Term::App(_, _, xs) | Term::NAryOp(_, xs) => xs.iter().any(contains_past),
+        Term::UnaryOp(_, x) => contains_past(x),
+        Term::BinOp(_, x, y) => contains_past(x) || contains_past(y),
+        Term::Ite { cond, then, else_ } => {
+            contains_past(cond) || contains_past(then) || contains_past(else_)
+        }
+        Term::Quantified { body, .. } => contains_past(body),
+    }
+}
+
+/// Conjoin `updates` to the transition relation `tr`, inside its top-level
+/// disjunctions and existential quantifiers (where that doesn't capture any
+/// names), so that each disjunct still describes a complete transition.
+fn add_to_transition(tr: &Term, updates: &[Term]) -> Term {
+    match tr {
+        Term::NAryOp(NOp::Or, ts) => Term::NAryOp(
+            NOp::Or,
+            ts.iter().map(|t| add_to_transition(t, updates)).collect(),
+        ),
+        Term::Quantified {
+            quantifier: Quantifier::Exists,
+            binders,
+            body,
+        } if binders
+            .iter()
+            .all(|b| updates.iter().all(|u| !occurs_free(&b.name, u))) =>
+        {
+            Term::Quantified {
+                quantifier: Quantifier::Exists,
+                binders: binders.clone(),
+                body: Box::new(add_to_transition(body, updates)),
+            }
+        }
+        tr => Term::and([tr.clone()].into_iter().chain(updates.iter().cloned())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parser::parse,
+        semantics::{Interpretation, Model, Trace},
+        sorts::sort_check_module,
+        syntax::*,
+    };
+
+    fn monitored(source: &str) -> Module {
+        let mut m = parse(source).expect("test module should parse");
+        sort_check_module(&mut m).expect("test module should sort check");
+        assert!(m.uses_past());
+        let _ = m
+            .monitor_past()
+            .expect("past operators should be monitored");
+        m
+    }
+
+    #[test]
+    fn test_monitor_past() {
+        let m = monitored(
+            "sort value
+mutable proposed(value): bool
+mutable decided(value): bool
+
+assume forall v:value. !proposed(v) & !decided(v)
+assume always exists v:value.
+    (forall x:value. proposed(x)' <-> proposed(x) | x = v) & (forall x:value. decided(x)' <-> decided(x)) |
+    (forall x:value. proposed(x)' <-> proposed(x)) & (forall x:value. decided(x)' <-> decided(x) | x = v)
+assert always forall v:value. decided(v) -> (true since proposed(v))
+assert always forall v:value. decided(v) -> X^-1 (true since proposed(v))
+",
+        );
+
+        // the same subformula shares a history relation
+        let histories = m
+            .signature
+            .relations
+            .iter()
+            .filter(|r| r.name.starts_with("__past_"))
+            .collect::<Vec<_>>();
+        assert_eq!(histories.len(), 2);
+        assert!(!m.uses_past());
+        assert_eq!(histories[0].args, vec![Sort::uninterpreted("value")]);
+        assert!(m.statements.iter().all(|s| match s {
+            ThmStmt::Assert(p) => !format!("{}", p.assert.x).contains("since"),
+            _ => true,
+        }));
+
+        let d = crate::transitions::extract(&m).expect("monitored module should extract");
+        assert_eq!(d.inits.len(), 3);
+        assert_eq!(d.transitions.len(), 1);
+        match &d.transitions[0] {
+            Term::Quantified {
+                quantifier: Quantifier::Exists,
+                body,
+                ..
+            } => assert!(matches!(**body, Term::NAryOp(NOp::Or, _))),
+            tr => panic!("expected the updates under the existential, got {tr}"),
+        }
+    }
+
+    #[test]
+    fn test_monitor_past_semantics() {
+        let mut original =
+            parse("mutable p: bool\nmutable q: bool\nassume always (p since q) | X^-1 X^-1 p\n")
+                .unwrap();
+        sort_check_module(&mut original).unwrap();
+        let mut m = original.clone();
+        let drop_history = m.monitor_past().unwrap();
+        assert_eq!(m.signature.relations.len(), 5);
+
+        let always = |s: &ThmStmt| match s {
+            ThmStmt::Assume(Spanned {
+                x: Term::UnaryOp(UOp::Always, t),
+                ..
+            }) => t.as_ref().clone(),
+            _ => panic!("expected an always assumption"),
+        };
+        let inits = (0..3)
+            .map(|i| match &m.statements[i] {
+                ThmStmt::Assume(t) => t.x.clone(),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        let (updates, rewritten, original) = (
+            always(&m.statements[3]),
+            always(&m.statements[4]),
+            always(&original.statements[0]),
+        );
+
+        let state = |bits: usize| {
+            let interp = (0..5)
+                .map(|i| Interpretation::new(&[2], |_| (bits >> i) & 1))
+                .collect();
+            Model::new(&m.signature, &vec![], interp)
+        };
+
+        // every trace of 4 states, given by the values of p and q
+        for trace in 0..(1 << 8) {
+            let pq = |i: usize| (trace >> (2 * i)) & 3;
+            let mut states = vec![state(pq(0))];
+            assert!(inits
+                .iter()
+                .all(|t| Trace::finite(states.clone()).eval(t) == 1));
+            for i in 1..4 {
+                let next = (0..8)
+                    .map(|h| state(pq(i) | (h << 2)))
+                    .filter(|next| {
+                        let step = Trace::finite(vec![states[i - 1].clone(), next.clone()]);
+                        step.eval(&updates) == 1
+                    })
+                    .collect::<Vec<_>>();
+                assert_eq!(next.len(), 1, "history relations should be determined");
+                states.extend(next);
+            }
+
+            let monitored = Trace::finite(states.clone());
+            let unmonitored = Trace::finite(states.iter().map(&drop_history).collect());
+            for i in 0..4 {
+                assert_eq!(
+                    monitored.eval_at(&rewritten, i),
+                    unmonitored.eval_at(&original, i),
+                    "position {i} of trace {trace:#b}"
+                );
+            }
+        }
+    }
+}
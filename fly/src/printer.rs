@@ -6,6 +6,7 @@
 use std::fmt;
 
 use crate::syntax::*;
+use crate::term::simplify::simplify;
 
 fn precedence(t: &Term) -> usize {
     use crate::syntax::{BinOp::*, NOp::*, Quantifier::*, Term::*, UOp::*};
@@ -265,6 +266,11 @@ fn proof(p: &Proof) -> String {
         .invariants
         .iter()
         .map(|inv| format!("  invariant {}", term(&inv.x)))
+        .chain(
+            p.candidates
+                .iter()
+                .map(|c| format!("  candidate {}", term(&c.x))),
+        )
         .collect::<Vec<_>>()
         .join("\n");
     format!("{assert}\nproof {{\n{invariants}\n}}")
@@ -272,12 +278,33 @@ fn proof(p: &Proof) -> String {
 
 fn thm_stmt(s: &ThmStmt) -> String {
     match s {
-        ThmStmt::Assume(t) => format!("assume {}", term(t)),
+        ThmStmt::Assume(t) => format!("assume {}", term(&t.x)),
         ThmStmt::Assert(p) => proof(p),
+        ThmStmt::Fair(f) => {
+            let kind = match f.kind {
+                FairnessKind::Weak => "weak",
+                FairnessKind::Strong => "strong",
+            };
+            format!("assume {kind} fair {}", term(&f.constraint()))
+        }
+    }
+}
+
+fn pragma(p: &Pragma) -> String {
+    if p.value.is_empty() || p.value.contains(|c: char| c.is_whitespace() || c == '#') {
+        format!("pragma {} \"{}\"", p.name, p.value)
+    } else {
+        format!("pragma {} {}", p.name, p.value)
     }
 }
 
 fn module(m: &Module) -> String {
+    let pragmas = if m.pragmas.is_empty() {
+        "".to_string()
+    } else {
+        let pragmas = m.pragmas.iter().map(pragma).collect::<Vec<_>>().join("\n");
+        format!("{pragmas}\n\n")
+    };
     let sig = signature(&m.signature);
     let defs = m
         .defs
@@ -291,7 +318,7 @@ fn module(m: &Module) -> String {
         .map(thm_stmt)
         .collect::<Vec<_>>()
         .join("\n");
-    format!("{sig}\n{defs}{stmts}")
+    format!("{pragmas}{sig}\n{defs}{stmts}")
 }
 
 /// Pretty-print a module.
@@ -299,6 +326,86 @@ pub fn fmt(m: &Module) -> String {
     module(m)
 }
 
+/// Pretty-print a module with all of its terms simplified, which is more
+/// readable for modules produced by transformations such as inlining.
+pub fn fmt_simplified(m: &Module) -> String {
+    let mut m = m.clone();
+    for def in &mut m.defs {
+        def.body = simplify(&def.body);
+    }
+    for statement in &mut m.statements {
+        match statement {
+            ThmStmt::Assume(t) => t.x = simplify(&t.x),
+            ThmStmt::Assert(p) => {
+                // keep the `always` of a safety property even if its body
+                // simplifies to a literal
+                p.assert.x = match &p.assert.x {
+                    Term::UnaryOp(UOp::Always, t) => Term::always(simplify(t)),
+                    t => simplify(t),
+                };
+                for t in p.invariants.iter_mut().chain(&mut p.candidates) {
+                    t.x = simplify(&t.x);
+                }
+            }
+            ThmStmt::Fair(f) => {
+                f.enabled = simplify(&f.enabled);
+                f.taken = simplify(&f.taken);
+            }
+        }
+    }
+    module(&m)
+}
+
+/// Find the end of the `proof` block following an assertion that ends at
+/// `start` in `source`, if there is one.
+fn proof_block_end(source: &str, start: usize) -> Option<usize> {
+    let mut i = start;
+    // skip whitespace and comments between the assertion and its proof
+    loop {
+        let rest = &source[i..];
+        let trimmed = rest.trim_start();
+        i += rest.len() - trimmed.len();
+        if !trimmed.starts_with('#') {
+            break;
+        }
+        i += trimmed.find('\n').unwrap_or(trimmed.len());
+    }
+    let rest = &source[i..];
+    if !rest.strip_prefix("proof")?.trim_start().starts_with('{') {
+        return None;
+    }
+    let mut in_comment = false;
+    for (j, c) in rest.char_indices() {
+        match c {
+            '#' => in_comment = true,
+            '\n' => in_comment = false,
+            '}' if !in_comment => return Some(i + j + 1),
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Insert proofs into the source text of a module. Each proof is given by the
+/// span of its `assert` statement and the invariants to put in it, and replaces
+/// the existing `proof` block of that assertion, if any. The invariants are
+/// simplified before they are printed.
+pub fn replace_proofs(source: &str, proofs: &[(Span, Vec<Term>)]) -> String {
+    let mut proofs = proofs.iter().collect::<Vec<_>>();
+    proofs.sort_by_key(|(span, _)| span.start);
+    let mut source = source.to_string();
+    // edit from the end so that earlier spans remain valid
+    for (span, invariants) in proofs.into_iter().rev() {
+        let end = proof_block_end(&source, span.end).unwrap_or(span.end);
+        let invariants = invariants
+            .iter()
+            .map(|inv| format!("  invariant {}\n", term(&simplify(inv))))
+            .collect::<String>();
+        source.replace_range(span.end..end, &format!("\nproof {{\n{invariants}}}"));
+    }
+    source
+}
+
 #[cfg(test)]
 mod module_tests {
     use super::*;
@@ -312,4 +419,72 @@ mod module_tests {
         let m = parse(&s).expect("basic1.fly should parse");
         insta::assert_display_snapshot!(module(&m));
     }
+
+    #[test]
+    fn test_fmt_simplified() {
+        let m = parse(
+            "mutable p: bool
+mutable q: bool
+
+assume !p & (q & true)
+assert always (p -> p) | q
+proof {
+    invariant !!q | false
+}
+",
+        )
+        .expect("test module should parse");
+        insta::assert_display_snapshot!(fmt_simplified(&m), @r###"
+        mutable p: bool
+        mutable q: bool
+
+        assume !p & q
+        assert always true
+        proof {
+          invariant q
+        }
+        "###);
+    }
+
+    #[test]
+    fn test_replace_proofs() {
+        let s = "mutable p: bool
+mutable q: bool
+
+assume !p & !q & (always p'=(p|q) & q'=q)
+assert always !q
+assert always !p
+# an old proof
+proof {
+    invariant p -> q # not needed }
+}
+";
+        let m = parse(s).expect("test module should parse");
+        let spans = m
+            .statements
+            .iter()
+            .filter_map(|s| match s {
+                ThmStmt::Assert(p) => p.assert.span,
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let proofs = vec![
+            (spans[1], vec![crate::parser::term("!q")]),
+            (spans[0], vec![]),
+        ];
+        insta::assert_display_snapshot!(replace_proofs(s, &proofs), @r###"
+        mutable p: bool
+        mutable q: bool
+
+        assume !p & !q & (always p'=(p|q) & q'=q)
+        assert always !q
+        proof {
+        }
+        assert always !p
+        proof {
+          invariant !q
+        }
+        "###);
+        parse(&replace_proofs(s, &proofs)).expect("edited module should parse");
+    }
 }
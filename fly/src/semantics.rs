@@ -51,7 +51,7 @@ impl Interpretation {
 
     /// Create a new interpretation of a given shape based on a function, by
     /// calling the function on all possible input tuple
-    pub fn new(shape: &Vec<usize>, f: impl Fn(&[Element]) -> Element) -> Self {
+    pub fn new(shape: &[usize], f: impl Fn(&[Element]) -> Element) -> Self {
         let args = &shape[..shape.len() - 1];
         let ret_card = shape[shape.len() - 1];
         // wrap f just to add this assertion
@@ -67,7 +67,7 @@ impl Interpretation {
             .map(|args| f(&args))
             .collect();
         Self {
-            shape: shape.clone(),
+            shape: shape.to_vec(),
             data,
         }
     }
@@ -416,6 +416,245 @@ where
         .join("\n")
 }
 
+/// A sequence of models over a shared signature and universe, on which
+/// temporal terms are evaluated.
+///
+/// A trace is either finite, in which case it is interpreted with the usual
+/// finite-trace (LTLf) semantics, or a lasso, which represents the infinite
+/// sequence that repeats the states from `loop_back` onwards forever. On finite
+/// traces, `X` (and `eventually` and `until`) only look at the remaining
+/// states, so `X p` is false in the last state, while `always p` only needs to
+/// hold in the remaining states. Past operators are strict at the first state:
+/// `X^-1 p` is false there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    /// The states of the trace
+    pub states: Vec<Model>,
+    /// For a lasso, the index of the state which follows the last state
+    pub loop_back: Option<usize>,
+}
+
+fn bool_to_element(b: bool) -> Element {
+    if b {
+        1
+    } else {
+        0
+    }
+}
+
+/// The nesting depth of past operators in a term.
+fn past_depth(t: &Term) -> usize {
+    match t {
+        Term::Literal(_) | Term::Id(_) => 0,
+        Term::App(_, _, args) => args.iter().map(past_depth).max().unwrap_or(0),
+        Term::UnaryOp(Previous, t) => past_depth(t) + 1,
+        Term::UnaryOp(_, t) => past_depth(t),
+        Term::BinOp(Since, lhs, rhs) => past_depth(lhs).max(past_depth(rhs)) + 1,
+        Term::BinOp(_, lhs, rhs) => past_depth(lhs).max(past_depth(rhs)),
+        Term::NAryOp(_, ts) => ts.iter().map(past_depth).max().unwrap_or(0),
+        Term::Ite { cond, then, else_ } => past_depth(cond)
+            .max(past_depth(then))
+            .max(past_depth(else_)),
+        Term::Quantified { body, .. } => past_depth(body),
+    }
+}
+
+impl Trace {
+    fn wf(&self) {
+        assert!(!self.states.is_empty(), "a trace must have a state");
+        let first = &self.states[0];
+        for state in &self.states {
+            assert_eq!(state.signature, first.signature);
+            assert_eq!(state.universe, first.universe);
+        }
+        if let Some(loop_back) = self.loop_back {
+            assert!(loop_back < self.states.len());
+        }
+    }
+
+    /// Construct a finite trace.
+    pub fn finite(states: Vec<Model>) -> Self {
+        let trace = Trace {
+            states,
+            loop_back: None,
+        };
+        trace.wf();
+        trace
+    }
+
+    /// Construct a lasso, where the last state is followed by the state at
+    /// index `loop_back`.
+    pub fn lasso(states: Vec<Model>, loop_back: usize) -> Self {
+        let trace = Trace {
+            states,
+            loop_back: Some(loop_back),
+        };
+        trace.wf();
+        trace
+    }
+
+    /// Evaluate a closed term at the first state of the trace.
+    pub fn eval(&self, t: &Term) -> Element {
+        self.eval_at(t, 0)
+    }
+
+    /// Evaluate a closed term at the given position of the trace. For a lasso,
+    /// the position may be beyond the last state.
+    pub fn eval_at(&self, t: &Term, position: usize) -> Element {
+        self.eval_assign(t, position, Assignment::new())
+    }
+
+    /// Evaluate a term at the given position of the trace, given an assignment
+    /// of logical variables to elements.
+    pub fn eval_assign(&self, t: &Term, position: usize, assignment: Assignment) -> Element {
+        let unrolled = Unrolled::new(self, past_depth(t));
+        unrolled.eval(t, unrolled.normalize(position), assignment)
+    }
+}
+
+/// A trace whose loop is unrolled enough times that the past of each state in
+/// the loop determines the value of every past subterm: a subterm with `d`
+/// nested past operators has the same value at all positions which agree
+/// modulo the loop length once `d` iterations of the loop are behind them.
+struct Unrolled<'a> {
+    states: Vec<&'a Model>,
+    loop_back: Option<usize>,
+}
+
+impl<'a> Unrolled<'a> {
+    fn new(trace: &'a Trace, past_depth: usize) -> Self {
+        let mut states = trace.states.iter().collect_vec();
+        let loop_back = trace.loop_back.map(|loop_back| {
+            let cycle = &trace.states[loop_back..];
+            for _ in 0..past_depth {
+                states.extend(cycle);
+            }
+            states.len() - cycle.len()
+        });
+        Unrolled { states, loop_back }
+    }
+
+    /// Map a position of the (infinite) trace to an index into `states`.
+    fn normalize(&self, position: usize) -> usize {
+        match self.loop_back {
+            Some(loop_back) if position >= self.states.len() => {
+                loop_back + (position - loop_back) % (self.states.len() - loop_back)
+            }
+            _ => {
+                assert!(
+                    position < self.states.len(),
+                    "position {position} is beyond the end of the trace"
+                );
+                position
+            }
+        }
+    }
+
+    fn next(&self, i: usize) -> Option<usize> {
+        if i + 1 < self.states.len() {
+            Some(i + 1)
+        } else {
+            self.loop_back
+        }
+    }
+
+    /// The positions from `i` onwards, each visited once, in order.
+    fn future(&self, i: usize) -> impl Iterator<Item = usize> {
+        let wrapped = match self.loop_back {
+            Some(loop_back) if loop_back < i => loop_back..i,
+            _ => 0..0,
+        };
+        (i..self.states.len()).chain(wrapped)
+    }
+
+    fn eval(&self, t: &Term, i: usize, assignment: Assignment) -> Element {
+        let go = |t: &Term| self.eval(t, i, assignment.clone());
+        let holds = |t: &Term, j: usize| self.eval(t, j, assignment.clone()) == 1;
+        let next = |t: &Term| match self.next(i) {
+            Some(j) => j,
+            None => panic!("tried to eval {t} beyond the end of the trace"),
+        };
+        match t {
+            Term::Literal(_) | Term::Id(_) => self.states[i].eval_assign(t, assignment),
+            Term::App(f, p, args) => {
+                let args: Vec<Element> = args.iter().map(go).collect();
+                let mut j = i;
+                for _ in 0..*p {
+                    j = next(t);
+                }
+                let state = self.states[j];
+                state.interp[state.signature.relation_idx(f)].get(&args)
+            }
+            Term::UnaryOp(Not, t) => 1 - go(t),
+            Term::UnaryOp(Prime, t) => self.eval(t, next(t), assignment),
+            Term::UnaryOp(Next, t) => bool_to_element(self.next(i).is_some_and(|j| holds(t, j))),
+            Term::UnaryOp(Previous, t) => bool_to_element(i > 0 && holds(t, i - 1)),
+            Term::UnaryOp(Always, t) => bool_to_element(self.future(i).all(|j| holds(t, j))),
+            Term::UnaryOp(Eventually, t) => bool_to_element(self.future(i).any(|j| holds(t, j))),
+            Term::BinOp(Until, lhs, rhs) => {
+                for j in self.future(i) {
+                    if holds(rhs, j) {
+                        return 1;
+                    }
+                    if !holds(lhs, j) {
+                        return 0;
+                    }
+                }
+                0
+            }
+            Term::BinOp(Since, lhs, rhs) => {
+                for j in (0..=i).rev() {
+                    if holds(rhs, j) {
+                        return 1;
+                    }
+                    if !holds(lhs, j) {
+                        return 0;
+                    }
+                }
+                0
+            }
+            Term::BinOp(Equals | Iff, lhs, rhs) => bool_to_element(go(lhs) == go(rhs)),
+            Term::BinOp(NotEquals, lhs, rhs) => bool_to_element(go(lhs) != go(rhs)),
+            Term::BinOp(Implies, lhs, rhs) => bool_to_element(go(lhs) <= go(rhs)),
+            Term::NAryOp(And, ts) => bool_to_element(ts.iter().all(|t| go(t) == 1)),
+            Term::NAryOp(Or, ts) => bool_to_element(ts.iter().any(|t| go(t) == 1)),
+            Term::Ite { cond, then, else_ } => {
+                if go(cond) == 1 {
+                    go(then)
+                } else {
+                    go(else_)
+                }
+            }
+            Term::Quantified {
+                quantifier: _,
+                binders,
+                body,
+            } if binders.is_empty() => go(body),
+            Term::Quantified {
+                quantifier,
+                binders,
+                body,
+            } => {
+                let mut iter = binders
+                    .iter()
+                    .map(|b| (0..self.states[i].cardinality(&b.sort)).collect::<Vec<Element>>())
+                    .multi_cartesian_product_fixed()
+                    .map(|elements| {
+                        let mut assignment = assignment.clone();
+                        for (binder, element) in binders.iter().zip(elements) {
+                            assignment.insert(binder.name.clone(), element);
+                        }
+                        self.eval(body, i, assignment) == 1
+                    });
+                bool_to_element(match quantifier {
+                    Forall => iter.all(|x| x),
+                    Exists => iter.any(|x| x),
+                })
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
@@ -426,7 +665,8 @@ mod tests {
         },
     };
 
-    use super::{Interpretation, Model, Universe};
+    use super::{Interpretation, Model, Trace, Universe};
+    use crate::parser::{parse_signature, term};
 
     #[test]
     fn test1() {
@@ -499,18 +739,18 @@ mod tests {
 
     #[test]
     fn test_interp_new() {
-        let interp = Interpretation::new(&vec![3], |_| 2);
+        let interp = Interpretation::new(&[3], |_| 2);
         assert_eq!(interp.get(&[]), 2);
         assert_eq!(interp.data, vec![2]);
 
-        let interp = Interpretation::new(&vec![3, 2, 4], |es| es[0] + es[1]);
+        let interp = Interpretation::new(&[3, 2, 4], |es| es[0] + es[1]);
         for i in 0..3 {
             for j in 0..2 {
                 assert_eq!(interp.get(&[i, j]), i + j, "wrong value at {i}, {j}");
             }
         }
 
-        let interp = Interpretation::new(&vec![3, 2, 4, 7], |es| es[0] + es[1] * es[2]);
+        let interp = Interpretation::new(&[3, 2, 4, 7], |es| es[0] + es[1] * es[2]);
         for i in 0..3 {
             for j in 0..2 {
                 for k in 0..4 {
@@ -932,4 +1172,84 @@ mod tests {
         assert_eq!(fth_model.eval(&thr_as_term), 0);
         assert_eq!(fth_model.eval(&fth_as_term), 1);
     }
+
+    /// States of a signature with `p` and `r(node)`, where `r(n0) = p` and
+    /// `r(n1) = !p`, given by the values of `p`.
+    fn states(ps: &[bool]) -> Vec<Model> {
+        let sig = parse_signature("sort node\nmutable p: bool\nmutable r(node): bool");
+        ps.iter()
+            .map(|&p| {
+                let p = p as Element;
+                Model::new(
+                    &sig,
+                    &vec![2],
+                    vec![
+                        Interpretation::new(&[2], |_| p),
+                        Interpretation::new(&[2, 2], |args| if args[0] == 0 { p } else { 1 - p }),
+                    ],
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_trace_finite() {
+        let trace = Trace::finite(states(&[true, true, false]));
+        assert_eq!(trace.eval(&term("always p")), 0);
+        assert_eq!(trace.eval(&term("eventually !p")), 1);
+        assert_eq!(trace.eval(&term("p until !p")), 1);
+        assert_eq!(trace.eval(&term("X p")), 1);
+        assert_eq!(trace.eval(&term("X X p")), 0);
+        assert_eq!(trace.eval(&term("X X X true")), 0);
+        assert_eq!(trace.eval(&term("p'")), 1);
+        assert_eq!(trace.eval_at(&term("always !p"), 2), 1);
+        assert_eq!(trace.eval_at(&term("X true"), 2), 0);
+
+        assert_eq!(trace.eval(&term("X^-1 true")), 0);
+        assert_eq!(trace.eval_at(&term("X^-1 p"), 2), 1);
+        assert_eq!(trace.eval_at(&term("!p since p"), 2), 1);
+        assert_eq!(trace.eval_at(&term("p since !p"), 1), 0);
+
+        assert_eq!(trace.eval(&term("exists n:node. always r(n)")), 0);
+        assert_eq!(trace.eval(&term("always exists n:node. r(n)")), 1);
+        assert_eq!(trace.eval(&term("forall n:node. eventually r(n)")), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "beyond the end of the trace")]
+    fn test_trace_finite_prime() {
+        let trace = Trace::finite(states(&[true]));
+        trace.eval(&term("p'"));
+    }
+
+    #[test]
+    fn test_trace_lasso() {
+        // !p, then alternating p and !p forever
+        let trace = Trace::lasso(states(&[false, true, false]), 1);
+        assert_eq!(trace.eval(&term("always eventually p")), 1);
+        assert_eq!(trace.eval(&term("eventually always p")), 0);
+        assert_eq!(trace.eval(&term("always (p <-> X !p)")), 1);
+        assert_eq!(trace.eval(&term("always (p <-> !p')")), 1);
+        assert_eq!(trace.eval(&term("!p until p")), 1);
+        assert_eq!(trace.eval_at(&term("p'"), 2), 1);
+        assert_eq!(trace.eval_at(&term("p"), 5), 1);
+
+        // the state before the loop's start differs between iterations
+        assert_eq!(trace.eval_at(&term("X^-1 p"), 1), 0);
+        assert_eq!(trace.eval_at(&term("X^-1 p"), 4), 1);
+        assert_eq!(trace.eval(&term("always (p -> X^-1 !p)")), 1);
+        assert_eq!(trace.eval(&term("always eventually (p & X^-1 X^-1 p)")), 1);
+        assert_eq!(trace.eval(&term("always (X^-1 X^-1 p -> p)")), 1);
+        assert_eq!(trace.eval(&term("eventually always (p since p)")), 0);
+        assert_eq!(trace.eval(&term("always (p -> (!p since p))")), 1);
+
+        assert_eq!(
+            trace.eval(&term("forall n:node. always eventually r(n)")),
+            1
+        );
+        assert_eq!(
+            trace.eval(&term("exists n:node. eventually always r(n)")),
+            0
+        );
+    }
 }
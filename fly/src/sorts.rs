@@ -112,14 +112,16 @@ pub fn has_all_sort_annotations_module(module: &Module) -> bool {
         .iter()
         .all(|def| has_all_sort_annotations_term(&def.body))
         && module.statements.iter().all(|statement| match statement {
-            ThmStmt::Assume(term) => has_all_sort_annotations_term(term),
+            ThmStmt::Assume(term) => has_all_sort_annotations_term(&term.x),
             ThmStmt::Assert(proof) => {
                 proof
                     .invariants
                     .iter()
+                    .chain(&proof.candidates)
                     .all(|inv| has_all_sort_annotations_term(&inv.x))
                     && has_all_sort_annotations_term(&proof.assert.x)
             }
+            ThmStmt::Fair(fairness) => has_all_sort_annotations_term(&fairness.constraint()),
         })
 }
 
@@ -160,17 +162,21 @@ enum RelationOrIndividual {
 }
 
 impl RelationOrIndividual {
-    fn args_ret(args: &Vec<Sort>, ret: &Sort) -> RelationOrIndividual {
+    fn args_ret(args: &[Sort], ret: &Sort) -> RelationOrIndividual {
         if args.is_empty() {
             Self::known(ret)
         } else {
-            Self::Relation(args.clone(), ret.clone())
+            Self::Relation(args.to_vec(), ret.clone())
         }
     }
 
     fn definition(decl: &Definition) -> RelationOrIndividual {
         Self::args_ret(
-            &decl.binders.iter().map(|b| b.sort.clone()).collect(),
+            &decl
+                .binders
+                .iter()
+                .map(|b| b.sort.clone())
+                .collect::<Vec<_>>(),
             &decl.ret_sort,
         )
     }
@@ -258,7 +264,7 @@ impl Scope<'_> {
     ///
     /// This function also checks that the signature is well formed in the sense that all the sorts
     /// mentioned by the relations exist.
-    pub fn new(signature: &Signature) -> Result<Scope, SortError> {
+    pub fn new(signature: &Signature) -> Result<Scope<'_>, SortError> {
         let mut sorts = HashSet::new();
         for sort in &signature.sorts {
             // This assert is guaranteed to pass by the parser, but we double check it here for the
@@ -445,15 +451,25 @@ impl Scope<'_> {
         statement: &mut ThmStmt,
     ) -> Result<(), (SortError, Option<Span>)> {
         match statement {
-            ThmStmt::Assume(term) => self.sort_check_term_bool(term).map_err(|x| (x, None))?,
+            ThmStmt::Assume(term) => self
+                .sort_check_term_bool(&mut term.x)
+                .map_err(|x| (x, None))?,
             ThmStmt::Assert(proof) => {
-                for invariant in &mut proof.invariants {
+                for invariant in proof.invariants.iter_mut().chain(&mut proof.candidates) {
                     self.sort_check_term_bool(&mut invariant.x)
                         .map_err(|x| (x, invariant.span))?
                 }
                 self.sort_check_term_bool(&mut proof.assert.x)
                     .map_err(|x| (x, proof.assert.span))?
             }
+            ThmStmt::Fair(fairness) => {
+                // check the constraint as `forall binders. enabled -> taken`,
+                // which also infers the sorts of the binders
+                let mut constraint = fairness.constraint();
+                self.sort_check_term_bool(&mut constraint)
+                    .map_err(|x| (x, None))?;
+                *fairness = Fairness::from_constraint(fairness.kind, constraint);
+            }
         }
         Ok(())
     }
@@ -526,7 +542,7 @@ impl InternalContext<'_> {
     }
 
     /// Create a new context for an inner scope.
-    fn new_inner_scope(&mut self) -> InternalContext {
+    fn new_inner_scope(&mut self) -> InternalContext<'_> {
         InternalContext {
             scope: self.scope.clone(),
             unification_table: self.unification_table,
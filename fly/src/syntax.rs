@@ -488,41 +488,6 @@ impl Term {
     pub fn negate(t: Term) -> Self {
         Self::UnaryOp(UOp::Not, Box::new(t))
     }
-
-    /// Construct a simplified term logically equivalent to `!t`
-    ///
-    /// TODO(oded): I think this should be eliminated once we have NNF
-    ///             conversion (or even before). This is a very strange function
-    ///             anyway, used only once in UPDR. Note that it supports
-    ///             negating forall but not exists. Maybe for now it should be moved to updr.rs.
-    pub fn negate_and_simplify(t: Term) -> Self {
-        match t {
-            Term::Literal(b) => Term::Literal(!b),
-            Term::UnaryOp(UOp::Not, t) => *t,
-            Term::UnaryOp(..) => panic!("got UnaryOp other than Not!"),
-            Term::BinOp(BinOp::NotEquals, lhs, rhs) => Term::BinOp(BinOp::Equals, lhs, rhs),
-            Term::BinOp(BinOp::Equals, lhs, rhs) => Term::BinOp(BinOp::NotEquals, lhs, rhs),
-            Term::NAryOp(NOp::Or, terms) => Term::NAryOp(
-                NOp::And,
-                terms.into_iter().map(Term::negate_and_simplify).collect(),
-            ),
-            Term::NAryOp(NOp::And, terms) => Term::NAryOp(
-                NOp::Or,
-                terms.into_iter().map(Term::negate_and_simplify).collect(),
-            ),
-            Term::Id(_) | Term::App(_, _, _) => Term::negate(t),
-            Term::Quantified {
-                quantifier: Quantifier::Forall,
-                binders,
-                body,
-            } => Term::Quantified {
-                quantifier: Quantifier::Exists,
-                binders,
-                body: Box::new(Term::negate_and_simplify(*body)),
-            },
-            _ => panic!("got illegal operator in negate and simplify"),
-        }
-    }
 }
 
 // TODO(oded): rename Relation to Function
@@ -600,7 +565,7 @@ impl Signature {
     /// Check if `name` is a relation in the signature, or a primed version of
     /// one.
     pub fn contains_relation(&self, name: &str) -> bool {
-        let symbol_no_primes = name.trim_end_matches(|c| c == '\'');
+        let symbol_no_primes = name.trim_end_matches('\'');
         return self.relations.iter().any(|r| r.name == symbol_no_primes);
     }
 
@@ -757,6 +722,156 @@ pub struct Proof {
     pub assert: Spanned<Term>,
     /// Invariants whose conjunction should be inductive to prove `assert`
     pub invariants: Vec<Spanned<Term>>,
+    /// Candidate invariants to start inference from, which are not checked
+    /// by verification
+    pub candidates: Vec<Spanned<Term>>,
+}
+
+/// The strength of a fairness constraint.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum FairnessKind {
+    /// Weak fairness (justice): if the action is eventually enabled forever,
+    /// it is taken infinitely often.
+    Weak,
+    /// Strong fairness (compassion): if the action is enabled infinitely often,
+    /// it is taken infinitely often.
+    Strong,
+}
+
+/// A fairness constraint, which restricts the infinite runs considered when
+/// checking liveness. For every value of the binders, a weak constraint means
+/// `(eventually always enabled) -> (always eventually taken)` and a strong
+/// constraint means `(always eventually enabled) -> (always eventually taken)`.
+/// The `enabled` condition is a single-state term, while `taken` may also refer
+/// to the next state (to say that some action is taken).
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Fairness {
+    /// Whether this is weak or strong fairness
+    pub kind: FairnessKind,
+    /// The constraint applies separately to each value of these binders
+    pub binders: Vec<Binder>,
+    /// When the action is enabled
+    pub enabled: Term,
+    /// When the action is taken
+    pub taken: Term,
+}
+
+impl Fairness {
+    /// Build a fairness constraint from `forall binders. enabled -> taken`,
+    /// where the quantifier is optional and a missing `enabled ->` means that
+    /// the action is always enabled.
+    pub fn from_constraint(kind: FairnessKind, constraint: Term) -> Self {
+        let (binders, body) = match constraint {
+            Term::Quantified {
+                quantifier: Quantifier::Forall,
+                binders,
+                body,
+            } => (binders, *body),
+            body => (vec![], body),
+        };
+        let (enabled, taken) = match body {
+            Term::BinOp(BinOp::Implies, enabled, taken) => (*enabled, *taken),
+            taken => (Term::true_(), taken),
+        };
+        Fairness {
+            kind,
+            binders,
+            enabled,
+            taken,
+        }
+    }
+
+    /// The constraint as `forall binders. enabled -> taken`, the inverse of
+    /// [`Fairness::from_constraint`].
+    pub fn constraint(&self) -> Term {
+        // `enabled ->` can only be left out if that doesn't change how the
+        // constraint is split up again
+        let body = match &self.taken {
+            Term::BinOp(BinOp::Implies, ..) => None,
+            Term::Quantified {
+                quantifier: Quantifier::Forall,
+                ..
+            } if self.binders.is_empty() => None,
+            taken if self.enabled == Term::true_() => Some(taken.clone()),
+            _ => None,
+        }
+        .unwrap_or_else(|| Term::implies(self.enabled.clone(), self.taken.clone()));
+        if self.binders.is_empty() {
+            body
+        } else {
+            Term::Quantified {
+                quantifier: Quantifier::Forall,
+                binders: self.binders.clone(),
+                body: Box::new(body),
+            }
+        }
+    }
+
+    /// Recognize a fairness constraint written as a temporal term, one of
+    /// `(eventually always e) -> (always eventually t)` (weak),
+    /// `(always eventually e) -> (always eventually t)` (strong), or
+    /// `always eventually t` (weak, always enabled), optionally under a
+    /// universal quantifier.
+    pub fn from_term(term: &Term) -> Option<Self> {
+        fn inner(t: &Term, first: UOp, second: UOp) -> Option<&Term> {
+            match t {
+                Term::UnaryOp(op1, t) if *op1 == first => match t.as_ref() {
+                    Term::UnaryOp(op2, t) if *op2 == second => Some(t),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+
+        let (binders, body) = match term {
+            Term::Quantified {
+                quantifier: Quantifier::Forall,
+                binders,
+                body,
+            } => (binders.clone(), body.as_ref()),
+            body => (vec![], body),
+        };
+        let (kind, enabled, taken) = match body {
+            Term::BinOp(BinOp::Implies, lhs, rhs) => {
+                let taken = inner(rhs, UOp::Always, UOp::Eventually)?;
+                if let Some(enabled) = inner(lhs, UOp::Eventually, UOp::Always) {
+                    (FairnessKind::Weak, enabled, taken)
+                } else {
+                    let enabled = inner(lhs, UOp::Always, UOp::Eventually)?;
+                    (FairnessKind::Strong, enabled, taken)
+                }
+            }
+            body => (
+                FairnessKind::Weak,
+                &Term::Literal(true),
+                inner(body, UOp::Always, UOp::Eventually)?,
+            ),
+        };
+        Some(Fairness {
+            kind,
+            binders,
+            enabled: enabled.clone(),
+            taken: taken.clone(),
+        })
+    }
+
+    /// The constraint as a temporal term.
+    pub fn to_term(&self) -> Term {
+        let enabled = match self.kind {
+            FairnessKind::Weak => Term::eventually(Term::always(self.enabled.clone())),
+            FairnessKind::Strong => Term::always(Term::eventually(self.enabled.clone())),
+        };
+        let body = Term::implies(enabled, Term::always(Term::eventually(self.taken.clone())));
+        if self.binders.is_empty() {
+            body
+        } else {
+            Term::Quantified {
+                quantifier: Quantifier::Forall,
+                binders: self.binders.clone(),
+                body: Box::new(body),
+            }
+        }
+    }
 }
 
 /// A theorem statement that can appear in a module. Statements are interpreted
@@ -765,14 +880,28 @@ pub struct Proof {
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub enum ThmStmt {
     /// Assume that a term is true without proof for the remaining statements
-    Assume(Term),
+    Assume(Spanned<Term>),
     /// Assert a term with an inductive proof
     Assert(Proof),
+    /// Assume a fairness constraint on the infinite runs
+    Fair(Fairness),
+}
+
+/// A pragma is a per-file setting for the tools that process a module, such as
+/// `pragma smt_logic UF`. The value is not interpreted by the parser.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Pragma {
+    /// Name of the setting
+    pub name: String,
+    /// Value of the setting
+    pub value: String,
 }
 
 /// A Module consists of a Signature and some theorem statements to be proven in that signature.
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct Module {
+    /// Pragmas given at the top of the file
+    pub pragmas: Vec<Pragma>,
     /// Signature for all terms in the module
     pub signature: Signature,
     /// Helper definitions (essentially macros) that may be used in the module's
@@ -782,6 +911,16 @@ pub struct Module {
     pub statements: Vec<ThmStmt>,
 }
 
+impl Module {
+    /// Get the values of all pragmas with a given name, in order.
+    pub fn pragma_values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.pragmas
+            .iter()
+            .filter(move |p| p.name == name)
+            .map(|p| p.value.as_str())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::vec;
@@ -0,0 +1,269 @@
+// Copyright 2022-2023 VMware, Inc.
+// SPDX-License-Identifier: BSD-2-Clause
+
+//! Hash-consed terms.
+//!
+//! A [`TermStore`] keeps a single copy of every distinct term added to it, and
+//! refers to it with an [`HTerm`] handle. Handles are `Copy`, and two handles
+//! from the same store are equal exactly when their terms are equal, so
+//! comparing and hashing them takes constant time. The children of a stored
+//! term are themselves handles (see [`Node`]), so terms share their common
+//! subterms.
+//!
+//! Handles are only meaningful for the store that created them.
+
+use std::collections::HashMap;
+
+use crate::syntax::{BinOp, Binder, NOp, Quantifier, Term, UOp};
+use crate::term::subst::Substitution;
+
+/// A handle to a term in a [`TermStore`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct HTerm(u32);
+
+/// A single level of a hash-consed term, with handles for its subterms. The
+/// variants mirror those of [`Term`].
+#[allow(missing_docs)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Node {
+    Literal(bool),
+    Id(String),
+    App(String, usize, Vec<HTerm>),
+    UnaryOp(UOp, HTerm),
+    BinOp(BinOp, HTerm, HTerm),
+    NAryOp(NOp, Vec<HTerm>),
+    Ite {
+        cond: HTerm,
+        then: HTerm,
+        else_: HTerm,
+    },
+    Quantified {
+        quantifier: Quantifier,
+        binders: Vec<Binder>,
+        body: HTerm,
+    },
+}
+
+/// An arena of hash-consed terms.
+#[derive(Debug, Clone, Default)]
+pub struct TermStore {
+    nodes: Vec<Node>,
+    handles: HashMap<Node, HTerm>,
+}
+
+impl TermStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of distinct terms in the store.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the store is empty.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Get the node that `t` refers to.
+    pub fn node(&self, t: HTerm) -> &Node {
+        &self.nodes[t.0 as usize]
+    }
+
+    /// Add a node to the store, returning the existing handle if it is already
+    /// there.
+    pub fn intern_node(&mut self, node: Node) -> HTerm {
+        if let Some(&t) = self.handles.get(&node) {
+            return t;
+        }
+        let t = HTerm(
+            self.nodes
+                .len()
+                .try_into()
+                .expect("too many terms in the store"),
+        );
+        self.nodes.push(node.clone());
+        self.handles.insert(node, t);
+        t
+    }
+
+    /// Look up a node without adding it to the store.
+    pub fn get_node(&self, node: &Node) -> Option<HTerm> {
+        self.handles.get(node).copied()
+    }
+
+    /// Add a term and all of its subterms to the store.
+    pub fn intern(&mut self, t: &Term) -> HTerm {
+        let node = match t {
+            Term::Literal(b) => Node::Literal(*b),
+            Term::Id(s) => Node::Id(s.clone()),
+            Term::App(f, p, args) => {
+                Node::App(f.clone(), *p, args.iter().map(|t| self.intern(t)).collect())
+            }
+            Term::UnaryOp(op, t) => Node::UnaryOp(*op, self.intern(t)),
+            Term::BinOp(op, lhs, rhs) => Node::BinOp(*op, self.intern(lhs), self.intern(rhs)),
+            Term::NAryOp(op, args) => {
+                Node::NAryOp(*op, args.iter().map(|t| self.intern(t)).collect())
+            }
+            Term::Ite { cond, then, else_ } => Node::Ite {
+                cond: self.intern(cond),
+                then: self.intern(then),
+                else_: self.intern(else_),
+            },
+            Term::Quantified {
+                quantifier,
+                binders,
+                body,
+            } => Node::Quantified {
+                quantifier: *quantifier,
+                binders: binders.clone(),
+                body: self.intern(body),
+            },
+        };
+        self.intern_node(node)
+    }
+
+    /// Look up a term without adding it to the store. Returns `None` if the
+    /// term (or any of its subterms) is not in the store.
+    pub fn get(&self, t: &Term) -> Option<HTerm> {
+        let node = match t {
+            Term::Literal(b) => Node::Literal(*b),
+            Term::Id(s) => Node::Id(s.clone()),
+            Term::App(f, p, args) => Node::App(
+                f.clone(),
+                *p,
+                args.iter().map(|t| self.get(t)).collect::<Option<_>>()?,
+            ),
+            Term::UnaryOp(op, t) => Node::UnaryOp(*op, self.get(t)?),
+            Term::BinOp(op, lhs, rhs) => Node::BinOp(*op, self.get(lhs)?, self.get(rhs)?),
+            Term::NAryOp(op, args) => Node::NAryOp(
+                *op,
+                args.iter().map(|t| self.get(t)).collect::<Option<_>>()?,
+            ),
+            Term::Ite { cond, then, else_ } => Node::Ite {
+                cond: self.get(cond)?,
+                then: self.get(then)?,
+                else_: self.get(else_)?,
+            },
+            Term::Quantified {
+                quantifier,
+                binders,
+                body,
+            } => Node::Quantified {
+                quantifier: *quantifier,
+                binders: binders.clone(),
+                body: self.get(body)?,
+            },
+        };
+        self.get_node(&node)
+    }
+
+    /// Convert a handle back to a [`Term`].
+    pub fn to_term(&self, t: HTerm) -> Term {
+        let go = |t: &HTerm| self.to_term(*t);
+        match self.node(t) {
+            Node::Literal(b) => Term::Literal(*b),
+            Node::Id(s) => Term::Id(s.clone()),
+            Node::App(f, p, args) => Term::App(f.clone(), *p, args.iter().map(go).collect()),
+            Node::UnaryOp(op, t) => Term::UnaryOp(*op, Box::new(go(t))),
+            Node::BinOp(op, lhs, rhs) => Term::BinOp(*op, Box::new(go(lhs)), Box::new(go(rhs))),
+            Node::NAryOp(op, args) => Term::NAryOp(*op, args.iter().map(go).collect()),
+            Node::Ite { cond, then, else_ } => Term::Ite {
+                cond: Box::new(go(cond)),
+                then: Box::new(go(then)),
+                else_: Box::new(go(else_)),
+            },
+            Node::Quantified {
+                quantifier,
+                binders,
+                body,
+            } => Term::Quantified {
+                quantifier: *quantifier,
+                binders: binders.clone(),
+                body: Box::new(go(body)),
+            },
+        }
+    }
+
+    /// Look up the result of a substitution over a quantifier-free term
+    /// without adding it to the store. Returns `None` if the result is not in
+    /// the store.
+    pub fn get_substituted_qf(&self, t: HTerm, substitution: &Substitution) -> Option<HTerm> {
+        let go = |t: &HTerm| self.get_substituted_qf(*t, substitution);
+        let node = match self.node(t) {
+            Node::Literal(_) => return Some(t),
+            Node::Id(s) => match substitution.get(s) {
+                Some(t) => return self.get(t),
+                None => return Some(t),
+            },
+            Node::App(f, p, args) => {
+                Node::App(f.clone(), *p, args.iter().map(go).collect::<Option<_>>()?)
+            }
+            Node::UnaryOp(op, t) => Node::UnaryOp(*op, go(t)?),
+            Node::BinOp(op, lhs, rhs) => Node::BinOp(*op, go(lhs)?, go(rhs)?),
+            Node::NAryOp(op, args) => {
+                Node::NAryOp(*op, args.iter().map(go).collect::<Option<_>>()?)
+            }
+            Node::Ite { cond, then, else_ } => Node::Ite {
+                cond: go(cond)?,
+                then: go(then)?,
+                else_: go(else_)?,
+            },
+            Node::Quantified { .. } => {
+                panic!("Quantifier-free substitution was given quantifier term")
+            }
+        };
+        self.get_node(&node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::term;
+
+    #[test]
+    fn test_hashcons() {
+        let mut store = TermStore::new();
+        let t = term("forall x:t. r(x, y) & (r(x, y) | p)");
+        let h = store.intern(&t);
+        assert_eq!(store.to_term(h), t);
+        // x, y, r(x, y), p, the disjunction, the conjunction and the forall
+        assert_eq!(store.len(), 7);
+
+        assert_eq!(store.intern(&t), h);
+        assert_eq!(store.len(), 7);
+        assert_eq!(store.get(&term("r(x, y)")), store.get(&term("r(x,y)")));
+        assert!(store.get(&term("r(y, x)")).is_none());
+
+        let Node::Quantified { body, .. } = store.node(h) else {
+            panic!("expected a quantifier");
+        };
+        let Node::NAryOp(NOp::And, conjuncts) = store.node(*body) else {
+            panic!("expected a conjunction");
+        };
+        let Node::NAryOp(NOp::Or, disjuncts) = store.node(conjuncts[1]) else {
+            panic!("expected a disjunction");
+        };
+        // the common subterm is shared
+        assert_eq!(conjuncts[0], disjuncts[0]);
+    }
+
+    #[test]
+    fn test_get_substituted_qf() {
+        let mut store = TermStore::new();
+        let a = store.intern(&term("r(x, y)"));
+        let b = store.intern(&term("r(y, x)"));
+        let swap = Substitution::from([
+            ("x".to_string(), Term::id("y")),
+            ("y".to_string(), Term::id("x")),
+        ]);
+        assert_eq!(store.get_substituted_qf(a, &swap), Some(b));
+        assert_eq!(store.get_substituted_qf(b, &swap), Some(a));
+        let rename = Substitution::from([("x".to_string(), Term::id("z"))]);
+        assert_eq!(store.get_substituted_qf(a, &rename), None);
+        assert_eq!(store.len(), 4);
+    }
+}
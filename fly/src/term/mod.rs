@@ -5,5 +5,7 @@
 
 pub mod cnf;
 pub mod fo;
+pub mod hashcons;
 pub mod prime;
+pub mod simplify;
 pub mod subst;
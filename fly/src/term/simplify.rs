@@ -0,0 +1,663 @@
+// Copyright 2022-2023 VMware, Inc.
+// SPDX-License-Identifier: BSD-2-Clause
+
+//! Simplify terms and convert them to normal forms.
+//!
+//! [`simplify`] performs cheap rewrites that never make a term larger:
+//! constant folding, flattening of conjunctions and disjunctions, removal of
+//! duplicate and complementary conjuncts or disjuncts, and elimination of
+//! unused binders. The normal forms are negation normal form ([`nnf`]),
+//! miniscoped form ([`miniscope`]), and prenex normal form ([`prenex`]).
+//!
+//! All of these preserve the FO-LTL semantics of a term over both finite and
+//! infinite traces. Temporal operators without a dual (`X`, `X^-1`, `until`
+//! and `since`) are treated as atoms by the normal forms, although the
+//! arguments are still normalized.
+
+use std::collections::HashSet;
+
+use itertools::Itertools;
+
+use crate::syntax::{BinOp, Binder, NOp, Quantifier, Term, UOp};
+use crate::term::subst::{free_vars, substitute, FreshNames, Substitution};
+
+fn quantify(quantifier: Quantifier, binders: Vec<Binder>, body: Term) -> Term {
+    match quantifier {
+        Quantifier::Forall => Term::forall(binders, body),
+        Quantifier::Exists => Term::exists(binders, body),
+    }
+}
+
+fn dual(quantifier: Quantifier) -> Quantifier {
+    match quantifier {
+        Quantifier::Forall => Quantifier::Exists,
+        Quantifier::Exists => Quantifier::Forall,
+    }
+}
+
+/// The free variables of a simplified term, computed bottom-up along with it so
+/// that each quantifier does not have to traverse its body again.
+type Vars = HashSet<String>;
+
+/// Pair a simplified term with its free variables, given the free variables of
+/// the parts it was built from. A literal has none, even if it was built from
+/// parts that did.
+fn with_vars(t: Term, vars: Vars) -> (Term, Vars) {
+    match t {
+        Term::Literal(_) => (t, Vars::new()),
+        t => (t, vars),
+    }
+}
+
+fn union(vars: impl IntoIterator<Item = Vars>) -> Vars {
+    vars.into_iter().concat()
+}
+
+/// Simplify a conjunction or disjunction of `ts`, flattening nested operands of
+/// the same kind and keeping the first occurrence of each operand.
+fn simplify_nary(op: NOp, ts: &[Term]) -> (Term, Vars) {
+    // the literal that determines the value of the whole term
+    let absorbing = op == NOp::Or;
+    let mut operands: Vec<Term> = vec![];
+    let mut seen: HashSet<Term> = HashSet::new();
+    // operands are only dropped if they are literals or duplicates, so the
+    // result has the free variables of all of them
+    let mut vars = Vars::new();
+    let mut pending = ts
+        .iter()
+        .map(|t| {
+            let (t, t_vars) = simplify_vars(t);
+            vars.extend(t_vars);
+            t
+        })
+        .collect_vec();
+    pending.reverse();
+    while let Some(t) = pending.pop() {
+        match t {
+            Term::Literal(b) if b == absorbing => return (Term::Literal(absorbing), Vars::new()),
+            Term::Literal(_) => (),
+            Term::NAryOp(op2, ts2) if op2 == op => pending.extend(ts2.into_iter().rev()),
+            t => {
+                if seen.contains(&Term::not(t.clone())) {
+                    return (Term::Literal(absorbing), Vars::new());
+                }
+                if seen.insert(t.clone()) {
+                    operands.push(t);
+                }
+            }
+        }
+    }
+    let t = match op {
+        NOp::And => Term::and(operands),
+        NOp::Or => Term::or(operands),
+    };
+    with_vars(t, vars)
+}
+
+/// Simplify `t` without changing its meaning. The result is never larger than
+/// `t`.
+pub fn simplify(t: &Term) -> Term {
+    simplify_vars(t).0
+}
+
+/// Simplify `t` as in [`simplify`], also returning the free variables of the
+/// result.
+fn simplify_vars(t: &Term) -> (Term, Vars) {
+    match t {
+        Term::Literal(_) => (t.clone(), Vars::new()),
+        Term::Id(name) => (t.clone(), Vars::from([name.clone()])),
+        Term::App(f, p, args) => {
+            let (args, vars): (Vec<_>, Vec<_>) = args.iter().map(simplify_vars).unzip();
+            (Term::App(f.clone(), *p, args), union(vars))
+        }
+        Term::UnaryOp(op, arg) => {
+            let (arg, vars) = simplify_vars(arg);
+            let t = match (op, arg) {
+                (UOp::Not, Term::Literal(b)) => Term::Literal(!b),
+                (UOp::Not, arg) => Term::not(arg),
+                (UOp::Prime | UOp::Always | UOp::Eventually, Term::Literal(b)) => Term::Literal(b),
+                (UOp::Next | UOp::Previous, Term::Literal(false)) => Term::false_(),
+                (UOp::Always, Term::UnaryOp(UOp::Always, arg)) => Term::always(*arg),
+                (UOp::Eventually, Term::UnaryOp(UOp::Eventually, arg)) => Term::eventually(*arg),
+                (op, arg) => Term::UnaryOp(*op, Box::new(arg)),
+            };
+            with_vars(t, vars)
+        }
+        Term::BinOp(op, lhs, rhs) => {
+            let ((lhs, lhs_vars), (rhs, rhs_vars)) = (simplify_vars(lhs), simplify_vars(rhs));
+            // every case below that keeps only one side drops a literal
+            let vars = union([lhs_vars, rhs_vars]);
+            let t = match (op, lhs, rhs) {
+                (BinOp::Equals | BinOp::Iff, lhs, rhs) if lhs == rhs => Term::true_(),
+                (BinOp::NotEquals, lhs, rhs) if lhs == rhs => Term::false_(),
+                (BinOp::Equals | BinOp::Iff, Term::Literal(b), t)
+                | (BinOp::Equals | BinOp::Iff, t, Term::Literal(b)) => {
+                    if b {
+                        t
+                    } else {
+                        return simplify_vars(&Term::not(t));
+                    }
+                }
+                (BinOp::NotEquals, Term::Literal(b), t)
+                | (BinOp::NotEquals, t, Term::Literal(b)) => {
+                    if b {
+                        return simplify_vars(&Term::not(t));
+                    } else {
+                        t
+                    }
+                }
+                (BinOp::Iff, lhs, rhs) if Term::not(lhs.clone()) == rhs => Term::false_(),
+                (BinOp::Implies, Term::Literal(false), _)
+                | (BinOp::Implies, _, Term::Literal(true)) => Term::true_(),
+                (BinOp::Implies, Term::Literal(true), t) => t,
+                (BinOp::Implies, t, Term::Literal(false)) => return simplify_vars(&Term::not(t)),
+                (BinOp::Implies, lhs, rhs) if lhs == rhs => Term::true_(),
+                (BinOp::Until | BinOp::Since, _, Term::Literal(b)) => Term::Literal(b),
+                (op, lhs, rhs) => Term::BinOp(*op, Box::new(lhs), Box::new(rhs)),
+            };
+            with_vars(t, vars)
+        }
+        Term::NAryOp(op, ts) => simplify_nary(*op, ts),
+        Term::Ite { cond, then, else_ } => {
+            let (cond, cond_vars) = simplify_vars(cond);
+            let (then, then_vars) = simplify_vars(then);
+            let (else_, else_vars) = simplify_vars(else_);
+            match (cond, then, else_) {
+                (Term::Literal(true), then, _) => (then, then_vars),
+                (Term::Literal(false), _, else_) => (else_, else_vars),
+                (_, then, else_) if then == else_ => (then, then_vars),
+                (cond, Term::Literal(then), Term::Literal(_)) => {
+                    if then {
+                        (cond, cond_vars)
+                    } else {
+                        simplify_vars(&Term::not(cond))
+                    }
+                }
+                (cond, Term::Literal(true), else_) => simplify_nary(NOp::Or, &[cond, else_]),
+                (cond, Term::Literal(false), else_) => {
+                    simplify_nary(NOp::And, &[Term::not(cond), else_])
+                }
+                (cond, then, Term::Literal(true)) => {
+                    simplify_nary(NOp::Or, &[Term::not(cond), then])
+                }
+                (cond, then, Term::Literal(false)) => simplify_nary(NOp::And, &[cond, then]),
+                (cond, then, else_) => (
+                    Term::ite(cond, then, else_),
+                    union([cond_vars, then_vars, else_vars]),
+                ),
+            }
+        }
+        Term::Quantified {
+            quantifier,
+            binders,
+            body,
+        } => {
+            let (body, mut vars) = simplify_vars(body);
+            let used = binders
+                .iter()
+                .filter(|b| vars.contains(&b.name))
+                .cloned()
+                .collect_vec();
+            for binder in binders {
+                vars.remove(&binder.name);
+            }
+            (quantify(*quantifier, used, body), vars)
+        }
+    }
+}
+
+fn nnf_polarity(t: &Term, positive: bool) -> Term {
+    let go = |t: &Term| nnf_polarity(t, positive);
+    let negated = |t: Term| if positive { t } else { Term::not(t) };
+    match t {
+        Term::Literal(b) => Term::Literal(*b == positive),
+        Term::Id(_) | Term::App(..) | Term::BinOp(BinOp::Equals | BinOp::NotEquals, _, _) => {
+            negated(t.clone())
+        }
+        Term::UnaryOp(UOp::Not, t) => nnf_polarity(t, !positive),
+        Term::UnaryOp(UOp::Prime, arg) if matches!(**arg, Term::Id(_) | Term::App(..)) => {
+            negated(t.clone())
+        }
+        Term::UnaryOp(UOp::Prime, t) => Term::prime(go(t)),
+        Term::UnaryOp(UOp::Always, t) if positive => Term::always(go(t)),
+        Term::UnaryOp(UOp::Always, t) => Term::eventually(go(t)),
+        Term::UnaryOp(UOp::Eventually, t) if positive => Term::eventually(go(t)),
+        Term::UnaryOp(UOp::Eventually, t) => Term::always(go(t)),
+        Term::UnaryOp(op @ (UOp::Next | UOp::Previous), t) => {
+            negated(Term::UnaryOp(*op, Box::new(nnf(t))))
+        }
+        Term::BinOp(op @ (BinOp::Until | BinOp::Since), lhs, rhs) => {
+            negated(Term::BinOp(*op, Box::new(nnf(lhs)), Box::new(nnf(rhs))))
+        }
+        Term::BinOp(BinOp::Implies, lhs, rhs) => {
+            let (lhs, rhs) = (nnf_polarity(lhs, !positive), go(rhs));
+            if positive {
+                Term::or([lhs, rhs])
+            } else {
+                Term::and([lhs, rhs])
+            }
+        }
+        Term::BinOp(BinOp::Iff, lhs, rhs) => {
+            let (lhs, not_lhs) = (nnf(lhs), nnf_polarity(lhs, false));
+            let (rhs, not_rhs) = (go(rhs), nnf_polarity(rhs, !positive));
+            Term::or([Term::and([lhs, rhs]), Term::and([not_lhs, not_rhs])])
+        }
+        Term::NAryOp(op, ts) => {
+            let ts = ts.iter().map(go);
+            match (op, positive) {
+                (NOp::And, true) | (NOp::Or, false) => Term::and(ts),
+                (NOp::Or, true) | (NOp::And, false) => Term::or(ts),
+            }
+        }
+        Term::Ite { cond, then, else_ } => Term::or([
+            Term::and([nnf(cond), go(then)]),
+            Term::and([nnf_polarity(cond, false), go(else_)]),
+        ]),
+        Term::Quantified {
+            quantifier,
+            binders,
+            body,
+        } => {
+            let quantifier = if positive {
+                *quantifier
+            } else {
+                dual(*quantifier)
+            };
+            quantify(quantifier, binders.clone(), go(body))
+        }
+    }
+}
+
+/// Convert a boolean term to negation normal form, where negation is only
+/// applied to atoms and to temporal operators without a dual. Implications,
+/// bi-implications and if-then-else are expanded into conjunctions and
+/// disjunctions.
+pub fn nnf(t: &Term) -> Term {
+    nnf_polarity(t, true)
+}
+
+/// A term being miniscoped. Conjunctions and disjunctions are kept as their
+/// operands, so that binders can be pushed into them, and every part records
+/// its free variables, so that they are computed once, bottom-up.
+struct Scoped {
+    vars: Vars,
+    kind: ScopedKind,
+}
+
+enum ScopedKind {
+    NAry(NOp, Vec<Scoped>),
+    Term(Term),
+}
+
+impl Scoped {
+    fn term(t: Term, vars: Vars) -> Self {
+        Self {
+            vars,
+            kind: ScopedKind::Term(t),
+        }
+    }
+
+    fn nary(op: NOp, ts: Vec<Scoped>) -> Self {
+        Self {
+            vars: union(ts.iter().map(|t| t.vars.clone())),
+            kind: ScopedKind::NAry(op, ts),
+        }
+    }
+
+    fn into_term(self) -> Term {
+        match self.kind {
+            ScopedKind::NAry(op, ts) => {
+                Term::NAryOp(op, ts.into_iter().map(Scoped::into_term).collect())
+            }
+            ScopedKind::Term(t) => t,
+        }
+    }
+}
+
+/// Quantify `body` over a single binder.
+fn quantify_scoped(quantifier: Quantifier, binder: &Binder, body: Scoped) -> Scoped {
+    let mut vars = body.vars.clone();
+    vars.remove(&binder.name);
+    Scoped::term(
+        quantify(quantifier, vec![binder.clone()], body.into_term()),
+        vars,
+    )
+}
+
+/// Push a single binder into `body` as far as possible.
+fn push_binder(quantifier: Quantifier, binder: &Binder, body: Scoped) -> Scoped {
+    if !body.vars.contains(&binder.name) {
+        return body;
+    }
+    match (quantifier, body.kind) {
+        (Quantifier::Forall, ScopedKind::NAry(op @ NOp::And, ts))
+        | (Quantifier::Exists, ScopedKind::NAry(op @ NOp::Or, ts)) => Scoped::nary(
+            op,
+            ts.into_iter()
+                .map(|t| push_binder(quantifier, binder, t))
+                .collect(),
+        ),
+        (Quantifier::Forall, ScopedKind::NAry(op @ NOp::Or, ts))
+        | (Quantifier::Exists, ScopedKind::NAry(op @ NOp::And, ts)) => {
+            // the operands that mention the binder take the place of the first
+            // of them
+            let first = ts
+                .iter()
+                .position(|t| t.vars.contains(&binder.name))
+                .unwrap();
+            let (mut with, mut without): (Vec<Scoped>, Vec<Scoped>) =
+                ts.into_iter().partition(|t| t.vars.contains(&binder.name));
+            if without.is_empty() {
+                return quantify_scoped(quantifier, binder, Scoped::nary(op, with));
+            }
+            let with = if with.len() == 1 {
+                push_binder(quantifier, binder, with.pop().unwrap())
+            } else {
+                quantify_scoped(quantifier, binder, Scoped::nary(op, with))
+            };
+            without.insert(first, with);
+            Scoped::nary(op, without)
+        }
+        (quantifier, kind) => quantify_scoped(
+            quantifier,
+            binder,
+            Scoped {
+                vars: body.vars,
+                kind,
+            },
+        ),
+    }
+}
+
+fn miniscope_scoped(t: &Term) -> Scoped {
+    match t {
+        Term::Literal(_) | Term::Id(_) | Term::App(..) => Scoped::term(t.clone(), free_vars(t)),
+        Term::UnaryOp(op, t) => {
+            let t = miniscope_scoped(t);
+            let vars = t.vars.clone();
+            Scoped::term(Term::UnaryOp(*op, Box::new(t.into_term())), vars)
+        }
+        Term::BinOp(op, lhs, rhs) => {
+            let (lhs, rhs) = (miniscope_scoped(lhs), miniscope_scoped(rhs));
+            let vars = union([lhs.vars.clone(), rhs.vars.clone()]);
+            Scoped::term(
+                Term::BinOp(*op, Box::new(lhs.into_term()), Box::new(rhs.into_term())),
+                vars,
+            )
+        }
+        Term::NAryOp(op, ts) => Scoped::nary(*op, ts.iter().map(miniscope_scoped).collect()),
+        Term::Ite { cond, then, else_ } => {
+            let (cond, then, else_) = (
+                miniscope_scoped(cond),
+                miniscope_scoped(then),
+                miniscope_scoped(else_),
+            );
+            let vars = union([cond.vars.clone(), then.vars.clone(), else_.vars.clone()]);
+            Scoped::term(
+                Term::ite(cond.into_term(), then.into_term(), else_.into_term()),
+                vars,
+            )
+        }
+        Term::Quantified {
+            quantifier,
+            binders,
+            body,
+        } => binders
+            .iter()
+            .rev()
+            .fold(miniscope_scoped(body), |body, binder| {
+                push_binder(*quantifier, binder, body)
+            }),
+    }
+}
+
+/// Push quantifiers inwards as far as possible, so that each quantifier scopes
+/// over as little of the term as it can. Unused binders are dropped.
+pub fn miniscope(t: &Term) -> Term {
+    miniscope_scoped(t).into_term()
+}
+
+/// A term annotated with the free variables of each of its subterms, so that
+/// they are computed once, bottom-up.
+struct Annotated<'a> {
+    term: &'a Term,
+    vars: Vars,
+    children: Vec<Annotated<'a>>,
+}
+
+fn annotate(t: &Term) -> Annotated<'_> {
+    let children = match t {
+        Term::Literal(_) | Term::Id(_) => vec![],
+        Term::App(_, _, ts) | Term::NAryOp(_, ts) => ts.iter().map(annotate).collect(),
+        Term::UnaryOp(_, t) => vec![annotate(t)],
+        Term::BinOp(_, lhs, rhs) => vec![annotate(lhs), annotate(rhs)],
+        Term::Ite { cond, then, else_ } => vec![annotate(cond), annotate(then), annotate(else_)],
+        Term::Quantified { body, .. } => vec![annotate(body)],
+    };
+    let vars = match t {
+        Term::Id(name) => Vars::from([name.clone()]),
+        Term::Quantified { binders, .. } => {
+            let mut vars = children[0].vars.clone();
+            for binder in binders {
+                vars.remove(&binder.name);
+            }
+            vars
+        }
+        _ => union(children.iter().map(|t| t.vars.clone())),
+    };
+    Annotated {
+        term: t,
+        vars,
+        children,
+    }
+}
+
+/// The state of a conversion to prenex normal form.
+struct Prenex {
+    /// Generates names for renamed binders.
+    names: FreshNames,
+}
+
+impl Prenex {
+    /// Split a term in negation normal form into a quantifier prefix (from
+    /// outermost to innermost) and a matrix.
+    fn pull(&mut self, t: &Annotated) -> (Vec<(Quantifier, Binder)>, Term) {
+        match t.term {
+            Term::Quantified {
+                quantifier,
+                binders,
+                ..
+            } => {
+                let (prefix, matrix) = self.pull(&t.children[0]);
+                let prefix = binders
+                    .iter()
+                    .map(|b| (*quantifier, b.clone()))
+                    .chain(prefix)
+                    .collect();
+                (prefix, matrix)
+            }
+            Term::NAryOp(op, _) => {
+                // binders pulled out of one operand must not capture free
+                // variables of the others, nor clash with each other
+                let mut avoid = t.vars.clone();
+                let mut prefix = vec![];
+                let mut matrices = vec![];
+                for t in &t.children {
+                    let (mut p, mut matrix) = self.pull(t);
+                    // rename from the innermost binder, which shadows the
+                    // outer ones
+                    for (_, binder) in p.iter_mut().rev() {
+                        if avoid.contains(&binder.name) {
+                            let name = self.names.fresh(&binder.name);
+                            let renaming =
+                                Substitution::from([(binder.name.clone(), Term::id(&name))]);
+                            matrix = substitute(&matrix, &renaming);
+                            binder.name = name;
+                        }
+                    }
+                    avoid.extend(p.iter().map(|(_, b)| b.name.clone()));
+                    prefix.extend(p);
+                    matrices.push(matrix);
+                }
+                (prefix, Term::NAryOp(*op, matrices))
+            }
+            _ => (vec![], self.matrix(t)),
+        }
+    }
+
+    /// Convert the subterms of a term that quantifiers cannot be pulled out of.
+    fn matrix(&mut self, t: &Annotated) -> Term {
+        match t.term {
+            Term::UnaryOp(op, _) => Term::UnaryOp(*op, Box::new(self.prenex(&t.children[0]))),
+            Term::BinOp(op @ (BinOp::Until | BinOp::Since), _, _) => Term::BinOp(
+                *op,
+                Box::new(self.prenex(&t.children[0])),
+                Box::new(self.prenex(&t.children[1])),
+            ),
+            _ => t.term.clone(),
+        }
+    }
+
+    fn prenex(&mut self, t: &Annotated) -> Term {
+        let (prefix, matrix) = self.pull(t);
+        prefix
+            .into_iter()
+            .rev()
+            .fold(matrix, |body, (quantifier, binder)| {
+                quantify(quantifier, vec![binder], body)
+            })
+    }
+}
+
+/// Convert a boolean term to prenex normal form: a quantifier prefix followed
+/// by a quantifier-free matrix in negation normal form. Bound variables are
+/// renamed where needed to avoid capture. Quantifiers are not pulled out of
+/// temporal operators, whose arguments are converted separately.
+pub fn prenex(t: &Term) -> Term {
+    let t = nnf(t);
+    let mut names = FreshNames::default();
+    names.avoid_term(&t);
+    Prenex { names }.prenex(&annotate(&t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::term;
+
+    #[test]
+    fn test_simplify() {
+        let simplified = |s: &str| simplify(&term(s));
+        assert_eq!(simplified("p & true & (q & p)"), term("p & q"));
+        assert_eq!(simplified("p | (q | false) | p"), term("p | q"));
+        assert_eq!(simplified("p & q & !p"), term("false"));
+        assert_eq!(simplified("p | !q | (q & true)"), term("true"));
+        assert_eq!(simplified("a = b & !(a != b)"), term("a = b"));
+        assert_eq!(simplified("(false -> p) & (p -> true)"), term("true"));
+        assert_eq!(simplified("(true -> p) | (q -> false)"), term("p | !q"));
+        assert_eq!(simplified("p = true & (q <-> false)"), term("p & !q"));
+        assert_eq!(simplified("!!p"), term("p"));
+        assert_eq!(
+            simplified("always always p & eventually true"),
+            term("always p")
+        );
+        assert_eq!(simplified("if true then a else b"), term("a"));
+        assert_eq!(simplified("if p then true else q"), term("p | q"));
+        assert_eq!(simplified("if p then q else false"), term("p & q"));
+        assert_eq!(simplified("if p then x else x"), term("x"));
+        assert_eq!(
+            simplified("forall x:t, y:t. exists z:t. r(x) & true"),
+            term("forall x:t. r(x)")
+        );
+        assert_eq!(simplified("forall x:t. true"), term("true"));
+        // binders are dropped once simplification removes their occurrences
+        assert_eq!(
+            simplified("forall x:t. if true then p else q(x)"),
+            term("p")
+        );
+        assert_eq!(simplified("forall x:t. q(x) | p | !q(x)"), term("true"));
+        assert_eq!(
+            simplified("forall x:t, y:t. r(y) & (x = x)"),
+            term("forall y:t. r(y)")
+        );
+        // X true does not hold at the end of a finite trace
+        assert_eq!(simplified("X true"), term("X true"));
+    }
+
+    #[test]
+    fn test_simplify_deterministic() {
+        // every SMT query is simplified, so the result must only depend on the
+        // term (and not, say, on the iteration order of a hash set)
+        let t = term(
+            "forall x:t. (r(x) | !q(x) | r(x)) & (p | (q(x) & true) | p) & \
+             !(exists y:t. s(x, y) & s(y, x) & s(x, y)) & (p -> p)",
+        );
+        let simplified = simplify(&t);
+        for _ in 0..20 {
+            assert_eq!(simplify(&t), simplified);
+        }
+        assert_eq!(simplify(&simplified), simplified);
+        assert_eq!(
+            simplified,
+            term("forall x:t. (r(x) | !q(x)) & (p | q(x)) & !(exists y:t. s(x, y) & s(y, x))")
+        );
+    }
+
+    #[test]
+    fn test_nnf() {
+        let normalized = |s: &str| nnf(&term(s));
+        assert_eq!(normalized("!(p & !q)"), term("!p | q"));
+        assert_eq!(normalized("!(p -> q)"), term("p & !q"));
+        assert_eq!(normalized("!(a = b)"), term("a != b"));
+        assert_eq!(
+            normalized("!forall x:t. exists y:t. r(x, y)"),
+            term("exists x:t. forall y:t. !r(x, y)")
+        );
+        assert_eq!(normalized("!always p'"), term("eventually !p'"));
+        assert_eq!(normalized("!(p <-> q)"), term("p & !q | !p & q"));
+        assert_eq!(normalized("if p then q else r"), term("p & q | !p & r"));
+        assert_eq!(normalized("!X !(p & q)"), term("!X (!p | !q)"));
+    }
+
+    #[test]
+    fn test_miniscope() {
+        let miniscoped = |s: &str| miniscope(&term(s));
+        assert_eq!(
+            miniscoped("forall x:t. p(x) & q(x)"),
+            term("(forall x:t. p(x)) & (forall x:t. q(x))")
+        );
+        assert_eq!(
+            miniscoped("forall x:t, y:t. p(x) | q(y)"),
+            term("(forall x:t. p(x)) | (forall y:t. q(y))")
+        );
+        assert_eq!(
+            miniscoped("exists x:t. a & p(x) & q(x)"),
+            term("a & (exists x:t. p(x) & q(x))")
+        );
+        assert_eq!(
+            miniscoped("forall x:t. exists y:t. r(y)"),
+            term("exists y:t. r(y)")
+        );
+    }
+
+    #[test]
+    fn test_prenex() {
+        let prenexed = |s: &str| prenex(&term(s));
+        assert_eq!(
+            prenexed("(forall x:t. p(x)) -> (exists y:t. q(y))"),
+            term("exists x:t, y:t. !p(x) | q(y)")
+        );
+        assert_eq!(
+            prenexed("p(x) & (exists x:t. q(x))"),
+            term("exists x_1:t. p(x) & q(x_1)")
+        );
+        assert_eq!(
+            prenexed("(forall x:t. p(x)) & (exists x:t. q(x))"),
+            term("forall x:t. exists x_1:t. p(x) & q(x_1)")
+        );
+        assert_eq!(
+            prenexed("forall x:t. p(x) & always (exists y:t. !!q(x, y))"),
+            term("forall x:t. p(x) & always (exists y:t. q(x, y))")
+        );
+    }
+}
@@ -2,14 +2,262 @@
 // SPDX-License-Identifier: BSD-2-Clause
 
 //! Perform substitutions of Id terms by other terms.
+//!
+//! [`substitute`] avoids capturing the free variables of the substituted terms
+//! by renaming binders to fresh names, which are generated by [`FreshNames`].
+//! Terms that differ only in the names of their bound variables are compared
+//! with [`alpha_equivalent`] and given a common representative by
+//! [`canonicalize`].
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use crate::syntax::Term;
+use crate::syntax::{Binder, Signature, Term};
 
 /// A map from identifiers to Terms.
 pub type Substitution = HashMap<String, Term>;
 
+/// The identifiers that occur free in `t`.
+pub fn free_vars(t: &Term) -> HashSet<String> {
+    fn go(t: &Term, bound: &mut Vec<String>, vars: &mut HashSet<String>) {
+        match t {
+            Term::Literal(_) => (),
+            Term::Id(s) => {
+                if !bound.contains(s) {
+                    vars.insert(s.clone());
+                }
+            }
+            Term::App(_, _, args) | Term::NAryOp(_, args) => {
+                for arg in args {
+                    go(arg, bound, vars);
+                }
+            }
+            Term::UnaryOp(_, t) => go(t, bound, vars),
+            Term::BinOp(_, lhs, rhs) => {
+                go(lhs, bound, vars);
+                go(rhs, bound, vars);
+            }
+            Term::Ite { cond, then, else_ } => {
+                go(cond, bound, vars);
+                go(then, bound, vars);
+                go(else_, bound, vars);
+            }
+            Term::Quantified { binders, body, .. } => {
+                let n = bound.len();
+                bound.extend(binders.iter().map(|b| b.name.clone()));
+                go(body, bound, vars);
+                bound.truncate(n);
+            }
+        }
+    }
+
+    let mut vars = HashSet::new();
+    go(t, &mut vec![], &mut vars);
+    vars
+}
+
+/// A generator of names that are distinct from every name it has been told to
+/// avoid, and from every name it has generated before.
+#[derive(Debug, Clone, Default)]
+pub struct FreshNames {
+    taken: HashSet<String>,
+}
+
+impl FreshNames {
+    /// Create a generator that avoids the function names of `signature`.
+    pub fn new(signature: &Signature) -> Self {
+        let mut names = Self::default();
+        names.avoid(signature.relations.iter().map(|r| r.name.clone()));
+        names
+    }
+
+    /// Avoid the given names.
+    pub fn avoid<I: IntoIterator<Item = String>>(&mut self, names: I) {
+        self.taken.extend(names)
+    }
+
+    /// Avoid every identifier and binder name that occurs in `t`.
+    pub fn avoid_term(&mut self, t: &Term) {
+        match t {
+            Term::Literal(_) => (),
+            Term::Id(s) => {
+                self.taken.insert(s.clone());
+            }
+            Term::App(_, _, args) | Term::NAryOp(_, args) => {
+                for arg in args {
+                    self.avoid_term(arg);
+                }
+            }
+            Term::UnaryOp(_, t) => self.avoid_term(t),
+            Term::BinOp(_, lhs, rhs) => {
+                self.avoid_term(lhs);
+                self.avoid_term(rhs);
+            }
+            Term::Ite { cond, then, else_ } => {
+                self.avoid_term(cond);
+                self.avoid_term(then);
+                self.avoid_term(else_);
+            }
+            Term::Quantified { binders, body, .. } => {
+                self.avoid(binders.iter().map(|b| b.name.clone()));
+                self.avoid_term(body);
+            }
+        }
+    }
+
+    /// Generate a fresh name based on `name`: `name` itself if it is not
+    /// taken, and otherwise `name_1`, `name_2`, and so on.
+    pub fn fresh(&mut self, name: &str) -> String {
+        let name = if self.taken.contains(name) {
+            (1..)
+                .map(|i| format!("{name}_{i}"))
+                .find(|n| !self.taken.contains(n))
+                .unwrap()
+        } else {
+            name.to_string()
+        };
+        self.taken.insert(name.clone());
+        name
+    }
+}
+
+fn substitute_fresh(term: &Term, substitution: &Substitution, names: &mut FreshNames) -> Term {
+    let mut go = |t: &Term| substitute_fresh(t, substitution, names);
+    match term {
+        Term::Literal(_) => term.clone(),
+        Term::Id(s) => substitution.get(s).unwrap_or(term).clone(),
+        Term::App(f, p, args) => Term::App(f.clone(), *p, args.iter().map(go).collect()),
+        Term::UnaryOp(op, arg) => Term::UnaryOp(*op, Box::new(go(arg))),
+        Term::BinOp(op, arg1, arg2) => Term::BinOp(*op, Box::new(go(arg1)), Box::new(go(arg2))),
+        Term::NAryOp(op, args) => Term::NAryOp(*op, args.iter().map(go).collect()),
+        Term::Ite { cond, then, else_ } => Term::Ite {
+            cond: Box::new(go(cond)),
+            then: Box::new(go(then)),
+            else_: Box::new(go(else_)),
+        },
+        Term::Quantified {
+            quantifier,
+            binders,
+            body,
+        } => {
+            // the binders shadow the substitution, and are renamed if they
+            // would capture a free variable of a term substituted in the body
+            let mut substitution = substitution.clone();
+            for b in binders {
+                substitution.remove(&b.name);
+            }
+            let free = free_vars(body);
+            let captured: HashSet<String> = substitution
+                .iter()
+                .filter(|(x, _)| free.contains(*x))
+                .flat_map(|(_, t)| free_vars(t))
+                .collect();
+            let binders = binders
+                .iter()
+                .map(|b| {
+                    if captured.contains(&b.name) {
+                        let name = names.fresh(&b.name);
+                        substitution.insert(b.name.clone(), Term::Id(name.clone()));
+                        Binder {
+                            name,
+                            sort: b.sort.clone(),
+                        }
+                    } else {
+                        b.clone()
+                    }
+                })
+                .collect();
+            Term::Quantified {
+                quantifier: *quantifier,
+                binders,
+                body: Box::new(substitute_fresh(body, &substitution, names)),
+            }
+        }
+    }
+}
+
+/// Perform a substitution of the free occurrences of identifiers in any term.
+/// Bound variables are renamed where needed so that the free variables of the
+/// substituted terms are not captured.
+pub fn substitute(term: &Term, substitution: &Substitution) -> Term {
+    let mut names = FreshNames::default();
+    names.avoid_term(term);
+    for (x, t) in substitution {
+        names.avoid([x.clone()]);
+        names.avoid_term(t);
+    }
+    substitute_fresh(term, substitution, &mut names)
+}
+
+/// Rename the bound variables of `t` to `x0`, `x1`, ... in the order in which
+/// they are bound, skipping names that occur free in `t`. Two terms are
+/// alpha-equivalent exactly when their canonical renamings are equal.
+pub fn canonicalize(t: &Term) -> Term {
+    fn canon(
+        t: &Term,
+        renaming: &mut Vec<(String, String)>,
+        next: &mut dyn FnMut() -> String,
+    ) -> Term {
+        let mut go = |t: &Term| canon(t, renaming, next);
+        match t {
+            Term::Literal(_) => t.clone(),
+            Term::Id(s) => match renaming.iter().rev().find(|(x, _)| x == s) {
+                Some((_, y)) => Term::Id(y.clone()),
+                None => t.clone(),
+            },
+            Term::App(f, p, args) => Term::App(f.clone(), *p, args.iter().map(go).collect()),
+            Term::UnaryOp(op, arg) => Term::UnaryOp(*op, Box::new(go(arg))),
+            Term::BinOp(op, arg1, arg2) => Term::BinOp(*op, Box::new(go(arg1)), Box::new(go(arg2))),
+            Term::NAryOp(op, args) => Term::NAryOp(*op, args.iter().map(go).collect()),
+            Term::Ite { cond, then, else_ } => Term::Ite {
+                cond: Box::new(go(cond)),
+                then: Box::new(go(then)),
+                else_: Box::new(go(else_)),
+            },
+            Term::Quantified {
+                quantifier,
+                binders,
+                body,
+            } => {
+                let n = renaming.len();
+                let binders = binders
+                    .iter()
+                    .map(|b| {
+                        let name = next();
+                        renaming.push((b.name.clone(), name.clone()));
+                        Binder {
+                            name,
+                            sort: b.sort.clone(),
+                        }
+                    })
+                    .collect();
+                let body = canon(body, renaming, next);
+                renaming.truncate(n);
+                Term::Quantified {
+                    quantifier: *quantifier,
+                    binders,
+                    body: Box::new(body),
+                }
+            }
+        }
+    }
+
+    let free = free_vars(t);
+    let mut i = 0;
+    let mut next = || loop {
+        let name = format!("x{i}");
+        i += 1;
+        if !free.contains(&name) {
+            return name;
+        }
+    };
+    canon(t, &mut vec![], &mut next)
+}
+
+/// Whether `t1` and `t2` are equal up to the names of their bound variables.
+pub fn alpha_equivalent(t1: &Term, t2: &Term) -> bool {
+    canonicalize(t1) == canonicalize(t2)
+}
+
 /// Perform a substitution over a quantifier-free term.
 pub fn substitute_qf(term: &Term, substitution: &Substitution) -> Term {
     match term {
@@ -58,7 +306,7 @@ pub fn substitute_qf(term: &Term, substitution: &Substitution) -> Term {
 #[allow(clippy::redundant_clone)]
 mod tests {
     use super::*;
-    use crate::parser::term;
+    use crate::parser::{parse_signature, term};
 
     #[test]
     fn test_subst_qf() {
@@ -83,4 +331,94 @@ mod tests {
         assert_eq!(substitute_qf(&t1, &suby), t1_suby);
         assert_eq!(substitute_qf(&t1, &subt), t1_subt);
     }
+
+    #[test]
+    fn test_substitute() {
+        let sub = |pairs: &[(&str, &str)]| -> Substitution {
+            pairs
+                .iter()
+                .map(|(x, t)| (x.to_string(), term(t)))
+                .collect()
+        };
+
+        assert_eq!(
+            substitute(&term("forall x:t. r(x, y)"), &sub(&[("y", "f(z)")])),
+            term("forall x:t. r(x, f(z))")
+        );
+        // bound occurrences are not substituted
+        assert_eq!(
+            substitute(&term("p(x) & forall x:t. p(x)"), &sub(&[("x", "z")])),
+            term("p(z) & forall x:t. p(x)")
+        );
+        // the binder is renamed to avoid capturing x
+        assert_eq!(
+            substitute(&term("forall x:t. r(x, y)"), &sub(&[("y", "x")])),
+            term("forall x_1:t. r(x_1, x)")
+        );
+        // the fresh name also avoids the names in the body
+        assert_eq!(
+            substitute(
+                &term("exists x:t. r(x, y) & (forall x_1:t. p(x_1))"),
+                &sub(&[("y", "x")])
+            ),
+            term("exists x_2:t. r(x_2, x) & (forall x_1:t. p(x_1))")
+        );
+        // simultaneous substitution
+        assert_eq!(
+            substitute(&term("r(x, y)"), &sub(&[("x", "y"), ("y", "x")])),
+            term("r(y, x)")
+        );
+    }
+
+    #[test]
+    fn test_fresh_names() {
+        let sig = parse_signature(
+            "
+            sort t
+            immutable x: t
+            mutable x_1(t): bool
+            ",
+        );
+        let mut names = FreshNames::new(&sig);
+        names.avoid_term(&term("forall y:t. p(y)"));
+        assert_eq!(names.fresh("x"), "x_2");
+        assert_eq!(names.fresh("x"), "x_3");
+        assert_eq!(names.fresh("y"), "y_1");
+        assert_eq!(names.fresh("z"), "z");
+        assert_eq!(names.fresh("z"), "z_1");
+    }
+
+    #[test]
+    fn test_alpha_equivalence() {
+        let alpha = |t1: &str, t2: &str| alpha_equivalent(&term(t1), &term(t2));
+
+        assert!(alpha("forall x:t. p(x)", "forall y:t. p(y)"));
+        assert!(alpha(
+            "forall x:t, y:t. r(x, y)",
+            "forall y:t, x:t. r(y, x)"
+        ));
+        assert!(!alpha(
+            "forall x:t, y:t. r(x, y)",
+            "forall x:t, y:t. r(y, x)"
+        ));
+        assert!(!alpha("forall x:t. p(x)", "forall x:s. p(x)"));
+        assert!(!alpha("forall x:t. p(x)", "exists x:t. p(x)"));
+        // free variables must match
+        assert!(!alpha("forall x:t. r(x, y)", "forall y:t. r(y, y)"));
+        assert!(alpha("forall x:t. r(x, x0)", "forall x1:t. r(x1, x0)"));
+        // shadowing
+        assert!(alpha(
+            "forall x:t. exists x:t. p(x)",
+            "forall y:t. exists z:t. p(z)"
+        ));
+        assert!(!alpha(
+            "forall x:t. exists x:t. p(x)",
+            "forall y:t. exists z:t. p(y)"
+        ));
+
+        assert_eq!(
+            canonicalize(&term("forall a:t. r(a, x0) & exists b:t. p(b)")),
+            term("forall x1:t. r(x1, x0) & exists x2:t. p(x2)")
+        );
+    }
 }
@@ -9,33 +9,69 @@ use crate::term::prime::Next;
 use thiserror::Error;
 
 /// Contains the different parts of the extracted transition system.
+#[derive(Clone)]
 pub struct DestructuredModule {
-    /// The initial conditions (assumes with no primes)
+    /// The initial conditions (assumes with no primes), in order
     pub inits: Vec<Term>,
-    /// The transitions (assume-alwayses with one prime)
+    /// The transitions (assume-alwayses with one prime), in order
     pub transitions: Vec<Term>,
-    /// The axioms (assume-alwayses with no primes)
+    /// The axioms (assume-alwayses with no primes), in order
     pub axioms: Vec<Term>,
     /// The assertions about the transition system
     pub proofs: Vec<Proof>,
+    /// The fairness constraints (ignored by safety checks)
+    pub fairness: Vec<Fairness>,
+    /// The locations of the assumptions the inits, transitions, and axioms
+    /// come from
+    pub spans: AssumptionSpans,
+}
+
+/// The locations of the inits, transitions, and axioms of a
+/// [`DestructuredModule`], in the same order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AssumptionSpans {
+    /// The locations of the initial conditions
+    pub inits: Vec<Option<Span>>,
+    /// The locations of the transitions
+    pub transitions: Vec<Option<Span>>,
+    /// The locations of the axioms
+    pub axioms: Vec<Option<Span>>,
 }
 
 /// Contains the parts of assertions in the module.
 /// Checking an Assert means checking safety & invariants.
 // This is different than the Proof in syntax.rs because safety has always been
 // unwrapped from an Always
+#[derive(Clone)]
 pub struct Proof {
     /// The safety property to check
     pub safety: Spanned<Term>,
     /// The invariants that we want to verify prove the safety property
     pub invariants: Vec<Spanned<Term>>,
+    /// Candidate invariants for inference (not verified)
+    pub candidates: Vec<Spanned<Term>>,
+    /// The assumptions which precede this assertion in the module
+    pub scope: Scope,
+}
+
+/// The prefixes of the inits, transitions, and axioms of a [`DestructuredModule`]
+/// which were assumed before an assertion.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Scope {
+    /// The number of initial conditions assumed
+    pub inits: usize,
+    /// The number of transitions assumed
+    pub transitions: usize,
+    /// The number of axioms assumed
+    pub axioms: usize,
 }
 
 /// An error during transition system extraction
 #[derive(Debug, Error, PartialEq)]
 pub enum ExtractionError {
-    /// `extract` is only correct on modules where all assumes come before all asserts
-    #[error("all assumes should precede all asserts, but found {0:?}")]
+    /// Fairness constraints are not scoped, so they must come before all asserts
+    /// (and so must all assumptions, for [`extract_in_order`])
+    #[error("expected this to precede all asserts, but found {0:?}")]
     OutOfOrderStatement(ThmStmt),
     /// The term should not have any primes (one-state)
     #[error("expected no primes in {0}")]
@@ -48,37 +84,74 @@ pub enum ExtractionError {
     AssertWithoutAlways(Term),
 }
 
-/// Extract the different types of terms from a Module
+/// Extract the different types of terms from a Module.
+///
+/// Statements are interpreted in order: each assertion is checked under the
+/// assumptions before it (recorded in its [`Scope`]), and may also assume the
+/// assertions before it.
 pub fn extract(module: &Module) -> Result<DestructuredModule, ExtractionError> {
-    let mut assumes = Vec::new();
-    let mut asserts = Vec::new();
-    for statement in &module.statements {
-        match statement {
-            ThmStmt::Assert(proof) => asserts.push(proof),
-            ThmStmt::Assume(term) if asserts.is_empty() => assumes.push(term.clone()),
-            _ => return Err(ExtractionError::OutOfOrderStatement(statement.clone())),
-        }
-    }
-
     let mut inits = Vec::new();
     let mut transitions = Vec::new();
     let mut axioms = Vec::new();
-    for assume in assumes {
-        match assume {
+    let mut asserts = Vec::new();
+    let mut fairness = Vec::new();
+    let mut spans = AssumptionSpans::default();
+    for statement in &module.statements {
+        let assume = match statement {
+            ThmStmt::Assert(proof) => {
+                let scope = Scope {
+                    inits: inits.len(),
+                    transitions: transitions.len(),
+                    axioms: axioms.len(),
+                };
+                asserts.push((proof, scope));
+                continue;
+            }
+            ThmStmt::Assume(term) => match Fairness::from_term(&term.x) {
+                Some(constraint) if asserts.is_empty() => {
+                    fairness.push(constraint);
+                    continue;
+                }
+                Some(_) => return Err(ExtractionError::OutOfOrderStatement(statement.clone())),
+                None => term.clone(),
+            },
+            ThmStmt::Fair(constraint) if asserts.is_empty() => {
+                fairness.push(constraint.clone());
+                continue;
+            }
+            ThmStmt::Fair(_) => {
+                return Err(ExtractionError::OutOfOrderStatement(statement.clone()))
+            }
+        };
+        match assume.x {
             Term::UnaryOp(UOp::Always, term) if FirstOrder::unrolling(&term) == Some(0) => {
-                axioms.push(*term)
+                axioms.push(*term);
+                spans.axioms.push(assume.span);
             }
             Term::UnaryOp(UOp::Always, term) if FirstOrder::unrolling(&term) == Some(1) => {
-                transitions.push(*term)
+                transitions.push(*term);
+                spans.transitions.push(assume.span);
             }
             Term::UnaryOp(UOp::Always, term) => return Err(ExtractionError::TooFuture(*term)),
-            term if FirstOrder::unrolling(&term) == Some(0) => inits.push(term),
+            term if FirstOrder::unrolling(&term) == Some(0) => {
+                inits.push(term);
+                spans.inits.push(assume.span);
+            }
             term => return Err(ExtractionError::AnyFuture(term)),
         }
     }
 
+    for constraint in &fairness {
+        if FirstOrder::unrolling(&constraint.enabled) != Some(0) {
+            return Err(ExtractionError::AnyFuture(constraint.enabled.clone()));
+        }
+        if !matches!(FirstOrder::unrolling(&constraint.taken), Some(0 | 1)) {
+            return Err(ExtractionError::TooFuture(constraint.taken.clone()));
+        }
+    }
+
     let mut proofs = Vec::new();
-    for assert in asserts {
+    for (assert, scope) in asserts {
         let safety = match &assert.assert.x {
             Term::UnaryOp(UOp::Always, term) if FirstOrder::unrolling(term) == Some(0) => {
                 *term.clone()
@@ -101,16 +174,34 @@ pub fn extract(module: &Module) -> Result<DestructuredModule, ExtractionError> {
                 term => return Err(ExtractionError::AnyFuture(term.clone())),
             }
         }
-        proofs.push(Proof { safety, invariants })
+        let mut candidates = Vec::new();
+        for candidate in &assert.candidates {
+            match &candidate.x {
+                term if FirstOrder::unrolling(term) == Some(0) => {
+                    candidates.push(candidate.clone())
+                }
+                term => return Err(ExtractionError::AnyFuture(term.clone())),
+            }
+        }
+        proofs.push(Proof {
+            safety,
+            invariants,
+            candidates,
+            scope,
+        })
     }
 
     let next = Next::new(&module.signature);
     for term in inits.iter_mut().chain(&mut transitions).chain(&mut axioms) {
         *term = next.normalize(term);
     }
+    for constraint in &mut fairness {
+        constraint.enabled = next.normalize(&constraint.enabled);
+        constraint.taken = next.normalize(&constraint.taken);
+    }
     for proof in &mut proofs {
         proof.safety.x = next.normalize(&proof.safety.x);
-        for invariant in &mut proof.invariants {
+        for invariant in proof.invariants.iter_mut().chain(&mut proof.candidates) {
             invariant.x = next.normalize(&invariant.x);
         }
     }
@@ -120,17 +211,159 @@ pub fn extract(module: &Module) -> Result<DestructuredModule, ExtractionError> {
         transitions,
         axioms,
         proofs,
+        fairness,
+        spans,
     })
 }
 
+/// A liveness property `always (trigger -> eventually goal)`, where `trigger`
+/// and `goal` have no primes. `always eventually goal` has the trigger `true`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Liveness {
+    /// The condition which requires the goal to eventually hold
+    pub trigger: Term,
+    /// The condition which must eventually hold after each trigger
+    pub goal: Term,
+}
+
+impl Liveness {
+    /// Recognize an assertion of the form `always eventually q` or
+    /// `always (p -> eventually q)`.
+    fn from_assertion(term: &Term) -> Option<Liveness> {
+        let Term::UnaryOp(UOp::Always, body) = term else {
+            return None;
+        };
+        let (trigger, goal) = match body.as_ref() {
+            Term::UnaryOp(UOp::Eventually, goal) => (Term::true_(), goal.as_ref()),
+            Term::BinOp(BinOp::Implies, trigger, eventually) => match eventually.as_ref() {
+                Term::UnaryOp(UOp::Eventually, goal) => (trigger.as_ref().clone(), goal.as_ref()),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        if FirstOrder::unrolling(&trigger) != Some(0) || FirstOrder::unrolling(goal) != Some(0) {
+            return None;
+        }
+        Some(Liveness {
+            trigger,
+            goal: goal.clone(),
+        })
+    }
+
+    /// The property as a temporal term.
+    pub fn to_term(&self) -> Term {
+        Term::always(Term::implies(
+            self.trigger.clone(),
+            Term::eventually(self.goal.clone()),
+        ))
+    }
+}
+
+/// Split `module` into one module for each maximal run of consecutive
+/// assertions, which contains that run and all the assumptions before it (so
+/// that the run can be checked without the later assumptions). A module without
+/// assertions is returned as is.
+pub fn split_assertions(module: &Module) -> Vec<Module> {
+    let mut modules: Vec<Module> = vec![];
+    let mut assumptions = vec![];
+    let mut in_run = false;
+    for statement in &module.statements {
+        match statement {
+            ThmStmt::Assert(_) => {
+                if !in_run {
+                    modules.push(Module {
+                        statements: assumptions.clone(),
+                        ..module.clone()
+                    });
+                    in_run = true;
+                }
+                modules
+                    .last_mut()
+                    .unwrap()
+                    .statements
+                    .push(statement.clone());
+            }
+            ThmStmt::Assume(_) | ThmStmt::Fair(_) => {
+                assumptions.push(statement.clone());
+                in_run = false;
+            }
+        }
+    }
+    if modules.is_empty() {
+        modules.push(module.clone());
+    }
+    modules
+}
+
+/// Extract the transition system like [`extract`], but reject assumptions which
+/// come after an assertion. This is for consumers which check every assertion
+/// under all of the assumptions, rather than under its [`Scope`].
+pub fn extract_in_order(module: &Module) -> Result<DestructuredModule, ExtractionError> {
+    let mut statements = module.statements.iter();
+    if statements.any(|statement| matches!(statement, ThmStmt::Assert(_))) {
+        if let Some(statement) = statements.find(|s| !matches!(s, ThmStmt::Assert(_))) {
+            return Err(ExtractionError::OutOfOrderStatement(statement.clone()));
+        }
+    }
+    extract(module)
+}
+
+/// Extract the transition system like [`extract`], but also accept liveness
+/// assertions of the forms recognized by [`Liveness`], which are returned
+/// separately instead of as proofs.
+pub fn extract_with_liveness(
+    module: &Module,
+) -> Result<(DestructuredModule, Vec<Spanned<Liveness>>), ExtractionError> {
+    let mut liveness = vec![];
+    let mut safety = module.clone();
+    safety.statements.retain(|statement| match statement {
+        ThmStmt::Assert(proof) => match Liveness::from_assertion(&proof.assert.x) {
+            Some(property) => {
+                liveness.push(Spanned {
+                    x: property,
+                    span: proof.assert.span,
+                });
+                false
+            }
+            None => true,
+        },
+        ThmStmt::Assume(_) | ThmStmt::Fair(_) => true,
+    });
+
+    let next = Next::new(&module.signature);
+    for property in &mut liveness {
+        property.x.trigger = next.normalize(&property.x.trigger);
+        property.x.goal = next.normalize(&property.x.goal);
+    }
+
+    Ok((extract(&safety)?, liveness))
+}
+
 impl DestructuredModule {
+    /// The inits, transitions, and axioms under which the `i`th proof is
+    /// checked: the assumptions in its scope, plus the safety properties of
+    /// the proofs before it as axioms.
+    pub fn assumptions(&self, i: usize) -> (&[Term], &[Term], Vec<Term>) {
+        let scope = self.proofs[i].scope;
+        let axioms = self.axioms[..scope.axioms]
+            .iter()
+            .chain(self.proofs[..i].iter().map(|proof| &proof.safety.x))
+            .cloned()
+            .collect();
+        (
+            &self.inits[..scope.inits],
+            &self.transitions[..scope.transitions],
+            axioms,
+        )
+    }
+
     /// Returns only the axioms that mention at least one mutable relation
     // optimization: axioms that only mention immutable relations can be treated as inits
     // by the bounded model checkers
     pub fn mutable_axioms<'a>(
         &'a self,
         relations: &'a [RelationDecl],
-    ) -> impl Iterator<Item = &Term> + 'a {
+    ) -> impl Iterator<Item = &'a Term> + 'a {
         self.axioms
             .iter()
             .filter(|term| contains_mutable_relations(term, relations))
@@ -151,3 +384,72 @@ fn contains_mutable_relations(term: &Term, relations: &[RelationDecl]) -> bool {
         Term::Quantified { body, .. } => go(body),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse, term};
+
+    #[test]
+    fn test_extract_in_order() {
+        let m = parse(
+            "mutable x: bool
+mutable y: bool
+
+assume !x
+assume always (x' <-> !x)
+assert always (x | !x)
+assume !y
+assume always (y -> x)
+assert always !x
+assert always (y -> x)
+assume always (y' <-> y)
+",
+        )
+        .unwrap();
+        let d = extract(&m).unwrap();
+        assert_eq!(d.inits, vec![term("!x"), term("!y")]);
+        assert_eq!(d.transitions.len(), 2);
+        assert_eq!(
+            d.proofs.iter().map(|p| p.scope).collect::<Vec<_>>(),
+            vec![
+                Scope {
+                    inits: 1,
+                    transitions: 1,
+                    axioms: 0
+                },
+                Scope {
+                    inits: 2,
+                    transitions: 1,
+                    axioms: 1
+                },
+                Scope {
+                    inits: 2,
+                    transitions: 1,
+                    axioms: 1
+                },
+            ]
+        );
+
+        let (inits, transitions, axioms) = d.assumptions(2);
+        assert_eq!(inits, &d.inits[..]);
+        assert_eq!(transitions, &d.transitions[..1]);
+        assert_eq!(axioms, vec![term("y -> x"), term("x | !x"), term("!x")]);
+
+        assert_eq!(
+            extract_in_order(&m).err(),
+            Some(ExtractionError::OutOfOrderStatement(
+                m.statements[3].clone()
+            ))
+        );
+        assert!(extract_in_order(&split_assertions(&m)[0]).is_ok());
+
+        let runs = split_assertions(&m);
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].statements, m.statements[..3]);
+        // earlier assertions are not part of later runs
+        let expected = [0, 1, 3, 4, 5, 6].map(|i| m.statements[i].clone());
+        assert_eq!(runs[1].statements, expected);
+        assert_eq!(split_assertions(&runs[1]).len(), 1);
+    }
+}
@@ -4,7 +4,10 @@
 use fly::{
     semantics::{Assignment, Model},
     syntax::{BinOp, Term},
-    term::subst::{substitute_qf, Substitution},
+    term::{
+        hashcons::{HTerm, Node, TermStore},
+        subst::Substitution,
+    },
 };
 use itertools::Itertools;
 use std::sync::Arc;
@@ -55,7 +58,11 @@ where
 
 pub struct Atoms {
     pub to_term: Vec<Term>,
-    pub to_index: HashMap<Term, usize>,
+    /// The atoms are also kept hash-consed, so that looking up the index of a
+    /// substituted atom does not hash whole terms.
+    pub store: TermStore,
+    pub to_handle: Vec<HTerm>,
+    pub to_index: HashMap<HTerm, usize>,
 }
 
 impl Atoms {
@@ -69,17 +76,24 @@ impl Atoms {
                 let univ_t = univ_prefix.quantify(t.clone());
                 let univ_not_t = univ_prefix.quantify(Term::negate(t.clone()));
 
-                fo.implication_cex(solver, &[], &univ_t).is_cex()
-                    && fo.implication_cex(solver, &[], &univ_not_t).is_cex()
+                fo.implication_cex(solver, &[], &univ_t, None).is_cex()
+                    && fo.implication_cex(solver, &[], &univ_not_t, None).is_cex()
             })
             .collect();
-        let to_index = to_term
+        let mut store = TermStore::new();
+        let to_handle = to_term.iter().map(|t| store.intern(t)).collect_vec();
+        let to_index = to_handle
             .iter()
             .enumerate()
-            .map(|(index, term)| (term.clone(), index))
+            .map(|(index, &handle)| (handle, index))
             .collect();
 
-        Self { to_term, to_index }
+        Self {
+            to_term,
+            store,
+            to_handle,
+            to_index,
+        }
     }
 }
 
@@ -103,36 +117,25 @@ impl RestrictedAtoms {
     }
 
     pub fn substitute(&self, atom: usize, substitution: &Substitution) -> Option<usize> {
-        match &self.atoms.to_term[atom] {
-            Term::BinOp(BinOp::Equals, t1, t2) => {
-                let t1_sub = substitute_qf(t1, substitution);
-                let t2_sub = substitute_qf(t2, substitution);
-
-                let eq12 = Term::equals(t1_sub.clone(), t2_sub.clone());
-                let eq21 = Term::equals(t2_sub, t1_sub);
-
-                let mut res = None;
-
-                if let Some(i) = self.atoms.to_index.get(&eq12) {
-                    if self.allowed.contains(i) {
-                        res = Some(*i);
-                    }
-                }
-
-                if let Some(i) = self.atoms.to_index.get(&eq21) {
-                    if self.allowed.contains(i) {
-                        res = Some(*i);
-                    }
-                }
-
-                res
-            }
-            _ => self
-                .atoms
-                .to_index
-                .get(&substitute_qf(&self.atoms.to_term[atom], substitution))
+        let store = &self.atoms.store;
+        let index = |handle: Option<HTerm>| {
+            handle
+                .and_then(|h| self.atoms.to_index.get(&h))
                 .filter(|&i| self.allowed.contains(i))
-                .copied(),
+                .copied()
+        };
+        let handle = self.atoms.to_handle[atom];
+        match store.node(handle) {
+            Node::BinOp(BinOp::Equals, t1, t2) => {
+                let t1_sub = store.get_substituted_qf(*t1, substitution)?;
+                let t2_sub = store.get_substituted_qf(*t2, substitution)?;
+
+                let eq12 = store.get_node(&Node::BinOp(BinOp::Equals, t1_sub, t2_sub));
+                let eq21 = store.get_node(&Node::BinOp(BinOp::Equals, t2_sub, t1_sub));
+
+                index(eq21).or(index(eq12))
+            }
+            _ => index(store.get_substituted_qf(handle, substitution)),
         }
     }
 
@@ -0,0 +1,249 @@
+// Copyright 2022-2023 VMware, Inc.
+// SPDX-License-Identifier: BSD-2-Clause
+
+//! Automatically tune the search parameters of qalpha.
+//!
+//! A schedule of configurations is derived from the signature, from cheap to
+//! expensive: the sorts are ordered so that the domain of each function comes
+//! before its range, and the sizes of the quantifier-free body and the number
+//! of existential quantifiers grow along the schedule. The configurations are
+//! run in order, splitting a time budget between them, until one proves safety.
+
+use std::{
+    fmt::Display,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use fly::syntax::{Module, Quantifier, Signature, Sort};
+use itertools::Itertools;
+
+use crate::{
+    basics::{InferenceConfig, QfBody},
+    fixpoint::{defaults, qalpha_dynamic, FoundFixpoint, QalphaError},
+    quant::QuantifierConfig,
+};
+
+/// The sizes of the quantifier-free body and of each sort's quantifiers,
+/// from cheap to expensive: `(max_same_sort, cubes, cube_size, non_unit)`.
+const LEVELS: [(usize, usize, usize, usize); 3] = [(2, 3, 1, 0), (3, 4, 2, 1), (3, 6, 4, 3)];
+
+/// The maximal number of existential quantifiers tried.
+const MAX_EXIST: usize = 2;
+
+/// A configuration of qalpha chosen by the automatic schedule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoConfig {
+    /// The sorts of the quantifier prefix, in order.
+    pub sorts: Vec<String>,
+    pub max_same_sort: usize,
+    pub max_exist: usize,
+    pub cubes: usize,
+    pub cube_size: usize,
+    pub non_unit: usize,
+}
+
+impl AutoConfig {
+    /// Apply this configuration to a base configuration. Only the file name and
+    /// the solver and reporting settings of the base are kept; every search
+    /// parameter is either set by this configuration or left at its default, so
+    /// that running qalpha with the flags of this configuration (see its
+    /// [`Display`] implementation) reproduces the run.
+    pub fn apply(&self, base: &InferenceConfig) -> InferenceConfig {
+        let sig = base.cfg.signature.clone();
+        let sorts = self
+            .sorts
+            .iter()
+            .map(|s| sig.sort_idx(&Sort::Uninterpreted(s.clone())))
+            .collect_vec();
+        InferenceConfig {
+            fname: base.fname.clone(),
+            fallback: base.fallback,
+            cfg: QuantifierConfig::new(
+                sig,
+                vec![None::<Quantifier>; sorts.len()],
+                sorts.clone(),
+                &vec![self.max_same_sort; sorts.len()],
+            ),
+            qf_body: QfBody::PDnf,
+            max_size: defaults::MAX_QUANT,
+            max_existentials: Some(self.max_exist),
+            clauses: None,
+            clause_size: None,
+            cubes: Some(self.cubes),
+            cube_size: Some(self.cube_size),
+            non_unit: Some(self.non_unit),
+            nesting: None,
+            include_eq: true,
+            disj: base.disj,
+            gradual_smt: base.gradual_smt,
+            minimal_smt: base.minimal_smt,
+            extend_width: None,
+            extend_depth: None,
+            until_safe: true,
+            abort_unsafe: false,
+            no_search: false,
+            growth_factor: None,
+            memory_limit_mb: base.memory_limit_mb,
+            rlimit: base.rlimit,
+            solver: base.solver,
+            checkpoint: None,
+            resume: None,
+            domain_workers: 1,
+            ice: false,
+            bounds: None,
+            explain: base.explain,
+            deadline: None,
+        }
+    }
+}
+
+impl Display for AutoConfig {
+    /// The command-line flags which select this configuration.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "--custom-quant")?;
+        for sort in &self.sorts {
+            write!(f, " -q '* {sort} {}'", self.max_same_sort)?;
+        }
+        write!(
+            f,
+            " --qf-body pdnf --max-exist {} --cubes {} --cube-size {} --non-unit {} --until-safe",
+            self.max_exist, self.cubes, self.cube_size, self.non_unit
+        )
+    }
+}
+
+/// Order the sorts of the signature so that the argument sorts of each
+/// function come before its return sort. Sorts are otherwise kept in the order
+/// of the signature, which is also used to break cycles.
+pub fn sort_order(sig: &Signature) -> Vec<String> {
+    let edges = sig
+        .relations
+        .iter()
+        .filter_map(|r| match &r.sort {
+            Sort::Uninterpreted(ret) => Some(
+                r.args
+                    .iter()
+                    .filter_map(|arg| match arg {
+                        Sort::Uninterpreted(arg) if arg != ret => Some((arg.clone(), ret.clone())),
+                        _ => None,
+                    })
+                    .collect_vec(),
+            ),
+            Sort::Bool => None,
+        })
+        .flatten()
+        .unique()
+        .collect_vec();
+
+    let mut remaining = sig.sorts.clone();
+    let mut order = vec![];
+    while !remaining.is_empty() {
+        let next = remaining
+            .iter()
+            .position(|s| {
+                !edges
+                    .iter()
+                    .any(|(arg, ret)| ret == s && remaining.contains(arg))
+            })
+            .unwrap_or(0);
+        order.push(remaining.remove(next));
+    }
+    order
+}
+
+/// The schedule of configurations to try for the given signature.
+pub fn schedule(sig: &Signature) -> Vec<AutoConfig> {
+    let sorts = sort_order(sig);
+    let max_exist = MAX_EXIST.min(sorts.len());
+    LEVELS
+        .iter()
+        .flat_map(|&(max_same_sort, cubes, cube_size, non_unit)| {
+            let sorts = sorts.clone();
+            (0..=max_exist).map(move |max_exist| AutoConfig {
+                sorts: sorts.clone(),
+                max_same_sort,
+                max_exist,
+                cubes,
+                cube_size,
+                non_unit,
+            })
+        })
+        .collect()
+}
+
+/// Run the configurations of the schedule in order, giving each an equal share
+/// of the remaining `budget`, until one proves safety. Return that
+/// configuration (or `None` if there is none) and the last fixpoint found.
+pub fn qalpha_auto(
+    base: &InferenceConfig,
+    m: &Module,
+    budget: Duration,
+    print_invariant: bool,
+) -> Result<(Option<AutoConfig>, FoundFixpoint), QalphaError> {
+    let schedule = schedule(&m.signature);
+    let end = Instant::now() + budget;
+    let mut last = None;
+    for (i, auto_cfg) in schedule.iter().enumerate() {
+        let now = Instant::now();
+        if now >= end {
+            break;
+        }
+        let share = (end - now) / (schedule.len() - i) as u32;
+        println!();
+        println!(
+            "[auto {}/{}] Trying {auto_cfg} (budget {:.0}s)",
+            i + 1,
+            schedule.len(),
+            share.as_secs_f64()
+        );
+        let mut infer_cfg = auto_cfg.apply(base);
+        infer_cfg.deadline = Some(now + share);
+        let fixpoint = qalpha_dynamic(Arc::new(infer_cfg), m, print_invariant)?;
+        if fixpoint.invariant().is_some() {
+            println!();
+            println!("Cheapest configuration proving safety: {auto_cfg}");
+            return Ok((Some(auto_cfg.clone()), fixpoint));
+        }
+        last = Some(fixpoint);
+    }
+
+    println!();
+    println!("No configuration proved safety within the budget.");
+    let last = last.unwrap_or_else(|| FoundFixpoint::aborted(Duration::ZERO));
+    Ok((None, last))
+}
+
+#[cfg(test)]
+mod tests {
+    use fly::parser::parse_signature;
+
+    use super::{schedule, sort_order};
+
+    #[test]
+    fn test_sort_order() {
+        let sig = parse_signature(
+            "sort value\nsort round\nsort node\nimmutable decision(round): value\nmutable owner(value): node",
+        );
+        assert_eq!(sort_order(&sig), vec!["round", "value", "node"]);
+
+        let sig = parse_signature("sort a\nsort b\nimmutable f(a): b\nimmutable g(b): a");
+        assert_eq!(sort_order(&sig), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_schedule() {
+        let sig = parse_signature("sort node\nmutable p(node): bool");
+        let schedule = schedule(&sig);
+        assert_eq!(schedule.len(), 6);
+        assert_eq!(schedule[0].max_exist, 0);
+        assert_eq!(schedule[1].max_exist, 1);
+        assert!(schedule
+            .windows(2)
+            .all(|w| w[0].cubes <= w[1].cubes && w[0].cube_size <= w[1].cube_size));
+        assert_eq!(
+            schedule[0].to_string(),
+            "--custom-quant -q '* node 2' --qf-body pdnf --max-exist 0 --cubes 3 --cube-size 1 --non-unit 0 --until-safe"
+        );
+    }
+}
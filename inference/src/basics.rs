@@ -11,6 +11,7 @@ use std::{
 };
 
 use crate::quant::QuantifierConfig;
+use bounded::quant_enum::UniverseBounds;
 use fly::syntax::BinOp;
 use fly::syntax::Term::*;
 use fly::syntax::*;
@@ -18,6 +19,7 @@ use fly::term::{prime::clear_next, prime::Next};
 use fly::{ouritertools::OurItertools, semantics::Model, transitions::*};
 use smtlib::proc::{SatResp, SolverError};
 use solver::{
+    backends::SolverType,
     basics::{BasicSolver, BasicSolverCanceler, BasicSolverResp, QueryConf, SolverCancelers},
     conf::SolverConf,
 };
@@ -181,6 +183,7 @@ pub enum CexOrCore {
 /// namely single-vocabulary axioms, initial assertions and safety assertions,
 /// and double-vocabulary transition assertions.
 /// `disj` denotes whether to split the transitions disjunctively, if possible.
+#[derive(Clone)]
 pub struct FOModule {
     signature: Signature,
     pub module: DestructuredModule,
@@ -190,14 +193,21 @@ pub struct FOModule {
 }
 
 impl FOModule {
-    pub fn new(m: &Module, disj: bool, gradual: bool, minimal: bool) -> Self {
-        FOModule {
+    /// Fails if an assumption comes after an assertion, since every
+    /// assertion is checked under all of the assumptions.
+    pub fn new(
+        m: &Module,
+        disj: bool,
+        gradual: bool,
+        minimal: bool,
+    ) -> Result<Self, ExtractionError> {
+        Ok(FOModule {
             signature: m.signature.clone(),
-            module: extract(m).unwrap(),
+            module: extract_in_order(m)?,
             disj,
             gradual,
             minimal,
-        }
+        })
     }
 
     fn disj_trans(&self) -> Vec<Vec<&Term>> {
@@ -216,8 +226,17 @@ impl FOModule {
         }
     }
 
-    pub fn init_cex<B: BasicSolver>(&self, solver: &B, t: &Term) -> Option<Model> {
-        self.implication_cex(solver, &self.module.inits, t)
+    pub fn init_cex<B, C>(
+        &self,
+        solver: &B,
+        t: &Term,
+        cancelers: Option<SolverCancelers<SolverCancelers<C>>>,
+    ) -> Option<Model>
+    where
+        C: BasicSolverCanceler,
+        B: BasicSolver<Canceler = C>,
+    {
+        self.implication_cex(solver, &self.module.inits, t, cancelers)
             .into_option()
             .map(|mut models| {
                 assert_eq!(models.len(), 1);
@@ -350,7 +369,25 @@ impl FOModule {
         CexResult::UnsatCore(unsat_core)
     }
 
-    pub fn implication_cex<B: BasicSolver>(&self, solver: &B, hyp: &[Term], t: &Term) -> CexResult {
+    pub fn implication_cex<B, C>(
+        &self,
+        solver: &B,
+        hyp: &[Term],
+        t: &Term,
+        cancelers: Option<SolverCancelers<SolverCancelers<C>>>,
+    ) -> CexResult
+    where
+        C: BasicSolverCanceler,
+        B: BasicSolver<Canceler = C>,
+    {
+        let local_cancelers: SolverCancelers<C> = SolverCancelers::new();
+        if cancelers
+            .as_ref()
+            .is_some_and(|c| !c.add_canceler(local_cancelers.clone()))
+        {
+            return CexResult::Canceled;
+        }
+
         let mut core: Core = if self.gradual {
             Core::new(hyp, HashSet::new(), self.minimal)
         } else {
@@ -360,24 +397,23 @@ impl FOModule {
         let query_conf = QueryConf {
             sig: &self.signature,
             n_states: 1,
-            cancelers: None,
+            cancelers: Some(local_cancelers),
             minimal_model: true,
             save_tee: false,
         };
         let mut assertions = self.module.axioms.clone();
         assertions.push(Term::not(t));
         loop {
-            match solver
-                .check_sat(&query_conf, &assertions, &core.to_assumptions())
-                .expect("error in solver")
-            {
-                BasicSolverResp::Sat(models) => {
+            match solver.check_sat(&query_conf, &assertions, &core.to_assumptions()) {
+                Ok(BasicSolverResp::Sat(models)) => {
                     if !core.add_counter_model(models[0].clone()) {
                         return CexResult::Cex(models);
                     }
                 }
-                BasicSolverResp::Unsat(core) => return CexResult::UnsatCore(core),
-                BasicSolverResp::Unknown(reason) => return CexResult::Unknown(reason),
+                Ok(BasicSolverResp::Unsat(core)) => return CexResult::UnsatCore(core),
+                Ok(BasicSolverResp::Unknown(reason)) => return CexResult::Unknown(reason),
+                Err(SolverError::Killed) => return CexResult::Canceled,
+                Err(e) => panic!("error in solver: {e}"),
             }
         }
     }
@@ -388,6 +424,7 @@ impl FOModule {
         state: &Model,
         width: usize,
         depth: usize,
+        parent: Option<SolverCancelers<SolverCancelers<C>>>,
     ) -> Vec<Model>
     where
         C: BasicSolverCanceler,
@@ -399,6 +436,12 @@ impl FOModule {
         let state_term = state.to_term();
         let samples = Mutex::new(vec![]);
         let cancelers = SolverCancelers::new();
+        if parent
+            .as_ref()
+            .is_some_and(|parent| !parent.add_canceler(cancelers.clone()))
+        {
+            return vec![];
+        }
         let empty_assumptions = HashMap::new();
         let next = Next::new(&self.signature);
         let query_conf = QueryConf {
@@ -442,7 +485,9 @@ impl FOModule {
         if depth > 1 {
             let mut deep_samples: Vec<Model> = samples
                 .par_iter()
-                .flat_map(|sample| self.simulate_from(solver, sample, width, depth - 1))
+                .flat_map(|sample| {
+                    self.simulate_from(solver, sample, width, depth - 1, parent.clone())
+                })
                 .collect();
             samples.append(&mut deep_samples);
         }
@@ -568,10 +613,19 @@ impl FOModule {
         }
     }
 
-    pub fn trans_safe_cex<B: BasicSolver>(&self, solver: &B, hyp: &[Term]) -> CexResult {
+    pub fn trans_safe_cex<B, C>(
+        &self,
+        solver: &B,
+        hyp: &[Term],
+        cancelers: Option<SolverCancelers<SolverCancelers<C>>>,
+    ) -> CexResult
+    where
+        C: BasicSolverCanceler,
+        B: BasicSolver<Canceler = C>,
+    {
         let mut core = HashSet::new();
         for s in self.module.proofs.iter() {
-            match self.trans_cex(solver, hyp, &s.safety.x, true, None, false) {
+            match self.trans_cex(solver, hyp, &s.safety.x, true, cancelers.clone(), false) {
                 CexResult::UnsatCore(new_core) => core.extend(new_core),
                 res => return res,
             }
@@ -591,12 +645,14 @@ impl FOModule {
     }
 }
 
+#[derive(Clone)]
 pub enum QfBody {
     CNF,
     PDnf,
     PDnfNaive,
 }
 
+#[derive(Clone)]
 pub struct InferenceConfig {
     pub fname: String,
 
@@ -628,6 +684,18 @@ pub struct InferenceConfig {
     pub abort_unsafe: bool,
     pub no_search: bool,
     pub growth_factor: Option<usize>,
+    pub memory_limit_mb: Option<usize>,
+    pub rlimit: Option<usize>,
+    /// The solver for single-solver queries, such as checking candidate invariants.
+    pub solver: SolverType,
+    pub checkpoint: Option<String>,
+    pub resume: Option<String>,
+    pub domain_workers: usize,
+    pub ice: bool,
+    pub bounds: Option<UniverseBounds>,
+    pub explain: bool,
+    /// Stop the run (returning no fixpoint) once this time passes.
+    pub deadline: Option<Instant>,
 }
 
 pub fn parse_quantifier(
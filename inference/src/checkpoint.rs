@@ -0,0 +1,311 @@
+// Copyright 2022-2023 VMware, Inc.
+// SPDX-License-Identifier: BSD-2-Clause
+
+//! Save and restore the progress of a qalpha run.
+//!
+//! A checkpoint records which iteration of the domain search the run is in,
+//! and the models used so far to weaken the current [`InductionFrame`]. Since
+//! weakening is deterministic, replaying these models on a fresh frame over
+//! the same domains reproduces its lemmas. The lemmas themselves are also
+//! saved, for inspection.
+//!
+//! A checkpoint also has a fingerprint of the module, the lemma domains, and
+//! the options controlling the search, so that it is only resumed with an
+//! identical configuration.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    fs,
+    hash::Hasher,
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use fly::{
+    printer,
+    semantics::{Element, Interpretation, Model},
+    syntax::{Module, Signature},
+};
+use fxhash::FxHasher64;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    basics::InferenceConfig,
+    fixpoint::defaults,
+    lemma::InductionFrame,
+    subsume::OrderSubsumption,
+    weaken::{Domain, LemmaQf},
+};
+
+/// How often to save a checkpoint while running qalpha.
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// An error from saving or loading a checkpoint.
+#[derive(Error, Debug)]
+pub enum CheckpointError {
+    /// Reading or writing the file failed
+    #[error("checkpoint I/O failed: {0}")]
+    Io(#[from] io::Error),
+    /// The file is not a valid checkpoint
+    #[error("malformed checkpoint: {0}")]
+    Malformed(#[from] serde_json::Error),
+    /// The checkpoint was saved for another module or configuration
+    #[error("checkpoint is for a different module or configuration")]
+    Mismatch,
+}
+
+/// The interpretation of a single symbol in a [`Sample`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SampleInterp {
+    shape: Vec<usize>,
+    data: Vec<Element>,
+}
+
+/// A model used to weaken the frame, without its signature.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Sample {
+    universe: Vec<usize>,
+    interp: Vec<SampleInterp>,
+}
+
+impl Sample {
+    /// Record a model as a sample.
+    pub fn from_model(model: &Model) -> Self {
+        Self {
+            universe: model.universe.clone(),
+            interp: model
+                .interp
+                .iter()
+                .map(|i| SampleInterp {
+                    shape: i.shape.clone(),
+                    data: i.data.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Convert the sample back to a model of the signature `sig`.
+    pub fn to_model(&self, sig: &Signature) -> Model {
+        let interp = self
+            .interp
+            .iter()
+            .map(|i| Interpretation {
+                shape: i.shape.clone(),
+                data: i.data.clone(),
+            })
+            .collect();
+        Model::new(sig, &self.universe, interp)
+    }
+}
+
+/// The saved progress of a qalpha run.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// Identifies the module and domains this checkpoint is for.
+    pub fingerprint: String,
+    /// The iteration of the domain search (starting at 1).
+    pub iteration: usize,
+    /// The models used to weaken the frame in this iteration, in order.
+    pub samples: Vec<Sample>,
+    /// The lemmas in the frame when the checkpoint was saved.
+    pub lemmas: Vec<String>,
+}
+
+impl Checkpoint {
+    /// Load a checkpoint from a file.
+    pub fn load(path: &Path) -> Result<Self, CheckpointError> {
+        let contents = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// Load a checkpoint to resume a run with the given fingerprint.
+    pub fn load_for(path: &Path, fingerprint: &str) -> Result<Self, CheckpointError> {
+        let checkpoint = Self::load(path)?;
+        if checkpoint.fingerprint != fingerprint {
+            return Err(CheckpointError::Mismatch);
+        }
+        Ok(checkpoint)
+    }
+
+    /// Save the checkpoint to a file. The file is replaced atomically, so an
+    /// interrupted save leaves the previous checkpoint intact.
+    pub fn save(&self, path: &Path) -> Result<(), CheckpointError> {
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_string(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Describe the options of `infer_cfg` which determine how a qalpha run moves
+/// through the lemma domains and which models it weakens with, other than the
+/// domains themselves.
+pub fn search_options(infer_cfg: &InferenceConfig) -> String {
+    let growth_factor = infer_cfg
+        .growth_factor
+        .unwrap_or(defaults::DOMAIN_GROWTH_FACTOR);
+    // sorted, so that the description does not depend on hashing
+    let bounds = infer_cfg
+        .bounds
+        .as_ref()
+        .map(|bounds| bounds.iter().collect::<BTreeMap<_, _>>());
+    format!(
+        "growth_factor={growth_factor} until_safe={} abort_unsafe={} extend={:?} bounds={bounds:?} ice={} disj={} gradual_smt={} minimal_smt={}",
+        infer_cfg.until_safe,
+        infer_cfg.abort_unsafe,
+        (infer_cfg.extend_width, infer_cfg.extend_depth),
+        infer_cfg.ice,
+        infer_cfg.disj,
+        infer_cfg.gradual_smt,
+        infer_cfg.minimal_smt,
+    )
+}
+
+/// Compute the fingerprint of a qalpha run over `m` which searches through
+/// `domains` (in order) with the given [`search_options`].
+///
+/// The fingerprint hashes the bytes of a printed description of the run with a
+/// fixed hash function, so it does not change between builds.
+pub fn fingerprint<L: LemmaQf>(m: &Module, domains: &[Domain<L>], search_options: &str) -> String {
+    let mut canonical = printer::fmt(m);
+    for (prefix, lemma_qf, atoms) in domains {
+        canonical.push_str(&format!("\n{prefix:?} {lemma_qf:?} {}", atoms.len()));
+    }
+    canonical.push_str(&format!("\n{search_options}"));
+    let mut hasher = FxHasher64::default();
+    hasher.write(canonical.as_bytes());
+    format!("{:016x}", hasher.finish())
+}
+
+/// Periodically saves checkpoints during a qalpha run.
+pub struct Checkpointer {
+    path: PathBuf,
+    fingerprint: String,
+    iteration: usize,
+    last_save: Instant,
+}
+
+impl Checkpointer {
+    /// Create a checkpointer saving to `path`.
+    pub fn new(path: &Path, fingerprint: String) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            fingerprint,
+            iteration: 1,
+            last_save: Instant::now(),
+        }
+    }
+
+    /// Start a new iteration of the domain search, saving a checkpoint with
+    /// no samples.
+    pub fn start_iteration(&mut self, iteration: usize) {
+        self.iteration = iteration;
+        self.write(vec![], vec![]);
+    }
+
+    /// Save a checkpoint of `frame` if enough time has passed since the last
+    /// one.
+    pub fn maybe_save<O, L, B>(&mut self, frame: &InductionFrame<O, L, B>)
+    where
+        O: OrderSubsumption<Base = B>,
+        L: LemmaQf<Base = B>,
+        B: Clone + Debug + Send,
+    {
+        if self.last_save.elapsed() >= CHECKPOINT_INTERVAL {
+            self.save(frame);
+        }
+    }
+
+    /// Save a checkpoint of `frame`.
+    pub fn save<O, L, B>(&mut self, frame: &InductionFrame<O, L, B>)
+    where
+        O: OrderSubsumption<Base = B>,
+        L: LemmaQf<Base = B>,
+        B: Clone + Debug + Send,
+    {
+        let samples = frame.samples().iter().map(Sample::from_model).collect();
+        let lemmas = frame.proof().iter().map(printer::term).collect();
+        self.write(samples, lemmas);
+    }
+
+    fn write(&mut self, samples: Vec<Sample>, lemmas: Vec<String>) {
+        let checkpoint = Checkpoint {
+            fingerprint: self.fingerprint.clone(),
+            iteration: self.iteration,
+            samples,
+            lemmas,
+        };
+        // failing to checkpoint is not fatal to the run
+        if let Err(err) = checkpoint.save(&self.path) {
+            log::warn!("could not save checkpoint: {err}");
+        }
+        self.last_save = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use fly::{
+        parser::{parse, parse_signature},
+        semantics::Interpretation,
+        semantics::Model,
+    };
+
+    use super::{fingerprint, Checkpoint, CheckpointError, Sample};
+    use crate::lemma::LemmaCnf;
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let sig = parse_signature(
+            r#"
+            sort node
+            mutable leader(node): bool
+            immutable le(node, node): bool
+            "#,
+        );
+        let model = Model::new(
+            &sig,
+            &vec![2],
+            vec![
+                Interpretation::new(&[2, 2], |args| args[0]),
+                Interpretation::new(&[2, 2, 2], |args| (args[0] <= args[1]) as usize),
+            ],
+        );
+        let checkpoint = Checkpoint {
+            fingerprint: "0123456789abcdef".to_string(),
+            iteration: 3,
+            samples: vec![Sample::from_model(&model)],
+            lemmas: vec!["forall n:node. leader(n) -> le(n, n)".to_string()],
+        };
+        let path = env::temp_dir().join("flyvy-test-checkpoint.json");
+        checkpoint.save(&path).unwrap();
+        let loaded = Checkpoint::load(&path).unwrap();
+        assert_eq!(loaded, checkpoint);
+        assert_eq!(loaded.samples[0].to_model(&sig), model);
+        assert_eq!(
+            Checkpoint::load_for(&path, &checkpoint.fingerprint).unwrap(),
+            checkpoint
+        );
+        assert!(matches!(
+            Checkpoint::load_for(&path, "fedcba9876543210"),
+            Err(CheckpointError::Mismatch)
+        ));
+    }
+
+    #[test]
+    fn test_fingerprint_stable() {
+        let m = parse("mutable p: bool\nassume p\nassert always p\n").unwrap();
+        // the fingerprint must not change between builds, or saved checkpoints
+        // could no longer be resumed
+        assert_eq!(fingerprint::<LemmaCnf>(&m, &[], ""), "dcc8bc88d835d411");
+        assert_ne!(
+            fingerprint::<LemmaCnf>(&m, &[], "growth_factor=5"),
+            fingerprint::<LemmaCnf>(&m, &[], "growth_factor=2"),
+        );
+    }
+}
@@ -0,0 +1,273 @@
+// Copyright 2022-2023 VMware, Inc.
+// SPDX-License-Identifier: BSD-2-Clause
+
+//! Explain why a fixpoint found by qalpha does not prove safety.
+//!
+//! The diagnosis finds a state satisfying the fixpoint which violates safety,
+//! checks whether it is reachable by enumerating the reachable states of its
+//! size with the set checker, and, if it is not, generalizes it into a lemma
+//! which holds in all of those states. The quantifiers and literals of that
+//! lemma indicate how the lemma domain would need to grow to exclude the state.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use fly::{
+    semantics::Model,
+    syntax::{Module, Sort, Term},
+    term::simplify::simplify,
+};
+use itertools::Itertools;
+use solver::conf::SolverConf;
+
+use crate::{
+    basics::{FOModule, InferenceConfig, QfBody},
+    fixpoint::bounded_reachable,
+    pdr::Diagram,
+};
+
+/// How long to spend enumerating reachable states.
+const REACHABILITY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Whether the counterexample to safety is reachable.
+#[derive(Debug)]
+pub enum Reachability {
+    /// The state is reachable, so safety does not hold.
+    Reachable,
+    /// The state is not reachable; the lemma excludes it and holds in all
+    /// reachable states of the same size.
+    Unreachable {
+        lemma: Term,
+        suggestions: Vec<String>,
+    },
+    /// Reachability could not be checked.
+    Unknown(String),
+}
+
+/// The reasons a fixpoint does not prove safety.
+#[derive(Debug)]
+pub struct Diagnosis {
+    /// The safety assertions not implied by the fixpoint.
+    pub unproven: Vec<Term>,
+    /// A state satisfying the fixpoint which violates the first of them.
+    pub cex: Option<(Model, Reachability)>,
+}
+
+impl Diagnosis {
+    pub fn report(&self) {
+        println!();
+        println!("Diagnosis:");
+        if self.unproven.is_empty() {
+            println!("    The fixpoint implies every safety assertion.");
+            return;
+        }
+        println!("    Safety assertions not implied by the fixpoint:");
+        for safety in &self.unproven {
+            println!("        {safety}");
+        }
+        let Some((cex, reachability)) = &self.cex else {
+            return;
+        };
+        println!("    Counterexample to safety satisfying the fixpoint:");
+        for line in cex.to_string().lines() {
+            println!("        {line}");
+        }
+        match reachability {
+            Reachability::Reachable => {
+                println!("    The counterexample is reachable, so safety does not hold.")
+            }
+            Reachability::Unreachable { lemma, suggestions } => {
+                println!("    The counterexample is unreachable. It is excluded by the lemma");
+                println!("        {}", simplify(lemma));
+                println!("    which holds in all reachable states of the same size.");
+                if suggestions.is_empty() {
+                    println!("    This lemma fits the configured domain.");
+                } else {
+                    println!("    To exclude the counterexample, try:");
+                    for suggestion in suggestions {
+                        println!("        {suggestion}");
+                    }
+                }
+            }
+            Reachability::Unknown(reason) => {
+                println!("    Could not check whether the counterexample is reachable: {reason}")
+            }
+        }
+    }
+}
+
+/// Generalize the diagram of `cex` by dropping conjuncts, as long as no state
+/// in `reachable` satisfies it, and return the lemma which excludes it.
+fn generalize(cex: &Model, reachable: &[Model]) -> (Term, usize) {
+    let diagram = Diagram::new(cex);
+    let mut core = (0..diagram.conjuncts.len()).collect_vec();
+    for i in 0..diagram.conjuncts.len() {
+        let smaller = core.iter().copied().filter(|&j| j != i).collect_vec();
+        let cube = Diagram {
+            binders: diagram.binders.clone(),
+            conjuncts: smaller
+                .iter()
+                .map(|&j| diagram.conjuncts[j].clone())
+                .collect(),
+        }
+        .to_term();
+        if reachable.iter().all(|state| state.eval(&cube) == 0) {
+            core = smaller;
+        }
+    }
+    (diagram.block(&core), core.len())
+}
+
+/// Suggest configuration changes needed for the lemma domain to include a
+/// universally quantified lemma with `literals` literals. If the domain already
+/// includes it, suggest allowing more existential quantifiers instead, since the
+/// counterexample may need a lemma which is not universal to be excluded.
+fn suggestions(infer_cfg: &InferenceConfig, lemma: &Term, literals: usize) -> Vec<String> {
+    let binders = match lemma {
+        Term::Quantified { binders, .. } => binders.clone(),
+        _ => vec![],
+    };
+    let mut needed: HashMap<String, usize> = HashMap::new();
+    for binder in &binders {
+        if let Sort::Uninterpreted(sort) = &binder.sort {
+            *needed.entry(sort.clone()).or_default() += 1;
+        }
+    }
+    let mut available: HashMap<String, usize> = HashMap::new();
+    for (sort, names) in infer_cfg.cfg.sorts.iter().zip(&infer_cfg.cfg.names) {
+        let sort = infer_cfg.cfg.signature.sorts[*sort].clone();
+        *available.entry(sort).or_default() += names.len();
+    }
+
+    let mut suggestions = vec![];
+    for (sort, count) in needed.iter().sorted() {
+        let available = available.get(sort).copied().unwrap_or(0);
+        if *count > available {
+            suggestions.push(format!(
+                "{count} quantified variables of sort {sort} (configured: {available})"
+            ));
+        }
+    }
+    if binders.len() > infer_cfg.max_size {
+        suggestions.push(format!("--max-size {}", binders.len()));
+    }
+    match infer_cfg.qf_body {
+        QfBody::CNF if infer_cfg.clause_size.is_some_and(|size| size < literals) => {
+            suggestions.push(format!("--clause-size {literals}"))
+        }
+        QfBody::PDnf | QfBody::PDnfNaive if infer_cfg.cubes.is_some_and(|c| c < literals) => {
+            suggestions.push(format!("--cubes {literals}"))
+        }
+        _ => (),
+    }
+    if suggestions.is_empty() {
+        if let Some(max_exist) = infer_cfg
+            .max_existentials
+            .filter(|&e| e < infer_cfg.cfg.num_vars())
+        {
+            suggestions.push(format!(
+                "--max-exist {} (to allow lemmas with existential quantifiers)",
+                max_exist + 1
+            ));
+        }
+    }
+    suggestions
+}
+
+/// Diagnose why the fixpoint `proof` does not prove the safety assertions of
+/// the module.
+pub fn diagnose(
+    infer_cfg: &InferenceConfig,
+    conf: &SolverConf,
+    m: &Module,
+    fo: &FOModule,
+    proof: &[Term],
+) -> Diagnosis {
+    let hyp = fo.module.axioms.iter().chain(proof).cloned().collect_vec();
+    let mut unproven = vec![];
+    let mut cex = None;
+    for p in &fo.module.proofs {
+        if let Some(model) = fo.implies_cex(conf, &hyp, &p.safety.x) {
+            unproven.push(p.safety.x.clone());
+            cex.get_or_insert(model);
+        }
+    }
+
+    let cex = cex.map(|cex| {
+        let universe = m
+            .signature
+            .sorts
+            .iter()
+            .cloned()
+            .zip(cex.universe.iter().copied())
+            .collect();
+        let deadline = Instant::now() + REACHABILITY_TIMEOUT;
+        let reachability = match bounded_reachable(m, &universe, Some(deadline)) {
+            Ok(states) => {
+                let diagram = cex.to_diagram();
+                if states.iter().any(|state| state.eval(&diagram) == 1) {
+                    Reachability::Reachable
+                } else {
+                    let (lemma, literals) = generalize(&cex, &states);
+                    let suggestions = suggestions(infer_cfg, &lemma, literals);
+                    Reachability::Unreachable { lemma, suggestions }
+                }
+            }
+            Err(err) => Reachability::Unknown(err),
+        };
+        (cex, reachability)
+    });
+
+    Diagnosis { unproven, cex }
+}
+
+#[cfg(test)]
+mod tests {
+    use fly::{
+        parser::parse_signature,
+        semantics::{Interpretation, Model},
+    };
+
+    use super::generalize;
+
+    #[test]
+    fn test_generalize() {
+        let sig = parse_signature("sort node\nmutable p(node): bool\nmutable q(node): bool");
+        // A single node satisfying both p and q.
+        let cex = Model::new(
+            &sig,
+            &vec![1],
+            vec![
+                Interpretation {
+                    shape: vec![1, 2],
+                    data: vec![1],
+                },
+                Interpretation {
+                    shape: vec![1, 2],
+                    data: vec![1],
+                },
+            ],
+        );
+        // A reachable node satisfying only p.
+        let reachable = Model::new(
+            &sig,
+            &vec![1],
+            vec![
+                Interpretation {
+                    shape: vec![1, 2],
+                    data: vec![1],
+                },
+                Interpretation {
+                    shape: vec![1, 2],
+                    data: vec![0],
+                },
+            ],
+        );
+
+        let (lemma, literals) = generalize(&cex, std::slice::from_ref(&reachable));
+        assert_eq!(literals, 1);
+        assert_eq!(cex.eval(&lemma), 0);
+        assert!(lemma.to_string().contains("q("));
+        assert_eq!(reachable.eval(&lemma), 1);
+    }
+}
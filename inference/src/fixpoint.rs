@@ -5,27 +5,40 @@
 //! lemma domain.
 
 use itertools::Itertools;
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::sync::Arc;
-use std::time::Duration;
+use std::thread;
+use std::time::{Duration, Instant};
 use std::{collections::VecDeque, fmt::Debug};
 
 use crate::basics::QfBody;
 use crate::{
     atoms::{restrict, restrict_by_prefix, Atoms, Literal, RestrictedAtoms},
     basics::{FOModule, InferenceConfig},
-    lemma::InductionFrame,
+    checkpoint::{self, Checkpoint, CheckpointError, Checkpointer},
+    ice::IceError,
+    lemma::{FrameCancelers, InductionFrame},
     subsume::OrderSubsumption,
     weaken::{Domain, LemmaQf},
 };
-use crate::{lemma, subsume};
+use crate::{diagnose, houdini, ice, lemma, parallel, subsume};
+use bounded::quant_enum::UniverseBounds;
+use fly::semantics::Model;
 use fly::syntax::{Module, Term, ThmStmt};
+use fly::term::simplify::simplify;
+use fly::transitions::ExtractionError;
 use solver::{
     backends::SolverType,
-    basics::{BasicSolver, FallbackSolvers, ParallelSolvers, SingleSolver},
+    basics::{
+        BasicSolver, BasicSolverCanceler, FallbackSolvers, ParallelSolvers, SingleSolver,
+        SolverCancelers,
+    },
     conf::SolverConf,
 };
 
 use rayon::prelude::*;
+use thiserror::Error;
 
 pub mod defaults {
     use super::QfBody;
@@ -41,13 +54,19 @@ pub mod defaults {
     pub const MAX_NON_UNIT: Option<usize> = Some(3);
 }
 
-/// Check how much of the handwritten invariant the given lemmas cover.
+/// Check how much of the handwritten invariant the given lemmas cover, or
+/// return `None` if canceled first.
 fn invariant_cover<S: BasicSolver>(
     m: &Module,
     solver: &S,
     fo: &FOModule,
     lemmas: &[Term],
-) -> (usize, usize) {
+    parent: Option<&FrameCancelers<S::Canceler>>,
+) -> Option<(usize, usize)> {
+    let cancelers = SolverCancelers::new();
+    if parent.is_some_and(|parent| !parent.add_canceler(cancelers.clone())) {
+        return None;
+    }
     let proof = m
         .statements
         .iter()
@@ -61,10 +80,16 @@ fn invariant_cover<S: BasicSolver>(
     let covered = proof
         .invariants
         .par_iter()
-        .filter(|inv| !fo.implication_cex(solver, lemmas, &inv.x).is_cex())
+        .filter(|inv| {
+            !fo.implication_cex(solver, lemmas, &inv.x, Some(cancelers.clone()))
+                .is_cex()
+        })
         .count();
 
-    (covered, proof.invariants.len())
+    if cancelers.is_canceled() {
+        return None;
+    }
+    Some((covered, proof.invariants.len()))
 }
 
 /// An inductive fixpoint
@@ -84,11 +109,41 @@ pub struct FoundFixpoint {
 }
 
 impl FoundFixpoint {
+    /// The invariant found, if it implies the safety predicates. This is the
+    /// minimized proof when one is available.
+    pub fn invariant(&self) -> Option<&[Term]> {
+        if !self.safe {
+            return None;
+        }
+        self.minimized_proof
+            .as_ref()
+            .or(self.proof.as_ref())
+            .map(|p| p.as_slice())
+    }
+
+    /// The result of a run which stopped before reaching a fixpoint.
+    pub(crate) fn aborted(time_taken: Duration) -> Self {
+        FoundFixpoint {
+            proof: None,
+            minimized_proof: None,
+            safe: false,
+            time_taken,
+            covering: None,
+        }
+    }
+
+    /// Add known invariants to the proof.
+    fn add_facts(&mut self, facts: &[Term]) {
+        for proof in self.proof.iter_mut().chain(&mut self.minimized_proof) {
+            proof.splice(0..0, facts.iter().cloned());
+        }
+    }
+
     pub fn report(&self, print_invariant: bool) {
         let print_inv = |inv: &[Term]| {
             println!("proof {{");
             for lemma in inv {
-                println!("  invariant {lemma}");
+                println!("  invariant {}", simplify(lemma));
             }
             println!("}}");
         };
@@ -119,10 +174,97 @@ impl FoundFixpoint {
     }
 }
 
+/// An error which stops a qalpha run.
+#[derive(Error, Debug)]
+pub enum QalphaError {
+    /// The checkpoint to resume from could not be loaded
+    #[error("could not resume from {path}: {err}")]
+    Resume {
+        /// The checkpoint file
+        path: String,
+        /// Why it could not be loaded
+        err: CheckpointError,
+    },
+    /// An ICE run failed
+    #[error(transparent)]
+    Ice(#[from] IceError),
+    /// The states reachable within the given bounds could not be enumerated
+    #[error("could not enumerate bounded states: {0}")]
+    Bounded(String),
+    /// The module could not be split into a transition system
+    #[error(transparent)]
+    Extraction(#[from] ExtractionError),
+}
+
+/// Enumerate the states reachable within the given bounds (up to isomorphism),
+/// using the set checker. Fails if `deadline` passes first.
+pub(crate) fn bounded_reachable(
+    m: &Module,
+    universe: &UniverseBounds,
+    deadline: Option<Instant>,
+) -> Result<Vec<Model>, String> {
+    let mut converted = m.clone();
+    let back_convert = converted
+        .convert_non_bool_relations()
+        .map_err(|err| err.to_string())?;
+    let states = bounded::set::reachable(&converted, universe, None, deadline, false)
+        .map_err(|err| err.to_string())?;
+    Ok(states.iter().map(back_convert).collect())
+}
+
+/// Enumerate the states reachable within the given bounds, to weaken the
+/// frame with before making any SMT queries.
+fn bounded_samples(
+    m: &Module,
+    universe: &UniverseBounds,
+    deadline: Option<Instant>,
+) -> Result<Vec<Model>, QalphaError> {
+    let states = bounded_reachable(m, universe, deadline).map_err(QalphaError::Bounded)?;
+    log::info!("Bounded reachable states: {}", states.len());
+    Ok(states)
+}
+
+/// Run `f` with cancelers which are canceled once `deadline` passes.
+fn with_deadline<C, T, F>(deadline: Option<Instant>, f: F) -> T
+where
+    C: BasicSolverCanceler,
+    F: FnOnce(&FrameCancelers<C>) -> T,
+{
+    let cancelers = FrameCancelers::new();
+    let (done, timer) = mpsc::channel::<()>();
+    thread::scope(|s| {
+        if let Some(deadline) = deadline {
+            let cancelers = cancelers.clone();
+            s.spawn(move || {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                if timer.recv_timeout(timeout) == Err(RecvTimeoutError::Timeout) {
+                    cancelers.cancel();
+                }
+            });
+        }
+        let res = f(&cancelers);
+        drop(done);
+        res
+    })
+}
+
+fn solver_conf(
+    infer_cfg: &InferenceConfig,
+    solver_type: SolverType,
+    timeout_s: usize,
+    seed: usize,
+) -> SolverConf {
+    let mut conf = SolverConf::new(solver_type, true, &infer_cfg.fname, timeout_s, seed);
+    conf.backend
+        .memory_limit_mb(infer_cfg.memory_limit_mb)
+        .rlimit(infer_cfg.rlimit);
+    conf
+}
+
 fn parallel_solver(infer_cfg: &InferenceConfig) -> impl BasicSolver {
     ParallelSolvers::new(vec![
-        SolverConf::new(SolverType::Z3, true, &infer_cfg.fname, 0, 0),
-        SolverConf::new(SolverType::Cvc5, true, &infer_cfg.fname, 0, 0),
+        solver_conf(infer_cfg, SolverType::Z3, 0, 0),
+        solver_conf(infer_cfg, SolverType::Cvc5, 0, 0),
     ])
 }
 
@@ -134,24 +276,18 @@ fn fallback_solver(infer_cfg: &InferenceConfig) -> impl BasicSolver {
     // ending with no timeout at all. The seed changes are meant to add some
     // variation vis-a-vis previous attempts.
     FallbackSolvers::new(vec![
-        SolverConf::new(SolverType::Z3, true, &infer_cfg.fname, 3, 0),
-        SolverConf::new(SolverType::Cvc5, true, &infer_cfg.fname, 3, 0),
-        SolverConf::new(SolverType::Z3, true, &infer_cfg.fname, 60, 1),
-        SolverConf::new(SolverType::Cvc5, true, &infer_cfg.fname, 60, 1),
-        SolverConf::new(SolverType::Z3, true, &infer_cfg.fname, 600, 2),
-        SolverConf::new(SolverType::Cvc5, true, &infer_cfg.fname, 600, 2),
-        SolverConf::new(SolverType::Z3, true, &infer_cfg.fname, 0, 3),
+        solver_conf(infer_cfg, SolverType::Z3, 3, 0),
+        solver_conf(infer_cfg, SolverType::Cvc5, 3, 0),
+        solver_conf(infer_cfg, SolverType::Z3, 60, 1),
+        solver_conf(infer_cfg, SolverType::Cvc5, 60, 1),
+        solver_conf(infer_cfg, SolverType::Z3, 600, 2),
+        solver_conf(infer_cfg, SolverType::Cvc5, 600, 2),
+        solver_conf(infer_cfg, SolverType::Z3, 0, 3),
     ])
 }
 
 fn simulation_solver(infer_cfg: &InferenceConfig) -> impl BasicSolver {
-    SingleSolver::new(SolverConf::new(
-        SolverType::Z3,
-        true,
-        &infer_cfg.fname,
-        3,
-        0,
-    ))
+    SingleSolver::new(solver_conf(infer_cfg, SolverType::Z3, 3, 0))
 }
 
 pub fn qalpha<O, L, B, S1, S2>(
@@ -160,19 +296,58 @@ pub fn qalpha<O, L, B, S1, S2>(
     main_solver: &S1,
     simulation_solver: &S2,
     print_invariant: bool,
-) where
+) -> Result<FoundFixpoint, QalphaError>
+where
     O: OrderSubsumption<Base = B>,
     L: LemmaQf<Base = B>,
     B: Clone + Debug + Send,
     S1: BasicSolver,
     S2: BasicSolver,
 {
-    let fo = FOModule::new(
+    let mut fo = FOModule::new(
         m,
         infer_cfg.disj,
         infer_cfg.gradual_smt,
         infer_cfg.minimal_smt,
-    );
+    )?;
+
+    // Candidates which are together inductive hold in all reachable states, so
+    // they are added to the axioms and reported as part of the invariant. The
+    // other candidates seed the frame, which keeps those that become inductive
+    // relative to its lemmas.
+    let candidates = fo
+        .module
+        .proofs
+        .iter()
+        .flat_map(|p| p.candidates.iter().map(|c| c.x.clone()))
+        .collect_vec();
+    let facts = if candidates.is_empty() {
+        vec![]
+    } else {
+        let conf = solver_conf(&infer_cfg, infer_cfg.solver, 600, 0);
+        let facts = houdini::inductive_subset(&conf, &m.signature, &fo.module, &candidates)
+            .unwrap_or_else(|err| {
+                log::warn!("could not check candidate invariants: {err:?}");
+                vec![]
+            });
+        println!(
+            "Inductive candidate invariants: {} / {}",
+            facts.len(),
+            candidates.len()
+        );
+        facts
+    };
+    fo.module.axioms.extend(facts.iter().cloned());
+    let seeds = candidates
+        .into_iter()
+        .filter(|c| !facts.contains(c))
+        .collect_vec();
+
+    let bounded_samples = match &infer_cfg.bounds {
+        Some(universe) => bounded_samples(m, universe, infer_cfg.deadline)?,
+        None => vec![],
+    };
+
     log::debug!("Computing atoms...");
     let atoms = Arc::new(Atoms::new(&infer_cfg, main_solver, &fo));
     let unrestricted = Arc::new(restrict(&atoms, |_| true));
@@ -241,6 +416,30 @@ pub fn qalpha<O, L, B, S1, S2>(
         domains.len() + active_domains.len()
     );
 
+    let fingerprint = checkpoint::fingerprint(
+        m,
+        &active_domains
+            .iter()
+            .chain(domains.iter())
+            .cloned()
+            .collect_vec(),
+        &checkpoint::search_options(&infer_cfg),
+    );
+    let resume =
+        match &infer_cfg.resume {
+            Some(path) => Some(Checkpoint::load_for(Path::new(path), &fingerprint).map_err(
+                |err| QalphaError::Resume {
+                    path: path.clone(),
+                    err,
+                },
+            )?),
+            None => None,
+        };
+    let mut checkpointer = infer_cfg
+        .checkpoint
+        .as_ref()
+        .map(|path| Checkpointer::new(Path::new(path), fingerprint.clone()));
+
     let mut domain_size: usize = domain_size_of(&active_domains);
     let mut next_domain_size = defaults::MIN_DOMAIN_SIZE;
     let mut iteration: usize = 1;
@@ -269,21 +468,83 @@ pub fn qalpha<O, L, B, S1, S2>(
             );
         }
 
-        let fixpoint = run_qalpha::<O, L, B, S1, S2>(
-            infer_cfg.clone(),
-            main_solver,
-            simulation_solver,
-            m,
-            &fo,
-            unrestricted.clone(),
-            active_domains.clone(),
-            extend,
-        );
+        let resume_samples = match &resume {
+            Some(resume) if iteration < resume.iteration => {
+                println!("Skipped (resuming from iteration {})", resume.iteration);
+                iteration += 1;
+                next_domain_size = domain_size
+                    * infer_cfg
+                        .growth_factor
+                        .unwrap_or(defaults::DOMAIN_GROWTH_FACTOR);
+                continue;
+            }
+            Some(resume) if iteration == resume.iteration => {
+                println!("Resuming with {} samples", resume.samples.len());
+                resume
+                    .samples
+                    .iter()
+                    .map(|sample| sample.to_model(&m.signature))
+                    .collect()
+            }
+            _ => vec![],
+        };
+        if let Some(checkpointer) = &mut checkpointer {
+            checkpointer.start_iteration(iteration);
+        }
+
+        let mut fixpoint = if infer_cfg.ice {
+            run_ice::<O, L, B, S1>(
+                infer_cfg.clone(),
+                main_solver,
+                m,
+                &fo,
+                unrestricted.clone(),
+                active_domains.clone(),
+                &bounded_samples,
+            )?
+        } else if infer_cfg.domain_workers > 1 {
+            run_qalpha_parallel::<O, L, B, S1, S2>(
+                infer_cfg.clone(),
+                main_solver,
+                simulation_solver,
+                m,
+                &fo,
+                unrestricted.clone(),
+                active_domains.clone(),
+                extend,
+                &bounded_samples,
+            )
+        } else {
+            run_qalpha::<O, L, B, S1, S2>(
+                infer_cfg.clone(),
+                main_solver,
+                simulation_solver,
+                m,
+                &fo,
+                unrestricted.clone(),
+                active_domains.clone(),
+                extend,
+                &bounded_samples,
+                &seeds,
+                resume_samples,
+                checkpointer.as_mut(),
+            )
+        };
 
+        fixpoint.add_facts(&facts);
         fixpoint.report(print_invariant);
 
-        if (fixpoint.safe && infer_cfg.until_safe) || domains.is_empty() {
-            break;
+        let timed_out = infer_cfg
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline);
+        if (fixpoint.safe && infer_cfg.until_safe) || domains.is_empty() || timed_out {
+            if infer_cfg.explain && !fixpoint.safe {
+                if let Some(proof) = &fixpoint.proof {
+                    let conf = solver_conf(&infer_cfg, infer_cfg.solver, 600, 0);
+                    diagnose::diagnose(&infer_cfg, &conf, m, &fo, proof).report();
+                }
+            }
+            return Ok(fixpoint);
         }
 
         iteration += 1;
@@ -294,7 +555,11 @@ pub fn qalpha<O, L, B, S1, S2>(
     }
 }
 
-pub fn qalpha_dynamic(infer_cfg: Arc<InferenceConfig>, m: &Module, print_invariant: bool) {
+pub fn qalpha_dynamic(
+    infer_cfg: Arc<InferenceConfig>,
+    m: &Module,
+    print_invariant: bool,
+) -> Result<FoundFixpoint, QalphaError> {
     match (&infer_cfg.qf_body, infer_cfg.fallback) {
         (QfBody::CNF, false) => qalpha::<subsume::Cnf<Literal>, lemma::LemmaCnf, _, _, _>(
             infer_cfg.clone(),
@@ -356,6 +621,10 @@ fn run_qalpha<O, L, B, S1, S2>(
     atoms: Arc<RestrictedAtoms>,
     domains: Vec<Domain<L>>,
     extend: Option<(usize, usize)>,
+    bounded_samples: &[Model],
+    seeds: &[Term],
+    resume_samples: Vec<Model>,
+    mut checkpointer: Option<&mut Checkpointer>,
 ) -> FoundFixpoint
 where
     O: OrderSubsumption<Base = B>,
@@ -379,49 +648,230 @@ where
         log::debug!("    {a}");
     }
 
-    let mut frame: InductionFrame<O, L, B> =
-        InductionFrame::new(infer_cfg.clone(), atoms, domains, extend);
+    with_deadline(infer_cfg.deadline, |cancelers| {
+        with_deadline(infer_cfg.deadline, |simulation_cancelers| {
+            let mut frame: InductionFrame<O, L, B> =
+                InductionFrame::new(infer_cfg.clone(), atoms, domains, extend);
+            if checkpointer.is_some() {
+                frame.record_samples();
+            }
+            frame.seed(seeds.to_vec());
+            // The samples of a checkpoint already include the bounded samples.
+            if resume_samples.is_empty() && !bounded_samples.is_empty() {
+                frame.add_samples(bounded_samples.to_vec());
+            }
+            if !resume_samples.is_empty() {
+                frame.resume(resume_samples);
+                if let Some(checkpointer) = &mut checkpointer {
+                    checkpointer.save(&frame);
+                }
+            }
+
+            // Begin by overapproximating the initial states.
+            while frame.init_cycle(fo, main_solver, Some(cancelers)) {
+                if let Some(checkpointer) = &mut checkpointer {
+                    checkpointer.maybe_save(&frame);
+                }
+            }
+            if cancelers.is_canceled() {
+                frame.log_info("Deadline reached");
+                return FoundFixpoint::aborted(start.elapsed());
+            }
 
-    // Begin by overapproximating the initial states.
-    while frame.init_cycle(fo, main_solver) {}
+            // Handle transition CTI's.
+            loop {
+                if let Some(checkpointer) = &mut checkpointer {
+                    checkpointer.maybe_save(&frame);
+                }
 
-    // Handle transition CTI's.
-    loop {
-        // If enabled, extend CTI traces using simulations.
-        if extend.is_some() {
-            frame.extend(fo, simulation_solver);
-        }
+                // If enabled, extend CTI traces using simulations.
+                if extend.is_some() {
+                    frame.extend(fo, simulation_solver, Some(simulation_cancelers));
+                }
+
+                if infer_cfg.abort_unsafe {
+                    frame.log_info("Checking safety...");
+                    if !frame.is_safe(fo, main_solver, Some(cancelers)) {
+                        return FoundFixpoint::aborted(start.elapsed());
+                    }
+                }
+
+                let found = frame.trans_cycle(fo, main_solver, Some(cancelers));
+                if cancelers.is_canceled() {
+                    frame.log_info("Deadline reached");
+                    return FoundFixpoint::aborted(start.elapsed());
+                }
+                if !found {
+                    break;
+                }
+            }
+
+            if let Some(checkpointer) = &mut checkpointer {
+                checkpointer.save(&frame);
+            }
 
-        if infer_cfg.abort_unsafe {
             frame.log_info("Checking safety...");
-            if !frame.is_safe(fo, main_solver) {
-                return FoundFixpoint {
-                    proof: None,
-                    minimized_proof: None,
-                    safe: false,
-                    time_taken: start.elapsed(),
-                    covering: None,
-                };
+            let safe = frame.is_safe(fo, main_solver, Some(cancelers));
+            if cancelers.is_canceled() {
+                frame.log_info("Deadline reached");
+                return FoundFixpoint::aborted(start.elapsed());
             }
-        }
+            let time_taken = start.elapsed();
+            let proof: Vec<Term> = frame.proof();
+            let minimized_proof = frame.minimized_proof();
+            let covering = invariant_cover(m, main_solver, fo, &proof, Some(cancelers));
+
+            FoundFixpoint {
+                proof: Some(proof),
+                minimized_proof,
+                safe,
+                time_taken,
+                covering,
+            }
+        })
+    })
+}
+
+/// Run the qalpha algorithm on the configured lemma domains, split between
+/// concurrent workers (see [`parallel`]).
+#[allow(clippy::too_many_arguments)]
+fn run_qalpha_parallel<O, L, B, S1, S2>(
+    infer_cfg: Arc<InferenceConfig>,
+    main_solver: &S1,
+    simulation_solver: &S2,
+    m: &Module,
+    fo: &FOModule,
+    atoms: Arc<RestrictedAtoms>,
+    domains: Vec<Domain<L>>,
+    extend: Option<(usize, usize)>,
+    bounded_samples: &[Model],
+) -> FoundFixpoint
+where
+    O: OrderSubsumption<Base = B>,
+    L: LemmaQf<Base = B>,
+    B: Clone + Debug + Send,
+    S1: BasicSolver,
+    S2: BasicSolver,
+{
+    let start = std::time::Instant::now();
+    let proof = parallel::run_parallel::<O, L, B, S1, S2>(
+        infer_cfg.clone(),
+        main_solver,
+        simulation_solver,
+        fo,
+        atoms,
+        domains,
+        extend,
+        bounded_samples,
+        infer_cfg.domain_workers,
+    );
+    let Some(proof) = proof else {
+        return FoundFixpoint::aborted(start.elapsed());
+    };
 
-        if !frame.trans_cycle(fo, main_solver) {
-            break;
+    with_deadline(infer_cfg.deadline, |cancelers| {
+        log::info!("Checking safety...");
+        let minimized_proof = parallel::minimized_proof(fo, main_solver, &proof, Some(cancelers));
+        if cancelers.is_canceled() {
+            log::info!("Deadline reached");
+            return FoundFixpoint::aborted(start.elapsed());
+        }
+        let safe = minimized_proof.is_some();
+        let time_taken = start.elapsed();
+        let covering = invariant_cover(m, main_solver, fo, &proof, Some(cancelers));
+
+        FoundFixpoint {
+            proof: Some(proof),
+            minimized_proof,
+            safe,
+            time_taken,
+            covering,
         }
+    })
+}
+
+/// Learn an invariant over the configured lemma domains from samples (see
+/// [`ice`]).
+fn run_ice<O, L, B, S>(
+    infer_cfg: Arc<InferenceConfig>,
+    main_solver: &S,
+    m: &Module,
+    fo: &FOModule,
+    atoms: Arc<RestrictedAtoms>,
+    domains: Vec<Domain<L>>,
+    bounded_samples: &[Model],
+) -> Result<FoundFixpoint, IceError>
+where
+    O: OrderSubsumption<Base = B>,
+    L: LemmaQf<Base = B>,
+    B: Clone + Debug + Send,
+    S: BasicSolver,
+{
+    let start = std::time::Instant::now();
+    let conf = solver_conf(&infer_cfg, infer_cfg.solver, 0, 0);
+    with_deadline(infer_cfg.deadline, |cancelers| {
+        let proof = ice::run_ice::<O, L, B, S>(
+            infer_cfg.clone(),
+            main_solver,
+            &conf,
+            fo,
+            atoms,
+            domains,
+            bounded_samples,
+            Some(cancelers),
+        )?;
+        let time_taken = start.elapsed();
+        let covering = proof
+            .as_ref()
+            .and_then(|proof| invariant_cover(m, main_solver, fo, proof, Some(cancelers)));
+
+        Ok(FoundFixpoint {
+            safe: proof.is_some(),
+            proof,
+            minimized_proof: None,
+            time_taken,
+            covering,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use fly::syntax::Module;
+
+    use super::{bounded_samples, QalphaError};
+
+    fn module(source: &str) -> Module {
+        let mut m = fly::parser::parse(source).expect("parse error");
+        fly::sorts::sort_check_module(&mut m).expect("sort error");
+        m
     }
 
-    frame.log_info("Checking safety...");
-    let safe = frame.is_safe(fo, main_solver);
-    let time_taken = start.elapsed();
-    let proof: Vec<Term> = frame.proof();
-    let minimized_proof = frame.minimized_proof();
-    let covering = Some(invariant_cover(m, main_solver, fo, &proof));
-
-    FoundFixpoint {
-        proof: Some(proof),
-        minimized_proof,
-        safe,
-        time_taken,
-        covering,
+    #[test]
+    fn test_bounded_samples() {
+        let m = module(
+            "sort node\nmutable p(node): bool\nassume forall n:node. !p(n)\nassume always exists n:node. (forall x:node. p'(x) <-> p(x) | x = n)\nassert always true\n",
+        );
+        let universe = HashMap::from([("node".to_string(), 2)]);
+        let samples = bounded_samples(&m, &universe, None).unwrap();
+        // no node, one node, and both nodes satisfy p, up to isomorphism
+        assert_eq!(samples.len(), 3);
+        let p = fly::parser::term("exists n:node. p(n)");
+        assert_eq!(samples.iter().filter(|s| s.eval(&p) == 1).count(), 2);
+    }
+
+    #[test]
+    fn test_bounded_samples_error() {
+        // the set checker cannot handle mutable axioms it cannot prove
+        let m = module(
+            "mutable p: bool\nmutable q: bool\nassume p\nassume always p | q\nassume always (p' <-> !p)\nassert always p | q\n",
+        );
+        let universe = HashMap::new();
+        assert!(matches!(
+            bounded_samples(&m, &universe, None),
+            Err(QalphaError::Bounded(_))
+        ));
     }
 }
@@ -9,10 +9,20 @@ use std::{
     sync::Mutex,
 };
 
+use itertools::Itertools;
 use rayon::prelude::*;
 
-use fly::{syntax::*, term::prime::Next, transitions::*};
-use solver::{conf::SolverConf, SatResp};
+use fly::{
+    syntax::*,
+    term::{prime::Next, simplify::simplify},
+    transitions::*,
+};
+use smtlib::proc::SolverError;
+use solver::{
+    conf::SolverConf,
+    imp::{Backend, Solver},
+    SatResp,
+};
 use verify::{
     error::{AssertionFailure, FailureType, QueryError, SolveError},
     safety::InvariantAssertion,
@@ -23,6 +33,15 @@ use verify::{
 // user. The code currently overloads AssertionFailure which was only intended
 // for failures that are the direct result of checking a user assertion.
 
+/// Check satisfiability, treating a solver that exceeds its resource limit as
+/// returning unknown.
+fn check_sat<B: Backend>(solver: &mut Solver<B>) -> SatResp {
+    match solver.check_sat(HashMap::new()) {
+        Err(SolverError::ResourceLimit(reason)) => SatResp::Unknown(reason),
+        resp => resp.expect("error in solver"),
+    }
+}
+
 struct Houdini {
     conf: SolverConf,
     sig: Signature,
@@ -41,10 +60,19 @@ pub enum HoudiniError {
 }
 
 impl Houdini {
-    fn new(conf: SolverConf, sig: &Signature, assert: InvariantAssertion) -> Self {
+    fn new(
+        conf: SolverConf,
+        sig: &Signature,
+        assert: InvariantAssertion,
+        candidates: &[Term],
+    ) -> Self {
         let mut invs = vec![assert.inv.x.clone()];
-        // TODO: support customization of initial candidate invariants
         invs.extend(assert.proof_invs.iter().map(|inv| inv.x.clone()));
+        for c in candidates {
+            if !invs.contains(c) {
+                invs.push(c.clone());
+            }
+        }
         log::info!("Running Houdini, candidate invariants are:");
         for p in &invs {
             log::info!("    {p}")
@@ -70,7 +98,7 @@ impl Houdini {
             let mut solver = self.conf.solver(&self.sig, 1);
             solver.assert(&self.init);
             solver.assert(&Term::negate(q.clone()));
-            let resp = solver.check_sat(HashMap::new()).expect("error in solver");
+            let resp = check_sat(&mut solver);
             match resp {
                 SatResp::Sat => {
                     log::info!("        Got model");
@@ -120,7 +148,7 @@ impl Houdini {
                 }
                 solver.assert(&self.next);
                 solver.assert(&Term::negate(Next::new(&self.sig).prime(q)));
-                let resp = solver.check_sat(HashMap::new()).expect("error in solver");
+                let resp = check_sat(&mut solver);
                 if matches!(resp, SatResp::Sat) {
                     log::info!("        Got model");
                     let states = solver.get_model().expect("could not get model");
@@ -158,9 +186,37 @@ impl Houdini {
         self.invs.retain(|q| !not_implied.contains(q));
         Ok(false)
     }
+
+    /// Filter the candidate invariants down to the largest subset that is
+    /// inductive.
+    fn fixpoint(&mut self) -> Result<(), HoudiniError> {
+        self.initiation_filter()?;
+
+        log::info!("Candidate invariants are:");
+        for p in &self.invs {
+            log::info!("    {p}")
+        }
+        log::info!("");
+
+        // compute fixed point
+        log::info!("Computing fixed point:");
+        loop {
+            let done = self.inductive_iteration()?;
+            if done {
+                log::info!("Fixed point reached");
+                return Ok(());
+            }
+            log::info!("Candidate invariants are:");
+            for p in &self.invs {
+                log::info!("    {p}")
+            }
+            log::info!("");
+        }
+    }
 }
 
-/// Attempt to infer inductive invariants to prove `assert`.
+/// Attempt to infer inductive invariants to prove `assert`, starting from the
+/// proof invariants of `assert` and additional `candidates`.
 ///
 /// On success, returns a list of invariants which are together inductive and
 /// include `assert.inv`.
@@ -168,38 +224,49 @@ pub fn infer(
     conf: &SolverConf,
     sig: &Signature,
     assert: &InvariantAssertion,
+    candidates: &[Term],
 ) -> Result<Vec<Term>, HoudiniError> {
-    let mut state = Houdini::new(conf.clone(), sig, assert.clone());
-    state.initiation_filter()?;
-
-    log::info!("Candidate invariants are:");
-    for p in &state.invs {
-        log::info!("    {p}")
-    }
-    log::info!("");
-
-    // compute fixed point
-    log::info!("Computing fixed point:");
-    loop {
-        let done = state.inductive_iteration()?;
-        if done {
-            log::info!("Fixed point reached");
-            break;
-        }
-        log::info!("Candidate invariants are:");
-        for p in &state.invs {
-            log::info!("    {p}")
-        }
-        log::info!("");
-    }
+    let mut state = Houdini::new(conf.clone(), sig, assert.clone(), candidates);
+    state.fixpoint()?;
     if state.invs.is_empty() || state.invs[0] != assert.inv.x {
         return Err(HoudiniError::NotInductive);
     }
     Ok(state.invs)
 }
 
-/// Prove the assertions in a module using Houdini invariant inference.
-pub fn infer_module(conf: &SolverConf, m: &Module) -> Result<(), SolveError> {
+/// Filter `candidates` down to the largest subset that is together inductive
+/// for `module` (without any safety property).
+pub fn inductive_subset(
+    conf: &SolverConf,
+    sig: &Signature,
+    module: &DestructuredModule,
+    candidates: &[Term],
+) -> Result<Vec<Term>, HoudiniError> {
+    let next = Next::new(sig);
+    let mut state = Houdini {
+        conf: conf.clone(),
+        sig: sig.clone(),
+        init: Term::and(module.inits.iter().chain(&module.axioms).cloned()),
+        next: Term::and(
+            module
+                .transitions
+                .iter()
+                .chain(&module.axioms)
+                .cloned()
+                .chain(module.axioms.iter().map(|a| next.prime(a))),
+        ),
+        invs: candidates.iter().cloned().unique().collect(),
+    };
+    state.fixpoint()?;
+    Ok(state.invs)
+}
+
+/// Prove the assertions in a module using Houdini invariant inference, starting
+/// from the proof and candidate invariants of each assertion.
+///
+/// On success, returns the inferred proof invariants of each assertion (not
+/// including the assertion itself), in order.
+pub fn infer_module(conf: &SolverConf, m: &Module) -> Result<Vec<Vec<Term>>, SolveError> {
     // TODO: this is highly redundant with verify_module, some refactoring is
     // needed to separate the generic module processing with what kind of
     // inference/proof process we want for each assertion.
@@ -210,27 +277,28 @@ fn infer_destructured_module(
     conf: &SolverConf,
     module: &DestructuredModule,
     signature: &Signature,
-) -> Result<(), SolveError> {
-    let inits = &module.inits;
-    let transitions = &module.transitions;
-    // we push verified safety properties as axioms
-    let mut axioms = module.axioms.clone();
+) -> Result<Vec<Vec<Term>>, SolveError> {
     let mut errors = SolveError::default();
+    let mut proofs = vec![];
 
-    for proof in &module.proofs {
+    for (i, proof) in module.proofs.iter().enumerate() {
+        // earlier assertions are assumed as axioms, but later assumptions aren't
+        let (inits, transitions, axioms) = module.assumptions(i);
         if let Ok(assert) =
             InvariantAssertion::for_assert(signature, inits, transitions, &axioms, proof)
         {
-            let res = infer(conf, signature, &assert);
+            let candidates = proof.candidates.iter().map(|c| c.x.clone()).collect_vec();
+            let res = infer(conf, signature, &assert, &candidates);
             match res {
                 Ok(invs) => {
                     println!("# inferred invariant:");
                     println!("assert always {}", &proof.safety.x);
                     println!("proof {{");
-                    for inv in invs {
-                        println!("  invariant {inv}");
+                    for inv in &invs {
+                        println!("  invariant {}", simplify(inv));
                     }
                     println!("}}");
+                    proofs.push(invs[1..].to_vec());
                 }
                 Err(err) => errors.push(match err {
                     HoudiniError::InitInvUnknown(m) => AssertionFailure {
@@ -258,11 +326,9 @@ fn infer_destructured_module(
                 reason: FailureType::Unsupported,
             })
         }
-        // for future assertions, treat this assertion as an assumption
-        axioms.push(proof.safety.x.clone());
     }
     if errors.fails.is_empty() {
-        Ok(())
+        Ok(proofs)
     } else {
         Err(errors)
     }
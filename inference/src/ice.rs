@@ -0,0 +1,379 @@
+// Copyright 2022-2023 VMware, Inc.
+// SPDX-License-Identifier: BSD-2-Clause
+
+//! Learn invariants from positive, negative, and implication samples, in the
+//! style of ICE learning.
+//!
+//! Positive samples (reachable states) weaken an [`InductionFrame`] as in
+//! qalpha, so its lemmas form a pool which holds in all of them. A candidate
+//! invariant is then a subset of the pool which excludes every negative sample
+//! (a bad state) and respects every implication sample (a transition whose
+//! post-state must satisfy the candidate whenever its pre-state does). The
+//! subset is found by an SMT query over one boolean per lemma, and the
+//! candidate is then checked for safety and inductiveness, which yields a new
+//! sample whenever the check fails.
+//!
+//! The post-state of an implication becomes a positive sample once its
+//! pre-state is one. Models are compared exactly, so a pre-state which is
+//! only isomorphic to a positive sample (for example, with the elements of a
+//! sort permuted) keeps its implication. This is sound, since implications
+//! only constrain the candidate, but may take more iterations to converge.
+
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use fly::{
+    semantics::Model,
+    syntax::{RelationDecl, Signature, Sort, Term},
+};
+use itertools::Itertools;
+use smtlib::proc::SolverError;
+use solver::{
+    basics::{BasicSolver, SolverCancelers},
+    conf::SolverConf,
+    SatResp,
+};
+use thiserror::Error;
+
+use crate::{
+    atoms::RestrictedAtoms,
+    basics::{CexResult, FOModule, InferenceConfig},
+    lemma::{FrameCancelers, InductionFrame},
+    subsume::OrderSubsumption,
+    weaken::{Domain, LemmaQf},
+};
+
+/// An error which stops an ICE run.
+#[derive(Error, Debug)]
+pub enum IceError {
+    /// The solver could not decide whether the samples can be separated
+    #[error("separator query returned unknown: {0}")]
+    SeparatorUnknown(String),
+    /// The solver could not decide whether a candidate is inductive
+    #[error("inductiveness check returned unknown: {0}")]
+    InductivenessUnknown(String),
+    /// The solver failed
+    #[error("error in solver: {0}")]
+    Solver(#[from] SolverError),
+}
+
+/// The indices of the lemmas in `pool` which are false in `model`.
+fn falsified(pool: &[Term], model: &Model) -> Vec<usize> {
+    (0..pool.len())
+        .filter(|&i| model.eval(&pool[i]) == 0)
+        .collect()
+}
+
+/// The negative and implication samples, represented by the lemmas of the
+/// pool they falsify.
+struct Constraints {
+    negatives: Vec<Vec<usize>>,
+    implications: Vec<(Vec<usize>, Vec<usize>)>,
+}
+
+impl Constraints {
+    fn new(pool: &[Term], negatives: &[Model], implications: &[(Model, Model)]) -> Self {
+        Constraints {
+            negatives: negatives.iter().map(|n| falsified(pool, n)).collect(),
+            implications: implications
+                .iter()
+                .map(|(pre, post)| (falsified(pool, pre), falsified(pool, post)))
+                .collect(),
+        }
+    }
+
+    /// Whether the conjunction of the selected lemmas separates the samples.
+    fn separated_by(&self, selected: &HashSet<usize>) -> bool {
+        let some_selected = |ids: &[usize]| ids.iter().any(|i| selected.contains(i));
+        self.negatives.iter().all(|n| some_selected(n))
+            && self
+                .implications
+                .iter()
+                .all(|(pre, post)| some_selected(pre) || !some_selected(post))
+    }
+}
+
+/// Split `implications` into the post-states of those whose pre-state is one
+/// of the positive `samples`, which must be positive too, and the rest.
+fn forced(
+    samples: &[Model],
+    implications: Vec<(Model, Model)>,
+) -> (Vec<Model>, Vec<(Model, Model)>) {
+    let (forced, rest): (Vec<_>, Vec<_>) = implications
+        .into_iter()
+        .partition(|(pre, _)| samples.contains(pre));
+    (forced.into_iter().map(|(_, post)| post).collect(), rest)
+}
+
+/// Find a subset of `pool` which separates the samples, using an SMT query with
+/// a boolean `l{i}` for each lemma in the pool. The subset is then greedily
+/// minimized.
+fn separate(
+    conf: &SolverConf,
+    pool: &[Term],
+    constraints: &Constraints,
+) -> Result<Option<Vec<usize>>, IceError> {
+    if constraints.negatives.iter().any(|n| n.is_empty()) {
+        // A negative sample satisfies the whole pool.
+        return Ok(None);
+    }
+
+    let var = |i: &usize| Term::id(&format!("l{i}"));
+    let sig = Signature {
+        sorts: vec![],
+        relations: (0..pool.len())
+            .map(|i| RelationDecl {
+                mutable: false,
+                name: format!("l{i}"),
+                args: vec![],
+                sort: Sort::Bool,
+            })
+            .collect(),
+    };
+    let mut solver = conf.solver(&sig, 1);
+    for n in &constraints.negatives {
+        solver.assert(&Term::or(n.iter().map(var)));
+    }
+    for (pre, post) in &constraints.implications {
+        for l in post {
+            solver.assert(&Term::implies(var(l), Term::or(pre.iter().map(var))));
+        }
+    }
+
+    let resp = solver.check_sat(Default::default())?;
+    solver.save_tee();
+    let model = match resp {
+        SatResp::Sat => solver.get_model()?.remove(0),
+        SatResp::Unsat => return Ok(None),
+        SatResp::Unknown(reason) => return Err(IceError::SeparatorUnknown(reason)),
+    };
+
+    let mut selected: HashSet<usize> = (0..pool.len())
+        .filter(|i| model.eval(&var(i)) == 1)
+        .collect();
+    for i in selected.iter().copied().sorted().collect_vec() {
+        selected.remove(&i);
+        if !constraints.separated_by(&selected) {
+            selected.insert(i);
+        }
+    }
+
+    Ok(Some(selected.into_iter().sorted().collect()))
+}
+
+/// Learn an inductive invariant which implies safety over the given lemma
+/// domains, or return `None` if no subset of the domain's strongest lemmas
+/// separates the samples (or `cancelers` are canceled, as they are once the
+/// configured deadline passes). Fails if the solver cannot answer one of the
+/// queries.
+#[allow(clippy::too_many_arguments)]
+pub fn run_ice<O, L, B, S>(
+    infer_cfg: Arc<InferenceConfig>,
+    solver: &S,
+    conf: &SolverConf,
+    fo: &FOModule,
+    atoms: Arc<RestrictedAtoms>,
+    domains: Vec<Domain<L>>,
+    bounded_samples: &[Model],
+    parent: Option<&FrameCancelers<S::Canceler>>,
+) -> Result<Option<Vec<Term>>, IceError>
+where
+    O: OrderSubsumption<Base = B>,
+    L: LemmaQf<Base = B>,
+    B: Clone + Debug + Send,
+    S: BasicSolver,
+{
+    let cancelers = SolverCancelers::new();
+    if parent.is_some_and(|parent| !parent.add_canceler(cancelers.clone())) {
+        return Ok(None);
+    }
+    let mut frame: InductionFrame<O, L, B> = InductionFrame::new(infer_cfg, atoms, domains, None);
+    frame.record_samples();
+    if !bounded_samples.is_empty() {
+        frame.add_samples(bounded_samples.to_vec());
+    }
+    let mut negatives: Vec<Model> = vec![];
+    let mut implications: Vec<(Model, Model)> = vec![];
+
+    loop {
+        // The positive samples are the initial states and the post-states of
+        // implications from positive samples.
+        while frame.init_cycle(fo, solver, parent) {}
+        if cancelers.is_canceled() {
+            frame.log_info("Deadline reached");
+            return Ok(None);
+        }
+        loop {
+            let (posts, rest) = forced(frame.samples(), implications);
+            implications = rest;
+            if posts.is_empty() {
+                break;
+            }
+            frame.add_samples(posts);
+        }
+
+        let pool = frame.proof();
+        frame.log_info(format!(
+            "Separating {} negative and {} implication samples",
+            negatives.len(),
+            implications.len()
+        ));
+        let constraints = Constraints::new(&pool, &negatives, &implications);
+        let Some(selected) = separate(conf, &pool, &constraints)? else {
+            frame.log_info("No separator found");
+            return Ok(None);
+        };
+        let candidate = selected.iter().map(|&i| pool[i].clone()).collect_vec();
+        frame.log_info(format!("Checking candidate of {} lemmas", candidate.len()));
+
+        let hyp = fo
+            .module
+            .axioms
+            .iter()
+            .chain(&candidate)
+            .cloned()
+            .collect_vec();
+        if let Some(bad) = fo.safe_cex(conf, &hyp) {
+            frame.log_info("Found negative sample");
+            negatives.push(bad);
+            continue;
+        }
+
+        let mut cex = None;
+        for lemma in &candidate {
+            match fo.trans_cex(
+                solver,
+                &candidate,
+                lemma,
+                false,
+                Some(cancelers.clone()),
+                false,
+            ) {
+                CexResult::Cex(models) => {
+                    cex = Some(models);
+                    break;
+                }
+                CexResult::UnsatCore(_) => (),
+                CexResult::Canceled => {
+                    frame.log_info("Deadline reached");
+                    return Ok(None);
+                }
+                CexResult::Unknown(reason) => return Err(IceError::InductivenessUnknown(reason)),
+            }
+        }
+        match cex {
+            Some(mut models) => {
+                frame.log_info("Found implication sample");
+                let post = models.pop().unwrap();
+                let pre = models.pop().unwrap();
+                implications.push((pre, post));
+            }
+            None => {
+                frame.log_info("Candidate is inductive");
+                return Ok(Some(candidate));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use fly::{
+        parser::{parse_signature, term},
+        semantics::{Interpretation, Model},
+        syntax::Signature,
+    };
+    use solver::{backends::GenericBackend, backends::SolverType, conf::SolverConf, solver_path};
+
+    use super::{forced, separate, Constraints};
+
+    fn model(sig: &Signature, p: [usize; 2]) -> Model {
+        Model::new(
+            sig,
+            &vec![2],
+            vec![Interpretation {
+                shape: vec![2, 2],
+                data: p.to_vec(),
+            }],
+        )
+    }
+
+    #[test]
+    fn test_separated_by() {
+        let constraints = Constraints {
+            negatives: vec![vec![0, 1]],
+            implications: vec![(vec![2], vec![1])],
+        };
+        let sel = |ids: &[usize]| ids.iter().copied().collect::<HashSet<_>>();
+        assert!(constraints.separated_by(&sel(&[0])));
+        assert!(!constraints.separated_by(&sel(&[])));
+        assert!(!constraints.separated_by(&sel(&[1])));
+        assert!(constraints.separated_by(&sel(&[1, 2])));
+    }
+
+    #[test]
+    fn test_constraints() {
+        let sig = parse_signature("sort node\nmutable p(node): bool");
+        let pool = vec![
+            term("forall n:node. p(n)"),
+            term("exists n:node. p(n)"),
+            term("forall n:node. !p(n)"),
+        ];
+        let none = model(&sig, [0, 0]);
+        let one = model(&sig, [1, 0]);
+        let all = model(&sig, [1, 1]);
+        let constraints = Constraints::new(&pool, &[one], &[(none, all)]);
+        assert_eq!(constraints.negatives, vec![vec![0, 2]]);
+        assert_eq!(constraints.implications, vec![(vec![0, 1], vec![2])]);
+    }
+
+    #[test]
+    fn test_forced() {
+        let sig = parse_signature("sort node\nmutable p(node): bool");
+        let none = model(&sig, [0, 0]);
+        let one = model(&sig, [1, 0]);
+        let other = model(&sig, [0, 1]);
+        let all = model(&sig, [1, 1]);
+        // `other` is isomorphic to `one`, but only exact matches are forced.
+        let (posts, rest) = forced(
+            &[none.clone(), one.clone()],
+            vec![(one.clone(), all.clone()), (other.clone(), all.clone())],
+        );
+        assert_eq!(posts, vec![all.clone()]);
+        assert_eq!(rest, vec![(other, all)]);
+        let (posts, rest) = forced(&[], vec![(none.clone(), one.clone())]);
+        assert!(posts.is_empty());
+        assert_eq!(rest, vec![(none, one)]);
+    }
+
+    #[test]
+    fn test_separate() {
+        let conf = SolverConf {
+            backend: GenericBackend::new(SolverType::Z3, &solver_path("z3")),
+            tee: None,
+            transcript: false,
+        };
+        let pool = vec![term("p"), term("q"), term("r")];
+        // Lemma 1 must be excluded by an implication from a state violating
+        // nothing, so lemma 0 is needed to exclude the negative sample.
+        let constraints = Constraints {
+            negatives: vec![vec![0, 1]],
+            implications: vec![(vec![], vec![1])],
+        };
+        assert_eq!(separate(&conf, &pool, &constraints).unwrap(), Some(vec![0]));
+
+        let constraints = Constraints {
+            negatives: vec![vec![1]],
+            implications: vec![(vec![], vec![1])],
+        };
+        assert_eq!(separate(&conf, &pool, &constraints).unwrap(), None);
+        let constraints = Constraints {
+            negatives: vec![vec![]],
+            implications: vec![],
+        };
+        assert_eq!(separate(&conf, &pool, &constraints).unwrap(), None);
+    }
+}
@@ -7,6 +7,7 @@ use fly::ouritertools::OurItertools;
 use itertools::Itertools;
 use solver::basics::{BasicSolver, BasicSolverCanceler, SolverCancelers};
 use std::collections::VecDeque;
+use std::convert::Infallible;
 use std::fmt::{Debug, Display};
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::Instant;
@@ -801,6 +802,39 @@ pub fn ids(term: &Term) -> HashSet<String> {
     }
 }
 
+/// Cancelers which stop the queries of every transition cycle of a frame.
+/// Each cycle registers its own [`SolverCancelers`] with them.
+pub type FrameCancelers<C> = SolverCancelers<SolverCancelers<SolverCancelers<C>>>;
+
+/// Close `core` under `blocking`, which gives the ids of the lemmas used to
+/// establish the inductiveness of the lemma with the given id. Stops at the
+/// first error returned by `blocking`.
+pub fn close_core<I, E>(
+    core: impl IntoIterator<Item = usize>,
+    mut blocking: impl FnMut(usize) -> Result<I, E>,
+) -> Result<HashSet<usize>, E>
+where
+    I: IntoIterator<Item = usize>,
+{
+    let mut closed = HashSet::default();
+    let mut new_ids: HashSet<usize> = core.into_iter().collect();
+
+    while !new_ids.is_empty() {
+        let mut new_new_ids = HashSet::default();
+        for &id in &new_ids {
+            new_new_ids.extend(blocking(id)?.into_iter().filter(|id| !closed.contains(id)));
+        }
+
+        closed.extend(new_ids);
+        new_ids = new_new_ids
+            .into_iter()
+            .filter(|id| !closed.contains(id))
+            .collect();
+    }
+
+    Ok(closed)
+}
+
 /// A [`InductionFrame`] maintains quantified formulas during invariant inference.
 pub struct InductionFrame<O, L, B>
 where
@@ -823,6 +857,16 @@ where
     extend: Option<(usize, usize)>,
     /// A set of CTI's to extend.
     ctis: VecDeque<Model>,
+    /// All models used to weaken the frame so far, in order. Replaying these
+    /// on a new frame reproduces the weakened lemmas. These are only recorded
+    /// once enabled using [`InductionFrame::record_samples`].
+    samples: Option<Vec<Model>>,
+    /// The initial states found by [`InductionFrame::init_cycle`]. Unlike other
+    /// samples, these are known to be reachable.
+    initial_samples: Vec<Model>,
+    /// Lemmas outside the frame's domains which are kept in the frame while they
+    /// hold in all samples and are inductive relative to the frame, as in Houdini.
+    seeds: Vec<Term>,
     /// A subset of the frame's lemmas which inductively implies the safety assertions.
     safety_core: Option<HashSet<usize>>,
     /// The time of creation of the frame (for logging purposes)
@@ -857,6 +901,9 @@ where
             core_to_blocked: HashMap::default(),
             extend,
             ctis: VecDeque::new(),
+            samples: None,
+            initial_samples: vec![],
+            seeds: vec![],
             safety_core: None,
             start_time: Instant::now(),
         }
@@ -872,35 +919,88 @@ where
         self.weaken_lemmas.len()
     }
 
-    /// Get the term representation of the lemmas in the frame.
+    /// Start recording the models used to weaken the frame. This is off by
+    /// default, since the samples are only needed to checkpoint or share the
+    /// frame's progress.
+    pub fn record_samples(&mut self) {
+        self.samples.get_or_insert_with(Vec::new);
+    }
+
+    /// Seed the frame with lemmas which need not be in its domains (such as
+    /// candidate invariants given by the user). A seed is dropped once a sample
+    /// falsifies it.
+    pub fn seed(&mut self, seeds: Vec<Term>) {
+        self.seeds = seeds;
+    }
+
+    /// Get the models used to weaken the frame since recording was enabled,
+    /// in order.
+    pub fn samples(&self) -> &[Model] {
+        self.samples.as_deref().unwrap_or_default()
+    }
+
+    /// Get the initial states used to weaken the frame so far.
+    pub fn initial_samples(&self) -> &[Model] {
+        &self.initial_samples
+    }
+
+    /// Weaken the frame with previously collected samples (for example, from
+    /// a checkpoint), and update it accordingly.
+    pub fn resume(&mut self, samples: Vec<Model>) {
+        self.add_samples(samples);
+    }
+
+    /// Weaken the frame with samples found elsewhere (for example, by another
+    /// worker), and update it accordingly. Return whether any lemma was weakened.
+    pub fn add_samples(&mut self, samples: Vec<Model>) -> bool {
+        let mut weakened = false;
+        for sample in samples {
+            weakened |= self.weaken(sample);
+        }
+        self.update();
+        weakened
+    }
+
+    fn weaken(&mut self, model: Model) -> bool {
+        let weakened = self.weaken_lemmas.weaken(&model);
+        let seeds = self.seeds.len();
+        self.seeds.retain(|seed| model.eval(seed) != 0);
+        if self.seeds.len() < seeds {
+            self.safety_core = None;
+        }
+        if let Some(samples) = &mut self.samples {
+            samples.push(model);
+        }
+        weakened
+    }
+
+    /// Get the term representation of the lemmas in the frame, including the
+    /// remaining seeds.
     pub fn proof(&self) -> Vec<Term> {
-        self.lemmas.to_terms()
+        let mut proof = self.lemmas.to_terms();
+        proof.extend(self.seeds.iter().cloned());
+        proof
     }
 
     /// Get a minimized inductive set of lemmas in the frame which inductively implies safety,
     /// provided that `is_safe` has been called and returned `true`.
+    ///
+    /// Seeds are only inductive relative to the whole frame, so if any remain
+    /// the proof is not minimized.
     pub fn minimized_proof(&self) -> Option<Vec<Term>> {
         self.safety_core.as_ref()?;
-
-        let mut extended_core = HashSet::default();
-        let mut new_ids = self.safety_core.as_ref().unwrap().clone();
-
-        while !new_ids.is_empty() {
-            let mut new_new_ids = HashSet::default();
-            for id in &new_ids {
-                let (prefix, body) = self.lemmas.id_to_lemma(id);
-                let blocked_id = self.blocked.get_id(&prefix, body).unwrap();
-                new_new_ids.extend(
-                    self.blocked_to_core[&blocked_id]
-                        .difference(&extended_core)
-                        .copied(),
-                );
-            }
-
-            extended_core.extend(new_ids);
-            new_ids = new_new_ids;
+        if !self.seeds.is_empty() {
+            return Some(self.proof());
         }
 
+        let safety_core = self.safety_core.as_ref().unwrap().clone();
+        let extended_core = close_core(safety_core, |id| {
+            let (prefix, body) = self.lemmas.id_to_lemma(&id);
+            let blocked_id = self.blocked.get_id(&prefix, body).unwrap();
+            Ok::<_, Infallible>(self.blocked_to_core[&blocked_id].clone())
+        })
+        .unwrap();
+
         Some(
             extended_core
                 .into_iter()
@@ -931,7 +1031,16 @@ where
     }
 
     /// Get an initial state which violates one of the frame's lemmas.
-    fn init_cex<S: BasicSolver>(&mut self, fo: &FOModule, solver: &S) -> Option<Model> {
+    fn init_cex<S: BasicSolver>(
+        &mut self,
+        fo: &FOModule,
+        solver: &S,
+        parent: Option<&FrameCancelers<S::Canceler>>,
+    ) -> Option<Model> {
+        let cancelers = SolverCancelers::new();
+        if parent.is_some_and(|parent| !parent.add_canceler(cancelers.clone())) {
+            return None;
+        }
         let blocked_lock = RwLock::new((
             &mut self.blocked,
             &mut self.blocked_to_core,
@@ -948,24 +1057,33 @@ where
             })
             .find_map_any(|(prefix, body)| {
                 let term = prefix.quantify(self.lemmas.body_to_term(body));
-                if let Some(model) = fo.init_cex(solver, &term) {
+                if let Some(model) = fo.init_cex(solver, &term, Some(cancelers.clone())) {
                     return Some(model);
-                } else {
+                } else if !cancelers.is_canceled() {
+                    // A canceled query does not show that the lemma is blocked.
                     let mut blocked_write = blocked_lock.write().unwrap();
                     let core = self.lemmas.ids().collect();
                     let blocked_id = blocked_write.0.insert(prefix, body.clone());
-                    for i in &core {
-                        if let Some(hs) = blocked_write.2.get_mut(i) {
-                            hs.insert(blocked_id);
-                        } else {
-                            blocked_write.2.insert(*i, HashSet::from_iter([blocked_id]));
+                    // an alpha-equivalent lemma may already be blocked by its own core
+                    if !blocked_write.1.contains_key(&blocked_id) {
+                        for i in &core {
+                            if let Some(hs) = blocked_write.2.get_mut(i) {
+                                hs.insert(blocked_id);
+                            } else {
+                                blocked_write.2.insert(*i, HashSet::from_iter([blocked_id]));
+                            }
                         }
+                        blocked_write.1.insert(blocked_id, core);
                     }
-                    blocked_write.1.insert(blocked_id, core);
                 }
 
                 None
             });
+        let res = res.or_else(|| {
+            self.seeds
+                .iter()
+                .find_map(|seed| fo.init_cex(solver, seed, Some(cancelers.clone())))
+        });
 
         if self.extend.is_some() {
             self.ctis.extend(res.iter().cloned());
@@ -978,13 +1096,26 @@ where
     /// violating the frame and weaken it. Return whether such a counterexample was found.
     ///
     /// Note: only when no initial counterexamples are found, the frame is updated.
-    pub fn init_cycle<S: BasicSolver>(&mut self, fo: &FOModule, solver: &S) -> bool {
+    ///
+    /// If `cancelers` is given, canceling them stops the cycle's solver queries, as in
+    /// [`InductionFrame::trans_cycle`].
+    pub fn init_cycle<S: BasicSolver>(
+        &mut self,
+        fo: &FOModule,
+        solver: &S,
+        cancelers: Option<&FrameCancelers<S::Canceler>>,
+    ) -> bool {
         self.log_info("Finding CTI...");
-        match self.init_cex(fo, solver) {
+        let cex = self.init_cex(fo, solver, cancelers);
+        if cancelers.is_some_and(|c| c.is_canceled()) {
+            return false;
+        }
+        match cex {
             Some(cti) => {
                 self.log_info("CTI found, type=initial");
                 self.log_info("Weakening...");
-                self.weaken_lemmas.weaken(&cti);
+                self.initial_samples.push(cti.clone());
+                self.weaken(cti);
 
                 true
             }
@@ -999,11 +1130,20 @@ where
     }
 
     /// Extend CTI traces and weaken the given lemmas accordingly,
-    /// until no more states can be sampled.
-    pub fn extend<S: BasicSolver>(&mut self, fo: &FOModule, solver: &S) {
+    /// until no more states can be sampled (or `parent` is canceled).
+    pub fn extend<S: BasicSolver>(
+        &mut self,
+        fo: &FOModule,
+        solver: &S,
+        parent: Option<&FrameCancelers<S::Canceler>>,
+    ) {
         self.log_info("Simulating CTI traces...");
         let (width, depth) = self.extend.unwrap();
-        while !self.ctis.is_empty() {
+        let cancelers = SolverCancelers::new();
+        if parent.is_some_and(|parent| !parent.add_canceler(cancelers.clone())) {
+            return;
+        }
+        while !self.ctis.is_empty() && !cancelers.is_canceled() {
             let mut new_ctis = VecDeque::new();
             self.log_debug(format!(
                 "Extending traces from {} CTI's...",
@@ -1015,7 +1155,8 @@ where
                 .enumerate()
                 .flat_map_iter(|(id, state)| {
                     self.log_debug(format!("Extending CTI trace #{id}..."));
-                    let samples = fo.simulate_from(solver, state, width, depth);
+                    let samples =
+                        fo.simulate_from(solver, state, width, depth, Some(cancelers.clone()));
                     self.log_debug(format!(
                         "Found {} simulated samples from CTI #{id}...",
                         samples.len(),
@@ -1035,7 +1176,7 @@ where
                 .into_par_iter()
                 .find_first(|i| self.weaken_lemmas.unsat(&samples[*i]))
             {
-                assert!(self.weaken_lemmas.weaken(&samples[i]));
+                assert!(self.weaken(samples[i].clone()));
                 self.log_debug(format!("Weakened ({} / {samples_len}).", i + 1));
                 new_ctis.push_back(samples[i].clone());
                 idx = i + 1;
@@ -1049,10 +1190,18 @@ where
     }
 
     /// Get an post-state of the frame which violates one of the frame's lemmas.
-    fn trans_cex<S: BasicSolver>(&mut self, fo: &FOModule, solver: &S) -> Option<Model> {
+    fn trans_cex<S: BasicSolver>(
+        &mut self,
+        fo: &FOModule,
+        solver: &S,
+        parent: Option<&FrameCancelers<S::Canceler>>,
+    ) -> Option<Model> {
         let (pre_ids, pre_terms): (Vec<usize>, Vec<Term>) = self.lemmas.to_terms_ids().unzip();
 
         let cancelers = SolverCancelers::new();
+        if parent.is_some_and(|parent| !parent.add_canceler(cancelers.clone())) {
+            return None;
+        }
         let unknown = Mutex::new(false);
         let first_sat = Mutex::new(None);
         let total_sat = Mutex::new(0_usize);
@@ -1078,7 +1227,7 @@ where
                 // so it doesn't need to be checked.
                 let lemma_id = self.lemmas.get_id(&prefix, body)?;
                 let pre_ids: &[usize] = &[&[lemma_id], &pre_ids[..]].concat();
-                let pre_terms: &[Term] = &[&[term.clone()], &pre_terms[..]].concat();
+                let pre_terms: &[Term] = &[std::slice::from_ref(&term), &pre_terms[..]].concat();
                 match fo.trans_cex(
                     solver,
                     pre_terms,
@@ -1113,14 +1262,19 @@ where
                             let mut blocked_write = blocked_lock.write().unwrap();
                             let core = core.into_iter().map(|i| pre_ids[i]).collect();
                             let blocked_id = blocked_write.0.insert(prefix, body.clone());
-                            for i in &core {
-                                if let Some(hs) = blocked_write.2.get_mut(i) {
-                                    hs.insert(blocked_id);
-                                } else {
-                                    blocked_write.2.insert(*i, HashSet::from_iter([blocked_id]));
+                            // an alpha-equivalent lemma may already be blocked by its own core
+                            if !blocked_write.1.contains_key(&blocked_id) {
+                                for i in &core {
+                                    if let Some(hs) = blocked_write.2.get_mut(i) {
+                                        hs.insert(blocked_id);
+                                    } else {
+                                        blocked_write
+                                            .2
+                                            .insert(*i, HashSet::from_iter([blocked_id]));
+                                    }
                                 }
+                                blocked_write.1.insert(blocked_id, core);
                             }
-                            blocked_write.1.insert(blocked_id, core);
                         }
                     }
                     CexResult::Canceled => (),
@@ -1133,6 +1287,11 @@ where
         if res.is_none() && unknown.into_inner().unwrap() {
             panic!("SMT queries got 'unknown' and no SAT results.")
         }
+        let res = match res {
+            Some(model) => Some(model),
+            None if parent.is_some_and(|parent| parent.is_canceled()) => None,
+            None => self.seeds_cex(fo, solver, &pre_terms),
+        };
 
         log::info!(
             "    SMT STATS: total_time={:.5}s, until_sat={:.5}s, sat_found={}, unsat_found={}",
@@ -1149,15 +1308,48 @@ where
         res
     }
 
+    /// Get a post-state of the frame and its seeds which violates one of the
+    /// seeds, where `pre_terms` are the frame's lemmas.
+    fn seeds_cex<S: BasicSolver>(
+        &self,
+        fo: &FOModule,
+        solver: &S,
+        pre_terms: &[Term],
+    ) -> Option<Model> {
+        let hyp = pre_terms.iter().chain(&self.seeds).cloned().collect_vec();
+        for seed in &self.seeds {
+            match fo.trans_cex(solver, &hyp, seed, false, None, false) {
+                CexResult::Cex(mut models) => {
+                    assert_eq!(models.len(), 2);
+                    return models.pop();
+                }
+                CexResult::UnsatCore(_) => (),
+                CexResult::Canceled => return None,
+                CexResult::Unknown(reason) => {
+                    panic!("SMT query for seeded lemma got 'unknown': {reason}")
+                }
+            }
+        }
+        None
+    }
+
     /// Perform a transition cycle, which attempts to sample a transition from the frame
     /// whose post-state violates the frame, and weaken it. Return whether such a counterexample was found.
-    pub fn trans_cycle<S: BasicSolver>(&mut self, fo: &FOModule, solver: &S) -> bool {
+    ///
+    /// If `cancelers` is given, canceling them stops the cycle's solver queries. A canceled cycle
+    /// finds no counterexample, so callers should check for cancellation before relying on the result.
+    pub fn trans_cycle<S: BasicSolver>(
+        &mut self,
+        fo: &FOModule,
+        solver: &S,
+        cancelers: Option<&FrameCancelers<S::Canceler>>,
+    ) -> bool {
         self.log_info("Finding CTI...");
-        match self.trans_cex(fo, solver) {
+        match self.trans_cex(fo, solver, cancelers) {
             Some(cti) => {
                 self.log_info("CTI found, type=transition");
                 self.log_info("Weakening...");
-                self.weaken_lemmas.weaken(&cti);
+                self.weaken(cti);
                 self.log_info("Updating frame...");
                 self.update();
 
@@ -1172,17 +1364,33 @@ where
     }
 
     /// Return whether the current frame inductively implies the safety assertions
-    /// of the given module.
-    pub fn is_safe<S: BasicSolver>(&mut self, fo: &FOModule, solver: &S) -> bool {
+    /// of the given module. A canceled check returns `false`.
+    pub fn is_safe<S: BasicSolver>(
+        &mut self,
+        fo: &FOModule,
+        solver: &S,
+        parent: Option<&FrameCancelers<S::Canceler>>,
+    ) -> bool {
         if self.safety_core.is_some() {
             return true;
         }
 
-        let (ids, terms): (Vec<usize>, Vec<Term>) = self.lemmas.to_terms_ids().unzip();
-        match fo.trans_safe_cex(solver, &terms) {
-            CexResult::Cex(_) => false,
+        let cancelers = SolverCancelers::new();
+        if parent.is_some_and(|parent| !parent.add_canceler(cancelers.clone())) {
+            return false;
+        }
+        let (ids, mut terms): (Vec<usize>, Vec<Term>) = self.lemmas.to_terms_ids().unzip();
+        terms.extend(self.seeds.iter().cloned());
+        match fo.trans_safe_cex(solver, &terms, Some(cancelers)) {
+            CexResult::Cex(_) | CexResult::Canceled => false,
             CexResult::UnsatCore(core) => {
-                self.safety_core = Some(core.into_iter().map(|i| ids[i]).collect());
+                // indices past the frame's lemmas are seeds
+                self.safety_core = Some(
+                    core.into_iter()
+                        .filter_map(|i| ids.get(i))
+                        .copied()
+                        .collect(),
+                );
                 true
             }
             _ => panic!("safety check failed"),
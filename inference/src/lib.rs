@@ -10,6 +10,7 @@
 // configure clippy
 #![allow(clippy::needless_return)]
 #![allow(clippy::large_enum_variant)]
+#![allow(clippy::result_large_err)]
 #![allow(clippy::upper_case_acronyms)]
 #![allow(clippy::type_complexity)]
 #![allow(clippy::new_without_default)]
@@ -20,12 +21,18 @@
 #![deny(rustdoc::broken_intra_doc_links)]
 
 pub mod atoms;
+pub mod auto;
 pub mod basics;
+pub mod checkpoint;
+pub mod diagnose;
 pub mod fixpoint;
 pub mod hashmap;
 pub mod houdini;
+pub mod ice;
 pub mod lemma;
 pub mod marco;
+pub mod parallel;
+pub mod pdr;
 pub mod quant;
 pub mod subsume;
 pub mod updr;
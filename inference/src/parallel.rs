@@ -0,0 +1,760 @@
+// Copyright 2022-2023 VMware, Inc.
+// SPDX-License-Identifier: BSD-2-Clause
+
+//! Explore several lemma domains of qalpha concurrently.
+//!
+//! The domains are split between workers, each of which computes a fixpoint
+//! over its own domains with its own [`InductionFrame`]. Workers share every
+//! initial state they use to weaken their frame, and every fixpoint they
+//! reach; since a fixpoint holds in all reachable states, other workers add
+//! its lemmas to their axioms. The conjunction of the fixpoints is the result.
+//!
+//! Only reachable states may be shared, since a frame only needs to satisfy
+//! those. Counterexamples to induction, and the simulations extending them,
+//! start from states of the worker's own frame, which need not be reachable,
+//! so they are kept private. The bounded samples are reachable too, but every
+//! worker starts with them.
+//!
+//! Workers communicate with the coordinator only through [`Message`]s, which
+//! can be encoded as JSON lines using [`JsonLines`]. Workers currently run as
+//! threads connected by channels, but the same protocol can connect workers in
+//! other processes over stdin/stdout or a local socket. Cancellation is done
+//! by sending [`Message::Cancel`], which cancels the worker's solver queries
+//! through its [`FrameCancelers`].
+//!
+//! The coordinator checks the lemmas it receives for safety on separate
+//! threads, so that a slow check delays neither the delivery of messages nor
+//! the deadline.
+
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    io::{self, BufRead, Write},
+    sync::{mpsc, Arc},
+    thread,
+    time::Instant,
+};
+
+use fly::{
+    parser::parse_term,
+    semantics::Model,
+    syntax::{Signature, Term},
+};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use solver::basics::{BasicSolver, BasicSolverCanceler, SolverCancelers};
+
+use crate::{
+    atoms::RestrictedAtoms,
+    basics::{CexResult, FOModule, InferenceConfig},
+    checkpoint::Sample,
+    lemma::{close_core, FrameCancelers, InductionFrame},
+    subsume::OrderSubsumption,
+    weaken::{Domain, LemmaQf},
+};
+
+/// A message between the coordinator of a parallel qalpha run and its workers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// An initial state used by `worker` to weaken its frame. Every frame must
+    /// satisfy it.
+    Sample { worker: usize, sample: Model },
+    /// The current lemmas of `worker`'s frame, sent before each transition
+    /// cycle when runs abort once safety cannot be proven.
+    Frame { worker: usize, lemmas: Vec<Term> },
+    /// `worker` has reached a fixpoint, so its lemmas hold in all reachable states.
+    Fixpoint { worker: usize, lemmas: Vec<Term> },
+    /// `worker` has stopped without reaching a fixpoint.
+    Canceled { worker: usize },
+    /// Sent to a worker to stop it.
+    Cancel,
+}
+
+/// The sending side of a connection carrying [`Message`]s.
+pub trait MessageSender: Send {
+    /// Send a message. Fails if the other side has disconnected.
+    fn send(&mut self, msg: &Message) -> io::Result<()>;
+}
+
+/// The receiving side of a connection carrying [`Message`]s.
+pub trait MessageReceiver: Send {
+    /// Wait for the next message. Returns `None` once the other side has
+    /// disconnected.
+    fn recv(&mut self) -> io::Result<Option<Message>>;
+}
+
+impl MessageSender for mpsc::Sender<Message> {
+    fn send(&mut self, msg: &Message) -> io::Result<()> {
+        mpsc::Sender::send(self, msg.clone())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "receiver disconnected"))
+    }
+}
+
+impl MessageReceiver for mpsc::Receiver<Message> {
+    fn recv(&mut self) -> io::Result<Option<Message>> {
+        Ok(mpsc::Receiver::recv(self).ok())
+    }
+}
+
+/// The encoding of a [`Message`] sent by [`JsonLines`]. Models are sent without
+/// their signature, and lemmas as text.
+#[derive(Debug, Serialize, Deserialize)]
+enum Encoded {
+    Sample { worker: usize, sample: Sample },
+    Frame { worker: usize, lemmas: Vec<String> },
+    Fixpoint { worker: usize, lemmas: Vec<String> },
+    Canceled { worker: usize },
+    Cancel,
+}
+
+fn encode_lemmas(lemmas: &[Term]) -> Vec<String> {
+    lemmas.iter().map(|t| t.to_string()).collect()
+}
+
+fn decode_lemmas(lemmas: &[String]) -> io::Result<Vec<Term>> {
+    lemmas
+        .iter()
+        .map(|s| {
+            parse_term(s).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("malformed lemma {s}: {err}"),
+                )
+            })
+        })
+        .collect()
+}
+
+impl Message {
+    fn encode(&self) -> Encoded {
+        match self {
+            Message::Sample { worker, sample } => Encoded::Sample {
+                worker: *worker,
+                sample: Sample::from_model(sample),
+            },
+            Message::Frame { worker, lemmas } => Encoded::Frame {
+                worker: *worker,
+                lemmas: encode_lemmas(lemmas),
+            },
+            Message::Fixpoint { worker, lemmas } => Encoded::Fixpoint {
+                worker: *worker,
+                lemmas: encode_lemmas(lemmas),
+            },
+            Message::Canceled { worker } => Encoded::Canceled { worker: *worker },
+            Message::Cancel => Encoded::Cancel,
+        }
+    }
+
+    fn decode(encoded: Encoded, sig: &Signature) -> io::Result<Self> {
+        Ok(match encoded {
+            Encoded::Sample { worker, sample } => Message::Sample {
+                worker,
+                sample: sample.to_model(sig),
+            },
+            Encoded::Frame { worker, lemmas } => Message::Frame {
+                worker,
+                lemmas: decode_lemmas(&lemmas)?,
+            },
+            Encoded::Fixpoint { worker, lemmas } => Message::Fixpoint {
+                worker,
+                lemmas: decode_lemmas(&lemmas)?,
+            },
+            Encoded::Canceled { worker } => Message::Canceled { worker },
+            Encoded::Cancel => Message::Cancel,
+        })
+    }
+}
+
+/// A connection which encodes each [`Message`] as a line of JSON, for use over
+/// a pipe (such as a worker's stdin and stdout) or a socket. Received models
+/// are interpreted over the signature of the module being inferred.
+pub struct JsonLines<T> {
+    conn: T,
+    sig: Signature,
+}
+
+impl<T> JsonLines<T> {
+    /// Exchange messages about a module with signature `sig` over `conn`.
+    pub fn new(conn: T, sig: &Signature) -> Self {
+        Self {
+            conn,
+            sig: sig.clone(),
+        }
+    }
+}
+
+impl<W: Write + Send> MessageSender for JsonLines<W> {
+    fn send(&mut self, msg: &Message) -> io::Result<()> {
+        serde_json::to_writer(&mut self.conn, &msg.encode())?;
+        self.conn.write_all(b"\n")?;
+        self.conn.flush()
+    }
+}
+
+impl<R: BufRead + Send> MessageReceiver for JsonLines<R> {
+    fn recv(&mut self) -> io::Result<Option<Message>> {
+        let mut line = String::new();
+        if self.conn.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let encoded = serde_json::from_str(&line)?;
+        Message::decode(encoded, &self.sig).map(Some)
+    }
+}
+
+/// Split the domains into at most `n` groups of similar size.
+fn partition<L: LemmaQf>(domains: Vec<Domain<L>>, n: usize) -> Vec<Vec<Domain<L>>> {
+    let n = n.clamp(1, domains.len().max(1));
+    let mut groups: Vec<(usize, Vec<Domain<L>>)> = (0..n).map(|_| (0, vec![])).collect();
+    for dom in domains
+        .into_iter()
+        .sorted_by_key(|(_, lemma_qf, _)| std::cmp::Reverse(lemma_qf.approx_space_size()))
+    {
+        let group = groups.iter_mut().min_by_key(|(size, _)| *size).unwrap();
+        group.0 += dom.1.approx_space_size();
+        group.1.push(dom);
+    }
+    groups
+        .into_iter()
+        .map(|(_, group)| group)
+        .filter(|group| !group.is_empty())
+        .collect()
+}
+
+/// The state of a single worker.
+struct Worker<O, L, B, T>
+where
+    O: OrderSubsumption<Base = B>,
+    L: LemmaQf<Base = B>,
+    B: Clone + Debug + Send,
+    T: MessageSender,
+{
+    id: usize,
+    fo: FOModule,
+    frame: InductionFrame<O, L, B>,
+    /// Messages from the coordinator, other than [`Message::Cancel`].
+    inbox: mpsc::Receiver<Message>,
+    outbox: T,
+    /// The number of the frame's initial samples that have already been sent.
+    sent: usize,
+}
+
+impl<O, L, B, T> Worker<O, L, B, T>
+where
+    O: OrderSubsumption<Base = B>,
+    L: LemmaQf<Base = B>,
+    B: Clone + Debug + Send,
+    T: MessageSender,
+{
+    /// Apply the samples and fixpoints received so far.
+    fn receive(&mut self) {
+        let mut samples = vec![];
+        for msg in self.inbox.try_iter() {
+            match msg {
+                Message::Sample { sample, .. } => samples.push(sample),
+                Message::Fixpoint { lemmas, .. } => self.fo.module.axioms.extend(lemmas),
+                Message::Frame { .. } | Message::Canceled { .. } | Message::Cancel => (),
+            }
+        }
+        if !samples.is_empty() {
+            self.frame
+                .log_info(format!("Received {} samples", samples.len()));
+            self.frame.add_samples(samples);
+        }
+    }
+
+    /// Send the initial samples found since the last call.
+    fn publish(&mut self) {
+        for model in &self.frame.initial_samples()[self.sent..] {
+            let msg = Message::Sample {
+                worker: self.id,
+                sample: model.clone(),
+            };
+            if self.outbox.send(&msg).is_err() {
+                log::warn!("worker {}: coordinator disconnected", self.id);
+            }
+        }
+        self.sent = self.frame.initial_samples().len();
+    }
+
+    fn send(&mut self, msg: Message) {
+        if self.outbox.send(&msg).is_err() {
+            log::warn!("worker {}: coordinator disconnected", self.id);
+        }
+    }
+
+    /// Compute a fixpoint, unless canceled first.
+    fn run<S1, S2>(
+        &mut self,
+        main_solver: &S1,
+        simulation_solver: &S2,
+        extend: bool,
+        abort_unsafe: bool,
+        cancelers: &FrameCancelers<S1::Canceler>,
+        simulation_cancelers: &FrameCancelers<S2::Canceler>,
+    ) -> Message
+    where
+        S1: BasicSolver,
+        S2: BasicSolver,
+    {
+        let canceled = Message::Canceled { worker: self.id };
+
+        // Begin by overapproximating the initial states.
+        loop {
+            self.receive();
+            let found = self
+                .frame
+                .init_cycle(&self.fo, main_solver, Some(cancelers));
+            if cancelers.is_canceled() {
+                return canceled;
+            }
+            self.publish();
+            if !found {
+                break;
+            }
+        }
+
+        // Handle transition CTI's.
+        loop {
+            self.receive();
+            if extend {
+                self.frame
+                    .extend(&self.fo, simulation_solver, Some(simulation_cancelers));
+            }
+            if abort_unsafe {
+                self.send(Message::Frame {
+                    worker: self.id,
+                    lemmas: self.frame.proof(),
+                });
+            }
+            let found = self
+                .frame
+                .trans_cycle(&self.fo, main_solver, Some(cancelers));
+            if cancelers.is_canceled() {
+                return canceled;
+            }
+            if !found {
+                break;
+            }
+        }
+
+        Message::Fixpoint {
+            worker: self.id,
+            lemmas: self.frame.proof(),
+        }
+    }
+}
+
+/// Run a worker which computes a fixpoint over `domains`, exchanging messages
+/// with the coordinator over `inbox` and `outbox`. Returns once the worker has
+/// reported a fixpoint (or cancellation) and the coordinator has disconnected.
+#[allow(clippy::too_many_arguments)]
+pub fn run_worker<O, L, B, S1, S2, R, T>(
+    id: usize,
+    infer_cfg: Arc<InferenceConfig>,
+    main_solver: &S1,
+    simulation_solver: &S2,
+    fo: &FOModule,
+    atoms: Arc<RestrictedAtoms>,
+    domains: Vec<Domain<L>>,
+    extend: Option<(usize, usize)>,
+    bounded_samples: &[Model],
+    mut inbox: R,
+    outbox: T,
+) where
+    O: OrderSubsumption<Base = B>,
+    L: LemmaQf<Base = B>,
+    B: Clone + Debug + Send,
+    S1: BasicSolver,
+    S2: BasicSolver,
+    R: MessageReceiver,
+    T: MessageSender,
+{
+    let cancelers: FrameCancelers<S1::Canceler> = FrameCancelers::new();
+    let simulation_cancelers: FrameCancelers<S2::Canceler> = FrameCancelers::new();
+    let (tx, rx) = mpsc::channel();
+
+    thread::scope(|s| {
+        // Listen for messages, canceling the worker's queries as soon as it is
+        // asked to stop.
+        let listener_cancelers = (cancelers.clone(), simulation_cancelers.clone());
+        s.spawn(move || loop {
+            match inbox.recv() {
+                Ok(Some(Message::Cancel)) => {
+                    listener_cancelers.0.cancel();
+                    listener_cancelers.1.cancel();
+                }
+                Ok(Some(msg)) => {
+                    let _ = tx.send(msg);
+                }
+                Ok(None) => break,
+                Err(err) => {
+                    log::warn!("worker {id}: could not receive message: {err}");
+                    break;
+                }
+            }
+        });
+
+        let abort_unsafe = infer_cfg.abort_unsafe;
+        let mut worker: Worker<O, L, B, T> = Worker {
+            id,
+            fo: fo.clone(),
+            frame: InductionFrame::new(infer_cfg, atoms, domains, extend),
+            inbox: rx,
+            outbox,
+            sent: 0,
+        };
+        // Every worker has the bounded samples, so they are not shared.
+        if !bounded_samples.is_empty() {
+            worker.frame.add_samples(bounded_samples.to_vec());
+        }
+        let msg = worker.run(
+            main_solver,
+            simulation_solver,
+            extend.is_some(),
+            abort_unsafe,
+            &cancelers,
+            &simulation_cancelers,
+        );
+        worker.send(msg);
+    });
+}
+
+/// An event handled by the coordinator of a parallel run.
+enum Event {
+    /// A message from a worker.
+    Message(Message),
+    /// The result of a safety check started by the coordinator.
+    Checked(Check, CexResult),
+}
+
+/// The sending side of a worker's connection to the coordinator.
+struct ToCoordinator(mpsc::Sender<Event>);
+
+impl MessageSender for ToCoordinator {
+    fn send(&mut self, msg: &Message) -> io::Result<()> {
+        self.0
+            .send(Event::Message(msg.clone()))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "receiver disconnected"))
+    }
+}
+
+/// A safety check run by the coordinator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Check {
+    /// Whether the fixpoints reached so far prove safety.
+    Fixpoints,
+    /// Whether the current frames of all workers can still prove safety.
+    Frames,
+}
+
+/// Compute fixpoints over `domains` using up to `workers` concurrent workers,
+/// and return the conjunction of the fixpoints of all workers which reached
+/// one.
+///
+/// With `until_safe`, once the fixpoints reached so far imply safety, the
+/// remaining workers are canceled. They are also canceled once the configured
+/// deadline passes. With `abort_unsafe`, once the conjunction of the current
+/// frames of all workers does not prove safety, neither will their fixpoints,
+/// so the workers are canceled and `None` is returned.
+#[allow(clippy::too_many_arguments)]
+pub fn run_parallel<O, L, B, S1, S2>(
+    infer_cfg: Arc<InferenceConfig>,
+    main_solver: &S1,
+    simulation_solver: &S2,
+    fo: &FOModule,
+    atoms: Arc<RestrictedAtoms>,
+    domains: Vec<Domain<L>>,
+    extend: Option<(usize, usize)>,
+    bounded_samples: &[Model],
+    workers: usize,
+) -> Option<Vec<Term>>
+where
+    O: OrderSubsumption<Base = B>,
+    L: LemmaQf<Base = B>,
+    B: Clone + Debug + Send,
+    S1: BasicSolver,
+    S2: BasicSolver,
+{
+    let groups = partition(domains, workers);
+    log::info!("Running {} workers", groups.len());
+    let (events, from_workers) = mpsc::channel();
+    // cancels the coordinator's safety checks
+    let check_cancelers: FrameCancelers<S1::Canceler> = FrameCancelers::new();
+
+    thread::scope(|s| {
+        let mut to_workers = vec![];
+        for (id, group) in groups.into_iter().enumerate() {
+            let (tx, rx) = mpsc::channel();
+            to_workers.push(Some(tx));
+            let outbox = ToCoordinator(events.clone());
+            let infer_cfg = infer_cfg.clone();
+            let atoms = atoms.clone();
+            s.spawn(move || {
+                run_worker::<O, L, B, S1, S2, _, _>(
+                    id,
+                    infer_cfg,
+                    main_solver,
+                    simulation_solver,
+                    fo,
+                    atoms,
+                    group,
+                    extend,
+                    bounded_samples,
+                    rx,
+                    outbox,
+                )
+            });
+        }
+
+        let broadcast = |to_workers: &mut [Option<mpsc::Sender<Message>>], msg: &Message| {
+            for tx in to_workers.iter_mut().flatten() {
+                // A worker which has already finished no longer listens.
+                let _ = MessageSender::send(tx, msg);
+            }
+        };
+        let cancel = |to_workers: &mut [Option<mpsc::Sender<Message>>]| {
+            broadcast(to_workers, &Message::Cancel);
+            check_cancelers.cancel();
+        };
+        let start_check = |check: Check, lemmas: Vec<Term>| {
+            let events = events.clone();
+            let check_cancelers = &check_cancelers;
+            s.spawn(move || {
+                let cancelers = SolverCancelers::new();
+                let result = if check_cancelers.add_canceler(cancelers.clone()) {
+                    fo.trans_safe_cex(main_solver, &lemmas, Some(cancelers))
+                } else {
+                    CexResult::Canceled
+                };
+                let _ = events.send(Event::Checked(check, result));
+            });
+        };
+
+        let mut running = to_workers.len();
+        let mut lemmas: Vec<Term> = vec![];
+        // The latest lemmas reported by each worker, either of its frame or
+        // of its fixpoint.
+        let mut frames: Vec<Option<Vec<Term>>> = vec![None; to_workers.len()];
+        // The checks in progress, and whether their lemmas have changed since
+        // they started.
+        let mut checks: HashMap<Check, bool> = HashMap::new();
+        let request_check =
+            |checks: &mut HashMap<Check, bool>, check, lemmas| match checks.get_mut(&check) {
+                Some(stale) => *stale = true,
+                None => {
+                    checks.insert(check, false);
+                    start_check(check, lemmas);
+                }
+            };
+        let mut canceled = false;
+        let mut aborted = false;
+        while running > 0 {
+            let event = match infer_cfg.deadline {
+                Some(deadline) if !canceled => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    match from_workers.recv_timeout(timeout) {
+                        Ok(event) => event,
+                        Err(mpsc::RecvTimeoutError::Timeout) => {
+                            log::info!("Deadline reached, canceling {running} workers");
+                            cancel(&mut to_workers);
+                            canceled = true;
+                            continue;
+                        }
+                        Err(mpsc::RecvTimeoutError::Disconnected) => {
+                            unreachable!("the coordinator holds a sender")
+                        }
+                    }
+                }
+                _ => from_workers.recv().expect("the coordinator holds a sender"),
+            };
+            let msg = match event {
+                Event::Message(msg) => msg,
+                Event::Checked(check, result) => {
+                    let stale = checks.remove(&check).unwrap();
+                    if canceled {
+                        continue;
+                    }
+                    match (check, result) {
+                        (Check::Fixpoints, CexResult::UnsatCore(_)) => {
+                            log::info!("Safety proven, canceling {running} workers");
+                            cancel(&mut to_workers);
+                            canceled = true;
+                        }
+                        (Check::Frames, CexResult::Cex(_)) => {
+                            log::info!("Safety cannot be proven, canceling {running} workers");
+                            cancel(&mut to_workers);
+                            canceled = true;
+                            aborted = true;
+                        }
+                        (Check::Fixpoints, _) if stale => {
+                            request_check(&mut checks, check, lemmas.clone())
+                        }
+                        (Check::Frames, _) if stale => {
+                            let current = frames.iter().flatten().flatten().cloned().collect();
+                            request_check(&mut checks, check, current)
+                        }
+                        _ => (),
+                    }
+                    continue;
+                }
+            };
+            match &msg {
+                Message::Sample { worker, .. } => {
+                    let tx = to_workers[*worker].take();
+                    broadcast(&mut to_workers, &msg);
+                    to_workers[*worker] = tx;
+                }
+                Message::Frame {
+                    worker,
+                    lemmas: frame,
+                } => {
+                    frames[*worker] = Some(frame.clone());
+                    if !canceled && frames.iter().all(|f| f.is_some()) {
+                        let current = frames.iter().flatten().flatten().cloned().collect();
+                        request_check(&mut checks, Check::Frames, current);
+                    }
+                }
+                Message::Fixpoint {
+                    worker,
+                    lemmas: new_lemmas,
+                } => {
+                    log::info!(
+                        "Worker {worker} reached a fixpoint ({} lemmas)",
+                        new_lemmas.len()
+                    );
+                    running -= 1;
+                    to_workers[*worker] = None;
+                    broadcast(&mut to_workers, &msg);
+                    lemmas.extend(new_lemmas.iter().cloned());
+                    frames[*worker] = Some(new_lemmas.clone());
+
+                    if infer_cfg.until_safe && !canceled && running > 0 {
+                        request_check(&mut checks, Check::Fixpoints, lemmas.clone());
+                    }
+                }
+                Message::Canceled { worker } => {
+                    log::info!("Worker {worker} canceled");
+                    running -= 1;
+                    to_workers[*worker] = None;
+                }
+                Message::Cancel => (),
+            }
+        }
+        // the result no longer depends on checks still in progress
+        check_cancelers.cancel();
+
+        if aborted {
+            return None;
+        }
+        Some(lemmas.into_iter().unique().collect())
+    })
+}
+
+/// Compute a subset of `proof` which is inductive and implies safety, by
+/// closing the core of the safety check under the cores of the inductiveness
+/// checks of its lemmas. Returns `None` if `proof` does not inductively imply
+/// safety (or `parent` is canceled), and all of `proof` if the inductiveness of
+/// one of its lemmas cannot be established.
+pub fn minimized_proof<S: BasicSolver>(
+    fo: &FOModule,
+    solver: &S,
+    proof: &[Term],
+    parent: Option<&FrameCancelers<S::Canceler>>,
+) -> Option<Vec<Term>> {
+    let cancelers = SolverCancelers::new();
+    if parent.is_some_and(|parent| !parent.add_canceler(cancelers.clone())) {
+        return None;
+    }
+    let CexResult::UnsatCore(safety_core) =
+        fo.trans_safe_cex(solver, proof, Some(cancelers.clone()))
+    else {
+        return None;
+    };
+    let core = close_core(safety_core, |id| {
+        match fo.trans_cex(
+            solver,
+            proof,
+            &proof[id],
+            false,
+            Some(cancelers.clone()),
+            false,
+        ) {
+            CexResult::UnsatCore(blocking) => Ok(blocking),
+            CexResult::Canceled => Err(None),
+            _ => Err(Some(proof.to_vec())),
+        }
+    });
+    match core {
+        Ok(core) => Some(
+            core.into_iter()
+                .sorted()
+                .map(|id| proof[id].clone())
+                .collect(),
+        ),
+        Err(proof) => proof,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::BufReader, os::unix::net::UnixStream, thread};
+
+    use fly::{
+        parser::{parse_signature, parse_term},
+        semantics::{Interpretation, Model},
+    };
+
+    use super::{JsonLines, Message, MessageReceiver, MessageSender};
+
+    #[test]
+    fn test_json_lines() {
+        let sig = parse_signature(
+            r#"
+            sort node
+            mutable leader(node): bool
+            immutable le(node, node): bool
+            "#,
+        );
+        let model = Model::new(
+            &sig,
+            &vec![2],
+            vec![
+                Interpretation::new(&[2, 2], |args| args[0]),
+                Interpretation::new(&[2, 2, 2], |args| (args[0] <= args[1]) as usize),
+            ],
+        );
+        let lemmas = vec![
+            parse_term("forall n:node. leader(n) -> le(n, n)").unwrap(),
+            parse_term("forall n1:node, n2:node. leader(n1) & leader(n2) -> n1 = n2").unwrap(),
+        ];
+        let messages = vec![
+            Message::Sample {
+                worker: 1,
+                sample: model,
+            },
+            Message::Frame {
+                worker: 0,
+                lemmas: lemmas.clone(),
+            },
+            Message::Fixpoint { worker: 2, lemmas },
+            Message::Canceled { worker: 3 },
+            Message::Cancel,
+        ];
+
+        let (worker, coordinator) = UnixStream::pair().unwrap();
+        let sent = messages.clone();
+        let sender = thread::spawn(move || {
+            let mut outbox = JsonLines::new(worker, &parse_signature(""));
+            for msg in &sent {
+                outbox.send(msg).unwrap();
+            }
+        });
+        let mut inbox = JsonLines::new(BufReader::new(coordinator), &sig);
+        for msg in &messages {
+            assert_eq!(inbox.recv().unwrap().as_ref(), Some(msg));
+        }
+        sender.join().unwrap();
+        assert_eq!(inbox.recv().unwrap(), None);
+    }
+}
@@ -0,0 +1,402 @@
+// Copyright 2022-2023 VMware, Inc.
+// SPDX-License-Identifier: BSD-2-Clause
+
+//! Implementation of PDR∀, a version of IC3/PDR which infers universally
+//! quantified inductive invariants. See
+//! <https://www.tau.ac.il/~sharonshoham/papers/jacm17.pdf>.
+//!
+//! Bad states are blocked using their diagrams, which are generalized into
+//! universal lemmas by a minimal unsat core over the conjuncts of the diagram.
+//! Each lemma is stored once, with the highest frame it is known to hold in, so
+//! frame `i` consists of the lemmas at level `i` or above. Every query uses a
+//! fresh solver.
+
+use std::collections::HashMap;
+
+use fly::{
+    semantics::Model,
+    syntax::*,
+    term::{prime::Next, simplify::nnf},
+    transitions::{extract_in_order, DestructuredModule, ExtractionError},
+};
+use itertools::Itertools;
+use solver::{conf::SolverConf, SatResp};
+
+/// The outcome of a PDR∀ search.
+#[derive(Debug, Clone)]
+pub enum PdrResult {
+    /// An inductive invariant which implies the safety properties.
+    Safe(Vec<Term>),
+    /// An abstract counterexample trace: a sequence of diagrams, starting from
+    /// an initial state and ending in a bad state, where each can reach the
+    /// next in one transition. There may be no concrete counterexample, but
+    /// there is no universal inductive invariant.
+    Unsafe(Vec<Term>),
+    /// The solver could not answer a query.
+    Unknown(String),
+}
+
+/// A diagram of a model, split into its existentially quantified variables and
+/// its conjuncts.
+#[derive(Debug, Clone)]
+pub(crate) struct Diagram {
+    pub(crate) binders: Vec<Binder>,
+    pub(crate) conjuncts: Vec<Term>,
+}
+
+impl Diagram {
+    pub(crate) fn new(model: &Model) -> Self {
+        match model.to_diagram() {
+            Term::Quantified {
+                quantifier: Quantifier::Exists,
+                binders,
+                body,
+            } => match *body {
+                Term::NAryOp(NOp::And, conjuncts) => Diagram { binders, conjuncts },
+                body => Diagram {
+                    binders,
+                    conjuncts: vec![body],
+                },
+            },
+            t => Diagram {
+                binders: vec![],
+                conjuncts: vec![t],
+            },
+        }
+    }
+
+    pub(crate) fn to_term(&self) -> Term {
+        Term::exists(self.binders.clone(), Term::and(self.conjuncts.clone()))
+    }
+
+    /// The lemma which excludes the states described by a subset of the
+    /// conjuncts of the diagram.
+    pub(crate) fn block(&self, core: &[usize]) -> Term {
+        let conjuncts = core
+            .iter()
+            .map(|&i| self.conjuncts[i].clone())
+            .collect_vec();
+        let binders = self
+            .binders
+            .iter()
+            .filter(|b| conjuncts.iter().any(|c| mentions(c, &b.name)))
+            .cloned()
+            .collect_vec();
+        Term::forall(
+            binders,
+            Term::or(conjuncts.iter().map(|c| nnf(&Term::not(c)))),
+        )
+    }
+}
+
+/// Whether the identifier `name` appears in `t`.
+fn mentions(t: &Term, name: &str) -> bool {
+    match t {
+        Term::Literal(_) => false,
+        Term::Id(s) => s == name,
+        Term::App(f, _, args) => f == name || args.iter().any(|a| mentions(a, name)),
+        Term::UnaryOp(_, t) => mentions(t, name),
+        Term::BinOp(_, lhs, rhs) => mentions(lhs, name) || mentions(rhs, name),
+        Term::NAryOp(_, ts) => ts.iter().any(|t| mentions(t, name)),
+        Term::Ite { cond, then, else_ } => {
+            mentions(cond, name) || mentions(then, name) || mentions(else_, name)
+        }
+        Term::Quantified { binders, body, .. } => {
+            binders.iter().all(|b| b.name != name) && mentions(body, name)
+        }
+    }
+}
+
+/// The result of a query whose last state is restricted to a diagram.
+enum QueryResp {
+    /// The query is satisfiable, with a model for each state.
+    Sat(Vec<Model>),
+    /// The query is unsatisfiable using only these conjuncts of the diagram.
+    Core(Vec<usize>),
+}
+
+struct Lemma {
+    term: Term,
+    /// The lemma holds in frames `1..=level`.
+    level: usize,
+}
+
+/// State for a PDR∀ invariant search.
+pub struct Pdr {
+    conf: SolverConf,
+    sig: Signature,
+    module: DestructuredModule,
+    lemmas: Vec<Lemma>,
+    /// The index of the last frame.
+    depth: usize,
+}
+
+impl Pdr {
+    /// Initialize a PDR∀ search for the assertions in `m`. Fails if an
+    /// assumption comes after an assertion.
+    pub fn new(conf: SolverConf, m: &Module) -> Result<Self, ExtractionError> {
+        Ok(Pdr {
+            conf,
+            sig: m.signature.clone(),
+            module: extract_in_order(m)?,
+            lemmas: vec![],
+            depth: 0,
+        })
+    }
+
+    /// The assertions of frame `i`. Frame 0 is the initial states.
+    fn frame(&self, i: usize) -> Vec<Term> {
+        if i == 0 {
+            return self.module.inits.clone();
+        }
+        self.lemmas
+            .iter()
+            .filter(|l| l.level >= i)
+            .map(|l| l.term.clone())
+            .collect()
+    }
+
+    fn safety(&self) -> Term {
+        Term::and(self.module.proofs.iter().map(|p| p.safety.x.clone()))
+    }
+
+    /// Check `assertions` over `n_states` states, restricting the last state
+    /// to `diagram`. The conjuncts of the diagram are guarded by indicators, so
+    /// that an unsat result gives a minimal subset of them.
+    fn query(
+        &self,
+        n_states: usize,
+        assertions: &[Term],
+        diagram: Option<&Diagram>,
+    ) -> Result<QueryResp, String> {
+        let next = Next::new(&self.sig);
+        let mut solver = self.conf.solver(&self.sig, n_states);
+        for a in &self.module.axioms {
+            solver.assert(a);
+            if n_states == 2 {
+                solver.assert(&next.prime(a));
+            }
+        }
+        for a in assertions {
+            solver.assert(a);
+        }
+        let mut indicators = HashMap::new();
+        let mut ind_to_conjunct = HashMap::new();
+        if let Some(diagram) = diagram {
+            let guarded = diagram
+                .conjuncts
+                .iter()
+                .enumerate()
+                .map(|(i, c)| {
+                    let ind = solver.get_indicator(&format!("c{i}"));
+                    indicators.insert(ind.clone(), true);
+                    ind_to_conjunct.insert(ind.clone(), i);
+                    let c = if n_states == 2 {
+                        next.prime(c)
+                    } else {
+                        c.clone()
+                    };
+                    Term::implies(ind, c)
+                })
+                .collect_vec();
+            solver.assert(&Term::exists(diagram.binders.clone(), Term::and(guarded)));
+        }
+        let resp = solver.check_sat(indicators).map_err(|e| e.to_string())?;
+        solver.save_tee();
+        match resp {
+            SatResp::Sat => {
+                let models = solver.get_minimal_model().map_err(|e| e.to_string())?;
+                Ok(QueryResp::Sat(models))
+            }
+            SatResp::Unsat if ind_to_conjunct.is_empty() => Ok(QueryResp::Core(vec![])),
+            SatResp::Unsat => {
+                let core = solver.get_minimal_unsat_core().map_err(|e| e.to_string())?;
+                Ok(QueryResp::Core(
+                    core.keys()
+                        .map(|ind| ind_to_conjunct[ind])
+                        .sorted()
+                        .collect(),
+                ))
+            }
+            SatResp::Unknown(reason) => Err(reason),
+        }
+    }
+
+    /// Find a bad state in frame `i`.
+    fn bad_state(&self, i: usize) -> Result<Option<Model>, String> {
+        let mut assertions = self.frame(i);
+        assertions.push(Term::negate(self.safety()));
+        match self.query(1, &assertions, None)? {
+            QueryResp::Sat(mut models) => Ok(Some(models.remove(0))),
+            QueryResp::Core(_) => Ok(None),
+        }
+    }
+
+    /// Check whether `lemma` holds after one transition from frame `i`.
+    fn holds_after(&self, i: usize, lemma: &Term) -> Result<bool, String> {
+        let mut assertions = self.frame(i);
+        assertions.extend(self.module.transitions.iter().cloned());
+        assertions.push(Term::negate(Next::new(&self.sig).prime(lemma)));
+        Ok(matches!(
+            self.query(2, &assertions, None)?,
+            QueryResp::Core(_)
+        ))
+    }
+
+    /// Learn a lemma in frame `i` and push it forward as far as possible.
+    fn add_lemma(&mut self, term: Term, mut level: usize) -> Result<(), String> {
+        while level < self.depth && self.holds_after(level, &term)? {
+            level += 1;
+        }
+        log::info!("learned lemma in frame {level}: {term}");
+        match self.lemmas.iter_mut().find(|l| l.term == term) {
+            Some(lemma) => lemma.level = lemma.level.max(level),
+            None => self.lemmas.push(Lemma { term, level }),
+        }
+        Ok(())
+    }
+
+    /// Block a state in frame `i`, recursively blocking its predecessors.
+    /// Returns an abstract counterexample trace if the state is reachable from
+    /// an initial state in the abstraction.
+    fn block(&mut self, model: &Model, i: usize) -> Result<Option<Vec<Term>>, String> {
+        let mut obligations = vec![(Diagram::new(model), i)];
+        while let Some((diagram, i)) = obligations.last().cloned() {
+            log::debug!("blocking in frame {i}: {}", diagram.to_term());
+            let init_core = match self.query(1, &self.module.inits, Some(&diagram))? {
+                QueryResp::Sat(_) => {
+                    let trace = obligations.iter().rev().map(|(d, _)| d.to_term());
+                    return Ok(Some(trace.collect()));
+                }
+                QueryResp::Core(core) => core,
+            };
+            let mut assertions = self.frame(i - 1);
+            assertions.extend(self.module.transitions.iter().cloned());
+            match self.query(2, &assertions, Some(&diagram))? {
+                QueryResp::Sat(models) => obligations.push((Diagram::new(&models[0]), i - 1)),
+                QueryResp::Core(trans_core) => {
+                    let core = init_core
+                        .into_iter()
+                        .chain(trans_core)
+                        .sorted()
+                        .dedup()
+                        .collect_vec();
+                    log::debug!(
+                        "generalized diagram from {} to {} conjuncts",
+                        diagram.conjuncts.len(),
+                        core.len()
+                    );
+                    self.add_lemma(diagram.block(&core), i)?;
+                    obligations.pop();
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Push lemmas forward, returning the index of a frame that is equal to
+    /// the next one (and is therefore inductive), if there is one.
+    fn propagate(&mut self) -> Result<Option<usize>, String> {
+        for i in 1..self.depth {
+            for idx in 0..self.lemmas.len() {
+                if self.lemmas[idx].level == i && self.holds_after(i, &self.lemmas[idx].term)? {
+                    self.lemmas[idx].level = i + 1;
+                }
+            }
+            if self.lemmas.iter().all(|l| l.level != i) {
+                return Ok(Some(i));
+            }
+        }
+        Ok(None)
+    }
+
+    fn log_frames(&self) {
+        for i in 1..=self.depth {
+            log::info!(
+                "frame {i}: {} lemmas ({} new)",
+                self.lemmas.iter().filter(|l| l.level >= i).count(),
+                self.lemmas.iter().filter(|l| l.level == i).count()
+            );
+        }
+    }
+
+    fn run(&mut self) -> Result<PdrResult, String> {
+        if let Some(model) = self.bad_state(0)? {
+            return Ok(PdrResult::Unsafe(vec![Diagram::new(&model).to_term()]));
+        }
+        self.depth = 1;
+        loop {
+            while let Some(model) = self.bad_state(self.depth)? {
+                if let Some(trace) = self.block(&model, self.depth)? {
+                    log::info!("found abstract counterexample");
+                    return Ok(PdrResult::Unsafe(trace));
+                }
+            }
+            self.depth += 1;
+            log::info!("added frame {}", self.depth);
+            if let Some(i) = self.propagate()? {
+                log::info!("frame {i} is inductive");
+                let invariant = self
+                    .lemmas
+                    .iter()
+                    .filter(|l| l.level > i)
+                    .map(|l| l.term.clone())
+                    .collect();
+                return Ok(PdrResult::Safe(invariant));
+            }
+            self.log_frames();
+        }
+    }
+
+    /// Search for a universal inductive invariant.
+    pub fn search(&mut self) -> PdrResult {
+        self.run().unwrap_or_else(PdrResult::Unknown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use solver::{
+        backends::{GenericBackend, SolverType},
+        conf::SolverConf,
+        solver_path,
+    };
+
+    use super::{Pdr, PdrResult};
+
+    fn conf() -> SolverConf {
+        SolverConf {
+            backend: GenericBackend::new(SolverType::Z3, &solver_path("z3")),
+            tee: None,
+            transcript: false,
+        }
+    }
+
+    #[test]
+    fn test_pdr_lockserver() {
+        let file = fs::read_to_string("../temporal-verifier/examples/lockserver.fly")
+            .expect("could not read input");
+        let mut m = fly::parser::parse(&file).expect("parse error");
+        fly::sorts::sort_check_module(&mut m).expect("sort error");
+        m.inline_defs();
+        match Pdr::new(conf(), &m).unwrap().search() {
+            PdrResult::Safe(invariant) => assert!(!invariant.is_empty()),
+            r => panic!("expected an invariant, got {r:?}"),
+        }
+    }
+
+    #[test]
+    fn test_pdr_unsafe() {
+        let file = fs::read_to_string("../temporal-verifier/tests/examples/lockserver_buggy.fly")
+            .expect("could not read input");
+        let mut m = fly::parser::parse(&file).expect("parse error");
+        fly::sorts::sort_check_module(&mut m).expect("sort error");
+        m.inline_defs();
+        assert!(matches!(
+            Pdr::new(conf(), &m).unwrap().search(),
+            PdrResult::Unsafe(_)
+        ));
+    }
+}
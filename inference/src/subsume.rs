@@ -734,6 +734,7 @@ impl OrderSubsumption for Literal {
 }
 
 #[derive(Clone)]
+#[allow(dead_code)]
 pub struct LiteralSubsumptionMap<V>(HashMap<usize, V>);
 
 #[cfg(test)]
@@ -12,6 +12,8 @@ use crate::basics::{CexOrCore, CexResult, FOModule, TermOrModel};
 use fly::syntax::Term::{NAryOp, Quantified, UnaryOp};
 use fly::syntax::*;
 use fly::term::cnf::term_to_cnf_clauses;
+use fly::term::simplify::nnf;
+use fly::transitions::ExtractionError;
 
 #[derive(Debug, Clone)]
 struct Frame {
@@ -24,6 +26,18 @@ impl Frame {
     }
 }
 
+/// The outcome of a UPDR search.
+#[derive(Debug, Clone)]
+pub enum UpdrResult {
+    /// An inductive invariant which implies the safety properties.
+    Safe(Vec<Term>),
+    /// An abstract counterexample trace: a sequence of diagrams, starting from
+    /// an initial state and ending in a bad state, where each can reach the
+    /// next in one transition. There may be no concrete counterexample, but
+    /// there is no universal inductive invariant.
+    Unsafe(Vec<Term>),
+}
+
 struct BackwardsReachableState {
     id: usize,
     term_or_model: TermOrModel,
@@ -51,9 +65,7 @@ impl Updr {
     }
 
     fn find_state_to_block(&mut self, module: &FOModule) -> Option<usize> {
-        // println!("start");
         loop {
-            // println!("loop");
             // Search for a known state.
             let bstate_min = self.backwards_reachable_states.iter_mut().min_by(|b1, b2| {
                 (b1.known_absent_until_frame, b1.num_steps_to_bad)
@@ -62,13 +74,11 @@ impl Updr {
             if bstate_min.is_none()
                 || bstate_min.as_ref().unwrap().known_absent_until_frame == self.frames.len() - 1
             {
-                // println!("break for no bstates");
                 break;
             }
             let found_state = bstate_min.unwrap();
             match &found_state.term_or_model {
                 TermOrModel::Term(t) => {
-                    // println!("m: {}", t);
                     if module
                         .implies_cex(
                             self.solver.as_conf(),
@@ -77,12 +87,10 @@ impl Updr {
                         )
                         .is_some()
                     {
-                        // println!("returning for term implies");
                         return Some(found_state.id);
                     }
                 }
                 TermOrModel::Model(m) => {
-                    // println!("m: {}", m.to_diagram());
                     if m.eval(&NAryOp(
                         NOp::And,
                         self.frames[found_state.known_absent_until_frame + 1]
@@ -90,7 +98,6 @@ impl Updr {
                             .clone(),
                     )) != 0
                     {
-                        // println!("returning for model eval");
                         return Some(found_state.id);
                     }
                 }
@@ -98,21 +105,14 @@ impl Updr {
             // The state does not appear in this frame.
             found_state.known_absent_until_frame += 1;
         }
-        // println!("broke");
 
         // Search for a new state.
         let last_frame = self.frames.last().unwrap();
-        // println!("last_frame.terms {}", &last_frame.terms[0]);
         let counter_example = module.safe_cex(self.solver.as_conf(), &last_frame.terms);
         if module.module.proofs.is_empty() || counter_example.is_none() {
-            // println!("None");
             // Nothing to block.
             return None;
         }
-        // println!(
-        //     "counter_example: {}",
-        //     &counter_example.as_ref().unwrap().to_diagram()
-        // );
         let new_state = BackwardsReachableState {
             id: self.backwards_reachable_states.len(),
             term_or_model: TermOrModel::Model(counter_example.unwrap()),
@@ -124,36 +124,40 @@ impl Updr {
         Some(self.backwards_reachable_states.len() - 1)
     }
 
-    fn establish_safety(&mut self, module: &FOModule) {
+    /// Block all known backwards reachable states, returning an abstract
+    /// counterexample trace if one of them cannot be blocked.
+    fn establish_safety(&mut self, module: &FOModule) -> Result<(), Vec<Term>> {
         while let Some(state_index) = self.find_state_to_block(module) {
-            // println!("got ID: {}", &state_index);
             self.currently_blocking_id = Some(state_index);
-            {
-                let bstate = &self.backwards_reachable_states[state_index];
-                let mut trace: Vec<(Term, TermOrModel)> = vec![];
-                self.block(
-                    &bstate.term_or_model.clone(),
-                    &bstate.known_absent_until_frame + 1,
-                    &mut trace,
-                    module,
-                );
-            }
+            let bstate = &self.backwards_reachable_states[state_index];
+            log::debug!(
+                "blocking state {state_index} ({} steps to bad) in frame {}",
+                bstate.num_steps_to_bad,
+                bstate.known_absent_until_frame + 1
+            );
+            self.block(
+                &bstate.term_or_model.clone(),
+                bstate.known_absent_until_frame + 1,
+                module,
+            )?;
             self.backwards_reachable_states[state_index].known_absent_until_frame += 1;
         }
+        Ok(())
     }
 
+    /// Block a state in the frame `frame_index` and all frames before it. If
+    /// the state is reachable from an initial state in the abstraction, returns
+    /// the trace from the initial state to it.
     fn block(
         &mut self,
         term_or_model: &TermOrModel,
         frame_index: usize,
-        trace: &mut Vec<(Term, TermOrModel)>,
         module: &FOModule,
-    ) {
+    ) -> Result<(), Vec<Term>> {
         let as_term: Term = match term_or_model {
             TermOrModel::Term(t) => t.clone(),
             TermOrModel::Model(m) => m.to_diagram(),
         };
-        // println!("blocking as term: {} at index {}", as_term, frame_index);
         if frame_index == 0
             || (frame_index == 1
                 && module
@@ -164,11 +168,12 @@ impl Updr {
                     )
                     .is_some())
         {
-            panic!("abstract cex");
+            log::info!("found abstract counterexample");
+            return Err(vec![as_term]);
         }
         let core = loop {
             match self.get_predecessor(term_or_model, frame_index - 1, module) {
-                CexOrCore::Cex((trans, pred)) => {
+                CexOrCore::Cex((_, pred)) => {
                     let src = &self.backwards_reachable_states[self.currently_blocking_id.unwrap()];
                     let steps_from_cex =
                         src.known_absent_until_frame + 2 - frame_index + src.num_steps_to_bad;
@@ -178,169 +183,91 @@ impl Updr {
                         known_absent_until_frame: 0,
                         num_steps_to_bad: steps_from_cex,
                     };
-                    if let TermOrModel::Model(m) = bstate.term_or_model.clone() {
-                        println!("managed to reach {m}");
-                    }
-                    if let TermOrModel::Term(t) = bstate.term_or_model.clone() {
-                        println!("managed to reach {t}");
-                    }
+                    log::debug!("found predecessor in frame {}: {pred}", frame_index - 1);
                     self.backwards_reachable_states.push(bstate);
-                    trace.push((trans.clone(), TermOrModel::Model(pred.clone())));
-                    self.block(&TermOrModel::Model(pred), frame_index - 1, trace, module);
-                    trace.pop();
+                    if let Err(mut trace) =
+                        self.block(&TermOrModel::Model(pred), frame_index - 1, module)
+                    {
+                        trace.push(as_term);
+                        return Err(trace);
+                    }
                 }
                 CexOrCore::Core(core_map) => break core_map,
             }
         };
-        // println!("CORE");
         let mut terms: Vec<Term> = vec![];
         for key in core.keys().sorted() {
-            // println!("{}", key);
             if let UnaryOp(UOp::Next, t) = key.clone() {
                 terms.push(*t);
             } else {
                 terms.push(key.clone());
             }
         }
-        // println!("END CORE");
-        if let Quantified {
-            quantifier: Quantifier::Exists,
-            mut body,
-            binders,
-        } = as_term
-        {
-            if !&terms.is_empty() {
-                body = Box::new(NAryOp(NOp::And, terms));
-            }
-            let negated = Term::negate(Quantified {
+        let blocked = match as_term {
+            // generalize a diagram to the part of it in the unsat core
+            Quantified {
                 quantifier: Quantifier::Exists,
                 body,
                 binders,
-            });
-            // println!("blocking in {} term: {}", frame_index, &negated);
-            for i in 0..(frame_index + 1) {
-                if self.frames[i].terms.contains(&negated) {
-                    continue;
-                }
-                self.frames[i].strengthen(negated.clone());
-            }
-            'push_frames: for i in frame_index..(self.frames.len() - 1) {
-                let prev_terms = self.frames[i].terms.clone();
-                if self.frames[i + 1].terms.contains(&negated) {
-                    continue;
-                }
-                if let CexResult::UnsatCore(_) = module.trans_cex(
-                    self.solver.as_ref(),
-                    &prev_terms,
-                    &negated,
-                    true,
-                    None,
-                    true,
-                ) {
-                    self.frames[i + 1].strengthen(negated.clone());
+            } => Quantified {
+                quantifier: Quantifier::Exists,
+                body: if terms.is_empty() {
+                    body
                 } else {
-                    break 'push_frames;
-                }
+                    Box::new(NAryOp(NOp::And, terms))
+                },
+                binders,
+            },
+            // a state without quantifiers (such as the negation of a
+            // quantifier-free safety property), which is blocked as is
+            term => term,
+        };
+        let negated = Term::negate(blocked);
+        for i in 0..(frame_index + 1) {
+            if self.frames[i].terms.contains(&negated) {
+                continue;
+            }
+            self.frames[i].strengthen(negated.clone());
+        }
+        'push_frames: for i in frame_index..(self.frames.len() - 1) {
+            let prev_terms = self.frames[i].terms.clone();
+            if self.frames[i + 1].terms.contains(&negated) {
+                continue;
+            }
+            if let CexResult::UnsatCore(_) = module.trans_cex(
+                self.solver.as_ref(),
+                &prev_terms,
+                &negated,
+                true,
+                None,
+                true,
+            ) {
+                self.frames[i + 1].strengthen(negated.clone());
+            } else {
+                break 'push_frames;
             }
-            // println!("frames in block:");
-            // self.print_frames();
-        } else {
-            panic!()
         }
+        Ok(())
     }
 
-    #[allow(clippy::let_and_return)]
     fn get_predecessor(
         &mut self,
         term_or_model: &TermOrModel,
         frame_index: usize,
         module: &FOModule,
     ) -> CexOrCore {
-        // run UPDR
         let prev_frame = &self.frames[frame_index];
-        let out = module.get_pred(self.solver.as_conf(), &prev_frame.terms, term_or_model);
-
-        // if let TermOrModel::Model(model) = term_or_model {
-        //     if let CexOrCore::Core(out) = &out {
-        //         // run MARCO on cores without affecting UPDR
-        //         if let Term::Quantified {
-        //             quantifier,
-        //             binders,
-        //             body,
-        //         } = &model.to_diagram()
-        //         {
-        //             if *quantifier == Quantifier::Exists {
-        //                 if let Term::NAryOp(NOp::And, conj) = &**body {
-        //                     let func = |array: &[bool]| {
-        //                         assert!(array.len() == conj.len());
-        //                         // only enable terms in conj that match array
-        //                         let chosen: Vec<Term> = (0..conj.len())
-        //                             .filter(|i| array[*i])
-        //                             .map(|i| conj[i].clone())
-        //                             .collect();
-        //                         let term = Term::Quantified {
-        //                             quantifier: Quantifier::Exists,
-        //                             binders: binders.clone(),
-        //                             body: Box::new(Term::NAryOp(NOp::And, chosen)),
-        //                         };
-        //                         let term = TermOrModel::Term(term);
-        //                         let out =
-        //                             module.get_pred(&self.solver_conf, &prev_frame.terms, &term);
-        //                         matches!(out, CexOrCore::Core(_))
-        //                     };
-
-        //                     use crate::inference::marco::*;
-        //                     // we use !func because func is monotone in the wrong direction
-        //                     let results: Vec<_> = marco(|array| !func(array), conj.len())
-        //                         // therefore we also want MUS instead of MSS
-        //                         .filter_map(|mss_or_mus| match mss_or_mus {
-        //                             MssOrMus::Mus(array) => Some(array),
-        //                             MssOrMus::Mss(_) => None,
-        //                         })
-        //                         .collect();
-
-        //                     println!("\nunsat core:");
-        //                     for (term, b) in out.iter().sorted() {
-        //                         if *b {
-        //                             println!("{}", term);
-        //                         }
-        //                     }
-
-        //                     println!("\nMARCO: {} cores", results.len());
-        //                     for (i, array) in results.iter().enumerate() {
-        //                         println!("core {}:", i);
-        //                         for (i, b) in array.iter().enumerate() {
-        //                             if *b {
-        //                                 println!("{}", conj[i]);
-        //                             }
-        //                         }
-        //                     }
-        //                     println!("\n");
-        //                 } else {
-        //                     panic!("MARCO: bad diagram");
-        //                 }
-        //             } else {
-        //                 panic!("MARCO: bad diagram");
-        //             }
-        //         } else {
-        //             panic!("MARCO: bad diagram");
-        //         }
-        //     }
-        // }
-
-        // return the actual UPDR output
-        out
+        module.get_pred(self.solver.as_conf(), &prev_frame.terms, term_or_model)
     }
 
-    fn find_frame(&mut self, m: &Module) -> Option<Frame> {
-        let module = FOModule::new(m, false, false, false);
+    fn find_frame(&mut self, module: &FOModule) -> Result<Frame, Vec<Term>> {
         self.backwards_reachable_states = Vec::new();
         for proof in &module.module.proofs {
             for clause in term_to_cnf_clauses(&proof.safety.x) {
                 self.backwards_reachable_states
                     .push(BackwardsReachableState {
                         id: self.backwards_reachable_states.len(),
-                        term_or_model: TermOrModel::Term(Term::negate_and_simplify(clause)),
+                        term_or_model: TermOrModel::Term(nnf(&Term::not(clause))),
                         num_steps_to_bad: 0,
                         known_absent_until_frame: 0,
                     })
@@ -355,39 +282,38 @@ impl Updr {
                 .flat_map(|t| -> Vec<Term> {
                     match t {
                         NAryOp(NOp::And, terms) => terms,
-                        _ => panic!("got malformed inits"),
+                        t => vec![t],
                     }
                 })
                 .collect(),
         }];
-        // println!("{}", &module.safeties[0]);
-        // println!("{}", backwards_reachable_states[0].term);
-        // println!("{}", frames[0].terms[0]);
-        // Some(frames[0].clone())
+        // A bad initial state cannot be blocked, and backwards reachable
+        // states are only tracked once there are two frames.
+        if let Some(model) = module.safe_cex(self.solver.as_conf(), &self.frames[0].terms) {
+            log::info!("found abstract counterexample");
+            return Err(vec![model.to_diagram()]);
+        }
         loop {
-            // println!("establish_safety");
-            self.establish_safety(&module);
-            self.print_frames();
-            // println!("simplify");
-            self.simplify(&module);
-            let inductive_frame: Option<Frame> = self.get_inductive_frame(&module);
-            if inductive_frame.is_some() {
-                println!("inductive_frame");
-                for t in &inductive_frame.as_ref().unwrap().terms {
-                    println!("{t}");
-                }
-                return inductive_frame;
+            self.establish_safety(module)?;
+            self.simplify(module);
+            self.log_frames();
+            if let Some(frame) = self.get_inductive_frame(module) {
+                log::info!("found inductive frame with {} terms", frame.terms.len());
+                return Ok(frame);
             }
-            // println!("add_frame_and_push");
-            self.add_frame_and_push(&module);
-            self.print_frames();
+            self.add_frame_and_push(module);
+            log::info!("added frame {}", self.frames.len() - 1);
         }
     }
 
-    /// Search for an inductive invariant.
-    pub fn search(&mut self, m: &Module) -> Option<Term> {
-        // TODO: is the and of the terms correct?
-        self.find_frame(m).map(|t| Term::and(t.terms))
+    /// Search for an inductive invariant. Fails if an assumption comes after
+    /// an assertion.
+    pub fn search(&mut self, m: &Module) -> Result<UpdrResult, ExtractionError> {
+        let module = FOModule::new(m, false, false, false)?;
+        Ok(match self.find_frame(&module) {
+            Ok(frame) => UpdrResult::Safe(frame.terms),
+            Err(trace) => UpdrResult::Unsafe(trace),
+        })
     }
 
     fn simplify(&mut self, module: &FOModule) {
@@ -412,12 +338,6 @@ impl Updr {
                 {
                     terms.push(term.clone())
                 } else {
-                    // println!("imp cex t: {}, {}, {}", &term, f_minus_t.len(), frame.terms.len());
-                    // for h in &f_minus_t {
-                    //     print!(" {} ", h)
-                    // }
-                    // println!("");
-                    // println!("removing in {} simplify: {}", i, &term);
                     removed.insert(term.clone());
                 }
             }
@@ -442,27 +362,26 @@ impl Updr {
         }
     }
 
-    fn print_frames(&self) {
-        println!("all frames:");
-        for frame in self.frames.iter() {
-            print!("[");
-            for term in frame.terms.iter() {
-                print!("{term}, ");
+    fn log_frames(&self) {
+        for (i, frame) in self.frames.iter().enumerate() {
+            log::info!("frame {i}: {} terms", frame.terms.len());
+            for term in &frame.terms {
+                log::debug!("    {term}");
             }
-            println!("]");
         }
-        println!("all BRS:");
-        for state in self.backwards_reachable_states.iter() {
-            print!(
-                "term: {} ",
-                match state.term_or_model.clone() {
-                    TermOrModel::Term(t) => t,
+        log::info!(
+            "{} backwards reachable states",
+            self.backwards_reachable_states.len()
+        );
+        for state in &self.backwards_reachable_states {
+            log::debug!(
+                "    {} (known absent until frame {}, {} steps to bad)",
+                match &state.term_or_model {
+                    TermOrModel::Term(t) => t.clone(),
                     TermOrModel::Model(m) => m.to_diagram(),
-                }
-            );
-            println!(
-                "known_absent_until_frame: {}, num_steps_to_bad : {}",
-                state.known_absent_until_frame, state.num_steps_to_bad
+                },
+                state.known_absent_until_frame,
+                state.num_steps_to_bad
             );
         }
     }
@@ -470,7 +389,6 @@ impl Updr {
     fn get_inductive_frame(&self, module: &FOModule) -> Option<Frame> {
         for i in 0..(self.frames.len() - 1) {
             let mut is_inductive = true;
-            // println!("checking inductiveness of frame {}", i);
             for term in &self.frames[i].terms {
                 if module
                     .implies_cex(self.solver.as_conf(), &self.frames[i + 1].terms, term)
@@ -486,3 +404,63 @@ impl Updr {
         return None;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::Arc};
+
+    use solver::{
+        backends::{GenericBackend, SolverType},
+        basics::SingleSolver,
+        conf::SolverConf,
+        solver_path,
+    };
+
+    use super::{Updr, UpdrResult};
+
+    fn updr() -> Updr {
+        Updr::new(Arc::new(SingleSolver::new(SolverConf {
+            backend: GenericBackend::new(SolverType::Z3, &solver_path("z3")),
+            tee: None,
+            transcript: false,
+        })))
+    }
+
+    fn module(source: &str) -> fly::syntax::Module {
+        let mut m = fly::parser::parse(source).expect("parse error");
+        fly::sorts::sort_check_module(&mut m).expect("sort error");
+        m.inline_defs();
+        m
+    }
+
+    #[test]
+    fn test_updr_lockserver() {
+        let file = fs::read_to_string("../temporal-verifier/examples/lockserver.fly")
+            .expect("could not read input");
+        match updr().search(&module(&file)).unwrap() {
+            UpdrResult::Safe(invariant) => assert!(!invariant.is_empty()),
+            r => panic!("expected an invariant, got {r:?}"),
+        }
+    }
+
+    #[test]
+    fn test_updr_unsafe_init() {
+        let m = module(
+            "mutable p: bool\nmutable q: bool\nassume p & !q\nassume always p'=(p|q) & q'=q\nassert always !p & !q\n",
+        );
+        match updr().search(&m).unwrap() {
+            UpdrResult::Unsafe(trace) => assert_eq!(trace.len(), 1),
+            r => panic!("expected a counterexample, got {r:?}"),
+        }
+    }
+
+    #[test]
+    fn test_updr_unsafe() {
+        let file = fs::read_to_string("../temporal-verifier/tests/examples/lockserver_buggy.fly")
+            .expect("could not read input");
+        match updr().search(&module(&file)).unwrap() {
+            UpdrResult::Unsafe(trace) => assert!(trace.len() > 1),
+            r => panic!("expected a counterexample, got {r:?}"),
+        }
+    }
+}
@@ -19,7 +19,7 @@ use crate::{
     quant::{QuantifierConfig, QuantifierPrefix},
     subsume::{OrderSubsumption, SubsumptionMap},
 };
-use fly::term::subst::Substitution;
+use fly::term::subst::{canonicalize, Substitution};
 use fly::{
     semantics::{Assignment, Model},
     syntax::{Quantifier, Term},
@@ -618,6 +618,11 @@ where
     to_prefixes: HashMap<usize, Arc<QuantifierPrefix>>,
     to_bodies: HashMap<usize, O>,
     bodies: O::Map<HashSet<usize>>,
+    /// The lemma with each canonical form, so that alpha-equivalent lemmas are
+    /// only inserted once.
+    canonical: HashMap<Term, usize>,
+    /// The canonical form of each lemma.
+    to_canonical: HashMap<usize, Term>,
     next: usize,
 }
 
@@ -640,6 +645,8 @@ where
             to_prefixes: HashMap::default(),
             to_bodies: HashMap::default(),
             bodies: O::Map::new(),
+            canonical: HashMap::default(),
+            to_canonical: HashMap::default(),
             next: 0,
         }
     }
@@ -651,6 +658,8 @@ where
             to_prefixes: HashMap::default(),
             to_bodies: HashMap::default(),
             bodies: O::Map::new(),
+            canonical: HashMap::default(),
+            to_canonical: HashMap::default(),
             next: 0,
         }
     }
@@ -696,11 +705,25 @@ where
             .sorted_by_key(|(prefix, _, _)| (prefix.existentials(), prefix.num_vars()))
     }
 
+    /// The canonical form of a lemma, which is the same for alpha-equivalent
+    /// lemmas.
+    fn canonical_form(&self, prefix: &QuantifierPrefix, body: &O) -> Term {
+        canonicalize(&prefix.quantify(self.body_to_term(body)))
+    }
+
+    /// Get the ID of a lemma. This is also the ID of every lemma that was
+    /// inserted as an alias of it (see [`LemmaSet::insert`]).
     pub fn get_id(&self, prefix: &QuantifierPrefix, body: &O) -> Option<usize> {
-        let ids = self.bodies.get(body)?;
-        ids.iter()
-            .copied()
-            .find(|id| self.to_prefixes[id].contains(prefix))
+        let id = self.bodies.get(body).and_then(|ids| {
+            ids.iter()
+                .copied()
+                .find(|id| self.to_prefixes[id].contains(prefix))
+        });
+        id.or_else(|| {
+            self.canonical
+                .get(&self.canonical_form(prefix, body))
+                .copied()
+        })
     }
 
     pub fn subsumes(&self, prefix: &QuantifierPrefix, body: &O) -> bool {
@@ -761,10 +784,20 @@ where
         subsumed
     }
 
+    /// Insert a lemma and return its ID. If an alpha-equivalent lemma is
+    /// already in the set, nothing is inserted and its ID is returned instead,
+    /// making the new lemma an alias of it.
     pub fn insert(&mut self, prefix: Arc<QuantifierPrefix>, body: O) -> usize {
+        let canonical = self.canonical_form(&prefix, &body);
+        if let Some(id) = self.canonical.get(&canonical) {
+            return *id;
+        }
+
         let id = self.next;
         self.next += 1;
 
+        self.canonical.insert(canonical.clone(), id);
+        self.to_canonical.insert(id, canonical);
         self.to_prefixes.insert(id, prefix);
         self.to_bodies.insert(id, body.clone());
         if let Some(hs) = self.bodies.get_mut(&body) {
@@ -789,6 +822,8 @@ where
     }
 
     pub fn remove(&mut self, id: &usize) {
+        let canonical = self.to_canonical.remove(id).unwrap();
+        self.canonical.remove(&canonical);
         self.to_prefixes.remove(id).unwrap();
         let body = self.to_bodies.remove(id).unwrap();
         let hs = self.bodies.get_mut(&body).unwrap();
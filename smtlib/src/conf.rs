@@ -3,6 +3,11 @@
 
 //! Construct launch and option configurations for Z3 and CVC5.
 
+/// The logic used when none is specified. This is quite permissive, since it
+/// includes arithmetic which we never use, but it works with all supported
+/// solvers.
+pub const DEFAULT_LOGIC: &str = "UFNIA";
+
 /// The full invocation of a solver binary.
 #[derive(Debug, Clone)]
 pub struct SolverCmd {
@@ -12,6 +17,10 @@ pub struct SolverCmd {
     pub args: Vec<String>,
     /// SMT options to send on startup
     pub options: Vec<(String, String)>,
+    /// SMT-LIB logic to set on startup
+    pub logic: String,
+    /// Limit on the solver's address space, in megabytes
+    pub memory_limit_mb: Option<usize>,
 }
 
 impl SolverCmd {
@@ -30,6 +39,16 @@ impl SolverCmd {
             .push((name.to_string(), val.as_ref().to_string()));
     }
 
+    /// Set the SMT-LIB logic (for example, `UF` or `UFNIA`).
+    pub fn set_logic<S: AsRef<str>>(&mut self, logic: S) {
+        self.logic = logic.as_ref().to_string();
+    }
+
+    /// Limit the memory the solver process can use. None sets no limit.
+    pub fn set_memory_limit_mb(&mut self, mb: Option<usize>) {
+        self.memory_limit_mb = mb;
+    }
+
     /// Build the command line string, for printing purposes.
     pub fn cmdline(&self) -> String {
         #[allow(clippy::useless_format)]
@@ -59,6 +78,8 @@ impl Z3Conf {
             cmd: cmd.to_string(),
             args: vec![],
             options: vec![],
+            logic: DEFAULT_LOGIC.to_string(),
+            memory_limit_mb: None,
         };
         cmd.args(["-in", "-smt2"]);
         cmd.option("model.completion", "true");
@@ -79,6 +100,11 @@ impl Z3Conf {
         self.0.option("timeout", format!("{ms}"));
     }
 
+    /// Set a deterministic resource limit per query. None sets no limit.
+    pub fn rlimit(&mut self, rlimit: Option<usize>) {
+        self.0.option("rlimit", format!("{}", rlimit.unwrap_or(0)));
+    }
+
     /// Get access to the raw options of the solver.
     pub fn options(&mut self) -> &mut SolverCmd {
         &mut self.0
@@ -103,6 +129,8 @@ impl CvcConf {
             cmd: cmd.to_string(),
             args: vec![],
             options: vec![],
+            logic: DEFAULT_LOGIC.to_string(),
+            memory_limit_mb: None,
         };
         // for CVC4, --lang smt2 is needed when using stdin, but when run on a
         // file with a .smt2 extension it will automatically use the right input
@@ -150,6 +178,12 @@ impl CvcConf {
         self.cmd.option("tlimit-per", format!("{ms}"));
     }
 
+    /// Set a deterministic resource limit per query. None sets no limit.
+    pub fn rlimit(&mut self, rlimit: Option<usize>) {
+        self.cmd
+            .option("rlimit-per", format!("{}", rlimit.unwrap_or(0)));
+    }
+
     /// Get access to the raw options of the solver.
     pub fn options(&mut self) -> &mut SolverCmd {
         &mut self.cmd
@@ -24,3 +24,4 @@ pub mod path;
 pub mod proc;
 pub mod sexp;
 mod tee;
+pub mod transcript;
@@ -10,13 +10,19 @@
 use crate::conf::SolverCmd;
 use crate::sexp;
 use crate::tee::Tee;
-use nix::{errno::Errno, sys::signal, unistd::Pid};
+use nix::{
+    errno::Errno,
+    sys::{resource, signal},
+    unistd::Pid,
+};
 use std::{
     ffi::{OsStr, OsString},
     io::{self, BufRead, BufReader, ErrorKind, Write},
+    os::unix::process::CommandExt,
     path::{Path, PathBuf},
     process::{Child, ChildStdin, ChildStdout, Command, Stdio},
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 use thiserror::Error;
 
@@ -47,6 +53,9 @@ pub struct SmtProc {
     stdin: ChildStdin,
     stdout: BufReader<ChildStdout>,
     tee: Option<Tee>,
+    // whether the solver runs with a memory limit, so that being killed may be
+    // the result of exceeding it
+    memory_limited: bool,
     // signal to SmtPids that this process has terminated (so we don't try to
     // kill the process long afterward when the pid might have been reused)
     terminated: Arc<Mutex<Status>>,
@@ -88,9 +97,15 @@ pub enum SolverError {
     /// Could not minimize
     #[error("could not minimize:\n{0}")]
     CouldNotMinimize(String),
+    /// The model returned by the solver could not be parsed or evaluated
+    #[error("could not interpret model:\n{0}")]
+    CouldNotInterpretModel(String),
     /// Solver returned an `(error ...)` response
     #[error("solver returned an error:\n{0}")]
     UnexpectedClose(String),
+    /// Solver ran out of memory or exceeded its resource limit
+    #[error("solver exceeded a resource limit: {0}")]
+    ResourceLimit(String),
     /// Solver killed specifically by SIGKILL signal
     #[error("solver was killed")]
     Killed,
@@ -146,13 +161,24 @@ impl SmtProc {
     pub fn new(mut cmd: SolverCmd, tee: Option<&Path>) -> Result<Self> {
         cmd.option("produce-models", "true");
         cmd.option("produce-unsat-assumptions", "true");
-        let mut child = Command::new(OsStr::new(&cmd.cmd))
+        let mut command = Command::new(OsStr::new(&cmd.cmd));
+        command
             .args(cmd.args.iter().map(OsString::from))
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .stderr(Stdio::inherit())
-            .spawn()
-            .map_err(SolverError::from)?;
+            .stderr(Stdio::inherit());
+        if let Some(mb) = cmd.memory_limit_mb {
+            let bytes = (mb as u64).saturating_mul(1024 * 1024);
+            // SAFETY: setrlimit is async-signal-safe, so it can be called
+            // between fork and exec.
+            unsafe {
+                command.pre_exec(move || {
+                    resource::setrlimit(resource::Resource::RLIMIT_AS, bytes, bytes)
+                        .map_err(io::Error::from)
+                });
+            }
+        }
+        let mut child = command.spawn().map_err(SolverError::from)?;
         let tee = match tee {
             Some(path) => {
                 let mut f = Tee::new(path);
@@ -168,6 +194,7 @@ impl SmtProc {
             stdin,
             stdout,
             tee,
+            memory_limited: cmd.memory_limit_mb.is_some(),
             terminated: Arc::new(Mutex::new(Status::Running { in_call: false })),
         };
         for (option, val) in &cmd.options {
@@ -177,9 +204,7 @@ impl SmtProc {
             ));
         }
         // silence a warning from CVC4/CVC5 when run manually without -q
-        // TODO: figure out what a good default logic is (possibly will be
-        // customized to the solver)
-        proc.send(&app("set-logic", vec![atom_s("UFNIA")]));
+        proc.send(&app("set-logic", vec![atom_s(&cmd.logic)]));
         Ok(proc)
     }
 
@@ -205,8 +230,11 @@ impl SmtProc {
     /// Low-level API to send the solver a command that expects a response,
     /// which is parsed as a single s-expression.
     fn send_with_reply(&mut self, data: &sexp::Sexp) -> Result<sexp::Sexp> {
+        let start = Instant::now();
         self.send(data);
-        self.get_response(|s| sexp::parse(s).expect("could not parse solver response"))
+        let resp = self.get_response(|s| s.to_string())?;
+        self.record_reply(&resp, start.elapsed());
+        Ok(sexp::parse(&resp).expect("could not parse solver response"))
     }
 
     /// Get an error presumed to be in resp, checking for termination first.
@@ -217,6 +245,9 @@ impl SmtProc {
     fn get_error<T>(&mut self, resp: &str) -> Result<T> {
         self.check_killed()?;
         let msg = Self::parse_error(resp);
+        if Self::is_resource_limit(&msg) {
+            return Err(SolverError::ResourceLimit(msg));
+        }
         return Err(SolverError::UnexpectedClose(msg));
     }
 
@@ -282,9 +313,11 @@ impl SmtProc {
         } else {
             app("check-sat-assuming", vec![sexp_l(assumptions.to_vec())])
         };
+        let start = Instant::now();
         self.send(&cmd);
         self.start_call()?;
         let sexp_resp = self.get_response(|s| s.to_string())?;
+        self.record_reply(&sexp_resp, start.elapsed());
         let resp = self.parse_sat(&sexp_resp)?;
         if matches!(resp, SatResp::Unknown(_)) {
             if let Some(name) = self.save_tee() {
@@ -292,7 +325,12 @@ impl SmtProc {
             }
         }
         self.end_call()?;
-        Ok(resp)
+        match resp {
+            SatResp::Unknown(reason) if Self::is_resource_limit(&reason) => {
+                Err(SolverError::ResourceLimit(reason))
+            }
+            _ => Ok(resp),
+        }
     }
 
     fn check_killed(&mut self) -> Result<()> {
@@ -337,7 +375,12 @@ impl SmtProc {
             let n = self.stdout.read_line(&mut buf)?;
             if n == 0 {
                 self.check_killed()?;
-                let msg = Self::parse_error(&buf);
+                // a solver that runs out of memory may crash without
+                // reporting an error
+                let msg = Self::try_parse_error(&buf).unwrap_or_else(|| buf.trim().to_string());
+                if Self::is_resource_limit(&msg) || self.killed_by_limit() {
+                    return Err(SolverError::ResourceLimit(msg));
+                }
                 return Err(SolverError::UnexpectedClose(msg));
             }
             // last line, without the newline
@@ -395,14 +438,52 @@ impl SmtProc {
 
     /// Parse an error message returned as an s-expression.
     fn parse_error(resp: &str) -> String {
+        Self::try_parse_error(resp).unwrap_or_else(|| panic!("no error sexp found in {resp}"))
+    }
+
+    /// Whether a reason for unknown or an error message indicates the solver
+    /// ran out of memory or exceeded its resource limit.
+    ///
+    /// Only the messages the supported solvers actually report are recognized:
+    /// Z3's "max. resource limit exceeded", "max. memory exceeded" and "out of
+    /// memory", and CVC's RESOURCEOUT and MEMOUT reasons, "(resource limits
+    /// reached)", and `std::bad_alloc` errors.
+    fn is_resource_limit(msg: &str) -> bool {
+        let msg = msg.trim().to_lowercase();
+        let reasons = ["resourceout", "memout", "(resource limits reached)"];
+        let errors = [
+            "max. resource limit exceeded",
+            "max. memory exceeded",
+            "out of memory",
+            "std::bad_alloc",
+        ];
+        reasons.contains(&msg.as_str()) || errors.iter().any(|e| msg.contains(e))
+    }
+
+    /// Whether the solver was killed for exceeding its memory limit, judging by
+    /// a SIGKILL (as sent by the kernel's out-of-memory killer). Without a
+    /// memory limit a SIGKILL has some other cause, so it is not reported as a
+    /// limit. Only call this once the solver has closed its output, since it
+    /// waits for the process to exit.
+    fn killed_by_limit(&mut self) -> bool {
+        use nix::sys::signal::Signal;
+        use std::os::unix::process::ExitStatusExt;
+        self.memory_limited
+            && matches!(
+                self.child.wait(),
+                Ok(status) if status.signal() == Some(Signal::SIGKILL as i32)
+            )
+    }
+
+    /// Parse an error message returned as an s-expression, if there is one.
+    fn try_parse_error(resp: &str) -> Option<String> {
         // Z3 returns check-sat errors as:
         // (error "error msg")
         // sat
         //
         // Thus we parse the result as a sequence of sexps and look for the
         // error sexp.
-        let sexps = sexp::parse_many(resp)
-            .unwrap_or_else(|err| panic!("could not parse error response {resp}: {err}"));
+        let sexps = sexp::parse_many(resp).ok()?;
         let error_msg = sexps
             .iter()
             .filter_map(|s| {
@@ -415,8 +496,7 @@ impl SmtProc {
                 })
             })
             .next();
-        let msg = error_msg.unwrap_or_else(|| panic!("no error sexp found in {resp}"));
-        msg.to_string()
+        error_msg.map(|msg| msg.to_string())
     }
 
     fn parse_sat(&mut self, resp: &str) -> Result<SatResp> {
@@ -463,6 +543,24 @@ impl SmtProc {
         }
     }
 
+    /// Get a proof (following an unsat reply), in whatever format the solver
+    /// was configured to produce (for example with `:proof-format-mode`).
+    ///
+    /// The proof is returned as text since some formats (such as Alethe) are
+    /// a sequence of s-expressions rather than a single one.
+    pub fn get_proof(&mut self) -> Result<String> {
+        let start = Instant::now();
+        self.start_call()?;
+        self.send(&app("get-proof", []));
+        let proof = self.get_response(|s| s.to_string())?;
+        self.record_reply(&proof, start.elapsed());
+        self.end_call()?;
+        if proof.starts_with("(error") {
+            return self.get_error(&proof);
+        }
+        Ok(proof)
+    }
+
     // =============
     // Tee support
     // =============
@@ -482,6 +580,22 @@ impl SmtProc {
         })
     }
 
+    /// Also record solver replies and their timing in the tee'd file, making it
+    /// a transcript that can be replayed with [`crate::transcript`].
+    ///
+    /// Does nothing if there is no tee'd smt2 file.
+    pub fn record_transcript(&mut self) {
+        if let Some(f) = &mut self.tee {
+            f.record_transcript();
+        }
+    }
+
+    fn record_reply(&mut self, response: &str, elapsed: Duration) {
+        if let Some(f) = &mut self.tee {
+            f.reply(response, elapsed);
+        }
+    }
+
     /// Add a comment to the tee'd file.
     ///
     /// The comment is passed as a closure, which is not evaluated if there is
@@ -515,7 +629,7 @@ mod tests {
         let mut solver = SmtProc::new(z3, None).unwrap();
         let response = solver.check_sat().wrap_err("could not check-sat").unwrap();
         assert!(
-            matches!(response, SatResp::Sat { .. }),
+            matches!(response, SatResp::Sat),
             "should be sat, got {response:?}"
         );
     }
@@ -553,6 +667,26 @@ mod tests {
         insta::assert_debug_snapshot!(response, @"Unsat");
     }
 
+    #[test]
+    fn test_is_resource_limit() {
+        assert!(SmtProc::is_resource_limit("(resource limits reached)"));
+        assert!(SmtProc::is_resource_limit("RESOURCEOUT"));
+        assert!(SmtProc::is_resource_limit("out of memory"));
+        assert!(SmtProc::is_resource_limit("max. resource limit exceeded"));
+        assert!(SmtProc::is_resource_limit("memout"));
+        assert!(SmtProc::is_resource_limit("std::bad_alloc"));
+        assert!(!SmtProc::is_resource_limit("timeout"));
+        assert!(!SmtProc::is_resource_limit("incomplete quantifiers"));
+        // unrelated errors that merely mention memory or resources
+        assert!(!SmtProc::is_resource_limit(
+            "unknown option :resource-limit-per-query"
+        ));
+        assert!(!SmtProc::is_resource_limit("invalid memory model"));
+        assert!(!SmtProc::is_resource_limit(
+            "resourceout detected in preprocessing"
+        ));
+    }
+
     #[test]
     fn test_spawn_many() {
         let z3 = Z3Conf::new(&solver_path("z3")).done();
@@ -2,6 +2,10 @@
 // SPDX-License-Identifier: BSD-2-Clause
 
 //! Record SMT output and save to a file for debugging purposes.
+//!
+//! In transcript mode the solver's replies and the time taken to produce them
+//! are also recorded, as specially-marked comments following the command that
+//! produced them (see [`crate::transcript`] for the format).
 
 use std::{
     collections::hash_map::DefaultHasher,
@@ -9,15 +13,24 @@ use std::{
     hash::{Hash, Hasher},
     io::{self, Write},
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use crate::sexp::Sexp;
+use crate::transcript::{REPLY_PREFIX, TIME_PREFIX};
+
+#[derive(Debug)]
+enum Entry {
+    Sent(Sexp),
+    Reply { response: String, elapsed: Duration },
+}
 
 /// Track and save SMT sent to solver so far.
 #[derive(Debug)]
 pub struct Tee {
     dir: PathBuf,
-    contents: Vec<Sexp>,
+    contents: Vec<Entry>,
+    transcript: bool,
 }
 
 fn calculate_hash<T: Hash>(v: T) -> String {
@@ -33,34 +46,68 @@ impl Tee {
         Self {
             dir: dir.as_ref().to_path_buf(),
             contents: vec![],
+            transcript: false,
         }
     }
 
+    /// Also record solver replies and timing from now on.
+    pub fn record_transcript(&mut self) {
+        self.transcript = true;
+    }
+
     /// Append a raw s-expression sent to solver.
     pub fn append(&mut self, s: Sexp) {
-        self.contents.push(s)
+        self.contents.push(Entry::Sent(s))
+    }
+
+    /// Record the solver's reply to the last command, if in transcript mode.
+    pub fn reply(&mut self, response: &str, elapsed: Duration) {
+        if self.transcript {
+            self.contents.push(Entry::Reply {
+                response: response.to_string(),
+                elapsed,
+            });
+        }
     }
 
     /// Save the SMT2 input currently sent to the solver to a file based on
     /// content hash. Returns the saved file name.
+    ///
+    /// Only the input is hashed, so repeating the same queries produces the
+    /// same file name even if replies or timing differ.
     pub fn save(&self) -> io::Result<PathBuf> {
-        let contents = self
-            .contents
-            .iter()
-            .map(|s| {
-                if let Sexp::Comment(c) = s {
+        let mut input = vec![];
+        let mut contents = vec![];
+        for entry in &self.contents {
+            match entry {
+                Entry::Sent(Sexp::Comment(c)) => {
                     #[allow(clippy::comparison_to_empty)]
-                    if c == "" {
-                        return "".to_string();
+                    let line = if c == "" {
+                        "".to_string()
+                    } else {
+                        format!(";; {c}")
+                    };
+                    input.push(line.clone());
+                    contents.push(line);
+                }
+                Entry::Sent(s) => {
+                    // TODO: this should be pretty-printed
+                    input.push(s.to_string());
+                    contents.push(s.to_string());
+                }
+                Entry::Reply { response, elapsed } => {
+                    for line in response.lines() {
+                        contents.push(format!("{REPLY_PREFIX}{line}"));
                     }
-                    return format!(";; {c}");
+                    contents.push(format!(
+                        "{TIME_PREFIX}{:.3}",
+                        elapsed.as_secs_f64() * 1000.0
+                    ));
                 }
-                // TODO: this should be pretty-printed
-                s.to_string()
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
-        let hash = calculate_hash(&contents);
+            }
+        }
+        let hash = calculate_hash(input.join("\n"));
+        let contents = contents.join("\n");
         let fname = PathBuf::from(format!("query-{hash}.smt2"));
         let dest = self.dir.join(&fname);
         let mut f = OpenOptions::new()
@@ -0,0 +1,382 @@
+// Copyright 2022-2023 VMware, Inc.
+// SPDX-License-Identifier: BSD-2-Clause
+
+//! Parse and replay SMT transcripts.
+//!
+//! A transcript is an smt2 file saved in transcript mode (see
+//! [`SmtProc::record_transcript`]). It is an ordinary smt2 file, so it can still
+//! be passed directly to a solver, but after each command that produced a
+//! response it has comments recording the reply and how long it took:
+//!
+//! ```text
+//! ;; inductive: assert always !(ping_pending & pong_pending)
+//! (check-sat-assuming (ind0))
+//! ;< unsat
+//! ;@ time-ms 12.345
+//! ```
+//!
+//! Lines starting with `;;` are comments issued by the caller (for example,
+//! the verification step that issued the following queries). Replaying a
+//! transcript re-sends its commands to a solver and compares the result of
+//! each `check-sat` with the recorded one.
+
+use std::{path::Path, time::Instant};
+
+use crate::{
+    conf::SolverCmd,
+    proc::{SatResp, SmtProc, SolverError},
+    sexp::{self, Sexp},
+};
+
+/// Prefix for a line of a recorded solver reply.
+pub(crate) const REPLY_PREFIX: &str = ";< ";
+/// Prefix for the time taken (in milliseconds) by the preceding reply.
+pub(crate) const TIME_PREFIX: &str = ";@ time-ms ";
+const COMMENT_PREFIX: &str = ";; ";
+
+/// A recorded reply from the solver.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reply {
+    /// The raw response text.
+    pub response: String,
+    /// How long the solver took to reply, in milliseconds.
+    pub time_ms: f64,
+}
+
+/// A single command sent to the solver.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    /// The command itself.
+    pub sexp: Sexp,
+    /// The most recent comment before this command, which describes the step
+    /// that issued it.
+    pub step: Option<String>,
+    /// The recorded reply, if the command had one.
+    pub reply: Option<Reply>,
+}
+
+/// A parsed transcript.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transcript {
+    /// The solver command line the transcript was recorded with.
+    pub solver: Option<String>,
+    /// The commands sent to the solver, in order.
+    pub commands: Vec<Command>,
+}
+
+/// The outcome of replaying a single `check-sat` from a transcript.
+#[derive(Debug, Clone)]
+pub struct CheckReplay {
+    /// The step that issued this check (see [`Command::step`]).
+    pub step: Option<String>,
+    /// The check command that was replayed.
+    pub command: Sexp,
+    /// The recorded reply, if there was one.
+    pub expected: Option<Reply>,
+    /// The replayed result.
+    pub actual: SatResp,
+    /// How long the replayed check took, in milliseconds.
+    pub time_ms: f64,
+}
+
+impl CheckReplay {
+    /// Whether the replayed result differs from the recorded one. Only the
+    /// kind of result is compared, not the reason given for unknown.
+    pub fn diverges(&self) -> bool {
+        match &self.expected {
+            Some(reply) => reply.response.split_whitespace().next() != Some(sat_kind(&self.actual)),
+            None => false,
+        }
+    }
+}
+
+/// The recorded form of a [`SatResp`], ignoring the reason for unknown.
+pub fn sat_kind(resp: &SatResp) -> &'static str {
+    match resp {
+        SatResp::Sat => "sat",
+        SatResp::Unsat => "unsat",
+        SatResp::Unknown(_) => "unknown",
+    }
+}
+
+fn is_check(sexp: &Sexp) -> bool {
+    matches!(sexp.app(), Some(("check-sat" | "check-sat-assuming", _)))
+}
+
+fn parse_sexps(buf: &mut String) -> Result<Vec<Sexp>, String> {
+    if buf.trim().is_empty() {
+        buf.clear();
+        return Ok(vec![]);
+    }
+    let sexps = sexp::parse_many(buf).map_err(|err| format!("could not parse {buf}: {err}"))?;
+    buf.clear();
+    Ok(sexps)
+}
+
+impl Transcript {
+    /// Parse a transcript from the contents of a saved smt2 file.
+    ///
+    /// A file saved without transcript mode also parses, but has no replies.
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut solver = None;
+        let mut commands: Vec<Command> = vec![];
+        let mut step = None;
+        let mut reply_lines: Vec<&str> = vec![];
+        // accumulates commands, which may span multiple lines
+        let mut buf = String::new();
+        for line in contents.lines() {
+            let special = line.starts_with(REPLY_PREFIX)
+                || line.starts_with(TIME_PREFIX)
+                || line.starts_with(COMMENT_PREFIX);
+            if !special {
+                buf.push_str(line);
+                buf.push('\n');
+                continue;
+            }
+            for sexp in parse_sexps(&mut buf)? {
+                commands.push(Command {
+                    sexp,
+                    step: step.clone(),
+                    reply: None,
+                });
+            }
+            if let Some(comment) = line.strip_prefix(COMMENT_PREFIX) {
+                if commands.is_empty() && solver.is_none() {
+                    solver = Some(comment.to_string());
+                } else {
+                    step = Some(comment.to_string());
+                }
+            } else if let Some(reply) = line.strip_prefix(REPLY_PREFIX) {
+                reply_lines.push(reply);
+            } else if let Some(time) = line.strip_prefix(TIME_PREFIX) {
+                let time_ms = time
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("malformed time {time}"))?;
+                let command = commands
+                    .last_mut()
+                    .ok_or_else(|| "reply before any command".to_string())?;
+                command.reply = Some(Reply {
+                    response: reply_lines.join("\n"),
+                    time_ms,
+                });
+                reply_lines.clear();
+            }
+        }
+        for sexp in parse_sexps(&mut buf)? {
+            commands.push(Command {
+                sexp,
+                step: step.clone(),
+                reply: None,
+            });
+        }
+        Ok(Self { solver, commands })
+    }
+
+    /// Re-run the transcript with the solver `cmd`, returning the result of
+    /// each check.
+    ///
+    /// The logic set at the start of the transcript is used to launch the
+    /// solver. If `keep_options` is true, so are the options set at the start
+    /// of the transcript, except those that `cmd` already sets; this is only
+    /// useful when replaying with the solver that recorded the transcript,
+    /// since options are solver-specific. Queries other than checks (such as
+    /// `get-model`) are not replayed.
+    pub fn replay(
+        &self,
+        cmd: SolverCmd,
+        keep_options: bool,
+    ) -> Result<Vec<CheckReplay>, SolverError> {
+        let mut checks = vec![];
+        self.replay_with(cmd, keep_options, |_, check| {
+            checks.push(check);
+            Ok(())
+        })?;
+        Ok(checks)
+    }
+
+    /// Re-run the transcript as in [`Transcript::replay`], calling `on_check`
+    /// after each check while the solver is still in the state following it
+    /// (for example, to get a proof after an unsat result).
+    pub fn replay_with<F>(
+        &self,
+        mut cmd: SolverCmd,
+        keep_options: bool,
+        mut on_check: F,
+    ) -> Result<(), SolverError>
+    where
+        F: FnMut(&mut SmtProc, CheckReplay) -> Result<(), SolverError>,
+    {
+        let mut commands = self.commands.iter().peekable();
+        while let Some(command) = commands.peek() {
+            match command.sexp.app() {
+                Some(("set-option", [name, val])) => {
+                    let name = name.to_string();
+                    let name = name.trim_start_matches(':');
+                    if keep_options && !cmd.options.iter().any(|(n, _)| n == name) {
+                        cmd.option(name, val.to_string());
+                    }
+                }
+                Some(("set-logic", [logic])) => cmd.set_logic(logic.to_string()),
+                _ => break,
+            }
+            commands.next();
+        }
+        let mut proc = SmtProc::new(cmd, None)?;
+        for command in commands {
+            if let Some((head, args)) = command.sexp.app() {
+                match head {
+                    "check-sat" | "check-sat-assuming" => {
+                        let assumptions = match args {
+                            [Sexp::List(assumptions)] => assumptions.as_slice(),
+                            _ => &[],
+                        };
+                        let start = Instant::now();
+                        let actual = proc.check_sat_assuming(assumptions)?;
+                        let check = CheckReplay {
+                            step: command.step.clone(),
+                            command: command.sexp.clone(),
+                            expected: command.reply.clone(),
+                            actual,
+                            time_ms: start.elapsed().as_secs_f64() * 1000.0,
+                        };
+                        on_check(&mut proc, check)?;
+                        continue;
+                    }
+                    "get-model" | "get-unsat-assumptions" | "get-info" | "echo" | "exit" => {
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+            proc.send(&command.sexp);
+        }
+        Ok(())
+    }
+
+    /// Whether the transcript was recorded with the solver binary `bin_name`
+    /// (for example, `z3`), judging by the recorded command line.
+    pub fn recorded_with(&self, bin_name: &str) -> bool {
+        self.solver.as_ref().is_some_and(|cmdline| {
+            cmdline
+                .split_whitespace()
+                .next()
+                .and_then(|bin| Path::new(bin).file_stem())
+                .is_some_and(|stem| stem == bin_name)
+        })
+    }
+
+    /// The recorded checks, with their replies.
+    pub fn checks(&self) -> impl Iterator<Item = &Command> {
+        self.commands.iter().filter(|c| is_check(&c.sexp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Transcript;
+    use crate::{
+        sexp::{parse, Sexp},
+        tee::Tee,
+    };
+    use std::{env, fs, time::Duration};
+
+    #[test]
+    fn test_parse_transcript() {
+        let contents = r#";; z3 -in -smt2
+(set-option :produce-models true)
+(set-logic UF)
+(declare-const a Bool)
+
+;; inductive: a
+(assert (and a
+; an inner comment
+(not a)))
+(check-sat)
+;< unsat
+;@ time-ms 1.500
+(get-info :reason-unknown)
+(check-sat)
+;< sat
+;@ time-ms 2.000
+(get-model)
+;< (
+;<   (define-fun a () Bool true)
+;< )
+;@ time-ms 0.250"#;
+        let t = Transcript::parse(contents).unwrap();
+        assert_eq!(t.solver.as_deref(), Some("z3 -in -smt2"));
+        assert_eq!(t.commands.len(), 8);
+        assert_eq!(t.commands[0].step, None);
+        assert_eq!(
+            t.commands[3].sexp,
+            parse("(assert (and a\n; an inner comment\n(not a)))").unwrap()
+        );
+        let checks = t.checks().collect::<Vec<_>>();
+        assert_eq!(checks.len(), 2);
+        assert_eq!(checks[0].step.as_deref(), Some("inductive: a"));
+        assert_eq!(checks[0].reply.as_ref().unwrap().response, "unsat");
+        assert_eq!(checks[1].reply.as_ref().unwrap().time_ms, 2.0);
+        let model = t.commands.last().unwrap().reply.as_ref().unwrap();
+        assert_eq!(model.response, "(\n  (define-fun a () Bool true)\n)");
+        assert_eq!(t.commands[5].reply, None);
+    }
+
+    #[test]
+    fn test_tee_round_trip() {
+        let dir = env::temp_dir().join(format!("flyvy-tee-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut tee = Tee::new(&dir);
+        tee.append(Sexp::Comment("z3 -in -smt2".to_string()));
+        let commands = [
+            "(set-logic UF)",
+            "(declare-const a Bool)",
+            "(assert (not a))",
+            "(check-sat)",
+            "(get-model)",
+        ]
+        .map(|s| parse(s).unwrap());
+        tee.append(commands[0].clone());
+        // replies are only recorded in transcript mode
+        tee.reply("success", Duration::from_millis(1));
+        tee.record_transcript();
+        tee.append(commands[1].clone());
+        tee.append(Sexp::Comment("".to_string()));
+        tee.append(Sexp::Comment("inductive: a".to_string()));
+        tee.append(commands[2].clone());
+        tee.append(commands[3].clone());
+        tee.reply("sat", Duration::from_micros(12345));
+        tee.append(commands[4].clone());
+        tee.reply(
+            "(\n  (define-fun a () Bool false)\n)",
+            Duration::from_micros(250),
+        );
+
+        let fname = tee.save().unwrap();
+        let contents = fs::read_to_string(dir.join(fname)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let lines = contents.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], ";; z3 -in -smt2");
+        assert!(lines.contains(&";; inductive: a"));
+        assert!(lines.contains(&";< sat"));
+        assert!(lines.contains(&";@ time-ms 12.345"));
+        assert!(lines.contains(&";<   (define-fun a () Bool false)"));
+        assert!(!lines.contains(&";< success"));
+
+        let t = Transcript::parse(&contents).unwrap();
+        assert_eq!(t.solver.as_deref(), Some("z3 -in -smt2"));
+        assert_eq!(
+            t.commands.iter().map(|c| &c.sexp).collect::<Vec<_>>(),
+            commands.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(t.commands[1].step, None);
+        assert_eq!(t.commands[3].step.as_deref(), Some("inductive: a"));
+        let check = t.checks().next().unwrap().reply.as_ref().unwrap();
+        assert_eq!(check.response, "sat");
+        assert_eq!(check.time_ms, 12.345);
+        let model = t.commands[4].reply.as_ref().unwrap();
+        assert_eq!(model.response, "(\n  (define-fun a () Bool false)\n)");
+        assert_eq!(model.time_ms, 0.25);
+        assert_eq!(t.commands[0].reply, None);
+    }
+}
@@ -6,6 +6,7 @@
 //! solver.
 
 use std::{
+    cell::RefCell,
     collections::{HashMap, HashSet},
     iter::zip,
 };
@@ -16,12 +17,13 @@ use fly::{
 };
 use smtlib::{
     conf::{CvcConf, SolverCmd, Z3Conf},
+    proc::SolverError,
     sexp::{self, Atom},
 };
 
 use crate::{
     imp::{Backend, FOModel},
-    models::{self, ModelSymbol, PartialInterp},
+    models::{self, Model, ModelError, ModelSymbol, PartialInterp},
 };
 
 /// The type of solver being used
@@ -48,6 +50,11 @@ impl SolverType {
 struct GenericOptions {
     timeout_ms: Option<usize>,
     seed: usize,
+    logic: Option<String>,
+    rlimit: Option<usize>,
+    memory_limit_mb: Option<usize>,
+    /// Extra options, applied after (and thus overriding) the defaults.
+    options: Vec<(String, String)>,
 }
 
 /// A Backend for launching and parsing Z3/CVC4/CVC5, with some hard-coded options.
@@ -81,6 +88,35 @@ impl GenericBackend {
         return self;
     }
 
+    /// Set a deterministic per-query resource limit (Z3's `rlimit`, CVC's
+    /// `rlimit-per`). None disables the limit.
+    pub fn rlimit(&mut self, rlimit: Option<usize>) -> &mut Self {
+        self.opts.rlimit = rlimit;
+        return self;
+    }
+
+    /// Limit the memory available to the solver process, in megabytes. None
+    /// disables the limit.
+    pub fn memory_limit_mb(&mut self, mb: Option<usize>) -> &mut Self {
+        self.opts.memory_limit_mb = mb;
+        return self;
+    }
+
+    /// Set the SMT-LIB logic. None uses the default logic.
+    pub fn logic(&mut self, logic: Option<String>) -> &mut Self {
+        self.opts.logic = logic;
+        return self;
+    }
+
+    /// Pass an arbitrary option to the solver, as in `(set-option :name val)`.
+    ///
+    /// Options are sent after the built-in defaults, so this can also be used
+    /// to override them.
+    pub fn option(&mut self, name: &str, val: &str) -> &mut Self {
+        self.opts.options.push((name.to_string(), val.to_string()));
+        return self;
+    }
+
     /// Get the solver type.
     pub fn solver_type(&self) -> SolverType {
         self.solver_type
@@ -92,22 +128,45 @@ impl GenericBackend {
     }
 }
 
-fn sort_cardinality(universes: &HashMap<String, usize>, sort: &Sort) -> usize {
+fn sort_cardinality(universes: &HashMap<String, usize>, sort: &Sort) -> Result<usize, SolverError> {
     match sort {
-        Sort::Bool => 2,
-        Sort::Uninterpreted(s) => *universes
+        Sort::Bool => Ok(2),
+        Sort::Uninterpreted(s) => universes
             .get(s)
-            .unwrap_or_else(|| panic!("unknown sort {s}")),
+            .copied()
+            .ok_or_else(|| SolverError::CouldNotInterpretModel(format!("unknown sort {s}"))),
+    }
+}
+
+/// Convert the result of evaluating a symbol in the model to an element of
+/// `sort`.
+fn element_of(model: &Model, sort: &Sort, res: &Atom) -> Result<Element, ModelError> {
+    let unexpected = || ModelError::new(format!("unexpected {sort} value {res}"));
+    let res = res.s().ok_or_else(unexpected)?;
+    match sort {
+        Sort::Bool => match res {
+            "false" => Ok(0),
+            "true" => Ok(1),
+            _ => Err(unexpected()),
+        },
+        Sort::Uninterpreted(sort_name) => model
+            .universes
+            .get(sort_name)
+            .and_then(|elements| elements.iter().position(|x| x == res))
+            .ok_or_else(unexpected),
     }
 }
 
 impl Backend for &GenericBackend {
     fn get_cmd(&self) -> SolverCmd {
-        match self.solver_type {
+        let mut cmd = match self.solver_type {
             SolverType::Z3 => {
                 let mut conf = Z3Conf::new(&self.bin);
                 conf.model_compact();
                 conf.timeout_ms(self.opts.timeout_ms);
+                if self.opts.rlimit.is_some() {
+                    conf.rlimit(self.opts.rlimit);
+                }
                 if self.opts.seed != 0 {
                     conf.options()
                         .option("smt.random_seed", format!("{}", self.opts.seed));
@@ -121,6 +180,9 @@ impl Backend for &GenericBackend {
                 conf.finite_models();
                 conf.interleave_enumerative_instantiation();
                 conf.timeout_ms(self.opts.timeout_ms);
+                if self.opts.rlimit.is_some() {
+                    conf.rlimit(self.opts.rlimit);
+                }
                 if self.opts.seed != 0 {
                     conf.options().option("seed", format!("{}", self.opts.seed));
                 }
@@ -131,12 +193,23 @@ impl Backend for &GenericBackend {
                 conf.finite_models();
                 conf.interleave_enumerative_instantiation();
                 conf.timeout_ms(self.opts.timeout_ms);
+                if self.opts.rlimit.is_some() {
+                    conf.rlimit(self.opts.rlimit);
+                }
                 if self.opts.seed != 0 {
                     conf.options().option("seed", format!("{}", self.opts.seed));
                 }
                 conf.done()
             }
+        };
+        if let Some(logic) = &self.opts.logic {
+            cmd.set_logic(logic);
+        }
+        cmd.set_memory_limit_mb(self.opts.memory_limit_mb);
+        for (name, val) in &self.opts.options {
+            cmd.option(name, val);
         }
+        cmd
     }
 
     fn parse(
@@ -146,12 +219,13 @@ impl Backend for &GenericBackend {
         _n_states: usize,
         indicators: &HashSet<String>,
         model: &sexp::Sexp,
-    ) -> FOModel {
+    ) -> Result<FOModel, SolverError> {
         let model = match self.solver_type {
             SolverType::Z3 => models::parse_z3(model),
             SolverType::Cvc4 => models::parse_cvc(model, false),
             SolverType::Cvc5 => models::parse_cvc(model, true),
-        };
+        }
+        .map_err(|err| SolverError::CouldNotInterpretModel(err.to_string()))?;
 
         let universe: HashMap<String, usize> = model
             .universes
@@ -185,9 +259,15 @@ impl Backend for &GenericBackend {
             let mut shape = arg_sorts
                 .iter()
                 .map(|sort| sort_cardinality(&universe, sort))
-                .collect::<Vec<usize>>();
-            shape.push(sort_cardinality(&universe, ret_sort));
+                .collect::<Result<Vec<usize>, _>>()?;
+            shape.push(sort_cardinality(&universe, ret_sort)?);
+            // the first error encountered while evaluating symbol (the
+            // interpretation is built with a callback, which cannot fail)
+            let error: RefCell<Option<ModelError>> = RefCell::new(None);
             let interp = Interpretation::new(&shape, |args: &[Element]| -> Element {
+                if error.borrow().is_some() {
+                    return 0;
+                }
                 // get the arguments as terms, based on model.universes
                 let args = zip(args, &arg_sorts)
                     .map(|(&e_idx, typ)| match typ {
@@ -207,30 +287,22 @@ impl Backend for &GenericBackend {
                     .collect::<Vec<_>>();
                 let repl: HashMap<&str, Atom> =
                     zip(binders.iter().map(|s| s.0.as_str()), args).collect();
-                let e = model
+                let res = model
                     .smt_eval(&repl, &part_interp, body)
-                    .unwrap_or_else(|err| panic!("could not interpret {symbol}: {err}"));
-                let res = e.s().expect("unhandled evaluation result");
-                match ret_sort {
-                    Sort::Bool => {
-                        if res == "false" {
-                            return 0;
-                        } else if res == "true" {
-                            return 1;
-                        } else {
-                            panic!("unexpected bool {res}")
-                        }
-                    }
-                    Sort::Uninterpreted(sort) => {
-                        let elements = &model.universes[sort];
-                        let res_idx = elements
-                            .iter()
-                            .position(|x| x == res)
-                            .unwrap_or_else(|| panic!("unknown {sort} element {res}"));
-                        return res_idx as Element;
+                    .and_then(|e| element_of(&model, ret_sort, &e));
+                match res {
+                    Ok(e) => e,
+                    Err(err) => {
+                        *error.borrow_mut() = Some(err);
+                        0
                     }
                 }
             });
+            if let Some(err) = error.into_inner() {
+                return Err(SolverError::CouldNotInterpretModel(format!(
+                    "could not interpret {symbol}: {err}"
+                )));
+            }
             part_interp
                 .interps
                 .insert(symbol.clone(), (interp, ret_sort.clone()));
@@ -242,7 +314,7 @@ impl Backend for &GenericBackend {
             .filter(|(symbol, _)| sig.contains_relation(symbol))
             .map(|(symbol, (interp, _))| (symbol, interp))
             .collect();
-        FOModel { universe, interp }
+        Ok(FOModel { universe, interp })
     }
 
     fn returns_minimal(&self) -> bool {
@@ -266,6 +338,25 @@ mod tests {
 
     use test_log::test;
 
+    #[test]
+    fn test_element_of_errors() {
+        let model = Model {
+            universes: HashMap::from([(
+                "node".to_string(),
+                vec!["node!val!0".to_string(), "node!val!1".to_string()],
+            )]),
+            symbols: HashMap::new(),
+        };
+        let node = Sort::Uninterpreted("node".to_string());
+        let other = Sort::Uninterpreted("other".to_string());
+        let atom = |s: &str| Atom::S(s.to_string());
+        assert_eq!(element_of(&model, &node, &atom("node!val!1")).unwrap(), 1);
+        assert_eq!(element_of(&model, &Sort::Bool, &atom("true")).unwrap(), 1);
+        assert!(element_of(&model, &node, &atom("node!val!2")).is_err());
+        assert!(element_of(&model, &other, &atom("node!val!0")).is_err());
+        assert!(element_of(&model, &Sort::Bool, &atom("node!val!0")).is_err());
+    }
+
     #[test]
     fn test_issue_5_parse_model_with_auxilliary_defs() {
         let _ = pretty_env_logger::try_init();
@@ -302,7 +393,9 @@ mod tests {
             .expect("could not find model file");
         let model_sexp = sexp::parse(&model_text).expect("test model does not parse");
 
-        let fo_model = (&backend).parse(&sig, 1, &HashSet::new(), &model_sexp);
+        let fo_model = (&backend)
+            .parse(&sig, 1, &HashSet::new(), &model_sexp)
+            .expect("could not interpret model");
         // a (primed) relation from the signature
         assert!(fo_model.interp.contains_key("leader'"));
         // auxilliary definition in Z3's model
@@ -329,10 +422,50 @@ mod tests {
             .expect("could not find model file");
         let model_sexp = sexp::parse(&model_text).expect("test model does not parse");
 
-        let fo_model = (&backend).parse(&sig, 0, &HashSet::new(), &model_sexp);
+        let fo_model = (&backend)
+            .parse(&sig, 0, &HashSet::new(), &model_sexp)
+            .expect("could not interpret model");
         assert!(fo_model.interp.contains_key("votes"));
     }
 
+    #[test]
+    fn test_logic_and_extra_options() {
+        let mut backend = GenericBackend::new(SolverType::Cvc5, "cvc5");
+        backend
+            .logic(Some("UF".to_string()))
+            .option("finite-model-find", "false");
+        let cmd = (&backend).get_cmd();
+        assert_eq!(cmd.logic, "UF");
+        // extra options come last so that they override the defaults
+        assert_eq!(
+            cmd.options.last(),
+            Some(&("finite-model-find".to_string(), "false".to_string()))
+        );
+
+        let backend = GenericBackend::new(SolverType::Z3, "z3");
+        assert_eq!((&backend).get_cmd().logic, "UFNIA");
+    }
+
+    #[test]
+    fn test_resource_limits() {
+        let has_option = |cmd: &SolverCmd, name: &str, val: &str| {
+            cmd.options.iter().any(|(n, v)| n == name && v == val)
+        };
+        let mut backend = GenericBackend::new(SolverType::Z3, "z3");
+        let cmd = (&backend).get_cmd();
+        assert_eq!(cmd.memory_limit_mb, None);
+        assert!(!cmd.options.iter().any(|(n, _)| n == "rlimit"));
+
+        backend.rlimit(Some(1000)).memory_limit_mb(Some(512));
+        let cmd = (&backend).get_cmd();
+        assert_eq!(cmd.memory_limit_mb, Some(512));
+        assert!(has_option(&cmd, "rlimit", "1000"));
+
+        let mut backend = GenericBackend::new(SolverType::Cvc5, "cvc5");
+        backend.rlimit(Some(1000));
+        assert!(has_option(&(&backend).get_cmd(), "rlimit-per", "1000"));
+    }
+
     #[test]
     fn test_check_sat_assuming_and_get_minimal_model() {
         let sig = parse_signature(
@@ -366,4 +499,31 @@ mod tests {
             "p(x) should be true due to assumption"
         );
     }
+
+    #[test]
+    fn test_get_minimal_unsat_core() {
+        let sig = parse_signature(
+            r#"
+            mutable p: bool
+            mutable q: bool
+            mutable r: bool
+        "#,
+        );
+        let backend = GenericBackend::new(SolverType::Z3, &solver_path("z3"));
+        let mut solver =
+            Solver::new(&sig, 1, &backend, None).expect("could not create solver for test");
+        let inds = ["p", "p_and_q", "not_p", "r"].map(|name| solver.get_indicator(name));
+        solver.assert(&Term::implies(inds[0].clone(), term("p")));
+        solver.assert(&Term::implies(inds[1].clone(), term("p & q")));
+        solver.assert(&Term::implies(inds[2].clone(), term("!p")));
+        solver.assert(&Term::implies(inds[3].clone(), term("r")));
+        let assumptions = inds.iter().map(|ind| (ind.clone(), true)).collect();
+        let resp = solver.check_sat(assumptions).unwrap();
+        assert_eq!(resp, SatResp::Unsat);
+        let core = solver.get_minimal_unsat_core().unwrap();
+        // either of the first two indicators with the third is minimal
+        assert_eq!(core.len(), 2);
+        assert!(core.contains_key(&inds[2]));
+        assert!(!core.contains_key(&inds[3]));
+    }
 }
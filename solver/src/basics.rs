@@ -172,6 +172,11 @@ impl<C: BasicSolverCanceler> SolverCancelers<C> {
         SolverCancelers(Arc::new(Mutex::new((false, vec![]))))
     }
 
+    /// Whether the set has been canceled.
+    pub fn is_canceled(&self) -> bool {
+        self.0.lock().unwrap().0
+    }
+
     /// Add the given canceler to the set of cancelers.
     ///
     /// Returns `true` if the [`BasicSolverCanceler`] was added, or `false` if the set has already been canceled.
@@ -238,9 +243,9 @@ impl BasicSolver for SingleSolver {
         assumptions: &HashMap<usize, (Term, bool)>,
     ) -> Result<BasicSolverResp, SolverError> {
         match check_sat_conf(&self.0, query_conf, assertions, assumptions) {
-            Ok(BasicSolverResp::Unknown(reason)) | Err(SolverError::CouldNotMinimize(reason)) => {
-                Ok(BasicSolverResp::Unknown(reason))
-            }
+            Ok(BasicSolverResp::Unknown(reason))
+            | Err(SolverError::CouldNotMinimize(reason))
+            | Err(SolverError::ResourceLimit(reason)) => Ok(BasicSolverResp::Unknown(reason)),
             res => res,
         }
     }
@@ -266,7 +271,8 @@ impl BasicSolver for FallbackSolvers {
         for solver_conf in &self.0 {
             match check_sat_conf(solver_conf, query_conf, assertions, assumptions) {
                 Ok(BasicSolverResp::Unknown(reason))
-                | Err(SolverError::CouldNotMinimize(reason)) => {
+                | Err(SolverError::CouldNotMinimize(reason))
+                | Err(SolverError::ResourceLimit(reason)) => {
                     unknowns.push(reason);
                 }
                 res => return res,
@@ -318,7 +324,8 @@ impl BasicSolver for ParallelSolvers {
                 match res {
                     Err(SolverError::Killed) => Err(SolverError::Killed),
                     Ok(BasicSolverResp::Unknown(reason))
-                    | Err(SolverError::CouldNotMinimize(reason)) => {
+                    | Err(SolverError::CouldNotMinimize(reason))
+                    | Err(SolverError::ResourceLimit(reason)) => {
                         Ok(BasicSolverResp::Unknown(reason))
                     }
                     // This case is reached only if the result is SAT, UNSAT, or some error other than SolverError::Killed,
@@ -21,14 +21,21 @@ pub struct SolverConf {
     pub backend: GenericBackend,
     /// The optional path to tee SMT output to.
     pub tee: Option<PathBuf>,
+    /// Whether the tee'd SMT output should also record solver replies and
+    /// timing (see [`smtlib::transcript`]).
+    pub transcript: bool,
 }
 
 impl SolverConf {
     /// Launch a new solver with the given configuration.
     pub fn solver(&self, sig: &Signature, n_states: usize) -> Solver<&GenericBackend> {
         // TODO: failures to start the solver should be bubbled up to user nicely
-        Solver::new(sig, n_states, &self.backend, self.tee.as_deref())
-            .expect("could not start solver")
+        let mut solver = Solver::new(sig, n_states, &self.backend, self.tee.as_deref())
+            .expect("could not start solver");
+        if self.transcript {
+            solver.record_transcript();
+        }
+        solver
     }
 
     /// Get a new solver configuration with the specified settings
@@ -64,7 +71,11 @@ impl SolverConf {
             None
         });
         backend.seed(seed);
-        SolverConf { backend, tee }
+        SolverConf {
+            backend,
+            tee,
+            transcript: false,
+        }
     }
 
     /// Get the solver type.
@@ -16,6 +16,7 @@ use fly::timing::TimeType;
 use fly::{
     semantics::{Interpretation, Model, Universe},
     syntax::{Binder, Signature, Sort, Term},
+    term::simplify::simplify,
 };
 use smtlib::{
     conf::SolverCmd,
@@ -39,7 +40,7 @@ pub trait Backend {
         n_states: usize,
         indicators: &HashSet<String>,
         model: &Sexp,
-    ) -> FOModel;
+    ) -> Result<FOModel, SolverError>;
 
     /// Indicates whether this solver returns minimal models when `(get-model)` is called.
     fn returns_minimal(&self) -> bool;
@@ -104,6 +105,11 @@ impl<B: Backend> Solver<B> {
         self.proc.pid()
     }
 
+    /// Also record solver replies and timing in the tee file, if there is one.
+    pub fn record_transcript(&mut self) {
+        self.proc.record_transcript();
+    }
+
     /// Save the solver state so far to a tee file.
     pub fn save_tee(&self) -> Option<PathBuf> {
         self.proc.save_tee()
@@ -145,11 +151,13 @@ impl<B: Backend> Solver<B> {
         }
     }
 
-    /// Send `(assert ...)` to the solver.
+    /// Send `(assert ...)` to the solver. The term is simplified first, and
+    /// the simplified term is what is recorded.
     pub fn assert(&mut self, t: &Term) {
-        self.proc.send(&app("assert", [sexp::term(t)]));
+        let t = simplify(t);
+        self.proc.send(&app("assert", [sexp::term(&t)]));
         self.last_assumptions = None;
-        self.asserts.push(t.clone())
+        self.asserts.push(t)
     }
 
     /// Create a comment in the tee'd SMT file, if there is one.
@@ -227,9 +235,8 @@ impl<B: Backend> Solver<B> {
     fn get_fo_model(&mut self, typ: TimeType, start: Instant) -> Result<FOModel, SolverError> {
         let model = self.proc.get_model()?;
         fly::timing::elapsed(typ, start);
-        Ok(self
-            .backend
-            .parse(&self.signature, self.n_states, &self.indicators, &model))
+        self.backend
+            .parse(&self.signature, self.n_states, &self.indicators, &model)
     }
 
     /// After a sat response to check_sat or check_sat_assuming, produce a trace
@@ -438,10 +445,28 @@ impl<B: Backend> Solver<B> {
     /// After a call to check-sat returns unsat, get a minimized unsat core: a
     /// minimal set of indicator variables which still result in unsat.
     ///
-    /// Not yet implemented so there is no algorithm here.
+    /// Starting from the solver's unsat core, each indicator is dropped in turn
+    /// and kept out if the remaining ones are still unsat (in which case the
+    /// core shrinks to the new unsat core). An indicator is kept if dropping it
+    /// gives sat or unknown.
     pub fn get_minimal_unsat_core(&mut self) -> Result<HashMap<Term, bool>, SolverError> {
-        eprintln!("unsat code minimization is not yet implemented");
-        self.get_unsat_core()
+        let mut core = self.get_unsat_core()?;
+        let indicators = core.keys().cloned().sorted().collect::<Vec<_>>();
+        for ind in indicators {
+            let Some(val) = core.remove(&ind) else {
+                // already dropped by a smaller core
+                continue;
+            };
+            if self.check_sat(core.clone())? == SatResp::Unsat {
+                if !core.is_empty() {
+                    core = self.get_unsat_core()?;
+                }
+            } else {
+                core.insert(ind, val);
+            }
+        }
+        self.last_assumptions = None;
+        Ok(core)
     }
 
     /// Call the SMT push command to create a new assertion stack frame.
@@ -5,7 +5,7 @@
 
 use lazy_static::lazy_static;
 use serde::Serialize;
-use std::{collections::HashMap, iter};
+use std::{collections::HashMap, iter, rc::Rc};
 use thiserror::Error;
 
 use regex::Regex;
@@ -14,7 +14,7 @@ use fly::{
     semantics::{Element, Interpretation},
     syntax::Sort,
 };
-use smtlib::sexp::{atom_s, sexp_l, Atom, Sexp};
+use smtlib::sexp::{app, atom_s, sexp_l, Atom, Sexp};
 
 /// Holds a `semantics::Interpretation` for some of the relations in a Model.
 #[derive(Debug, Clone)]
@@ -46,69 +46,97 @@ pub struct Model {
     pub symbols: HashMap<String, ModelSymbol>,
 }
 
-/// An error evaluating a function in an SMT solver model.
+/// An error parsing or evaluating an SMT solver model.
 #[derive(Debug, Error)]
-#[error("eval error: {0}")]
-pub struct EvalError(String);
+#[error("model error: {0}")]
+pub struct ModelError(String);
+
+impl ModelError {
+    /// Construct a `ModelError` from a message.
+    pub fn new(msg: String) -> Self {
+        Self(msg)
+    }
+}
 
 fn bool_atom(b: bool) -> Atom {
     let v = if b { "true" } else { "false" };
     Atom::S(v.to_string())
 }
 
-fn subst_hashmap(repl: &HashMap<&str, &Sexp>, e: &mut Sexp) {
-    match e {
-        Sexp::Atom(Atom::I(_)) | Sexp::Comment(_) => {}
-        Sexp::Atom(Atom::S(s)) => {
-            if let Some(&new) = repl.get(s.as_str()) {
-                *e = new.clone();
-            }
-        }
-        Sexp::List(ss) => {
-            for s in ss.iter_mut() {
-                subst_hashmap(repl, s)
-            }
-        }
-    }
+fn err<T>(msg: String) -> Result<T, ModelError> {
+    Err(ModelError(msg))
 }
 
-/// Parallel substitution into an sexp.
-pub fn subst(repl: &[(&str, Sexp)], e: &Sexp) -> Sexp {
-    let mut repl_map: HashMap<&str, &Sexp> = HashMap::with_capacity(repl.len());
-    for (name, e) in repl {
-        let old = repl_map.insert(name, e);
-        assert_eq!(old, None, "duplicate replacement for {name} in subst");
+fn parse_sort(sort: &Sexp) -> Result<Sort, ModelError> {
+    match sort.atom_s() {
+        Some("Bool") => Ok(Sort::Bool),
+        Some(sort_name) => Ok(Sort::uninterpreted(sort_name)),
+        None => err(format!("unsupported sort {sort}")),
     }
-    let mut new = e.clone();
-    subst_hashmap(&repl_map, &mut new);
-    new
 }
 
-fn parse_sort(sort: &Sexp) -> Sort {
-    let sort_name = sort.atom_s().unwrap();
-    if sort_name == "Bool" {
-        Sort::Bool
-    } else {
-        Sort::uninterpreted(sort_name)
-    }
-}
-
-fn parse_binders(binders: &Sexp) -> Vec<(String, Sort)> {
-    let binder_sexps = binders.list().unwrap();
+fn parse_binders(binders: &Sexp) -> Result<Vec<(String, Sort)>, ModelError> {
+    let binder_sexps = binders
+        .list()
+        .ok_or_else(|| ModelError(format!("binders {binders} should be a list")))?;
     binder_sexps
         .iter()
         .map(|b| {
             // b should be a list of (name type)
-            let ss = b.list().unwrap();
-            assert!(ss.len() == 2, "binder is ill-formed");
-            let name = ss[0].atom_s().unwrap().to_string();
-            let sort = parse_sort(&ss[1]);
-            (name, sort)
+            match b.list() {
+                Some([name, sort]) => {
+                    let name = name
+                        .atom_s()
+                        .ok_or_else(|| ModelError(format!("ill-formed binder {b}")))?;
+                    Ok((name.to_string(), parse_sort(sort)?))
+                }
+                _ => err(format!("ill-formed binder {b}")),
+            }
         })
         .collect()
 }
 
-pub(crate) fn parse_z3(model: &Sexp) -> Model {
+/// Parse the arguments to a `(define-fun name binders sort body)`.
+///
+/// A constant of sort `(Array S1 ... Sn T)` (as solvers use for auxiliary
+/// definitions) is turned into a function from `S1, ..., Sn` to `T`.
+fn parse_define_fun(args: &[Sexp]) -> Result<(String, ModelSymbol), ModelError> {
+    if let [name, binders, ret_sort, body] = args {
+        let name = name
+            .atom_s()
+            .ok_or_else(|| ModelError(format!("define-fun of non-atom {name}")))?;
+        let sym = match ret_sort.app() {
+            Some(("Array", [index_sorts @ .., elem_sort]))
+                if !index_sorts.is_empty() && binders == &sexp_l([]) =>
+            {
+                let binders = index_sorts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, sort)| Ok((format!("x!array!{i}"), parse_sort(sort)?)))
+                    .collect::<Result<Vec<_>, ModelError>>()?;
+                let body = app(
+                    "select",
+                    iter::once(body.clone()).chain(binders.iter().map(|(x, _)| atom_s(x))),
+                );
+                ModelSymbol {
+                    binders,
+                    body,
+                    ret_sort: parse_sort(elem_sort)?,
+                }
+            }
+            _ => ModelSymbol {
+                binders: parse_binders(binders)?,
+                body: body.clone(),
+                ret_sort: parse_sort(ret_sort)?,
+            },
+        };
+        Ok((name.to_string(), sym))
+    } else {
+        err("define-fun should have name, binders, sort, body".to_string())
+    }
+}
+
+pub(crate) fn parse_z3(model: &Sexp) -> Result<Model, ModelError> {
     let mut universes: HashMap<String, Vec<String>> = HashMap::new();
     let mut symbols: HashMap<String, ModelSymbol> = HashMap::new();
     if let Some(ss) = model.list() {
@@ -121,32 +149,21 @@ pub(crate) fn parse_z3(model: &Sexp) -> Model {
         for s in ss {
             if let Some((head, args)) = s.app() {
                 if head == "declare-fun" {
-                    assert_eq!(args.len(), 3, "declare-fun should have name, binders, sort");
-                    let name = args[0].atom_s().unwrap().to_string();
-                    let binders = &args[1];
-                    let sort = args[2].atom_s().unwrap();
                     // this is a universe element, with no body
-                    assert_eq!(
-                        binders,
-                        &sexp_l([]),
-                        "universe elements should take no argments"
-                    );
-                    universes.entry(sort.to_string()).or_default().push(name);
+                    match args {
+                        [name, binders, sort] if binders == &sexp_l([]) => {
+                            match (name.atom_s(), sort.atom_s()) {
+                                (Some(name), Some(sort)) => universes
+                                    .entry(sort.to_string())
+                                    .or_default()
+                                    .push(name.to_string()),
+                                _ => return err(format!("unexpected universe element {s}")),
+                            }
+                        }
+                        _ => return err(format!("unexpected universe element {s}")),
+                    }
                 } else if head == "define-fun" {
-                    assert_eq!(
-                        args.len(),
-                        4,
-                        "define-fun should have name, binders, sort, body"
-                    );
-                    let name = args[0].atom_s().unwrap().to_string();
-                    let binders = parse_binders(&args[1]);
-                    let ret_sort = parse_sort(&args[2]);
-                    let body = args[3].clone();
-                    let sym = ModelSymbol {
-                        binders,
-                        body,
-                        ret_sort,
-                    };
+                    let (name, sym) = parse_define_fun(args)?;
                     symbols.insert(name, sym);
                 } else if head == "forall" {
                     // ignore, cardinality constraint
@@ -156,10 +173,10 @@ pub(crate) fn parse_z3(model: &Sexp) -> Model {
             }
         }
     }
-    Model { universes, symbols }
+    Ok(Model { universes, symbols })
 }
 
-pub(crate) fn parse_cvc(model: &Sexp, version5: bool) -> Model {
+pub(crate) fn parse_cvc(model: &Sexp, version5: bool) -> Result<Model, ModelError> {
     let version4 = !version5;
     let mut universe_cardinalities: HashMap<String, usize> = HashMap::new();
     let mut symbols: HashMap<String, ModelSymbol> = HashMap::new();
@@ -171,8 +188,9 @@ pub(crate) fn parse_cvc(model: &Sexp, version5: bool) -> Model {
         // remove a leading "model" for CVC4
         let ss = {
             if version4 {
-                assert!(!ss.is_empty());
-                assert_eq!(ss[0], atom_s("model"));
+                if ss.first() != Some(&atom_s("model")) {
+                    return err("CVC4 model should start with `model`".to_string());
+                }
                 &ss[1..]
             } else {
                 ss
@@ -182,27 +200,19 @@ pub(crate) fn parse_cvc(model: &Sexp, version5: bool) -> Model {
             if let Sexp::Comment(s) = s {
                 if let Some(cs) = CARDINALITY_RE.captures(s) {
                     let sort = cs.get(1).unwrap().as_str().to_string();
-                    let card = cs.get(2).unwrap().as_str().parse::<usize>().unwrap();
+                    let card = cs
+                        .get(2)
+                        .unwrap()
+                        .as_str()
+                        .parse::<usize>()
+                        .map_err(|e| ModelError(format!("bad cardinality in `{s}`: {e}")))?;
                     universe_cardinalities.insert(sort, card);
                 }
                 continue;
             }
             if let Some((head, args)) = s.app() {
                 if head == "define-fun" {
-                    assert_eq!(
-                        args.len(),
-                        4,
-                        "define-fun should have name, binders, sort, body"
-                    );
-                    let name = args[0].atom_s().unwrap().to_string();
-                    let binders = parse_binders(&args[1]);
-                    let ret_sort = parse_sort(&args[2]);
-                    let body = args[3].clone();
-                    let sym = ModelSymbol {
-                        binders,
-                        body,
-                        ret_sort,
-                    };
+                    let (name, sym) = parse_define_fun(args)?;
                     symbols.insert(name, sym);
                 } else if version4 && head == "declare-sort" {
                     // cvc4 only
@@ -226,7 +236,7 @@ pub(crate) fn parse_cvc(model: &Sexp, version5: bool) -> Model {
             .collect();
         universes.insert(sort, elements);
     }
-    Model { universes, symbols }
+    Ok(Model { universes, symbols })
 }
 
 impl PartialInterp {
@@ -251,166 +261,539 @@ impl PartialInterp {
         self.interps.contains_key(f)
     }
 
-    fn eval(&self, f: &str, args: &[Atom]) -> String {
-        let (interp, ret_sort) = &self.interps[f];
+    fn eval(&self, f: &str, args: &[Atom]) -> Result<String, ModelError> {
+        let (interp, ret_sort) = self
+            .interps
+            .get(f)
+            .ok_or_else(|| ModelError(format!("no interpretation for {f}")))?;
         let args = args
             .iter()
             .map(|atom| match atom {
-                Atom::I(_) => panic!("cannot evaluate on integers"),
+                Atom::I(_) => err(format!("cannot evaluate {f} on integers")),
                 Atom::S(name) => {
                     if name == "true" {
-                        1
+                        Ok(1)
                     } else if name == "false" {
-                        0
+                        Ok(0)
                     } else {
-                        self.term_to_element[name]
+                        self.term_to_element
+                            .get(name)
+                            .copied()
+                            .ok_or_else(|| ModelError(format!("unknown element {name}")))
                     }
                 }
             })
-            .collect::<Vec<usize>>();
+            .collect::<Result<Vec<usize>, _>>()?;
+        let in_range = args.len() + 1 == interp.shape.len()
+            && iter::zip(&args, &interp.shape).all(|(a, card)| a < card);
+        if !in_range {
+            return err(format!("arguments out of range for {f}"));
+        }
         let result_el = interp.get(&args);
         match ret_sort {
             Sort::Bool => {
                 if result_el == 1 {
-                    "true".to_string()
+                    Ok("true".to_string())
                 } else {
-                    "false".to_string()
+                    Ok("false".to_string())
                 }
             }
-            Sort::Uninterpreted(sort) => self.universes[sort][result_el].clone(),
+            Sort::Uninterpreted(sort) => self
+                .universes
+                .get(sort)
+                .and_then(|elements| elements.get(result_el))
+                .cloned()
+                .ok_or_else(|| ModelError(format!("no element {result_el} of sort {sort}"))),
         }
     }
 }
 
-impl Model {
-    fn eval_bool(
-        &self,
-        repl: &HashMap<&str, Atom>,
-        part_eval: &PartialInterp,
-        e: &Sexp,
-    ) -> Result<bool, EvalError> {
-        let a = self.smt_eval(repl, part_eval, e)?;
-        match a {
+/// The result of evaluating an expression in a model.
+#[derive(Debug, Clone)]
+enum Value {
+    /// A value of a (non-array) sort: a boolean, a universe element, or an
+    /// integer.
+    Atom(Atom),
+    /// A function, which arises from a `lambda` or from array-valued terms
+    /// such as `(_ as-array f)`.
+    Fun(Rc<Fun>),
+}
+
+/// A function value, applied with [`Model::apply`].
+#[derive(Debug)]
+enum Fun {
+    /// `(lambda ((x1 s1) ... (xn sn)) body)`, closed over the environment it
+    /// was evaluated in
+    Lambda {
+        params: Vec<String>,
+        body: Sexp,
+        env: Env,
+    },
+    /// A function defined in the model, referenced by name or by `(_ as-array f)`
+    Symbol(String),
+    /// A constant array `((as const (Array ...)) v)`
+    Const(Value),
+    /// `(store base i1 ... in v)`
+    Store {
+        base: Value,
+        index: Vec<Value>,
+        val: Value,
+    },
+}
+
+/// Bindings of variables to values, for `let`, `lambda`, and model functions.
+type Env = HashMap<String, Value>;
+
+impl Value {
+    fn atom(&self) -> Result<&Atom, ModelError> {
+        match self {
+            Value::Atom(a) => Ok(a),
+            Value::Fun(_) => err("expected a value, got a function".to_string()),
+        }
+    }
+
+    fn bool(&self) -> Result<bool, ModelError> {
+        match self.atom()? {
             Atom::S(s) if s == "true" => Ok(true),
             Atom::S(s) if s == "false" => Ok(false),
-            _ => Err(EvalError(format!("unexpected bool: {a}"))),
+            a => err(format!("unexpected bool: {a}")),
         }
     }
 
+    fn fun(&self) -> Result<&Fun, ModelError> {
+        match self {
+            Value::Fun(f) => Ok(f),
+            Value::Atom(a) => err(format!("expected a function, got {a}")),
+        }
+    }
+
+    /// Compare two values for equality. Functions cannot be compared.
+    fn equals(&self, other: &Value) -> Result<bool, ModelError> {
+        Ok(self.atom()? == other.atom()?)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Atom(bool_atom(b))
+    }
+}
+
+/// Maximum nesting of model function calls, to report (rather than overflow
+/// the stack on) a model with cyclic definitions.
+const MAX_EVAL_DEPTH: usize = 100;
+
+impl Model {
     /// Evaluate an SMT expression, reducing constants with known semantics. Fails
     /// if this does not result in an Atom.
+    ///
+    /// Supports the expressions that solvers use in models: `let`, `ite`,
+    /// boolean connectives, `=` and `distinct`, references to other functions
+    /// in the model, and function values given by `lambda`, `as-array`,
+    /// constant arrays, `select`, and `store`.
     pub fn smt_eval(
         &self,
         repl: &HashMap<&str, Atom>,
         part_eval: &PartialInterp,
         e: &Sexp,
-    ) -> Result<Atom, EvalError> {
-        // println!("evaluating {e}");
-        let go_bool = |e: &Sexp| self.eval_bool(repl, part_eval, e);
-        let go = |e: &Sexp| self.smt_eval(repl, part_eval, e);
+    ) -> Result<Atom, ModelError> {
+        let env: Env = repl
+            .iter()
+            .map(|(&name, a)| (name.to_string(), Value::Atom(a.clone())))
+            .collect();
+        let v = self.eval(&env, part_eval, 0, e)?;
+        Ok(v.atom()?.clone())
+    }
+
+    fn eval_args(
+        &self,
+        env: &Env,
+        part_eval: &PartialInterp,
+        depth: usize,
+        args: &[&Sexp],
+    ) -> Result<Vec<Value>, ModelError> {
+        args.iter()
+            .map(|e| self.eval(env, part_eval, depth, e))
+            .collect()
+    }
+
+    fn eval_bool(
+        &self,
+        env: &Env,
+        part_eval: &PartialInterp,
+        depth: usize,
+        e: &Sexp,
+    ) -> Result<bool, ModelError> {
+        self.eval(env, part_eval, depth, e)?.bool()
+    }
+
+    fn eval(
+        &self,
+        env: &Env,
+        part_eval: &PartialInterp,
+        depth: usize,
+        e: &Sexp,
+    ) -> Result<Value, ModelError> {
+        let go = |e: &Sexp| self.eval(env, part_eval, depth, e);
+        let go_bool = |e: &Sexp| self.eval_bool(env, part_eval, depth, e);
         match e {
-            Sexp::Atom(a) => {
-                if let Some(id) = a.s() {
-                    Ok(repl.get(id).unwrap_or(a).clone())
+            Sexp::Atom(a @ Atom::I(_)) => Ok(Value::Atom(a.clone())),
+            Sexp::Atom(Atom::S(id)) => {
+                if let Some(v) = env.get(id) {
+                    Ok(v.clone())
+                } else if let Some(sym) = self.symbols.get(id) {
+                    if sym.binders.is_empty() {
+                        self.call(part_eval, depth, id, vec![])
+                    } else {
+                        // a reference to a function, as in (select f x)
+                        Ok(Value::Fun(Rc::new(Fun::Symbol(id.clone()))))
+                    }
                 } else {
-                    Ok(a.clone())
+                    // a universe element or a literal
+                    Ok(Value::Atom(Atom::S(id.clone())))
                 }
             }
-            Sexp::Comment(_) => Err(EvalError("comment".to_string())),
+            Sexp::Comment(_) => err("comment".to_string()),
             Sexp::List(ss) => {
                 let ss = ss
                     .iter()
                     .filter(|s| !matches!(s, Sexp::Comment(_)))
                     .collect::<Vec<_>>();
                 if ss.is_empty() {
-                    return Err(EvalError("empty list".to_string()));
+                    return err("empty list".to_string());
                 }
-                let head = ss[0].atom_s().ok_or(EvalError(format!(
-                    "unexpected function {} (non-atom)",
-                    ss[0]
-                )))?;
                 let args = &ss[1..];
-                if head == "and" {
-                    for arg in args {
-                        let v = go_bool(arg)?;
-                        if !v {
-                            return Ok(bool_atom(false));
+                let head = match ss[0].atom_s() {
+                    Some(head) => head,
+                    None => {
+                        // the head is itself an expression, as in ((as const
+                        // (Array A B)) v) or ((lambda ...) x)
+                        if let Some(("as", [c, _])) = ss[0].app() {
+                            if c.atom_s() == Some("const") && args.len() == 1 {
+                                let v = go(args[0])?;
+                                return Ok(Value::Fun(Rc::new(Fun::Const(v))));
+                            }
+                        }
+                        let f = go(ss[0])?;
+                        let args = self.eval_args(env, part_eval, depth, args)?;
+                        return self.apply(part_eval, depth, &f, args);
+                    }
+                };
+                match (head, args) {
+                    ("true" | "false", []) => Ok(Value::Atom(Atom::S(head.to_string()))),
+                    ("and", _) => {
+                        for arg in args {
+                            if !go_bool(arg)? {
+                                return Ok(false.into());
+                            }
+                        }
+                        Ok(true.into())
+                    }
+                    ("or", _) => {
+                        for arg in args {
+                            if go_bool(arg)? {
+                                return Ok(true.into());
+                            }
+                        }
+                        Ok(false.into())
+                    }
+                    ("not", [arg]) => Ok((!go_bool(arg)?).into()),
+                    ("=>", [_, ..]) => {
+                        // right associative: (=> a b c) is (=> a (=> b c))
+                        let (last, hyps) = args.split_last().unwrap();
+                        for hyp in hyps {
+                            if !go_bool(hyp)? {
+                                return Ok(true.into());
+                            }
+                        }
+                        Ok(go_bool(last)?.into())
+                    }
+                    ("xor", _) => {
+                        let mut v = false;
+                        for arg in args {
+                            v ^= go_bool(arg)?;
+                        }
+                        Ok(v.into())
+                    }
+                    ("=", [_, _, ..]) => {
+                        let vals = self.eval_args(env, part_eval, depth, args)?;
+                        for pair in vals.windows(2) {
+                            if !pair[0].equals(&pair[1])? {
+                                return Ok(false.into());
+                            }
                         }
+                        Ok(true.into())
                     }
-                    return Ok(bool_atom(true));
-                } else if head == "or" {
-                    for arg in args {
-                        let v = go_bool(arg)?;
-                        if v {
-                            return Ok(bool_atom(true));
+                    ("distinct", [_, _, ..]) => {
+                        let vals = self.eval_args(env, part_eval, depth, args)?;
+                        for (i, v1) in vals.iter().enumerate() {
+                            for v2 in &vals[i + 1..] {
+                                if v1.equals(v2)? {
+                                    return Ok(false.into());
+                                }
+                            }
                         }
+                        Ok(true.into())
                     }
-                    return Ok(bool_atom(false));
-                } else if head == "not" || head == "!" {
-                    assert_eq!(args.len(), 1);
-                    let x = go_bool(args[0])?;
-                    Ok(bool_atom(!x))
-                } else if head == "as" {
+                    ("ite", [cond, then, else_]) => {
+                        if go_bool(cond)? {
+                            go(then)
+                        } else {
+                            go(else_)
+                        }
+                    }
+                    // !, as in (! e :named n), only annotates e
+                    ("!", [e, ..]) => go(e),
                     // type cast (basically ignored)
-                    go(args[0])
-                } else if head == "=" {
-                    assert_eq!(args.len(), 2);
-                    let lhs = go(args[0])?;
-                    let rhs = go(args[1])?;
-                    Ok(bool_atom(lhs == rhs))
-                } else if head == "ite" {
-                    assert_eq!(args.len(), 3);
-                    let cond = go_bool(args[0])?;
-                    if cond {
-                        go(args[1])
-                    } else {
-                        go(args[2])
+                    ("as", [e, _]) => go(e),
+                    ("_", [as_array, f]) if as_array.atom_s() == Some("as-array") => {
+                        match f.atom_s() {
+                            Some(f) if self.symbols.contains_key(f) => {
+                                Ok(Value::Fun(Rc::new(Fun::Symbol(f.to_string()))))
+                            }
+                            _ => err(format!("as-array of unknown function {f}")),
+                        }
                     }
-                } else if head == "let" {
-                    // (let ((x1 e1) (x2 e2)) e)
-                    assert_eq!(args.len(), 2);
-                    let binders = args[0]
-                        .list()
-                        .expect("let argument should be a list of binders");
-                    let repl: Vec<(&str, Sexp)> = binders
-                        .iter()
-                        .map(|binder| {
-                            let (name, e) = binder.app().expect("unexpected binder");
-                            assert_eq!(e.len(), 1, "unexpected binder val");
-                            (name, e[0].clone())
-                        })
-                        .collect();
-                    let e = subst(&repl, args[1]);
-                    go(&e)
-                } else if self.symbols.contains_key(head) {
-                    if part_eval.has_eval(head) {
-                        let args = args.iter().flat_map(|e| go(e)).collect::<Vec<_>>();
-                        let res = part_eval.eval(head, &args);
-                        return Ok(Atom::S(res));
+                    ("let", [binders, body]) => {
+                        // (let ((x1 e1) (x2 e2)) e)
+                        //
+                        // the bindings are parallel, so each ei is evaluated
+                        // in the outer environment
+                        let binders = binders
+                            .list()
+                            .ok_or_else(|| ModelError(format!("ill-formed let binders {e}")))?;
+                        let mut new_env = env.clone();
+                        for binder in binders {
+                            match binder.app() {
+                                Some((name, [val])) => {
+                                    new_env.insert(name.to_string(), go(val)?);
+                                }
+                                _ => return err(format!("unexpected let binder {binder}")),
+                            }
+                        }
+                        self.eval(&new_env, part_eval, depth, body)
+                    }
+                    ("lambda", [binders, body]) => {
+                        let params = parse_lambda_params(binders)?;
+                        Ok(Value::Fun(Rc::new(Fun::Lambda {
+                            params,
+                            body: (*body).clone(),
+                            env: env.clone(),
+                        })))
+                    }
+                    ("select", [array, ..]) => {
+                        let f = go(array)?;
+                        let args = self.eval_args(env, part_eval, depth, &args[1..])?;
+                        self.apply(part_eval, depth, &f, args)
+                    }
+                    ("store", [array, _, _, ..]) => {
+                        let base = go(array)?;
+                        let mut rest = self.eval_args(env, part_eval, depth, &args[1..])?;
+                        let val = rest.pop().unwrap();
+                        Ok(Value::Fun(Rc::new(Fun::Store {
+                            base,
+                            index: rest,
+                            val,
+                        })))
+                    }
+                    _ => {
+                        let args = self.eval_args(env, part_eval, depth, args)?;
+                        if let Some(f) = env.get(head) {
+                            self.apply(part_eval, depth, f, args)
+                        } else if self.symbols.contains_key(head) {
+                            self.call(part_eval, depth, head, args)
+                        } else {
+                            err(format!("unexpected function {head}"))
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Call a function defined in the model.
+    fn call(
+        &self,
+        part_eval: &PartialInterp,
+        depth: usize,
+        f: &str,
+        args: Vec<Value>,
+    ) -> Result<Value, ModelError> {
+        if depth > MAX_EVAL_DEPTH {
+            return err(format!("evaluating {f} is too deeply nested"));
+        }
+        if part_eval.has_eval(f) {
+            let args = args
+                .iter()
+                .map(|v| v.atom().cloned())
+                .collect::<Result<Vec<_>, _>>()?;
+            return Ok(Value::Atom(Atom::S(part_eval.eval(f, &args)?)));
+        }
+        let Some(ModelSymbol { binders, body, .. }) = self.symbols.get(f) else {
+            return err(format!("unknown function {f}"));
+        };
+        if binders.len() == args.len() {
+            let env = iter::zip(binders, args)
+                .map(|((name, _), v)| (name.clone(), v))
+                .collect();
+            self.eval(&env, part_eval, depth + 1, body)
+        } else if binders.is_empty() {
+            // f is defined to be a function value, such as (_ as-array g)
+            let fun = self.eval(&Env::new(), part_eval, depth + 1, body)?;
+            self.apply(part_eval, depth + 1, &fun, args)
+        } else {
+            err(format!(
+                "wrong number of arguments to {f}: expected {}, got {}",
+                binders.len(),
+                args.len()
+            ))
+        }
+    }
+
+    /// Apply a function value to some arguments.
+    fn apply(
+        &self,
+        part_eval: &PartialInterp,
+        depth: usize,
+        f: &Value,
+        args: Vec<Value>,
+    ) -> Result<Value, ModelError> {
+        if depth > MAX_EVAL_DEPTH {
+            return err("function application is too deeply nested".to_string());
+        }
+        match f.fun()? {
+            Fun::Lambda { params, body, env } => {
+                if params.len() != args.len() {
+                    return err(format!(
+                        "wrong number of arguments to lambda: expected {}, got {}",
+                        params.len(),
+                        args.len()
+                    ));
+                }
+                let mut env = env.clone();
+                env.extend(iter::zip(params.iter().cloned(), args));
+                self.eval(&env, part_eval, depth + 1, body)
+            }
+            Fun::Symbol(name) => self.call(part_eval, depth + 1, name, args),
+            Fun::Const(v) => Ok(v.clone()),
+            Fun::Store { base, index, val } => {
+                if index.len() != args.len() {
+                    return err("wrong number of indices to store".to_string());
+                }
+                for (i, arg) in iter::zip(index, &args) {
+                    if !i.equals(arg)? {
+                        return self.apply(part_eval, depth + 1, base, args);
                     }
-                    let ModelSymbol { binders, body, .. } = &self.symbols[head];
-                    assert_eq!(
-                        binders.len(),
-                        args.len(),
-                        "wrong number of arguments to {head}"
-                    );
-                    // first evaluate all arguments
-                    let args = args
-                        .iter()
-                        .map(|e| -> Result<Sexp, EvalError> {
-                            let a = go(e)?;
-                            Ok(Sexp::Atom(a))
-                        })
-                        .collect::<Result<Vec<_>, _>>()?;
-                    // then substitute for each binder
-                    let repl: Vec<(&str, Sexp)> = iter::zip(binders, args.iter().cloned())
-                        .map(|(binder, e)| (binder.0.as_str(), e))
-                        .collect();
-                    go(&subst(&repl, body))
-                } else {
-                    Err(EvalError(format!("unexpected function {head}")))
                 }
+                Ok(val.clone())
             }
         }
     }
 }
+
+/// Get the names of the parameters of a lambda (ignoring their sorts).
+fn parse_lambda_params(binders: &Sexp) -> Result<Vec<String>, ModelError> {
+    let binders = binders
+        .list()
+        .ok_or_else(|| ModelError(format!("lambda binders {binders} should be a list")))?;
+    binders
+        .iter()
+        .map(|b| match b.app() {
+            Some((name, [_sort])) => Ok(name.to_string()),
+            _ => err(format!("ill-formed lambda binder {b}")),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smtlib::sexp::parse;
+
+    fn eval_in(model: &str, e: &str) -> Result<Atom, ModelError> {
+        let model = parse_z3(&parse(model).unwrap()).unwrap();
+        let part_interp = PartialInterp::for_model(&model);
+        model.smt_eval(&HashMap::new(), &part_interp, &parse(e).unwrap())
+    }
+
+    const MODEL: &str = r#"
+(
+  (declare-fun node!val!0 () node)
+  (declare-fun node!val!1 () node)
+  (define-fun n () node node!val!1)
+  (define-fun k!0 ((x!0 node)) Bool (= x!0 node!val!1))
+  (define-fun p () (Array node Bool) (_ as-array k!0))
+  (define-fun q ((x!0 node)) Bool (select p x!0))
+  (define-fun r ((x!0 node) (x!1 node)) Bool
+    ((lambda ((a node) (b node)) (and (distinct a b) (k!0 b))) x!0 x!1))
+  (define-fun s ((x!0 node)) Bool
+    (let ((a!1 (k!0 x!0)) (a!2 (= x!0 n))) (and a!1 a!2)))
+  (define-fun t () (Array node Bool)
+    (store ((as const (Array node Bool)) false) node!val!0 true))
+)
+"#;
+
+    #[test]
+    fn test_eval_function_values() {
+        let t = atom_s("true");
+        let f = atom_s("false");
+        let eval = |e: &str| Sexp::Atom(eval_in(MODEL, e).unwrap());
+        assert_eq!(eval("n"), atom_s("node!val!1"));
+        assert_eq!(eval("(q node!val!1)"), t);
+        assert_eq!(eval("(q node!val!0)"), f);
+        assert_eq!(eval("(p node!val!1)"), t);
+        assert_eq!(eval("(r node!val!0 node!val!1)"), t);
+        assert_eq!(eval("(r node!val!1 node!val!1)"), f);
+        assert_eq!(eval("(s node!val!1)"), t);
+        assert_eq!(eval("(s node!val!0)"), f);
+        assert_eq!(eval("(select t node!val!0)"), t);
+        assert_eq!(eval("(select t node!val!1)"), f);
+        assert_eq!(eval("(=> (q node!val!0) false)"), t);
+        assert_eq!(eval("(xor true (q node!val!1))"), f);
+    }
+
+    #[test]
+    fn test_eval_errors() {
+        // these all used to panic
+        assert!(eval_in(MODEL, "(unknown_fun node!val!0)").is_err());
+        assert!(eval_in(MODEL, "(not node!val!0)").is_err());
+        assert!(eval_in(MODEL, "(k!0 node!val!0 node!val!1)").is_err());
+        assert!(eval_in(MODEL, "p").is_err());
+        assert!(eval_in("((define-fun a () Bool (not a)))", "a").is_err());
+        assert!(parse_z3(&parse("((define-fun a Bool))").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_partial_interp_errors() {
+        let model = parse_z3(&parse(MODEL).unwrap()).unwrap();
+        let mut part_interp = PartialInterp::for_model(&model);
+        let node = Sort::Uninterpreted("node".to_string());
+        part_interp.interps.insert(
+            "f".to_string(),
+            (Interpretation::new(&[2, 2], |xs| 1 - xs[0]), node.clone()),
+        );
+        // an interpretation whose return sort has no universe in the model
+        part_interp.interps.insert(
+            "g".to_string(),
+            (
+                Interpretation::new(&[2, 2], |xs| xs[0]),
+                Sort::Uninterpreted("other".to_string()),
+            ),
+        );
+        let n0 = Atom::S("node!val!0".to_string());
+        let n1 = Atom::S("node!val!1".to_string());
+        assert_eq!(
+            part_interp.eval("f", std::slice::from_ref(&n0)).unwrap(),
+            "node!val!1"
+        );
+        assert!(part_interp
+            .eval("missing", std::slice::from_ref(&n0))
+            .is_err());
+        assert!(part_interp.eval("f", &[n0.clone(), n1]).is_err());
+        assert!(part_interp.eval("f", &[]).is_err());
+        assert!(part_interp
+            .eval("f", &[Atom::S("node!val!7".to_string())])
+            .is_err());
+        assert!(part_interp.eval("g", &[n0]).is_err());
+    }
+}
@@ -6,10 +6,12 @@
 use bounded::checker::CheckerAnswer;
 use codespan_reporting::diagnostic::{Diagnostic, Label};
 use path_slash::PathExt;
+use smtlib::transcript::{sat_kind, Transcript};
 use solver::basics::SingleSolver;
 use std::collections::HashMap;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 use std::{fs, process};
 
 use clap::Args;
@@ -20,16 +22,23 @@ use codespan_reporting::{
         termcolor::{ColorChoice, StandardStream},
     },
 };
-use fly::semantics::models_to_string;
-use fly::syntax::{Signature, Sort};
-use fly::{self, parser::parse_error_diagnostic, printer, sorts, timing};
+use fly::semantics::{models_to_string, Model, Trace};
+use fly::syntax::{Module, Signature, Sort, Span, Spanned, Term, ThmStmt};
+use fly::{self, parser::parse_error_diagnostic, printer, sorts, timing, transitions::extract};
+use inference::auto::qalpha_auto;
 use inference::basics::{parse_quantifier, InferenceConfig, QfBody};
 use inference::fixpoint::{self, qalpha_dynamic};
 use inference::houdini;
+use inference::pdr::{Pdr, PdrResult};
 use inference::quant::QuantifierConfig;
-use inference::updr::Updr;
-use solver::backends;
+use inference::updr::{Updr, UpdrResult};
+use solver::backends::{self, GenericBackend};
 use solver::conf::SolverConf;
+use solver::imp::Backend;
+use solver::{solver_path, SatResp};
+use verify::certificate::{check_certificate, emit_certificate, CertificateError};
+use verify::epr::{epr_check, EprViolation};
+use verify::error::QueryError;
 use verify::module::verify_module;
 
 #[derive(clap::ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
@@ -58,6 +67,11 @@ struct SolverArgs {
     /// Output smt2 file alongside input file
     smt: bool,
 
+    #[arg(long, global = true)]
+    /// Like --smt, but also record solver replies and timing so the file can
+    /// be replayed with replay-smt
+    smt_transcript: bool,
+
     #[arg(long, default_value_t = 600, global = true)]
     /// SMT solver timeout in seconds
     timeout: usize,
@@ -65,6 +79,25 @@ struct SolverArgs {
     #[arg(long, default_value_t = 0, global = true)]
     /// SMT solver random seed
     solver_seed: usize,
+
+    #[arg(long, global = true)]
+    /// Deterministic per-query resource limit for the SMT solver (Z3's rlimit,
+    /// CVC's rlimit-per)
+    rlimit: Option<usize>,
+
+    #[arg(long, global = true)]
+    /// Memory limit for each SMT solver process, in megabytes
+    memory_limit: Option<usize>,
+
+    #[arg(long, global = true)]
+    /// SMT-LIB logic to use (for example, UF for EPR files). Overrides the
+    /// file's `smt_logic` pragma
+    smt_logic: Option<String>,
+
+    #[arg(long, global = true)]
+    /// Extra option to pass to the solver, given as KEY=VALUE as in --solver-opt
+    /// mbqi=true. Applied after the file's `solver_opt` pragmas
+    solver_opt: Vec<String>,
 }
 
 #[derive(Args, Clone, Debug, PartialEq, Eq)]
@@ -76,10 +109,26 @@ struct VerifyArgs {
     /// Print timing statistics
     time: bool,
 
+    #[arg(long)]
+    /// On success, write the queries (and proofs, with cvc5) to this directory
+    /// so they can be checked with check-certificate
+    emit_certificate: Option<String>,
+
     /// File name for a .fly file
     file: String,
 }
 
+#[derive(Args, Clone, Debug, PartialEq, Eq)]
+struct UpdrVerifyArgs {
+    #[command(flatten)]
+    verify: VerifyArgs,
+
+    #[arg(long)]
+    /// Write the inferred invariant into the input file as the proof of each
+    /// assertion
+    write_proof: bool,
+}
+
 #[derive(Args, Clone, Debug, PartialEq, Eq)]
 struct QuantifierConfigArgs {
     #[arg(long)]
@@ -220,6 +269,54 @@ struct InferenceConfigArgs {
     #[arg(long)]
     /// Grow the domain of quantified lemmas by this factor each iteration (default: 5)
     growth_factor: Option<usize>,
+
+    #[arg(long)]
+    /// Periodically save progress to this file
+    checkpoint: Option<String>,
+
+    #[arg(long)]
+    /// Resume from a file saved with --checkpoint (using the same file and
+    /// options)
+    resume: Option<String>,
+
+    #[arg(long, default_value_t = 1, conflicts_with_all = ["checkpoint", "resume"])]
+    /// Explore the lemma domains of each run with this many concurrent
+    /// workers, which share initial states and fixpoints (not supported with
+    /// --checkpoint or --resume)
+    domain_workers: usize,
+
+    #[arg(long, conflicts_with_all = ["checkpoint", "resume"])]
+    /// Learn each run's invariant by separating positive, negative, and
+    /// implication samples instead of by weakening (not supported with
+    /// --checkpoint or --resume)
+    ice: bool,
+
+    #[arg(long)]
+    /// Before inference, weaken the frame with every state reachable within
+    /// these sort bounds, given as SORT=N as in --bound node=2 (every sort
+    /// needs a bound)
+    bound: Vec<String>,
+
+    #[arg(long)]
+    /// If the final fixpoint does not prove safety, explain why: report a
+    /// counterexample to safety, whether it is reachable, and how the lemma
+    /// domain would need to grow to exclude it
+    explain: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["checkpoint", "resume", "bound", "ice", "domain_workers"]
+    )]
+    /// Ignore the search options and instead try a schedule of configurations
+    /// derived from the signature, from cheap to expensive, reporting the
+    /// cheapest one which proves safety (only the solver options are kept; not
+    /// supported with --checkpoint, --resume, --bound, --ice, or
+    /// --domain-workers)
+    auto: bool,
+
+    #[arg(long, default_value_t = 3600)]
+    /// Total time budget (in seconds) for the configurations tried by --auto
+    auto_budget: u64,
 }
 
 impl InferenceConfigArgs {
@@ -262,6 +359,20 @@ impl InferenceConfigArgs {
             until_safe: self.until_safe,
             abort_unsafe: self.abort_unsafe,
             growth_factor: self.growth_factor,
+            memory_limit_mb: None,
+            rlimit: None,
+            solver: backends::SolverType::Z3,
+            checkpoint: self.checkpoint.clone(),
+            resume: self.resume.clone(),
+            domain_workers: self.domain_workers,
+            ice: self.ice,
+            bounds: if self.bound.is_empty() {
+                None
+            } else {
+                Some(parse_universe(&self.bound, sig))
+            },
+            explain: self.explain,
+            deadline: None,
         };
 
         if self.qf_body.is_none() {
@@ -294,6 +405,11 @@ enum InferCommand {
     },
     /// Run quantified-alpha-from-below
     Qalpha(QalphaArgs),
+    /// Run PDR∀ (universally quantified IC3/PDR)
+    Pdr {
+        /// File name for a .fly file
+        file: String,
+    },
 }
 
 #[derive(Args, Clone, Debug, PartialEq, Eq)]
@@ -309,6 +425,16 @@ struct InferArgs {
     /// Don't print the found invariant (for testing)
     no_print_invariant: bool,
 
+    #[arg(long, global = true)]
+    /// Write the inferred invariant into the input file as the proof of the
+    /// matching assertion (of every assertion, for qalpha), then verify it
+    write_proof: bool,
+
+    #[arg(long, global = true)]
+    /// File of candidate invariants to start from, one per line written as
+    /// `candidate t` (or `invariant t`)
+    candidates: Option<String>,
+
     #[command(subcommand)]
     infer_cmd: InferCommand,
 }
@@ -333,33 +459,40 @@ impl BoundedArgs {
     ///
     /// Ensures that every sort in the given signature is given a bound.
     fn get_universe(&self, sig: &Signature) -> HashMap<String, usize> {
-        let mut universe: HashMap<String, usize> = HashMap::new();
-        for b in &self.bound {
-            if let [sort_name, bound_size] = b.split('=').collect::<Vec<&str>>()[..] {
-                let sort_name = sort_name.to_string();
-                if !sig.sorts.contains(&sort_name) {
-                    eprintln!("unknown sort name {sort_name} in bound {b}");
-                    process::exit(1);
-                }
-                if let Ok(bound_size) = bound_size.parse::<usize>() {
-                    universe.insert(sort_name, bound_size);
-                } else {
-                    eprintln!("could not parse bound as integer in {b}");
-                    process::exit(1);
-                }
+        parse_universe(&self.bound, sig)
+    }
+}
+
+/// Parses bounds given as SORT=N into a universe size map.
+///
+/// Ensures that every sort in the given signature is given a bound.
+fn parse_universe(bounds: &[String], sig: &Signature) -> HashMap<String, usize> {
+    let mut universe: HashMap<String, usize> = HashMap::new();
+    for b in bounds {
+        if let [sort_name, bound_size] = b.split('=').collect::<Vec<&str>>()[..] {
+            let sort_name = sort_name.to_string();
+            if !sig.sorts.contains(&sort_name) {
+                eprintln!("unknown sort name {sort_name} in bound {b}");
+                process::exit(1);
+            }
+            if let Ok(bound_size) = bound_size.parse::<usize>() {
+                universe.insert(sort_name, bound_size);
             } else {
-                eprintln!("expected exactly one '=' in bound {b}");
+                eprintln!("could not parse bound as integer in {b}");
                 process::exit(1);
             }
-        }
-        if let Some(unbounded_sort) = sig.sorts.iter().find(|&s| !universe.contains_key(s)) {
-            eprintln!(
-                "need a bound for sort {unbounded_sort} on the command line, as in --bound {unbounded_sort}=N"
-            );
+        } else {
+            eprintln!("expected exactly one '=' in bound {b}");
             process::exit(1);
         }
-        universe
     }
+    if let Some(unbounded_sort) = sig.sorts.iter().find(|&s| !universe.contains_key(s)) {
+        eprintln!(
+            "need a bound for sort {unbounded_sort} on the command line, as in --bound {unbounded_sort}=N"
+        );
+        process::exit(1);
+    }
+    universe
 }
 
 #[derive(clap::Subcommand, Clone, Debug, PartialEq, Eq)]
@@ -367,7 +500,7 @@ enum Command {
     /// Verify all assertions using user-provided invariants.
     Verify(VerifyArgs),
     /// Verify assertions by inferring invariants with UPDR.
-    UpdrVerify(VerifyArgs),
+    UpdrVerify(UpdrVerifyArgs),
     /// Infer invariants using other invariant inference algorithms.
     Infer(InferArgs),
     /// Parse and re-print a fly file (for debugging)
@@ -380,6 +513,12 @@ enum Command {
         /// File name for a .fly file
         file: String,
     },
+    /// Check whether the verification conditions are in EPR, and report the
+    /// quantifier alternation cycles if not.
+    EprCheck {
+        /// File name for a .fly file
+        file: String,
+    },
     /// Apply bounded model checking to each assertion using a set of states.
     SetCheck {
         #[command(flatten)]
@@ -406,6 +545,39 @@ enum Command {
         #[command(flatten)]
         solver: SolverArgs,
     },
+    /// Re-run an SMT transcript (saved with --smt-transcript) and report
+    /// check-sat results that differ from the recorded ones.
+    ReplaySmt(ReplaySmtArgs),
+    /// Re-check the queries in a certificate written by verify
+    /// --emit-certificate.
+    CheckCertificate(CheckCertificateArgs),
+}
+
+#[derive(Args, Clone, Debug, PartialEq, Eq)]
+struct CheckCertificateArgs {
+    #[command(flatten)]
+    solver: SolverArgs,
+
+    /// Certificate directory
+    dir: String,
+}
+
+#[derive(Args, Clone, Debug, PartialEq, Eq)]
+struct ReplaySmtArgs {
+    #[arg(value_enum, long, default_value_t = SolverType::Z3)]
+    /// Solver to replay with
+    solver: SolverType,
+
+    #[arg(long, default_value_t = 600)]
+    /// SMT solver timeout in seconds
+    timeout: usize,
+
+    #[arg(long)]
+    /// Print every check, not just those whose result differs
+    all: bool,
+
+    /// File name for a .smt2 transcript
+    file: String,
 }
 
 impl InferCommand {
@@ -413,6 +585,7 @@ impl InferCommand {
         match self {
             InferCommand::Houdini { file } => file,
             InferCommand::Qalpha(QalphaArgs { file, .. }) => file,
+            InferCommand::Pdr { file } => file,
         }
     }
 }
@@ -422,9 +595,13 @@ impl Command {
         match self {
             Command::Verify(VerifyArgs { file, .. }) => file,
             Command::Infer(InferArgs { infer_cmd, .. }) => infer_cmd.file(),
-            Command::UpdrVerify(VerifyArgs { file, .. }) => file,
+            Command::UpdrVerify(UpdrVerifyArgs {
+                verify: VerifyArgs { file, .. },
+                ..
+            }) => file,
             Command::Print { file, .. } => file,
             Command::Inline { file, .. } => file,
+            Command::EprCheck { file } => file,
             Command::SetCheck {
                 bounded: BoundedArgs { file, .. },
                 ..
@@ -438,6 +615,8 @@ impl Command {
                 bounded: BoundedArgs { file, .. },
                 ..
             } => file,
+            Command::ReplaySmt(ReplaySmtArgs { file, .. }) => file,
+            Command::CheckCertificate(CheckCertificateArgs { dir, .. }) => dir,
         }
     }
 }
@@ -456,40 +635,290 @@ pub struct App {
     command: Command,
 }
 
+/// Parse a solver option given as KEY=VALUE, exiting with an error if it is
+/// malformed.
+fn parse_solver_opt(opt: &str) -> (&str, &str) {
+    match opt.split_once('=') {
+        Some((name, val)) if !name.is_empty() => (name, val),
+        _ => {
+            eprintln!("expected solver option of the form KEY=VALUE, got {opt}");
+            process::exit(1);
+        }
+    }
+}
+
 impl SolverArgs {
-    fn get_solver_conf(&self, fname: &String) -> SolverConf {
+    /// Get the solver configuration for these arguments, using the pragmas in
+    /// `m` as defaults.
+    fn get_solver_conf(&self, fname: &String, m: &Module) -> SolverConf {
         let backend_type = match &self.solver {
             SolverType::Z3 => backends::SolverType::Z3,
             SolverType::Cvc5 => backends::SolverType::Cvc5,
             SolverType::Cvc4 => backends::SolverType::Cvc4,
         };
 
-        SolverConf::new(
+        let mut conf = SolverConf::new(
             backend_type,
-            self.smt,
+            self.smt || self.smt_transcript,
             fname,
             self.timeout,
             self.solver_seed,
-        )
+        );
+        conf.transcript = self.smt_transcript;
+        conf.backend
+            .rlimit(self.rlimit)
+            .memory_limit_mb(self.memory_limit);
+        let logic = self
+            .smt_logic
+            .as_deref()
+            .or_else(|| m.pragma_values("smt_logic").last());
+        conf.backend.logic(logic.map(|l| l.to_string()));
+        for opt in m
+            .pragma_values("solver_opt")
+            .chain(self.solver_opt.iter().map(|s| s.as_str()))
+        {
+            let (name, val) = parse_solver_opt(opt);
+            conf.backend.option(name, val);
+        }
+        conf
     }
 }
 
 impl VerifyArgs {
-    fn get_solver_conf(&self) -> SolverConf {
-        self.solver.get_solver_conf(&self.file)
+    fn get_solver_conf(&self, m: &Module) -> SolverConf {
+        self.solver.get_solver_conf(&self.file, m)
     }
 }
 
 impl InferArgs {
-    fn get_solver_conf(&self) -> SolverConf {
+    fn get_solver_conf(&self, m: &Module) -> SolverConf {
         self.solver
-            .get_solver_conf(&self.infer_cmd.file().to_string())
+            .get_solver_conf(&self.infer_cmd.file().to_string(), m)
+    }
+}
+
+impl ReplaySmtArgs {
+    fn exec(&self) {
+        let contents = fs::read_to_string(&self.file).expect("could not read input file");
+        let transcript = match Transcript::parse(&contents) {
+            Ok(t) => t,
+            Err(err) => {
+                eprintln!("could not parse transcript {}: {err}", self.file);
+                process::exit(1);
+            }
+        };
+        let backend_type = match &self.solver {
+            SolverType::Z3 => backends::SolverType::Z3,
+            SolverType::Cvc5 => backends::SolverType::Cvc5,
+            SolverType::Cvc4 => backends::SolverType::Cvc4,
+        };
+        let bin_name = backend_type.bin_name();
+        let mut backend = GenericBackend::new(backend_type, &solver_path(bin_name));
+        backend.timeout_ms(if self.timeout > 0 {
+            Some(self.timeout * 1000)
+        } else {
+            None
+        });
+        // options recorded in the transcript are only meaningful to the same
+        // solver
+        let same_solver = transcript.recorded_with(bin_name);
+        let checks = match transcript.replay((&backend).get_cmd(), same_solver) {
+            Ok(checks) => checks,
+            Err(err) => {
+                eprintln!("replay failed: {err}");
+                process::exit(1);
+            }
+        };
+        let mut divergences = 0;
+        for (i, check) in checks.iter().enumerate() {
+            let diverges = check.diverges();
+            if diverges {
+                divergences += 1;
+            }
+            if !(diverges || self.all) {
+                continue;
+            }
+            let expected = match &check.expected {
+                Some(reply) => format!("{} in {:.1}ms", reply.response, reply.time_ms),
+                None => "(no reply recorded)".to_string(),
+            };
+            let actual = match &check.actual {
+                SatResp::Unknown(reason) => format!("unknown {reason}"),
+                resp => sat_kind(resp).to_string(),
+            };
+            println!(
+                "{}check {i}{}: recorded {expected}, replayed {actual} in {:.1}ms",
+                if diverges { "DIVERGES: " } else { "" },
+                check
+                    .step
+                    .as_ref()
+                    .map(|step| format!(" ({step})"))
+                    .unwrap_or_default(),
+                check.time_ms,
+            );
+        }
+        let recorded_ms: f64 = checks
+            .iter()
+            .filter_map(|c| c.expected.as_ref().map(|r| r.time_ms))
+            .sum();
+        let replayed_ms: f64 = checks.iter().map(|c| c.time_ms).sum();
+        println!(
+            "replayed {} checks with {bin_name}: {divergences} divergences, {replayed_ms:.1}ms (recorded {recorded_ms:.1}ms)",
+            checks.len(),
+        );
+        if divergences > 0 {
+            process::exit(1);
+        }
+    }
+}
+
+impl CheckCertificateArgs {
+    fn exec(&self) {
+        let backend_type = match &self.solver.solver {
+            SolverType::Z3 => backends::SolverType::Z3,
+            SolverType::Cvc5 => backends::SolverType::Cvc5,
+            SolverType::Cvc4 => backends::SolverType::Cvc4,
+        };
+        let mut conf = SolverConf::new(
+            backend_type,
+            false,
+            &self.dir,
+            self.solver.timeout,
+            self.solver.solver_seed,
+        );
+        conf.backend
+            .rlimit(self.solver.rlimit)
+            .memory_limit_mb(self.solver.memory_limit);
+        match check_certificate(&conf, Path::new(&self.dir)) {
+            Ok(failures) if failures.is_empty() => println!("certificate checks!"),
+            Ok(failures) => {
+                for failure in &failures {
+                    let query = &failure.query;
+                    eprintln!(
+                        "{}: {:?} of {} expected {}, got {}",
+                        query.file.display(),
+                        query.kind,
+                        query.invariant,
+                        query.expected,
+                        failure.actual,
+                    );
+                }
+                process::exit(1);
+            }
+            Err(err) => {
+                eprintln!("{err}");
+                process::exit(1);
+            }
+        }
+    }
+}
+
+/// Print a counterexample found by a bounded checker, converting each state
+/// with `convert`. A lasso ends by naming the state its last state steps to.
+fn trace_to_string(trace: &Trace, convert: impl Fn(&Model) -> Model) -> String {
+    let states = models_to_string(trace.states.iter().map(convert));
+    match trace.loop_back {
+        Some(loop_back) => format!("{states}\nthe last state steps back to state {loop_back}"),
+        None => states,
+    }
+}
+
+/// The quantifier alternation cycles of the verification conditions of `m`,
+/// which should have its definitions inlined.
+fn epr_violations(m: &Module) -> Vec<EprViolation> {
+    match extract(m) {
+        Ok(d) => epr_check(&d, &m.signature),
+        // verification reports this
+        Err(_) => vec![],
+    }
+}
+
+/// The spans of the assertions in `m`, in order.
+fn assertion_spans(m: &Module) -> Vec<Span> {
+    m.statements
+        .iter()
+        .filter_map(|s| match s {
+            ThmStmt::Assert(proof) => proof.assert.span,
+            ThmStmt::Assume(_) | ThmStmt::Fair(_) => None,
+        })
+        .collect()
+}
+
+/// Write `proofs` for the assertions of `m` (in order) into the fly file `path`,
+/// whose original contents are `source`, then verify the edited file.
+fn write_proofs(path: &str, source: &str, m: &Module, conf: &SolverConf, proofs: Vec<Vec<Term>>) {
+    let edits = assertion_spans(m)
+        .into_iter()
+        .zip(proofs)
+        .collect::<Vec<_>>();
+    let source = printer::replace_proofs(source, &edits);
+    // the input file is only replaced once the edited file verifies
+    let tmp = format!("{path}.tmp");
+    if let Err(err) = fs::write(&tmp, &source) {
+        eprintln!("could not write proof to {tmp}: {err}");
+        process::exit(1);
+    }
+
+    let mut m = fly::parser::parse(&source).expect("edited file should parse");
+    sorts::sort_check_module(&mut m).expect("edited file should sort check");
+    m.inline_defs();
+    if let Err(err) = verify_module(conf, &m) {
+        eprintln!("edited file does not verify, leaving {path} unchanged:");
+        let files = SimpleFile::new(&tmp, &source);
+        let writer = StandardStream::stderr(ColorChoice::Auto);
+        for fail in &err.fails {
+            let diagnostic = fail.diagnostic(());
+            terminal::emit(&mut writer.lock(), &Default::default(), &files, &diagnostic).unwrap();
+        }
+        let _ = fs::remove_file(&tmp);
+        process::exit(1);
+    }
+    if let Err(err) = fs::rename(&tmp, path) {
+        eprintln!("could not replace {path}: {err}");
+        process::exit(1);
+    }
+    println!("wrote proof to {path}");
+    println!("verifies!");
+}
+
+/// Add the candidate invariants in the file `path` to every assertion in `m`.
+fn add_candidates(m: &mut Module, path: &str) {
+    let contents = fs::read_to_string(path).expect("could not read candidates file");
+    let candidates = match fly::parser::parse_candidates(&contents) {
+        Ok(candidates) => candidates,
+        Err(err) => {
+            eprintln!("could not parse candidates in {path}: {err}");
+            process::exit(1);
+        }
+    };
+    for statement in &mut m.statements {
+        if let ThmStmt::Assert(proof) = statement {
+            proof.candidates.extend(candidates.iter().map(|c| Spanned {
+                x: c.clone(),
+                span: None,
+            }));
+        }
+    }
+}
+
+/// Exit with an error if `m` uses past-time operators, for the commands that
+/// don't replace them with history relations.
+fn reject_past(m: &Module) {
+    if m.uses_past() {
+        eprintln!("past-time operators (X^-1 and since) are not supported by this command");
+        process::exit(1);
     }
 }
 
 impl App {
     /// Run the application.
     pub fn exec(self) {
+        // these commands do not take a fly file
+        match &self.command {
+            Command::ReplaySmt(args) => return args.exec(),
+            Command::CheckCertificate(args) => return args.exec(),
+            _ => {}
+        }
         let file = fs::read_to_string(self.command.file()).expect("could not read input file");
         // We make sure paths look like Unix paths on all platforms, otherwise test snapshots don't match.
         let standardized_filename = Path::new(self.command.file()).to_slash_lossy();
@@ -515,6 +944,14 @@ impl App {
             }
         };
 
+        if let Command::Infer(InferArgs {
+            candidates: Some(path),
+            ..
+        }) = &self.command
+        {
+            add_candidates(&mut m, path);
+        }
+
         let r = sorts::sort_check_module(&mut m);
         if let Err((err, span)) = r {
             eprintln!("sort checking error:");
@@ -534,18 +971,48 @@ impl App {
                 println!("{}", printer::fmt(&m));
             }
             Command::Verify(ref args) => {
-                let conf = args.get_solver_conf();
+                let conf = args.get_solver_conf(&m);
                 m.inline_defs();
-                let r = verify_module(&conf, &m);
+                let drop_history = match m.monitor_past() {
+                    Ok(f) => f,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        process::exit(1)
+                    }
+                };
+                for violation in epr_violations(&m) {
+                    let diagnostic = violation.diagnostic(());
+                    terminal::emit(&mut writer.lock(), &config, &files, &diagnostic).unwrap();
+                }
+                let r = match &args.emit_certificate {
+                    Some(dir) => match emit_certificate(&conf, &m, Path::new(dir)) {
+                        Ok(manifest) => {
+                            println!(
+                                "wrote certificate with {} queries to {dir}",
+                                manifest.queries.len()
+                            );
+                            Ok(())
+                        }
+                        Err(CertificateError::Verify(err)) => Err(err),
+                        Err(err) => {
+                            eprintln!("{err}");
+                            process::exit(1);
+                        }
+                    },
+                    None => verify_module(&conf, &m),
+                };
                 if args.time {
                     timing::report();
                 }
                 match r {
                     Ok(()) => println!("verifies!"),
-                    Err(err) => {
+                    Err(mut err) => {
                         eprintln!("verification errors:");
 
-                        for fail in &err.fails {
+                        for fail in &mut err.fails {
+                            if let QueryError::Sat(models) = &mut fail.error {
+                                *models = models.iter().map(&drop_history).collect();
+                            }
                             let diagnostic = fail.diagnostic(());
                             terminal::emit(&mut writer.lock(), &config, &files, &diagnostic)
                                 .unwrap();
@@ -561,14 +1028,18 @@ impl App {
                     ..
                 },
             ) => {
-                let conf = args.get_solver_conf();
+                let conf = args.get_solver_conf(&m);
                 m.inline_defs();
+                reject_past(&m);
                 let r = houdini::infer_module(&conf, &m);
                 if args.time {
                     timing::report();
                 }
                 match r {
-                    Ok(()) => println!("verifies!"),
+                    Ok(proofs) if args.write_proof => {
+                        write_proofs(self.command.file(), &file, &m, &conf, proofs)
+                    }
+                    Ok(_) => println!("verifies!"),
                     Err(err) => {
                         eprintln!("verification errors:");
 
@@ -589,25 +1060,149 @@ impl App {
                 },
             ) => {
                 m.inline_defs();
-                let infer_cfg = Arc::new(
-                    qargs
-                        .infer_cfg
-                        .to_cfg(&m.signature, args.infer_cmd.file().to_string()),
-                );
-                qalpha_dynamic(infer_cfg, &m, !args.no_print_invariant);
+                reject_past(&m);
+                let mut infer_cfg = qargs
+                    .infer_cfg
+                    .to_cfg(&m.signature, args.infer_cmd.file().to_string());
+                infer_cfg.memory_limit_mb = args.solver.memory_limit;
+                infer_cfg.rlimit = args.solver.rlimit;
+                infer_cfg.solver = args.get_solver_conf(&m).backend.solver_type();
+                let result = if qargs.infer_cfg.auto {
+                    let budget = Duration::from_secs(qargs.infer_cfg.auto_budget);
+                    qalpha_auto(&infer_cfg, &m, budget, !args.no_print_invariant)
+                        .map(|(_, fixpoint)| fixpoint)
+                } else {
+                    qalpha_dynamic(Arc::new(infer_cfg), &m, !args.no_print_invariant)
+                };
+                let fixpoint = match result {
+                    Ok(fixpoint) => fixpoint,
+                    Err(err) => {
+                        eprintln!("{err}");
+                        process::exit(1);
+                    }
+                };
+                if args.time {
+                    timing::report();
+                }
+                if args.write_proof {
+                    let Some(invariant) = fixpoint.invariant() else {
+                        eprintln!("no safe invariant found, not writing proof");
+                        process::exit(1);
+                    };
+                    let conf = args.get_solver_conf(&m);
+                    let proofs = vec![invariant.to_vec(); assertion_spans(&m).len()];
+                    write_proofs(self.command.file(), &file, &m, &conf, proofs);
+                }
+            }
+            Command::Infer(
+                ref args @ InferArgs {
+                    infer_cmd: InferCommand::Pdr { .. },
+                    ..
+                },
+            ) => {
+                let conf = args.get_solver_conf(&m);
+                m.inline_defs();
+                reject_past(&m);
+                let result = match Pdr::new(conf.clone(), &m) {
+                    Ok(mut pdr) => pdr.search(),
+                    Err(err) => {
+                        eprintln!("{err}");
+                        process::exit(1);
+                    }
+                };
                 if args.time {
                     timing::report();
                 }
+                match result {
+                    PdrResult::Safe(invariant) => {
+                        if !args.no_print_invariant {
+                            println!("# inferred invariant:");
+                            println!("proof {{");
+                            for inv in &invariant {
+                                println!("  invariant {inv}");
+                            }
+                            println!("}}");
+                        }
+                        if args.write_proof {
+                            let proofs = vec![invariant; assertion_spans(&m).len()];
+                            write_proofs(self.command.file(), &file, &m, &conf, proofs);
+                        } else {
+                            println!("verifies!");
+                        }
+                    }
+                    PdrResult::Unsafe(trace) => {
+                        eprintln!("abstract counterexample:");
+                        for (i, state) in trace.iter().enumerate() {
+                            eprintln!("state {i}:\n{state}");
+                        }
+                        process::exit(1);
+                    }
+                    PdrResult::Unknown(reason) => {
+                        eprintln!("pdr failed: solver returned unknown ({reason})");
+                        process::exit(1);
+                    }
+                }
             }
             Command::Inline { .. } => {
                 let mut m = m;
                 m.inline_defs();
-                println!("{}", printer::fmt(&m));
+                println!("{}", printer::fmt_simplified(&m));
             }
-            Command::UpdrVerify(ref args @ VerifyArgs { .. }) => {
-                let conf = Arc::new(SingleSolver::new(args.get_solver_conf()));
-                let mut updr = Updr::new(conf);
-                let _result = updr.search(&m);
+            Command::EprCheck { .. } => {
+                m.inline_defs();
+                if let Err(e) = m.monitor_past() {
+                    eprintln!("{e}");
+                    process::exit(1)
+                }
+                let violations = epr_violations(&m);
+                if violations.is_empty() {
+                    println!("verification conditions are in EPR");
+                } else {
+                    for violation in &violations {
+                        let diagnostic = violation.diagnostic(());
+                        terminal::emit(&mut writer.lock(), &config, &files, &diagnostic).unwrap();
+                    }
+                    process::exit(1);
+                }
+            }
+            Command::UpdrVerify(ref args @ UpdrVerifyArgs { .. }) => {
+                let conf = args.verify.get_solver_conf(&m);
+                m.inline_defs();
+                reject_past(&m);
+                let mut updr = Updr::new(Arc::new(SingleSolver::new(conf.clone())));
+                let result = match updr.search(&m) {
+                    Ok(result) => result,
+                    Err(err) => {
+                        eprintln!("{err}");
+                        process::exit(1);
+                    }
+                };
+                if args.verify.time {
+                    timing::report();
+                }
+                match result {
+                    UpdrResult::Safe(invariant) => {
+                        println!("# inferred invariant:");
+                        println!("proof {{");
+                        for inv in &invariant {
+                            println!("  invariant {inv}");
+                        }
+                        println!("}}");
+                        if args.write_proof {
+                            let proofs = vec![invariant; assertion_spans(&m).len()];
+                            write_proofs(self.command.file(), &file, &m, &conf, proofs);
+                        } else {
+                            println!("verifies!");
+                        }
+                    }
+                    UpdrResult::Unsafe(trace) => {
+                        eprintln!("abstract counterexample:");
+                        for (i, state) in trace.iter().enumerate() {
+                            eprintln!("state {i}:\n{state}");
+                        }
+                        process::exit(1);
+                    }
+                }
             }
 
             Command::SetCheck {
@@ -615,6 +1210,13 @@ impl App {
                 compress_traces,
             } => {
                 m.inline_defs();
+                let drop_history = match m.monitor_past() {
+                    Ok(f) => f,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        process::exit(1)
+                    }
+                };
                 let back_convert_model = match m.convert_non_bool_relations() {
                     Ok(f) => f,
                     Err(e) => {
@@ -622,6 +1224,8 @@ impl App {
                         process::exit(1)
                     }
                 };
+                let back_convert_model =
+                    move |model: &Model| drop_history(&back_convert_model(model));
                 let univ = bounded.get_universe(&m.signature);
                 match bounded::set::check(
                     &m,
@@ -630,10 +1234,10 @@ impl App {
                     compress_traces.into(),
                     bounded.print_timing.unwrap_or(true),
                 ) {
-                    Ok(CheckerAnswer::Counterexample(models)) => {
+                    Ok(CheckerAnswer::Counterexample(trace)) => {
                         println!(
                             "found counterexample:\n{}",
-                            models_to_string(models.iter().map(back_convert_model))
+                            trace_to_string(&trace, back_convert_model)
                         )
                     }
                     Ok(CheckerAnswer::Unknown) => {
@@ -653,6 +1257,13 @@ impl App {
             }
             Command::SatCheck(bounded) => {
                 m.inline_defs();
+                let drop_history = match m.monitor_past() {
+                    Ok(f) => f,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        process::exit(1)
+                    }
+                };
                 let back_convert_model = match m.convert_non_bool_relations() {
                     Ok(f) => f,
                     Err(e) => {
@@ -660,6 +1271,8 @@ impl App {
                         process::exit(1)
                     }
                 };
+                let back_convert_model =
+                    move |model: &Model| drop_history(&back_convert_model(model));
                 let depth = match bounded.depth {
                     Some(depth) => depth,
                     None => {
@@ -669,10 +1282,10 @@ impl App {
                 };
                 let univ = bounded.get_universe(&m.signature);
                 match bounded::sat::check(&m, &univ, depth, bounded.print_timing.unwrap_or(true)) {
-                    Ok(CheckerAnswer::Counterexample(models)) => {
+                    Ok(CheckerAnswer::Counterexample(trace)) => {
                         println!(
                             "found counterexample:\n{}",
-                            models_to_string(models.iter().map(back_convert_model))
+                            trace_to_string(&trace, back_convert_model)
                         )
                     }
                     Ok(CheckerAnswer::Unknown) => {
@@ -684,6 +1297,13 @@ impl App {
             }
             Command::BddCheck { bounded, reversed } => {
                 m.inline_defs();
+                let drop_history = match m.monitor_past() {
+                    Ok(f) => f,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        process::exit(1)
+                    }
+                };
                 let back_convert_model = match m.convert_non_bool_relations() {
                     Ok(f) => f,
                     Err(e) => {
@@ -691,6 +1311,8 @@ impl App {
                         process::exit(1)
                     }
                 };
+                let back_convert_model =
+                    move |model: &Model| drop_history(&back_convert_model(model));
                 let univ = bounded.get_universe(&m.signature);
                 let check = match reversed {
                     false => bounded::bdd::check,
@@ -702,10 +1324,10 @@ impl App {
                     bounded.depth,
                     bounded.print_timing.unwrap_or(true),
                 ) {
-                    Ok(CheckerAnswer::Counterexample(models)) => {
+                    Ok(CheckerAnswer::Counterexample(trace)) => {
                         println!(
                             "found counterexample:\n{}",
-                            models_to_string(models.iter().map(back_convert_model))
+                            trace_to_string(&trace, back_convert_model)
                         )
                     }
                     Ok(CheckerAnswer::Unknown) => {
@@ -725,6 +1347,13 @@ impl App {
             }
             Command::SmtCheck { bounded, solver } => {
                 m.inline_defs();
+                let drop_history = match m.monitor_past() {
+                    Ok(f) => f,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        process::exit(1)
+                    }
+                };
                 let depth = match bounded.depth {
                     Some(depth) => depth,
                     None => {
@@ -734,12 +1363,15 @@ impl App {
                 };
                 match bounded::smt::check(
                     &m,
-                    &solver.get_solver_conf(&file),
+                    &solver.get_solver_conf(&file, &m),
                     depth,
                     bounded.print_timing.unwrap_or(true),
                 ) {
-                    Ok(CheckerAnswer::Counterexample(models)) => {
-                        println!("found counterexample:\n{}", models_to_string(models))
+                    Ok(CheckerAnswer::Counterexample(trace)) => {
+                        println!(
+                            "found counterexample:\n{}",
+                            trace_to_string(&trace, drop_history)
+                        )
                     }
                     Ok(CheckerAnswer::Unknown) => {
                         println!("answer: safe up to depth {depth} for given sort bounds")
@@ -748,6 +1380,9 @@ impl App {
                     Err(error) => eprintln!("{error}"),
                 }
             }
+            Command::ReplaySmt(_) | Command::CheckCertificate(_) => {
+                unreachable!("handled before parsing")
+            }
         }
     }
 }
@@ -67,7 +67,11 @@ mod tests {
                 .expect("should be an invariant assertion");
 
         let backend = GenericBackend::new(SolverType::Z3, &solver_path("z3"));
-        let conf = SolverConf { backend, tee: None };
+        let conf = SolverConf {
+            backend,
+            tee: None,
+            transcript: false,
+        };
 
         // we'll assume proof_inv (all the invariants) in the pre state and try
         // to prove Next::prime(inv) in the post state for each proof invariant
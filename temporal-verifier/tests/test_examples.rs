@@ -391,3 +391,53 @@ fn test_larger_examples() {
 
     test_dir("examples")
 }
+
+#[test]
+fn test_replay_smt() {
+    // recording a transcript requires the solver
+    if !check_version("z3") {
+        return;
+    }
+    // the transcript is saved in a log directory named after the input file,
+    // so use a copy with a unique name
+    let name = format!("replay_smt_{}", std::process::id());
+    let dir = env::temp_dir().join(&name);
+    fs::create_dir_all(&dir).unwrap();
+    let fly_file = dir.join(format!("{name}.fly"));
+    fs::copy("tests/examples/basic1.fly", &fly_file).unwrap();
+    let out = verifier()
+        .arg("verify")
+        .arg("--smt-transcript")
+        .arg(&fly_file)
+        .output()
+        .expect("could not run verifier");
+    assert!(out.status.success(), "verifier should succeed");
+
+    let log_dir = Path::new("../.flyvy-log").join(&name);
+    let transcripts = log_dir
+        .read_dir()
+        .expect("no transcripts saved")
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    assert!(!transcripts.is_empty(), "no transcripts saved");
+    for transcript in &transcripts {
+        let out = verifier()
+            .arg("replay-smt")
+            .arg("--all")
+            .arg(transcript)
+            .output()
+            .expect("could not run verifier");
+        let stdout = String::from_utf8(out.stdout).expect("non-utf8 output");
+        assert!(
+            out.status.success(),
+            "replaying {} diverged:\n{stdout}",
+            transcript.display()
+        );
+        assert!(
+            stdout.contains("recorded unsat in") && stdout.contains(": 0 divergences"),
+            "unexpected replay output:\n{stdout}"
+        );
+    }
+    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_dir_all(&log_dir).unwrap();
+}
@@ -0,0 +1,278 @@
+// Copyright 2022-2023 VMware, Inc.
+// SPDX-License-Identifier: BSD-2-Clause
+
+//! Emit and check proof certificates for verified modules.
+//!
+//! A certificate is a directory with one SMT-LIB file per query issued while
+//! verifying a module (initiation and consecution of each invariant), each of
+//! which is expected to be `unsat`, and a manifest listing them. When verifying
+//! with CVC5, each query also has an Alethe proof alongside it.
+
+use std::{
+    fs,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use fly::{
+    printer,
+    syntax::Module,
+    transitions::{extract, ExtractionError},
+};
+use serde::{Deserialize, Serialize};
+use smtlib::{
+    proc::{SatResp, SolverError},
+    transcript::Transcript,
+};
+use solver::{backends::SolverType, conf::SolverConf, imp::Backend};
+use thiserror::Error;
+
+use crate::{error::SolveError, module::verify_destructured_module_with};
+
+/// The name of the manifest file in a certificate directory.
+pub const MANIFEST: &str = "manifest.json";
+
+/// The kind of a verification query.
+#[allow(missing_docs)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryKind {
+    Initiation,
+    Consecution,
+}
+
+/// A single query in a certificate.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CertificateQuery {
+    /// What the query checks.
+    pub kind: QueryKind,
+    /// The invariant being checked, in flyvy syntax.
+    pub invariant: String,
+    /// The SMT-LIB file with the query, relative to the certificate directory.
+    pub file: PathBuf,
+    /// The expected result of the query.
+    pub expected: String,
+    /// An Alethe proof of the query, relative to the certificate directory.
+    pub proof: Option<PathBuf>,
+}
+
+/// The manifest of a certificate.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Manifest {
+    /// The command line of the solver that produced the certificate.
+    pub solver: String,
+    /// The queries in the certificate.
+    pub queries: Vec<CertificateQuery>,
+}
+
+/// An error from emitting or checking a certificate.
+#[derive(Error, Debug)]
+pub enum CertificateError {
+    /// The module did not verify, so there is no certificate.
+    #[error("module does not verify")]
+    Verify(SolveError),
+    /// The module is not supported by the verifier
+    #[error("{0}")]
+    Extract(#[from] ExtractionError),
+    /// A query could not be saved to the certificate
+    #[error("could not save the query for {0}")]
+    Save(String),
+    /// Reading or writing the certificate failed
+    #[error("certificate I/O failed: {0}")]
+    Io(#[from] io::Error),
+    /// The manifest is malformed
+    #[error("malformed manifest: {0}")]
+    Manifest(#[from] serde_json::Error),
+    /// A query file is malformed
+    #[error("malformed query {0}: {1}")]
+    Query(PathBuf, String),
+    /// The solver failed
+    #[error("solver failed on {0}: {1}")]
+    Solver(PathBuf, SolverError),
+}
+
+/// Verify `m` and, if it verifies, write a certificate to `dir`.
+pub fn emit_certificate(
+    conf: &SolverConf,
+    m: &Module,
+    dir: &Path,
+) -> Result<Manifest, CertificateError> {
+    let module = extract(m)?;
+    fs::create_dir_all(dir)?;
+    clear_certificate(dir)?;
+    let conf = SolverConf {
+        tee: Some(dir.to_path_buf()),
+        ..conf.clone()
+    };
+    let queries = Mutex::new(vec![]);
+    verify_destructured_module_with(&conf, &module, &m.signature, &|kind, inv, file| {
+        let invariant = printer::term(inv);
+        queries.lock().unwrap().push(match file {
+            Some(file) => Ok(CertificateQuery {
+                kind,
+                invariant,
+                file,
+                expected: "unsat".to_string(),
+                proof: None,
+            }),
+            None => Err(CertificateError::Save(invariant)),
+        });
+    })
+    .map_err(CertificateError::Verify)?;
+    let mut queries = queries
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+    if conf.solver_type() == SolverType::Cvc5 {
+        for query in &mut queries {
+            query.proof = Some(emit_proof(&conf, dir, &query.file)?);
+        }
+    }
+    let manifest = Manifest {
+        solver: (&conf.backend).get_cmd().cmdline(),
+        queries,
+    };
+    let f = fs::File::create(dir.join(MANIFEST))?;
+    serde_json::to_writer_pretty(f, &manifest)?;
+    Ok(manifest)
+}
+
+/// Remove the files of a previous certificate from `dir`, so that stale queries
+/// are not mixed with the new ones.
+fn clear_certificate(dir: &Path) -> Result<(), io::Error> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let is_query =
+            name.starts_with("query-") && (name.ends_with(".smt2") || name.ends_with(".alethe"));
+        if is_query || name == MANIFEST {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_query(dir: &Path, file: &Path) -> Result<Transcript, CertificateError> {
+    let contents = fs::read_to_string(dir.join(file))?;
+    Transcript::parse(&contents).map_err(|err| CertificateError::Query(file.to_path_buf(), err))
+}
+
+/// Re-run a query with CVC5 configured to produce Alethe proofs and save the
+/// proof next to it. Returns the proof's file name.
+fn emit_proof(conf: &SolverConf, dir: &Path, file: &Path) -> Result<PathBuf, CertificateError> {
+    let query = read_query(dir, file)?;
+    let mut cmd = (&conf.backend).get_cmd();
+    cmd.option("produce-proofs", "true");
+    cmd.option("proof-format-mode", "alethe");
+    let mut proof = String::new();
+    query
+        .replay_with(cmd, true, |proc, check| {
+            if check.actual == SatResp::Unsat {
+                proof = proc.get_proof()?;
+            }
+            Ok(())
+        })
+        .map_err(|err| CertificateError::Solver(file.to_path_buf(), err))?;
+    let proof_file = file.with_extension("alethe");
+    fs::write(dir.join(&proof_file), proof)?;
+    Ok(proof_file)
+}
+
+/// A query in a certificate that did not check.
+#[derive(Debug, Clone)]
+pub struct CertificateFailure {
+    /// The query that failed.
+    pub query: CertificateQuery,
+    /// What the solver returned instead of the expected result.
+    pub actual: String,
+}
+
+/// Re-check each query in the certificate in `dir` with the solver in `conf`.
+///
+/// Returns the queries whose result differs from the expected one. Proofs are
+/// not checked; that requires an external proof checker.
+pub fn check_certificate(
+    conf: &SolverConf,
+    dir: &Path,
+) -> Result<Vec<CertificateFailure>, CertificateError> {
+    let f = fs::File::open(dir.join(MANIFEST))?;
+    let manifest: Manifest = serde_json::from_reader(BufReader::new(f))?;
+    let bin_name = conf.solver_type().bin_name();
+    let mut failures = vec![];
+    for query in manifest.queries {
+        let transcript = read_query(dir, &query.file)?;
+        let checks = transcript
+            .replay(
+                (&conf.backend).get_cmd(),
+                transcript.recorded_with(bin_name),
+            )
+            .map_err(|err| CertificateError::Solver(query.file.clone(), err))?;
+        let actual = match checks.last() {
+            Some(check) => smtlib::transcript::sat_kind(&check.actual).to_string(),
+            None => "(no check-sat)".to_string(),
+        };
+        if actual != query.expected {
+            failures.push(CertificateFailure { query, actual });
+        }
+    }
+    Ok(failures)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use solver::backends::{GenericBackend, SolverType};
+    use solver::solver_path;
+
+    use super::{check_certificate, clear_certificate, emit_certificate, QueryKind, SolverConf};
+
+    #[test]
+    fn test_emit_and_check_certificate() {
+        let file = fs::read_to_string("../temporal-verifier/tests/examples/success/safety1.fly")
+            .expect("could not read input");
+        let m = fly::parser::parse(&file).expect("parse error");
+        let conf = SolverConf {
+            backend: GenericBackend::new(SolverType::Z3, &solver_path("z3")),
+            tee: None,
+            transcript: false,
+        };
+        let dir = env::temp_dir().join("flyvy-test-certificate");
+        _ = fs::remove_dir_all(&dir);
+        let manifest = emit_certificate(&conf, &m, &dir).expect("should verify");
+        assert!(manifest
+            .queries
+            .iter()
+            .any(|q| q.kind == QueryKind::Initiation));
+        assert!(manifest.queries.iter().all(|q| dir.join(&q.file).exists()));
+        let failures = check_certificate(&conf, &dir).expect("could not check certificate");
+        assert!(failures.is_empty());
+    }
+
+    #[test]
+    fn test_clear_certificate() {
+        let dir = env::temp_dir().join("flyvy-test-clear-certificate");
+        _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for name in [
+            "query-1.smt2",
+            "query-1.alethe",
+            "manifest.json",
+            "notes.txt",
+        ] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        clear_certificate(&dir).unwrap();
+        let mut left = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        left.sort();
+        assert_eq!(left, vec!["notes.txt"]);
+        _ = fs::remove_dir_all(&dir);
+    }
+}
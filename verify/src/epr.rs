@@ -0,0 +1,444 @@
+// Copyright 2022-2023 VMware, Inc.
+// SPDX-License-Identifier: BSD-2-Clause
+
+//! Check whether the verification conditions of a module are in EPR.
+//!
+//! A solver checks a verification condition by looking for a model of its
+//! negation. After skolemizing the negation, every existential variable of sort
+//! `B` in the scope of universal variables of sorts `A1, ..., An` becomes a
+//! function from the `Ai` to `B`, so it adds an edge `Ai -> B` to the quantifier
+//! alternation graph between sorts, as do the functions of the signature. The
+//! verification conditions are in EPR (so the solver is a decision procedure)
+//! if this graph has no cycles; otherwise each cycle is reported with the
+//! functions and quantifiers that cause it.
+
+use std::collections::{HashSet, VecDeque};
+
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+use fly::{syntax::*, transitions::DestructuredModule};
+use serde::Serialize;
+
+use crate::safety::InvariantAssertion;
+
+/// The reason for an edge of the quantifier alternation graph.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub enum EdgeReason {
+    /// A function of the signature
+    Function(String),
+    /// An existential variable (after skolemization) under a universal one
+    Alternation {
+        /// The universal variable
+        universal: String,
+        /// The existential variable
+        existential: String,
+    },
+}
+
+/// An edge of the quantifier alternation graph.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Edge {
+    /// The sort of the argument
+    pub from: String,
+    /// The sort of the result
+    pub to: String,
+    /// Why the edge is there
+    pub reason: EdgeReason,
+    /// The assertion or invariant whose verification condition has the edge
+    /// (`None` for functions of the signature)
+    pub loc: Option<Span>,
+}
+
+impl std::fmt::Display for Edge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> {}: ", self.from, self.to)?;
+        match &self.reason {
+            EdgeReason::Function(name) => write!(f, "function {name}"),
+            EdgeReason::Alternation {
+                universal,
+                existential,
+            } => write!(
+                f,
+                "exists {existential}:{} under forall {universal}:{} (after negation)",
+                self.to, self.from
+            ),
+        }
+    }
+}
+
+/// The quantifier alternation graph between the uninterpreted sorts.
+#[derive(Debug, Clone, Default)]
+pub struct AlternationGraph {
+    /// The edges, without duplicates (ignoring locations)
+    pub edges: Vec<Edge>,
+}
+
+impl AlternationGraph {
+    /// The graph with the edges of the functions of the signature.
+    pub fn new(signature: &Signature) -> Self {
+        let mut graph = Self::default();
+        for relation in &signature.relations {
+            if let Sort::Uninterpreted(to) = &relation.sort {
+                for arg in &relation.args {
+                    if let Sort::Uninterpreted(from) = arg {
+                        graph.add(Edge {
+                            from: from.clone(),
+                            to: to.clone(),
+                            reason: EdgeReason::Function(relation.name.clone()),
+                            loc: None,
+                        });
+                    }
+                }
+            }
+        }
+        graph
+    }
+
+    fn add(&mut self, edge: Edge) {
+        if !self
+            .edges
+            .iter()
+            .any(|e| (&e.from, &e.to, &e.reason) == (&edge.from, &edge.to, &edge.reason))
+        {
+            self.edges.push(edge);
+        }
+    }
+
+    /// Add the edges of the verification condition `vc` (which the solver
+    /// checks by negating it), reporting them at `loc`.
+    pub fn add_vc(&mut self, vc: &Term, loc: Option<Span>) {
+        // the polarity of a subterm in `vc` (so the opposite in its negation)
+        self.add_term(vc, (true, false), &mut vec![], loc);
+    }
+
+    /// Add the edges of `hypothesis`, which is assumed by verification
+    /// conditions, reporting them at `loc`.
+    pub fn add_hypothesis(&mut self, hypothesis: &Term, loc: Option<Span>) {
+        self.add_term(hypothesis, (false, true), &mut vec![], loc);
+    }
+
+    fn add_term(
+        &mut self,
+        term: &Term,
+        (pos, neg): (bool, bool),
+        universals: &mut Vec<Binder>,
+        loc: Option<Span>,
+    ) {
+        let both = (pos || neg, pos || neg);
+        match term {
+            Term::Literal(_) | Term::Id(_) => {}
+            Term::App(_, _, args) => {
+                for arg in args {
+                    self.add_term(arg, both, universals, loc);
+                }
+            }
+            Term::UnaryOp(UOp::Not, t) => self.add_term(t, (neg, pos), universals, loc),
+            Term::UnaryOp(_, t) => self.add_term(t, (pos, neg), universals, loc),
+            Term::BinOp(BinOp::Implies, lhs, rhs) => {
+                self.add_term(lhs, (neg, pos), universals, loc);
+                self.add_term(rhs, (pos, neg), universals, loc);
+            }
+            Term::BinOp(BinOp::Until | BinOp::Since, lhs, rhs) => {
+                self.add_term(lhs, (pos, neg), universals, loc);
+                self.add_term(rhs, (pos, neg), universals, loc);
+            }
+            Term::BinOp(BinOp::Equals | BinOp::NotEquals | BinOp::Iff, lhs, rhs) => {
+                self.add_term(lhs, both, universals, loc);
+                self.add_term(rhs, both, universals, loc);
+            }
+            Term::NAryOp(_, ts) => {
+                for t in ts {
+                    self.add_term(t, (pos, neg), universals, loc);
+                }
+            }
+            Term::Ite { cond, then, else_ } => {
+                self.add_term(cond, both, universals, loc);
+                self.add_term(then, (pos, neg), universals, loc);
+                self.add_term(else_, (pos, neg), universals, loc);
+            }
+            Term::Quantified {
+                quantifier,
+                binders,
+                body,
+            } => {
+                // in the negation, a forall is existential where it occurs
+                // positively in `vc`, and an exists where it occurs negatively
+                let (existential, universal) = match quantifier {
+                    Quantifier::Forall => (pos, neg),
+                    Quantifier::Exists => (neg, pos),
+                };
+                if existential {
+                    for binder in binders {
+                        for u in universals.iter() {
+                            if let (Sort::Uninterpreted(from), Sort::Uninterpreted(to)) =
+                                (&u.sort, &binder.sort)
+                            {
+                                self.add(Edge {
+                                    from: from.clone(),
+                                    to: to.clone(),
+                                    reason: EdgeReason::Alternation {
+                                        universal: u.name.clone(),
+                                        existential: binder.name.clone(),
+                                    },
+                                    loc,
+                                });
+                            }
+                        }
+                    }
+                }
+                let n = universals.len();
+                if universal {
+                    universals.extend(binders.iter().cloned());
+                }
+                self.add_term(body, (pos, neg), universals, loc);
+                universals.truncate(n);
+            }
+        }
+    }
+
+    /// A path of edges from `from` to `to` with as few edges as possible.
+    fn path(&self, from: &str, to: &str) -> Option<Vec<Edge>> {
+        let mut queue = VecDeque::from([(from, vec![])]);
+        let mut seen = HashSet::from([from]);
+        while let Some((sort, path)) = queue.pop_front() {
+            if sort == to {
+                return Some(path);
+            }
+            for edge in self.edges.iter().filter(|e| e.from == sort) {
+                if seen.insert(&edge.to) {
+                    let mut path = path.clone();
+                    path.push(edge.clone());
+                    queue.push_back((&edge.to, path));
+                }
+            }
+        }
+        None
+    }
+
+    /// One cycle through each strongly connected component of the graph that
+    /// has a cycle.
+    pub fn cycles(&self) -> Vec<Vec<Edge>> {
+        let mut cycles: Vec<Vec<Edge>> = vec![];
+        for edge in &self.edges {
+            // skip components which already have a cycle
+            if cycles.iter().any(|cycle| {
+                self.path(&edge.from, &cycle[0].from).is_some()
+                    && self.path(&cycle[0].from, &edge.from).is_some()
+            }) {
+                continue;
+            }
+            if let Some(path) = self.path(&edge.to, &edge.from) {
+                cycles.push([edge.clone()].into_iter().chain(path).collect());
+            }
+        }
+        cycles
+    }
+}
+
+/// A cycle in the quantifier alternation graph, which takes the verification
+/// conditions out of EPR.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct EprViolation {
+    /// The edges of the cycle, in order
+    pub cycle: Vec<Edge>,
+}
+
+impl EprViolation {
+    /// Convert the violation to a warning that can be printed.
+    pub fn diagnostic<FileId: Copy>(&self, file_id: FileId) -> Diagnostic<FileId> {
+        let sorts = self
+            .cycle
+            .iter()
+            .map(|e| e.from.as_str())
+            .chain([self.cycle[0].from.as_str()])
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        let mut labels = vec![];
+        for edge in &self.cycle {
+            if let Some(loc) = edge.loc {
+                let label = if labels.is_empty() {
+                    Label::primary(file_id, loc.start..loc.end)
+                } else {
+                    Label::secondary(file_id, loc.start..loc.end)
+                };
+                labels.push(label.with_message(format!("{edge}")));
+            }
+        }
+        Diagnostic::warning()
+            .with_message(format!(
+                "verification conditions are not in EPR: quantifier alternation cycle {sorts}"
+            ))
+            .with_labels(labels)
+            .with_notes(
+                self.cycle
+                    .iter()
+                    .filter(|e| e.loc.is_none())
+                    .map(|e| e.to_string())
+                    .collect(),
+            )
+    }
+}
+
+/// Compute the quantifier alternation graph of the verification conditions of
+/// `module` (those checked by `verify`), and return its cycles.
+pub fn epr_check(module: &DestructuredModule, signature: &Signature) -> Vec<EprViolation> {
+    let mut graph = AlternationGraph::new(signature);
+    for (i, proof) in module.proofs.iter().enumerate() {
+        let (inits, transitions, axioms) = module.assumptions(i);
+        let Ok(assert) =
+            InvariantAssertion::for_assert(signature, inits, transitions, &axioms, proof)
+        else {
+            continue;
+        };
+        // edges of the assumptions are reported where they are assumed, rather
+        // than at the assertion (edges are only added once)
+        let scope = proof.scope;
+        let spans = &module.spans;
+        let assumed = (inits.iter().zip(&spans.inits[..scope.inits]))
+            .chain(
+                transitions
+                    .iter()
+                    .zip(&spans.transitions[..scope.transitions]),
+            )
+            .chain(module.axioms.iter().zip(&spans.axioms[..scope.axioms]))
+            .chain(
+                module.proofs[..i]
+                    .iter()
+                    .map(|p| (&p.safety.x, &p.safety.span)),
+            );
+        for (term, span) in assumed {
+            graph.add_hypothesis(term, *span);
+        }
+        graph.add_vc(&assert.initiation().0, assert.inv.span);
+        for (span, consecution) in assert.consecutions() {
+            graph.add_vc(&consecution.0, span.or(assert.inv.span));
+        }
+    }
+    graph
+        .cycles()
+        .into_iter()
+        .map(|cycle| EprViolation { cycle })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use fly::{parser::term, sorts::sort_check_module, transitions::extract};
+
+    use super::*;
+
+    fn check(source: &str) -> Vec<EprViolation> {
+        let mut m = fly::parser::parse(source).expect("test module should parse");
+        sort_check_module(&mut m).expect("test module should sort check");
+        epr_check(&extract(&m).unwrap(), &m.signature)
+    }
+
+    #[test]
+    fn test_alternation_polarity() {
+        let sig = fly::parser::parse_signature("sort a\nsort b\nmutable r(a, b): bool");
+        let edges = |vc: &str| {
+            let mut graph = AlternationGraph::new(&sig);
+            graph.add_vc(&term(vc), None);
+            graph
+                .edges
+                .iter()
+                .map(|e| (e.from.clone(), e.to.clone()))
+                .collect::<Vec<_>>()
+        };
+        let ab = vec![("a".to_string(), "b".to_string())];
+        let ba = vec![("b".to_string(), "a".to_string())];
+
+        // the negation is exists x. forall y. !r(x, y)
+        assert_eq!(edges("forall x:a. exists y:b. r(x, y)"), vec![]);
+        // the negation is forall x. exists y. !r(x, y)
+        assert_eq!(edges("exists x:a. forall y:b. r(x, y)"), ab);
+        assert_eq!(edges("(forall x:a. exists y:b. r(x, y)) -> false"), ab);
+        assert_eq!(edges("!(exists y:b. forall x:a. r(x, y))"), vec![]);
+        assert_eq!(edges("!(forall y:b. exists x:a. r(x, y))"), ba);
+        // both polarities
+        assert_eq!(
+            edges("(forall x:a. exists y:b. r(x, y)) <-> true"),
+            vec![("a".to_string(), "b".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_epr_check() {
+        let violations = check(
+            "sort node
+immutable f(node): node
+mutable p(node): bool
+
+assume forall x:node. !p(x)
+assume always forall x:node. p(x)' <-> p(x) | p(f(x))
+assert always forall x:node. !p(x)
+",
+        );
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations[0].cycle[0].reason,
+            EdgeReason::Function("f".to_string())
+        );
+
+        let source = "sort node
+mutable p(node): bool
+mutable q(node, node): bool
+
+assume forall x:node. !p(x)
+assume always forall x:node. p(x)' <-> p(x)
+assume always forall x:node, y:node. q(x, y)' <-> q(x, y)
+assert always forall x:node. p(x) -> exists y:node. q(x, y)
+";
+        let violations = check(source);
+        assert_eq!(violations.len(), 1);
+        let [edge] = violations[0].cycle.as_slice() else {
+            panic!("expected a self-loop")
+        };
+        assert_eq!(
+            edge.reason,
+            EdgeReason::Alternation {
+                universal: "x".to_string(),
+                existential: "y".to_string()
+            }
+        );
+        let span = edge.loc.expect("edge should have a location");
+        assert_eq!(
+            &source[span.start..span.end],
+            "assert always forall x:node. p(x) -> exists y:node. q(x, y)"
+        );
+
+        // an alternation from an assumption is reported at the assumption
+        let source = "sort node
+immutable q(node, node): bool
+
+assume forall x:node. exists y:node. q(x, y)
+assert always forall x:node, y:node. q(x, y) -> q(y, x)
+";
+        let violations = check(source);
+        assert_eq!(violations.len(), 1);
+        let span = violations[0].cycle[0]
+            .loc
+            .expect("edge should have a location");
+        assert_eq!(
+            &source[span.start..span.end],
+            "assume forall x:node. exists y:node. q(x, y)"
+        );
+
+        assert!(check("sort node\nmutable p(node): bool\nassume forall x:node. !p(x)\nassume always forall x:node. p(x)' <-> p(x)\nassert always forall x:node. !p(x)\n").is_empty());
+    }
+
+    #[test]
+    fn test_epr_check_examples() {
+        let check_file = |name: &str| {
+            let source = fs::read_to_string(format!("../temporal-verifier/examples/{name}"))
+                .expect("could not read example");
+            let mut m = fly::parser::parse(&source).expect("example should parse");
+            sort_check_module(&mut m).expect("example should sort check");
+            m.inline_defs();
+            epr_check(&extract(&m).unwrap(), &m.signature)
+        };
+        assert!(check_file("consensus_epr.fly").is_empty());
+        assert!(!check_file("consensus_forall.fly").is_empty());
+    }
+}
@@ -6,6 +6,7 @@
 // configure clippy
 #![allow(clippy::needless_return)]
 #![allow(clippy::large_enum_variant)]
+#![allow(clippy::result_large_err)]
 #![allow(clippy::upper_case_acronyms)]
 #![allow(clippy::type_complexity)]
 #![deny(clippy::uninlined_format_args)]
@@ -14,6 +15,8 @@
 #![allow(rustdoc::private_intra_doc_links)]
 #![deny(rustdoc::broken_intra_doc_links)]
 
+pub mod certificate;
+pub mod epr;
 pub mod error;
 pub mod module;
 pub mod safety;
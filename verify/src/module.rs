@@ -4,12 +4,15 @@
 //! Verify that a module is correct.
 
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 use rayon::prelude::*;
 
+use super::certificate::QueryKind;
 use super::error::{AssertionFailure, FailureType, QueryError, SolveError};
 use super::safety::InvariantAssertion;
 use fly::{printer, syntax::*, transitions::*};
+use smtlib::proc::SolverError;
 use solver::{
     conf::SolverConf,
     imp::{Backend, Solver},
@@ -18,9 +21,12 @@ use solver::{
 
 fn verify_term<B: Backend>(solver: &mut Solver<B>, t: Term) -> Result<(), QueryError> {
     solver.assert(&Term::negate(t));
-    let resp = solver.check_sat(HashMap::new()).expect("error in solver");
+    let resp = match solver.check_sat(HashMap::new()) {
+        Err(SolverError::ResourceLimit(reason)) => SatResp::Unknown(reason),
+        resp => resp.expect("error in solver"),
+    };
     match resp {
-        SatResp::Sat { .. } => {
+        SatResp::Sat => {
             // TODO: should be configurable whether to minimize or not
             let states = solver
                 .get_minimal_model()
@@ -42,6 +48,18 @@ pub fn verify_destructured_module(
     conf: &SolverConf,
     module: &DestructuredModule,
     signature: &Signature,
+) -> Result<(), SolveError> {
+    verify_destructured_module_with(conf, module, signature, &|_, _, _| {})
+}
+
+/// Verify that a destructured module is correct, calling `on_query` with each
+/// query checked: its kind, the invariant it checks, and the tee file it was
+/// saved to (if `conf` has a tee).
+pub(crate) fn verify_destructured_module_with(
+    conf: &SolverConf,
+    module: &DestructuredModule,
+    signature: &Signature,
+    on_query: &(dyn Fn(QueryKind, &Term, Option<PathBuf>) + Sync),
 ) -> Result<(), SolveError> {
     let check_invariant = |assert: &InvariantAssertion| -> Result<(), Vec<AssertionFailure>> {
         let mut failures = vec![];
@@ -51,7 +69,7 @@ pub fn verify_destructured_module(
             solver.comment_with(|| format!("init implies: {}", printer::term(&assert.inv.x)));
             // TODO: break this down per invariant, as with consecutions()
             let res = verify_term(&mut solver, assert.initiation().0);
-            solver.save_tee();
+            on_query(QueryKind::Initiation, &assert.inv.x, solver.save_tee());
             if let Err(cex) = res {
                 failures.push(AssertionFailure {
                     loc: assert.inv.span,
@@ -69,7 +87,7 @@ pub fn verify_destructured_module(
                     let mut solver = conf.solver(signature, 2);
                     solver.comment_with(|| format!("inductive: {}", printer::term(&assert.inv.x)));
                     let res = verify_term(&mut solver, t.0);
-                    solver.save_tee();
+                    on_query(QueryKind::Consecution, &assert.inv.x, solver.save_tee());
                     if let Err(cex) = res {
                         Some(AssertionFailure {
                             loc: span.or(assert.inv.span),
@@ -90,13 +108,11 @@ pub fn verify_destructured_module(
         Ok(())
     };
 
-    let inits = &module.inits;
-    let transitions = &module.transitions;
-    // we push verified safety properties as axioms
-    let mut axioms = module.axioms.clone();
     let mut errors = SolveError::default();
 
-    for proof in &module.proofs {
+    for (i, proof) in module.proofs.iter().enumerate() {
+        // earlier assertions are assumed as axioms, but later assumptions aren't
+        let (inits, transitions, axioms) = module.assumptions(i);
         if let Ok(assert) =
             InvariantAssertion::for_assert(signature, inits, transitions, &axioms, proof)
         {
@@ -112,8 +128,6 @@ pub fn verify_destructured_module(
                 reason: FailureType::Unsupported,
             })
         }
-        // for future assertions, treat this assertion as an assumption
-        axioms.push(proof.safety.x.clone());
     }
 
     if errors.fails.is_empty() {
@@ -138,6 +152,7 @@ mod tests {
         let conf = SolverConf {
             backend: GenericBackend::new(SolverType::Z3, &z3_cmd),
             tee: None,
+            transcript: false,
         };
         verify_module(&conf, m)
     }